@@ -1,19 +1,38 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::num::IntErrorKind;
 
-use pest::Parser;
 use pest::iterators::Pair;
 use serde::{Deserialize, Serialize};
 
 use crate::compiler::CompilerError;
-use crate::parser::{Rule, SilverScriptParser};
+use crate::parser::{self, Rule, parse_comment_scan, parse_source_file};
 use chrono::NaiveDateTime;
 
+pub mod visit;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContractAst {
     pub name: String,
     pub params: Vec<ParamAst>,
     pub constants: HashMap<String, Expr>,
     pub functions: Vec<FunctionAst>,
+    /// `expectScript "..."`: the disassembly the compiled script must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_script: Option<String>,
+}
+
+impl ContractAst {
+    /// The constructor as written, e.g. `Escrow(pubkey buyer, int timeout)`.
+    pub fn constructor_signature(&self) -> String {
+        format_signature(&self.name, self.params.iter().map(|param| (param.type_name.as_str(), param.name.as_str())))
+    }
+}
+
+/// `name(type param, ..)` for a list of `(type_name, name)` pairs.
+pub fn format_signature<'a>(name: &str, params: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    let params: Vec<String> = params.into_iter().map(|(type_name, param)| format!("{type_name} {param}")).collect();
+    format!("{name}({})", params.join(", "))
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -40,18 +59,34 @@ pub struct FunctionAst {
     pub entrypoint: bool,
     #[serde(default)]
     pub return_types: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<AnnotationAst>,
     pub body: Vec<Statement>,
 }
 
+/// A `@name(args...)` annotation attached to a function definition.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationAst {
+    pub name: String,
+    #[serde(default)]
+    pub args: Vec<Expr>,
+    /// `key=value` arguments, in source order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub named_args: Vec<(String, Expr)>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParamAst {
     pub type_name: String,
     pub name: String,
+    /// Set by `@scale(n)` on an `int` param: the value is a fixed-point number with `n` fractional digits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Statement {
-    #[serde(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub span: Option<SourceSpan>,
     #[serde(flatten)]
     pub kind: StatementKind,
@@ -60,19 +95,88 @@ pub struct Statement {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "kind", content = "data", rename_all = "snake_case")]
 pub enum StatementKind {
-    VariableDefinition { type_name: String, modifiers: Vec<String>, name: String, expr: Option<Expr> },
-    TupleAssignment { left_type: String, left_name: String, right_type: String, right_name: String, expr: Expr },
-    ArrayPush { name: String, expr: Expr },
-    FunctionCall { name: String, args: Vec<Expr> },
-    FunctionCallAssign { bindings: Vec<ParamAst>, name: String, args: Vec<Expr> },
-    Assign { name: String, expr: Expr },
-    TimeOp { tx_var: TimeVar, expr: Expr, message: Option<String> },
-    Require { expr: Expr, message: Option<String> },
-    If { condition: Expr, then_branch: Vec<Statement>, else_branch: Option<Vec<Statement>> },
-    For { ident: String, start: Expr, end: Expr, body: Vec<Statement> },
-    Yield { expr: Expr },
-    Return { exprs: Vec<Expr> },
-    Console { args: Vec<ConsoleArg> },
+    VariableDefinition {
+        type_name: String,
+        modifiers: Vec<String>,
+        name: String,
+        expr: Option<Expr>,
+    },
+    /// `let name = expr;`: a local whose type is inferred from `expr` when compiling.
+    Let {
+        name: String,
+        expr: Expr,
+    },
+    TupleAssignment {
+        left_type: String,
+        left_name: String,
+        right_type: String,
+        right_name: String,
+        expr: Expr,
+    },
+    ArrayPush {
+        name: String,
+        expr: Expr,
+    },
+    FunctionCall {
+        name: String,
+        args: Vec<Expr>,
+    },
+    FunctionCallAssign {
+        bindings: Vec<ParamAst>,
+        name: String,
+        args: Vec<Expr>,
+    },
+    Assign {
+        name: String,
+        expr: Expr,
+    },
+    TimeOp {
+        tx_var: TimeVar,
+        expr: Expr,
+        message: Option<String>,
+    },
+    Require {
+        expr: Expr,
+        message: Option<String>,
+        /// Set by `@covenantOnly`: the check is compiled only for targets with covenants enabled.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        covenant_only: bool,
+    },
+    /// `else if` is an `else_branch` holding a single nested `If`; `header_span` covers `if (...)`.
+    If {
+        condition: Expr,
+        then_branch: Vec<Statement>,
+        else_branch: Option<Vec<Statement>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        header_span: Option<SourceSpan>,
+    },
+    /// Unrolled at compile time over `start..end`; `header_span` covers `for (...)`.
+    For {
+        ident: String,
+        start: Expr,
+        end: Expr,
+        body: Vec<Statement>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        header_span: Option<SourceSpan>,
+    },
+    Yield {
+        expr: Expr,
+    },
+    Return {
+        exprs: Vec<Expr>,
+    },
+    Console {
+        args: Vec<ConsoleArg>,
+    },
+    /// `log(...)`: emits no opcodes; the debugger evaluates and reports the arguments when execution reaches it.
+    Log {
+        args: Vec<Expr>,
+    },
+    /// `warnIf(condition, "message")`: emits no opcodes; the debugger records a warning when the condition holds.
+    WarnIf {
+        condition: Expr,
+        message: String,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -98,16 +202,72 @@ pub enum Expr {
     String(String),
     Identifier(String),
     Array(Vec<Expr>),
-    Call { name: String, args: Vec<Expr> },
-    New { name: String, args: Vec<Expr> },
-    Split { source: Box<Expr>, index: Box<Expr>, part: SplitPart },
-    Slice { source: Box<Expr>, start: Box<Expr>, end: Box<Expr> },
-    ArrayIndex { source: Box<Expr>, index: Box<Expr> },
-    Unary { op: UnaryOp, expr: Box<Expr> },
-    Binary { op: BinaryOp, left: Box<Expr>, right: Box<Expr> },
-    IfElse { condition: Box<Expr>, then_expr: Box<Expr>, else_expr: Box<Expr> },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    New {
+        name: String,
+        args: Vec<Expr>,
+    },
+    Split {
+        source: Box<Expr>,
+        index: Box<Expr>,
+        part: SplitPart,
+    },
+    Slice {
+        source: Box<Expr>,
+        start: Box<Expr>,
+        end: Box<Expr>,
+    },
+    ArrayIndex {
+        source: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Unary {
+        op: UnaryOp,
+        expr: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    /// `condition ? then_expr : else_expr`. The compiler also builds one for a variable assigned in an
+    /// `if`; only a parsed ternary has `spans`.
+    IfElse {
+        condition: Box<Expr>,
+        then_expr: Box<Expr>,
+        else_expr: Box<Expr>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        spans: Option<TernarySpans>,
+    },
     Nullary(NullaryOp),
-    Introspection { kind: IntrospectionKind, index: Box<Expr> },
+    Introspection {
+        kind: IntrospectionKind,
+        index: Box<Expr>,
+    },
+    /// Compile-time lookup table declared with `map K => V name { key: value, ..., default: value }`.
+    Map {
+        key_type: String,
+        value_type: String,
+        entries: Vec<(Expr, Expr)>,
+        default: Option<Box<Expr>>,
+    },
+    /// Fixed-point literal such as `1.50`: `digits` is the value without the point (150) and `scale`
+    /// the number of fractional digits (2). Lowered to a scaled `Int` before code generation.
+    Decimal {
+        digits: i64,
+        scale: u32,
+    },
+}
+
+/// Source locations of a ternary and of each arm, so the debugger can stop on the arm that ran.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TernarySpans {
+    pub expr: SourceSpan,
+    pub then_expr: SourceSpan,
+    pub else_expr: SourceSpan,
 }
 
 impl From<i64> for Expr {
@@ -179,6 +339,7 @@ pub enum BinaryOp {
     Mul,
     Div,
     Mod,
+    In,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -192,6 +353,7 @@ pub enum NullaryOp {
     TxOutputsLength,
     TxVersion,
     TxLockTime,
+    TxFee,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -203,6 +365,220 @@ pub enum IntrospectionKind {
     OutputLockingBytecode,
 }
 
+impl BinaryOp {
+    /// The operator as written in source.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::In => "in",
+        }
+    }
+
+    /// Binding strength in the grammar; higher binds tighter.
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::BitOr => 3,
+            BinaryOp::BitXor => 4,
+            BinaryOp::BitAnd => 5,
+            BinaryOp::Eq | BinaryOp::Ne => 6,
+            BinaryOp::In => 7,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 8,
+            BinaryOp::Add | BinaryOp::Sub => 9,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 10,
+        }
+    }
+}
+
+impl NullaryOp {
+    /// The expression as written in source.
+    pub fn source(self) -> &'static str {
+        match self {
+            NullaryOp::ActiveInputIndex => "this.activeInputIndex",
+            NullaryOp::ActiveBytecode => "this.activeBytecode",
+            NullaryOp::ThisScriptSize => "this.scriptSize",
+            NullaryOp::ThisScriptSizeDataPrefix => "this.scriptSizeDataPrefix",
+            NullaryOp::TxInputsLength => "tx.inputs.length",
+            NullaryOp::TxOutputsLength => "tx.outputs.length",
+            NullaryOp::TxVersion => "tx.version",
+            NullaryOp::TxLockTime => "tx.locktime",
+            NullaryOp::TxFee => "tx.fee",
+        }
+    }
+}
+
+const UNARY_PRECEDENCE: u8 = 11;
+const POSTFIX_PRECEDENCE: u8 = 12;
+
+/// Renders the expression back to source syntax, adding parentheses only where precedence needs them.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_expr(f, self, 0)
+    }
+}
+
+fn expr_precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::IfElse { .. } => 0,
+        Expr::Binary { op, .. } => op.precedence(),
+        Expr::Unary { .. } => UNARY_PRECEDENCE,
+        Expr::Int(value) if *value < 0 => UNARY_PRECEDENCE,
+        Expr::Decimal { digits, .. } if *digits < 0 => UNARY_PRECEDENCE,
+        _ => POSTFIX_PRECEDENCE,
+    }
+}
+
+fn write_expr(f: &mut fmt::Formatter<'_>, expr: &Expr, min_precedence: u8) -> fmt::Result {
+    if expr_precedence(expr) < min_precedence {
+        f.write_str("(")?;
+        write_expr(f, expr, 0)?;
+        return f.write_str(")");
+    }
+    match expr {
+        Expr::Int(value) => write!(f, "{value}"),
+        Expr::Decimal { digits, scale } => f.write_str(&format_decimal(*digits, *scale)),
+        Expr::Bool(value) => write!(f, "{value}"),
+        Expr::Bytes(bytes) => write!(f, "0x{}", faster_hex::hex_string(bytes)),
+        // Debug escaping only emits escapes the string literal grammar accepts.
+        Expr::String(value) => write!(f, "{value:?}"),
+        Expr::Identifier(name) => f.write_str(name),
+        Expr::Array(items) => {
+            f.write_str("[")?;
+            write_expr_list(f, items)?;
+            f.write_str("]")
+        }
+        Expr::Call { name, args } if name == "length" && args.len() == 1 => {
+            write_expr(f, &args[0], POSTFIX_PRECEDENCE)?;
+            f.write_str(".length")
+        }
+        Expr::Call { name, args } => {
+            write!(f, "{name}(")?;
+            write_expr_list(f, args)?;
+            f.write_str(")")
+        }
+        Expr::New { name, args } => {
+            write!(f, "new {name}(")?;
+            write_expr_list(f, args)?;
+            f.write_str(")")
+        }
+        Expr::Split { source, index, part } => {
+            write_expr(f, source, POSTFIX_PRECEDENCE)?;
+            write!(f, ".split({index})[{}]", if matches!(part, SplitPart::Left) { 0 } else { 1 })
+        }
+        Expr::Slice { source, start, end } => {
+            write_expr(f, source, POSTFIX_PRECEDENCE)?;
+            write!(f, ".slice({start}, {end})")
+        }
+        Expr::ArrayIndex { source, index } => {
+            write_expr(f, source, POSTFIX_PRECEDENCE)?;
+            write!(f, "[{index}]")
+        }
+        Expr::Unary { op, expr } => {
+            f.write_str(if matches!(op, UnaryOp::Not) { "!" } else { "-" })?;
+            write_expr(f, expr, UNARY_PRECEDENCE)
+        }
+        Expr::Binary { op, left, right } => {
+            let precedence = op.precedence();
+            write_expr(f, left, precedence)?;
+            write!(f, " {} ", op.symbol())?;
+            write_expr(f, right, precedence + 1)
+        }
+        Expr::IfElse { condition, then_expr, else_expr, .. } => {
+            write_expr(f, condition, 1)?;
+            f.write_str(" ? ")?;
+            write_expr(f, then_expr, 1)?;
+            f.write_str(" : ")?;
+            write_expr(f, else_expr, 0)
+        }
+        Expr::Nullary(op) => f.write_str(op.source()),
+        Expr::Introspection { kind, index } => {
+            let (list, field) = match kind {
+                IntrospectionKind::InputValue => ("inputs", "value"),
+                IntrospectionKind::InputLockingBytecode => ("inputs", "lockingBytecode"),
+                IntrospectionKind::OutputValue => ("outputs", "value"),
+                IntrospectionKind::OutputLockingBytecode => ("outputs", "lockingBytecode"),
+            };
+            write!(f, "tx.{list}[{index}].{field}")
+        }
+        Expr::Map { entries, default, .. } => {
+            f.write_str("{ ")?;
+            for (index, (key, value)) in entries.iter().enumerate() {
+                if index > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{key}: {value}")?;
+            }
+            if let Some(default) = default {
+                let separator = if entries.is_empty() { "" } else { ", " };
+                write!(f, "{separator}default: {default}")?;
+            }
+            f.write_str(" }")
+        }
+    }
+}
+
+fn write_expr_list(f: &mut fmt::Formatter<'_>, exprs: &[Expr]) -> fmt::Result {
+    for (index, expr) in exprs.iter().enumerate() {
+        if index > 0 {
+            f.write_str(", ")?;
+        }
+        write_expr(f, expr, 0)?;
+    }
+    Ok(())
+}
+
+/// `int i = start; i < end; i = i + 1` as the `start..end` range of `i`; `i <= end` includes `end`.
+/// The loop is unrolled, so the counter must be an `int` stepping by one.
+fn parse_for_clauses(pair: Pair<'_, Rule>) -> Result<(String, Expr, Expr), CompilerError> {
+    let mut inner = pair.into_inner();
+    let mut next = |what: &str| inner.next().ok_or_else(|| CompilerError::Unsupported(format!("missing for loop {what}")));
+    let type_name = next("counter type")?;
+    let ident = next("identifier")?.as_str().to_string();
+    let start = parse_expression(next("start")?)?;
+    let tested = next("condition")?;
+    let comparison = next("condition")?;
+    let bound = parse_expression(next("bound")?)?;
+    let stepped = next("step")?;
+    let step = parse_expression(next("step")?)?;
+
+    if type_name.as_str() != "int" {
+        return Err(CompilerError::Unsupported(format!("for loop counter '{ident}' must be an int")));
+    }
+    if tested.as_str() != ident {
+        return Err(CompilerError::Unsupported(format!("for loop condition must test the counter '{ident}'")));
+    }
+    let steps_by_one = match &step {
+        Expr::Binary { op: BinaryOp::Add, left, right } => {
+            matches!(left.as_ref(), Expr::Identifier(name) if *name == ident) && matches!(right.as_ref(), Expr::Int(1))
+        }
+        _ => false,
+    };
+    if stepped.as_str() != ident || !steps_by_one {
+        return Err(CompilerError::Unsupported(format!("for loop must step with {ident} = {ident} + 1")));
+    }
+    let end = match comparison.as_str() {
+        "<=" => Expr::Binary { op: BinaryOp::Add, left: Box::new(bound), right: Box::new(Expr::Int(1)) },
+        _ => bound,
+    };
+    Ok((ident, start, end))
+}
+
 fn validate_user_identifier(name: &str) -> Result<(), CompilerError> {
     if name.starts_with("__") {
         return Err(CompilerError::Unsupported("identifier cannot start with '__'".to_string()));
@@ -211,19 +587,89 @@ fn validate_user_identifier(name: &str) -> Result<(), CompilerError> {
 }
 
 pub fn parse_contract_ast(source: &str) -> Result<ContractAst, CompilerError> {
-    let mut pairs = SilverScriptParser::parse(Rule::source_file, source)?;
+    let mut pairs = parse_source_file(source)?;
     let source_pair = pairs.next().ok_or_else(|| CompilerError::Unsupported("empty source".to_string()))?;
     let mut contract = None;
+    let mut first_pragma: Option<SourceSpan> = None;
 
     for pair in source_pair.into_inner() {
-        if pair.as_rule() == Rule::contract_definition {
-            contract = Some(parse_contract_definition(pair)?);
+        match pair.as_rule() {
+            Rule::contract_definition => contract = Some(parse_contract_definition(pair)?),
+            Rule::pragma_directive if contract.is_some() => return Err(misplaced_pragma(&pair)),
+            Rule::pragma_directive => {
+                let span = SourceSpan::from_span(pair.as_span());
+                if let Some(first) = first_pragma {
+                    let reason = format!("duplicate pragma; the first one is at line {}", first.line);
+                    return Err(CompilerError::InvalidPragma { reason, span });
+                }
+                first_pragma = Some(span);
+            }
+            _ => {}
         }
     }
 
     contract.ok_or_else(|| CompilerError::Unsupported("no contract definition".to_string()))
 }
 
+/// Parses a standalone expression such as `y > 0`, e.g. a debugger watch. The whole input must be
+/// one expression.
+pub fn parse_expression_ast(source: &str) -> Result<Expr, CompilerError> {
+    let source = source.trim();
+    let pair = parser::parse_expression(source)?.next().ok_or_else(|| CompilerError::Unsupported("empty expression".to_string()))?;
+    let rest = &source[pair.as_span().end()..];
+    if !rest.trim().is_empty() {
+        return Err(CompilerError::Unsupported(format!("unexpected '{}' after expression", rest.trim())));
+    }
+    parse_expression(pair)
+}
+
+/// A comment kept by `parse_comments`. `text` includes the `//` or `/* */` delimiters.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Comment {
+    pub kind: CommentKind,
+    pub text: String,
+    pub span: SourceSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentKind {
+    /// `// ...`
+    Line,
+    /// `/* ... */`
+    Block,
+    /// `/// ...`
+    DocLine,
+    /// `/** ... */`
+    DocBlock,
+}
+
+/// Returns every comment in `source`, in source order. The AST drops comments, so tools that need
+/// them (formatting, doc extraction) read them from here and place them by span.
+pub fn parse_comments(source: &str) -> Result<Vec<Comment>, CompilerError> {
+    let scan = parse_comment_scan(source)?.next().ok_or_else(|| CompilerError::Unsupported("empty source".to_string()))?;
+    let comments = scan
+        .into_inner()
+        .filter_map(|pair| {
+            let text = pair.as_str();
+            let kind = match pair.as_rule() {
+                Rule::line_comment if text.starts_with("///") && !text.starts_with("////") => CommentKind::DocLine,
+                Rule::line_comment => CommentKind::Line,
+                Rule::block_comment if text.starts_with("/**") && text != "/**/" && !text.starts_with("/***") => CommentKind::DocBlock,
+                Rule::block_comment => CommentKind::Block,
+                _ => return None,
+            };
+            Some(Comment { kind, text: text.to_string(), span: SourceSpan::from_span(pair.as_span()) })
+        })
+        .collect();
+    Ok(comments)
+}
+
+/// `parse_contract_ast` plus the comments of the same source, as `solang_parser::parse` returns them.
+pub fn parse_contract_ast_with_comments(source: &str) -> Result<(ContractAst, Vec<Comment>), CompilerError> {
+    Ok((parse_contract_ast(source)?, parse_comments(source)?))
+}
+
 fn parse_contract_definition(pair: Pair<'_, Rule>) -> Result<ContractAst, CompilerError> {
     let mut inner = pair.into_inner();
     let name_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing contract name".to_string()))?;
@@ -232,6 +678,8 @@ fn parse_contract_definition(pair: Pair<'_, Rule>) -> Result<ContractAst, Compil
 
     let mut functions = Vec::new();
     let mut constants: HashMap<String, Expr> = HashMap::new();
+    let mut prologue: Option<Vec<Statement>> = None;
+    let mut expected_script: Option<String> = None;
 
     for item_pair in inner {
         if item_pair.as_rule() != Rule::contract_item {
@@ -255,16 +703,101 @@ fn parse_contract_definition(pair: Pair<'_, Rule>) -> Result<ContractAst, Compil
                     let expr = parse_expression(expr_pair)?;
                     constants.insert(name_pair.as_str().to_string(), expr);
                 }
+                Rule::map_definition => {
+                    let (name, map) = parse_map_definition(inner_item)?;
+                    constants.insert(name, map);
+                }
+                Rule::constructor_block => {
+                    if prologue.is_some() {
+                        return Err(CompilerError::Unsupported("a contract can have only one constructor block".to_string()));
+                    }
+                    prologue = Some(parse_constructor_block(inner_item)?);
+                }
+                Rule::expect_script => {
+                    if expected_script.is_some() {
+                        return Err(CompilerError::Unsupported("a contract can have only one expectScript".to_string()));
+                    }
+                    match parse_string_literal(single_inner(inner_item)?)? {
+                        Expr::String(value) => expected_script = Some(value),
+                        _ => return Err(CompilerError::Unsupported("expectScript needs a string literal".to_string())),
+                    }
+                }
+                Rule::pragma_directive => return Err(misplaced_pragma(&inner_item)),
                 _ => {}
             }
         }
     }
 
-    Ok(ContractAst { name: name_pair.as_str().to_string(), params, constants, functions })
+    // The constructor block's checks run at the start of every entrypoint; internal functions are
+    // only reached through an entrypoint, so they are left alone.
+    if let Some(prologue) = prologue {
+        for function in functions.iter_mut().filter(|function| function.entrypoint) {
+            function.body.splice(0..0, prologue.iter().cloned());
+        }
+    }
+
+    Ok(ContractAst { name: name_pair.as_str().to_string(), params, constants, functions, expected_script })
+}
+
+fn misplaced_pragma(pair: &Pair<'_, Rule>) -> CompilerError {
+    let reason = "pragma must come before the contract definition".to_string();
+    CompilerError::InvalidPragma { reason, span: SourceSpan::from_span(pair.as_span()) }
+}
+
+/// `constructor { require(...); ... }`: invariants over the constructor params, checked on every spend.
+fn parse_constructor_block(pair: Pair<'_, Rule>) -> Result<Vec<Statement>, CompilerError> {
+    let mut statements = Vec::new();
+    for stmt in pair.into_inner() {
+        let statement = parse_statement(stmt)?;
+        if !matches!(statement.kind, StatementKind::Require { .. }) {
+            return Err(CompilerError::Unsupported("a constructor block can only contain require statements".to_string()));
+        }
+        statements.push(statement);
+    }
+    Ok(statements)
+}
+
+fn parse_map_definition(pair: Pair<'_, Rule>) -> Result<(String, Expr), CompilerError> {
+    let mut inner = pair.into_inner();
+    let key_type = inner.next().ok_or_else(|| CompilerError::Unsupported("missing map key type".to_string()))?;
+    let value_type = inner.next().ok_or_else(|| CompilerError::Unsupported("missing map value type".to_string()))?;
+    let name_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing map name".to_string()))?;
+    validate_user_identifier(name_pair.as_str())?;
+
+    let mut entries = Vec::new();
+    let mut default = None;
+    for entry in inner {
+        let mut parts = entry.into_inner();
+        let first = parts.next().ok_or_else(|| CompilerError::Unsupported("missing map entry".to_string()))?;
+        if first.as_rule() == Rule::map_default {
+            if default.is_some() {
+                return Err(CompilerError::Unsupported(format!("map '{}' has more than one default", name_pair.as_str())));
+            }
+            let value = first.into_inner().next().ok_or_else(|| CompilerError::Unsupported("missing map default".to_string()))?;
+            default = Some(Box::new(parse_expression(value)?));
+            continue;
+        }
+        let value = parts.next().ok_or_else(|| CompilerError::Unsupported("missing map value".to_string()))?;
+        entries.push((parse_expression(first)?, parse_expression(value)?));
+    }
+
+    let map = Expr::Map { key_type: key_type.as_str().to_string(), value_type: value_type.as_str().to_string(), entries, default };
+    Ok((name_pair.as_str().to_string(), map))
 }
 
+/// Statements that look like calls and are parsed before `call_statement`; a contract function with
+/// one of these names would be shadowed at every call site.
+const STATEMENT_FUNCTION_NAMES: &[&str] = &["log", "warnIf"];
+
 fn parse_function_definition(pair: Pair<'_, Rule>) -> Result<FunctionAst, CompilerError> {
     let mut inner = pair.into_inner();
+    let mut annotations = Vec::new();
+    while let Some(next) = inner.peek() {
+        if next.as_rule() != Rule::annotation {
+            break;
+        }
+        annotations.push(parse_annotation(inner.next().expect("checked"))?);
+    }
     let mut entrypoint = false;
     let name_pair = match inner.next() {
         Some(pair) if pair.as_rule() == Rule::entrypoint => {
@@ -274,6 +807,12 @@ fn parse_function_definition(pair: Pair<'_, Rule>) -> Result<FunctionAst, Compil
         Some(pair) => pair,
         None => return Err(CompilerError::Unsupported("missing function name".to_string())),
     };
+    if STATEMENT_FUNCTION_NAMES.contains(&name_pair.as_str()) {
+        return Err(CompilerError::Unsupported(format!(
+            "function name '{}' is reserved: `{0}(...);` is a debug-only statement, so the function could never be called",
+            name_pair.as_str()
+        )));
+    }
     let params_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing function parameters".to_string()))?;
     let params = parse_typed_parameter_list(params_pair)?;
     let mut return_types = Vec::new();
@@ -289,7 +828,23 @@ fn parse_function_definition(pair: Pair<'_, Rule>) -> Result<FunctionAst, Compil
         body.push(parse_statement(stmt)?);
     }
 
-    Ok(FunctionAst { name: name_pair.as_str().to_string(), params, entrypoint, return_types, body })
+    Ok(FunctionAst { name: name_pair.as_str().to_string(), params, entrypoint, return_types, annotations, body })
+}
+
+fn parse_annotation(pair: Pair<'_, Rule>) -> Result<AnnotationAst, CompilerError> {
+    let mut inner = pair.into_inner();
+    let name_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing annotation name".to_string()))?;
+    let mut args = Vec::new();
+    let mut named_args = Vec::new();
+    for arg in inner.next().map(|list| list.into_inner()).into_iter().flatten() {
+        let mut parts = arg.into_inner();
+        let first = parts.next().ok_or_else(|| CompilerError::Unsupported("missing annotation argument".to_string()))?;
+        match parts.next() {
+            Some(value) => named_args.push((first.as_str().to_string(), parse_expression(value)?)),
+            None => args.push(parse_expression(first)?),
+        }
+    }
+    Ok(AnnotationAst { name: name_pair.as_str().to_string(), args, named_args })
 }
 
 fn parse_statement(pair: Pair<'_, Rule>) -> Result<Statement, CompilerError> {
@@ -354,6 +909,13 @@ fn parse_statement(pair: Pair<'_, Rule>) -> Result<Statement, CompilerError> {
             let expr = parse_expression(expr_pair)?;
             StatementKind::ArrayPush { name: ident.as_str().to_string(), expr }
         }
+        Rule::let_statement => {
+            let mut inner = pair.into_inner();
+            let ident = inner.next().ok_or_else(|| CompilerError::Unsupported("missing let name".to_string()))?;
+            validate_user_identifier(ident.as_str())?;
+            let expr_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing let expression".to_string()))?;
+            StatementKind::Let { name: ident.as_str().to_string(), expr: parse_expression(expr_pair)? }
+        }
         Rule::assign_statement => {
             let mut inner = pair.into_inner();
             let ident = inner.next().ok_or_else(|| CompilerError::Unsupported("missing assignment name".to_string()))?;
@@ -376,20 +938,32 @@ fn parse_statement(pair: Pair<'_, Rule>) -> Result<Statement, CompilerError> {
             StatementKind::TimeOp { tx_var, expr, message }
         }
         Rule::require_statement => {
-            let mut inner = pair.into_inner();
+            let mut inner = pair.into_inner().peekable();
+            let mut covenant_only = false;
+            while let Some(annotation) = inner.next_if(|next| next.as_rule() == Rule::annotation) {
+                let annotation = parse_annotation(annotation)?;
+                match annotation.name.as_str() {
+                    "covenantOnly" if annotation.args.is_empty() => covenant_only = true,
+                    "covenantOnly" => return Err(CompilerError::Unsupported("@covenantOnly takes no arguments".to_string())),
+                    other => return Err(CompilerError::Unsupported(format!("unknown require annotation '@{other}'"))),
+                }
+            }
             let expr_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing require expression".to_string()))?;
             let message = inner.next().map(parse_require_message).transpose()?;
             let expr = parse_expression(expr_pair)?;
-            StatementKind::Require { expr, message }
+            StatementKind::Require { expr, message, covenant_only }
         }
         Rule::if_statement => {
             let mut inner = pair.into_inner();
-            let cond_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing if condition".to_string()))?;
+            let header = inner.next().ok_or_else(|| CompilerError::Unsupported("missing if condition".to_string()))?;
+            let header_span = Some(SourceSpan::from_span(header.as_span()));
+            let cond_pair =
+                header.into_inner().next().ok_or_else(|| CompilerError::Unsupported("missing if condition".to_string()))?;
             let cond_expr = parse_expression(cond_pair)?;
             let then_block = inner.next().ok_or_else(|| CompilerError::Unsupported("missing if block".to_string()))?;
             let then_branch = parse_block(then_block)?;
             let else_branch = inner.next().map(parse_block).transpose()?;
-            StatementKind::If { condition: cond_expr, then_branch, else_branch }
+            StatementKind::If { condition: cond_expr, then_branch, else_branch, header_span }
         }
         Rule::call_statement => {
             let mut inner = pair.into_inner();
@@ -417,7 +991,7 @@ fn parse_statement(pair: Pair<'_, Rule>) -> Result<Statement, CompilerError> {
                         .as_str()
                         .to_string();
                     validate_user_identifier(&name)?;
-                    bindings.push(ParamAst { type_name, name });
+                    bindings.push(ParamAst { type_name, name, scale: None });
                 } else if item.as_rule() == Rule::function_call {
                     call_pair = Some(item);
                 }
@@ -430,17 +1004,25 @@ fn parse_statement(pair: Pair<'_, Rule>) -> Result<Statement, CompilerError> {
         }
         Rule::for_statement => {
             let mut inner = pair.into_inner();
-            let ident = inner.next().ok_or_else(|| CompilerError::Unsupported("missing for loop identifier".to_string()))?;
-            validate_user_identifier(ident.as_str())?;
-            let start_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing for loop start".to_string()))?;
-            let end_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing for loop end".to_string()))?;
+            let header = inner.next().ok_or_else(|| CompilerError::Unsupported("missing for loop header".to_string()))?;
             let block_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing for loop body".to_string()))?;
-
-            let start_expr = parse_expression(start_pair)?;
-            let end_expr = parse_expression(end_pair)?;
+            let header_span = Some(SourceSpan::from_span(header.as_span()));
+            let clauses =
+                header.into_inner().next().ok_or_else(|| CompilerError::Unsupported("missing for loop header".to_string()))?;
+            let (ident, start, end) = match clauses.as_rule() {
+                Rule::for_clauses => parse_for_clauses(clauses)?,
+                _ => {
+                    let mut inner = clauses.into_inner();
+                    let ident = inner.next().ok_or_else(|| CompilerError::Unsupported("missing for loop identifier".to_string()))?;
+                    let start_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing for loop start".to_string()))?;
+                    let end_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing for loop end".to_string()))?;
+                    (ident.as_str().to_string(), parse_expression(start_pair)?, parse_expression(end_pair)?)
+                }
+            };
+            validate_user_identifier(&ident)?;
             let body = parse_block(block_pair)?;
 
-            StatementKind::For { ident: ident.as_str().to_string(), start: start_expr, end: end_expr, body }
+            StatementKind::For { ident, start, end, body, header_span }
         }
         Rule::yield_statement => {
             let mut inner = pair.into_inner();
@@ -466,6 +1048,17 @@ fn parse_statement(pair: Pair<'_, Rule>) -> Result<Statement, CompilerError> {
             let args = parse_console_parameter_list(list_pair)?;
             StatementKind::Console { args }
         }
+        Rule::log_statement => {
+            let mut inner = pair.into_inner();
+            let list_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing log arguments".to_string()))?;
+            StatementKind::Log { args: parse_expression_list(list_pair)? }
+        }
+        Rule::warn_if_statement => {
+            let mut inner = pair.into_inner();
+            let cond_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing warnIf condition".to_string()))?;
+            let message_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing warnIf message".to_string()))?;
+            StatementKind::WarnIf { condition: parse_expression(cond_pair)?, message: parse_require_message(message_pair)? }
+        }
         _ => return Err(CompilerError::Unsupported(format!("unexpected statement: {:?}", pair.as_rule()))),
     };
 
@@ -509,12 +1102,14 @@ fn parse_require_message(pair: Pair<'_, Rule>) -> Result<String, CompilerError>
 fn parse_expression(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
     match pair.as_rule() {
         Rule::expression => parse_expression(single_inner(pair)?),
+        Rule::conditional => parse_conditional(pair),
         Rule::logical_or => parse_infix(pair, parse_expression, map_logical_or),
         Rule::logical_and => parse_infix(pair, parse_expression, map_logical_and),
         Rule::bit_or => parse_infix(pair, parse_expression, map_bit_or),
         Rule::bit_xor => parse_infix(pair, parse_expression, map_bit_xor),
         Rule::bit_and => parse_infix(pair, parse_expression, map_bit_and),
         Rule::equality => parse_infix(pair, parse_expression, map_equality),
+        Rule::membership => parse_infix(pair, parse_expression, map_membership),
         Rule::comparison => parse_infix(pair, parse_expression, map_comparison),
         Rule::term => parse_infix(pair, parse_expression, map_term),
         Rule::factor => parse_infix(pair, parse_expression, map_factor),
@@ -524,7 +1119,8 @@ fn parse_expression(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
         Rule::parenthesized => parse_expression(single_inner(pair)?),
         Rule::literal => parse_literal(single_inner(pair)?),
         Rule::number_literal => parse_number_literal(pair),
-        Rule::NumberLiteral => parse_number(pair.as_str()),
+        Rule::NumberLiteral => parse_number(pair),
+        Rule::DecimalLiteral => parse_decimal(pair),
         Rule::BooleanLiteral => Ok(Expr::Bool(pair.as_str() == "true")),
         Rule::HexLiteral => parse_hex_literal(pair.as_str()),
         Rule::Identifier => Ok(Expr::Identifier(pair.as_str().to_string())),
@@ -630,12 +1226,27 @@ fn parse_typed_parameter_list(pair: Pair<'_, Rule>) -> Result<Vec<ParamAst>, Com
         if param.as_rule() != Rule::parameter {
             continue;
         }
-        let mut inner = param.into_inner();
+        let mut inner = param.into_inner().peekable();
+        let mut scale = None;
+        while let Some(annotation) = inner.next_if(|next| next.as_rule() == Rule::annotation) {
+            let annotation = parse_annotation(annotation)?;
+            match (annotation.name.as_str(), annotation.args.as_slice()) {
+                ("scale", [Expr::Int(value)]) if (0..=MAX_DECIMAL_SCALE as i64).contains(value) => scale = Some(*value as u32),
+                ("scale", _) => {
+                    let message = format!("@scale expects a single integer between 0 and {MAX_DECIMAL_SCALE}");
+                    return Err(CompilerError::Unsupported(message));
+                }
+                (other, _) => return Err(CompilerError::Unsupported(format!("unknown parameter annotation '@{other}'"))),
+            }
+        }
         let type_name =
             inner.next().ok_or_else(|| CompilerError::Unsupported("missing parameter type".to_string()))?.as_str().trim().to_string();
         let ident = inner.next().ok_or_else(|| CompilerError::Unsupported("missing parameter name".to_string()))?.as_str().to_string();
         validate_user_identifier(&ident)?;
-        params.push(ParamAst { type_name, name: ident });
+        if scale.is_some() && type_name != "int" {
+            return Err(CompilerError::Unsupported(format!("@scale on '{ident}' requires an int parameter")));
+        }
+        params.push(ParamAst { type_name, name: ident, scale });
     }
     Ok(params)
 }
@@ -670,7 +1281,8 @@ fn parse_literal(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
     match pair.as_rule() {
         Rule::BooleanLiteral => Ok(Expr::Bool(pair.as_str() == "true")),
         Rule::number_literal => parse_number_literal(pair),
-        Rule::NumberLiteral => parse_number(pair.as_str()),
+        Rule::NumberLiteral => parse_number(pair),
+        Rule::DecimalLiteral => parse_decimal(pair),
         Rule::HexLiteral => parse_hex_literal(pair.as_str()),
         Rule::StringLiteral => parse_string_literal(pair),
         Rule::DateLiteral => parse_date_literal(pair),
@@ -678,27 +1290,68 @@ fn parse_literal(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
     }
 }
 
-fn parse_number(raw: &str) -> Result<Expr, CompilerError> {
+/// Largest magnitude a script number can hold: 8 bytes in sign-magnitude encoding.
+pub const MAX_SCRIPT_NUM: i64 = i64::MAX;
+
+fn parse_number(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
+    let raw = pair.as_str();
+    let invalid = || CompilerError::InvalidLiteral(format!("invalid number literal '{raw}'"));
+    let out_of_range = || literal_out_of_range(&pair);
+    let parse_i128 = |digits: &str| {
+        digits.parse::<i128>().map_err(|err| match err.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => out_of_range(),
+            _ => invalid(),
+        })
+    };
+
     let cleaned = raw.replace('_', "");
-    if let Some((base_str, exp_str)) = cleaned.split_once('e').or_else(|| cleaned.split_once('E')) {
-        if exp_str.is_empty() {
-            return Err(CompilerError::InvalidLiteral(format!("invalid number literal '{raw}'")));
-        }
-        let base: i64 = base_str.parse().map_err(|_| CompilerError::InvalidLiteral(format!("invalid number literal '{raw}'")))?;
-        let exp: i64 = exp_str.parse().map_err(|_| CompilerError::InvalidLiteral(format!("invalid number literal '{raw}'")))?;
-        if exp < 0 {
-            return Err(CompilerError::InvalidLiteral(format!("invalid number literal '{raw}'")));
-        }
-        let pow = 10i128.pow(exp as u32);
-        let value =
-            (base as i128).checked_mul(pow).ok_or_else(|| CompilerError::InvalidLiteral(format!("invalid number literal '{raw}'")))?;
-        if value > i64::MAX as i128 || value < i64::MIN as i128 {
-            return Err(CompilerError::InvalidLiteral(format!("invalid number literal '{raw}'")));
+    let value = match cleaned.split_once('e').or_else(|| cleaned.split_once('E')) {
+        Some((base_str, exp_str)) => {
+            if exp_str.is_empty() {
+                return Err(invalid());
+            }
+            let base = parse_i128(base_str)?;
+            let exp: u32 = exp_str.parse().map_err(|_| invalid())?;
+            let pow = 10i128.checked_pow(exp).ok_or_else(out_of_range)?;
+            base.checked_mul(pow).ok_or_else(out_of_range)?
         }
-        return Ok(Expr::Int(value as i64));
+        None => parse_i128(&cleaned)?,
+    };
+    checked_script_num(value).map(Expr::Int).ok_or_else(out_of_range)
+}
+
+/// Most fractional digits a decimal literal or `@scale` may have; 10^18 still fits a script number.
+pub const MAX_DECIMAL_SCALE: u32 = 18;
+
+fn parse_decimal(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
+    let cleaned = pair.as_str().replace('_', "");
+    let (whole, fraction) = cleaned.split_once('.').expect("grammar requires a decimal point");
+    let scale = fraction.len() as u32;
+    if scale > MAX_DECIMAL_SCALE {
+        return Err(literal_out_of_range(&pair));
+    }
+    let digits: i128 = format!("{whole}{fraction}").parse().map_err(|_| literal_out_of_range(&pair))?;
+    let digits = checked_script_num(digits).ok_or_else(|| literal_out_of_range(&pair))?;
+    Ok(Expr::Decimal { digits, scale })
+}
+
+/// Renders a scaled int as a decimal with exactly `scale` fractional digits, e.g. `150` at scale 2 as `1.50`.
+pub fn format_decimal(value: i64, scale: u32) -> String {
+    if scale == 0 {
+        return value.to_string();
     }
-    let value: i64 = cleaned.parse().map_err(|_| CompilerError::InvalidLiteral(format!("invalid number literal '{raw}'")))?;
-    Ok(Expr::Int(value))
+    let digits = format!("{:0>width$}", value.unsigned_abs(), width = scale as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale as usize);
+    let sign = if value < 0 { "-" } else { "" };
+    format!("{sign}{whole}.{fraction}")
+}
+
+fn checked_script_num(value: i128) -> Option<i64> {
+    (value.unsigned_abs() <= MAX_SCRIPT_NUM as u128).then_some(value as i64)
+}
+
+fn literal_out_of_range(pair: &Pair<'_, Rule>) -> CompilerError {
+    CompilerError::LiteralOutOfRange { literal: pair.as_str().to_string(), span: SourceSpan::from_span(pair.as_span()) }
 }
 
 fn parse_array(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
@@ -740,10 +1393,17 @@ fn parse_expression_list(pair: Pair<'_, Rule>) -> Result<Vec<Expr>, CompilerErro
 fn parse_cast(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
     let mut inner = pair.into_inner();
     let type_name = inner.next().ok_or_else(|| CompilerError::Unsupported("missing cast type".to_string()))?.as_str().to_string();
-    let args = match inner.next() {
+    let list = inner.next();
+    let args = match list.clone() {
         Some(list) => parse_expression_list(list)?,
         None => Vec::new(),
     };
+    // A hex literal cast to int is read as a little-endian script number, so it must fit in 8 bytes.
+    let wide_bytes = matches!(args.as_slice(), [Expr::Bytes(bytes)] if bytes.len() > 8);
+    let is_hex = |list: &&Pair<'_, Rule>| list.as_str().trim_start().to_ascii_lowercase().starts_with("0x");
+    if let Some(list) = list.as_ref().filter(|list| type_name == "int" && wide_bytes && is_hex(list)) {
+        return Err(literal_out_of_range(list));
+    }
     if type_name == "bytes" {
         return Ok(Expr::Call { name: "bytes".to_string(), args });
     }
@@ -763,12 +1423,12 @@ fn parse_cast(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
 }
 
 fn parse_number_literal(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
-    let mut inner = pair.into_inner();
+    let mut inner = pair.clone().into_inner();
     let number = inner.next().ok_or_else(|| CompilerError::InvalidLiteral("missing number literal".to_string()))?;
-    let value = parse_number(number.as_str())?;
+    let value = parse_number(number)?;
     if let Some(unit_pair) = inner.next() {
         let unit = unit_pair.as_str();
-        return apply_number_unit(value, unit);
+        return apply_number_unit(&pair, value, unit);
     }
     Ok(value)
 }
@@ -784,7 +1444,7 @@ fn parse_hex_literal(raw: &str) -> Result<Expr, CompilerError> {
     Ok(Expr::Bytes(bytes))
 }
 
-fn apply_number_unit(expr: Expr, unit: &str) -> Result<Expr, CompilerError> {
+fn apply_number_unit(pair: &Pair<'_, Rule>, expr: Expr, unit: &str) -> Result<Expr, CompilerError> {
     let value = match expr {
         Expr::Int(value) => value,
         _ => return Err(CompilerError::InvalidLiteral("number literal is not an int".to_string())),
@@ -800,7 +1460,7 @@ fn apply_number_unit(expr: Expr, unit: &str) -> Result<Expr, CompilerError> {
         "kas" => 100_000_000,
         _ => return Err(CompilerError::Unsupported(format!("number unit '{unit}' not supported"))),
     };
-    Ok(Expr::Int(value.saturating_mul(multiplier)))
+    checked_script_num(value as i128 * multiplier as i128).map(Expr::Int).ok_or_else(|| literal_out_of_range(pair))
 }
 
 fn parse_date_literal(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
@@ -825,13 +1485,63 @@ fn parse_date_literal(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
 
 fn parse_string_literal(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
     let raw = pair.as_str();
-    let unquoted = if raw.starts_with('"') && raw.ends_with('"') || raw.starts_with('\'') && raw.ends_with('\'') {
-        &raw[1..raw.len() - 1]
-    } else {
-        raw
-    };
-    let unescaped = unquoted.replace("\\\"", "\"").replace("\\'", "'");
-    Ok(Expr::String(unescaped))
+    if let Some(body) = raw.strip_prefix("\"\"\"").and_then(|rest| rest.strip_suffix("\"\"\"")) {
+        // Triple-quoted strings are raw: line breaks and backslashes are kept as written.
+        return Ok(Expr::String(body.to_string()));
+    }
+
+    let body = &raw[1..raw.len() - 1];
+    let mut bytes = Vec::with_capacity(body.len());
+    let mut chars = body.char_indices();
+    while let Some((index, ch)) = chars.next() {
+        if ch != '\\' {
+            bytes.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
+        }
+        // Offsets into `raw`, which has the opening quote in front of `body`.
+        let invalid = |end: usize| invalid_escape(&pair, index + 1, end + 1);
+        match chars.next().map(|(_, escaped)| escaped) {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('r') => bytes.push(b'\r'),
+            Some('0') => bytes.push(0),
+            Some(quoted @ ('\\' | '"' | '\'')) => bytes.push(quoted as u8),
+            Some('x') => {
+                let digits = body.get(chars.offset()..chars.offset() + 2);
+                let Some(digits) = digits.filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit())) else {
+                    return Err(invalid((chars.offset() + 2).min(body.len())));
+                };
+                bytes.push(u8::from_str_radix(digits, 16).expect("checked hex digits"));
+                chars.nth(1);
+            }
+            Some('u') => {
+                let hex = body[chars.offset()..]
+                    .strip_prefix('{')
+                    .and_then(|rest| rest.split_once('}'))
+                    .map(|(hex, _)| hex)
+                    .filter(|hex| (1..=6).contains(&hex.len()) && hex.bytes().all(|b| b.is_ascii_hexdigit()));
+                let decoded = hex.and_then(|hex| char::from_u32(u32::from_str_radix(hex, 16).ok()?));
+                let (Some(hex), Some(decoded)) = (hex, decoded) else {
+                    return Err(invalid(chars.offset()));
+                };
+                bytes.extend_from_slice(decoded.encode_utf8(&mut [0; 4]).as_bytes());
+                chars.nth(hex.len() + 1);
+            }
+            Some(_) => return Err(invalid(chars.offset())),
+            None => return Err(invalid(body.len())),
+        }
+    }
+    // `\xNN` escapes can produce bytes that are not UTF-8; such literals become byte strings.
+    Ok(match String::from_utf8(bytes) {
+        Ok(text) => Expr::String(text),
+        Err(err) => Expr::Bytes(err.into_bytes()),
+    })
+}
+
+fn invalid_escape(pair: &Pair<'_, Rule>, start: usize, end: usize) -> CompilerError {
+    let span = pair.as_span();
+    let escape = span.get(start..end).unwrap_or(span);
+    CompilerError::InvalidEscape { escape: escape.as_str().to_string(), span: SourceSpan::from_span(escape) }
 }
 
 fn parse_nullary(raw: &str) -> Result<Expr, CompilerError> {
@@ -844,6 +1554,7 @@ fn parse_nullary(raw: &str) -> Result<Expr, CompilerError> {
         "tx.outputs.length" => NullaryOp::TxOutputsLength,
         "tx.version" => NullaryOp::TxVersion,
         "tx.locktime" => NullaryOp::TxLockTime,
+        "tx.fee" => NullaryOp::TxFee,
         _ => return Err(CompilerError::Unsupported(format!("unknown nullary op: {raw}"))),
     };
     Ok(Expr::Nullary(op))
@@ -852,13 +1563,17 @@ fn parse_nullary(raw: &str) -> Result<Expr, CompilerError> {
 fn parse_introspection(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
     let text = pair.as_str();
     let mut inner = pair.into_inner();
-    let index_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing introspection index".to_string()))?;
+    // `input.<field>` is shorthand for `tx.inputs[this.activeInputIndex].<field>`.
+    let index = if text.starts_with("input") {
+        Box::new(Expr::Nullary(NullaryOp::ActiveInputIndex))
+    } else {
+        let index_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing introspection index".to_string()))?;
+        Box::new(parse_expression(index_pair)?)
+    };
     let field_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing introspection field".to_string()))?;
-
-    let index = Box::new(parse_expression(index_pair)?);
     let field = field_pair.as_str();
 
-    let kind = if text.starts_with("tx.inputs") {
+    let kind = if text.starts_with("tx.inputs") || text.starts_with("input") {
         match field {
             ".value" => IntrospectionKind::InputValue,
             ".lockingBytecode" => IntrospectionKind::InputLockingBytecode,
@@ -881,6 +1596,27 @@ fn single_inner(pair: Pair<'_, Rule>) -> Result<Pair<'_, Rule>, CompilerError> {
     pair.into_inner().next().ok_or_else(|| CompilerError::Unsupported("expected inner pair".to_string()))
 }
 
+fn parse_conditional(pair: Pair<'_, Rule>) -> Result<Expr, CompilerError> {
+    let expr_span = SourceSpan::from_span(pair.as_span());
+    let mut inner = pair.into_inner();
+    let condition = inner.next().ok_or_else(|| CompilerError::Unsupported("missing ternary condition".to_string()))?;
+    let Some(then_pair) = inner.next() else {
+        return parse_expression(condition);
+    };
+    let else_pair = inner.next().ok_or_else(|| CompilerError::Unsupported("missing ternary else branch".to_string()))?;
+    let spans = TernarySpans {
+        expr: expr_span,
+        then_expr: SourceSpan::from_span(then_pair.as_span()),
+        else_expr: SourceSpan::from_span(else_pair.as_span()),
+    };
+    Ok(Expr::IfElse {
+        condition: Box::new(parse_expression(condition)?),
+        then_expr: Box::new(parse_expression(then_pair)?),
+        else_expr: Box::new(parse_expression(else_pair)?),
+        spans: Some(spans),
+    })
+}
+
 fn parse_infix<F, G>(pair: Pair<'_, Rule>, mut parse_operand: F, mut map_op: G) -> Result<Expr, CompilerError>
 where
     F: FnMut(Pair<'_, Rule>) -> Result<Expr, CompilerError>,
@@ -946,6 +1682,13 @@ fn map_equality(pair: Pair<'_, Rule>) -> Result<BinaryOp, CompilerError> {
     }
 }
 
+fn map_membership(pair: Pair<'_, Rule>) -> Result<BinaryOp, CompilerError> {
+    match pair.as_rule() {
+        Rule::membership_op => Ok(BinaryOp::In),
+        _ => Err(CompilerError::Unsupported("unexpected membership operator".to_string())),
+    }
+}
+
 fn map_comparison(pair: Pair<'_, Rule>) -> Result<BinaryOp, CompilerError> {
     match pair.as_rule() {
         Rule::comparison_op => match pair.as_str() {
@@ -0,0 +1,132 @@
+use super::{ConsoleArg, ContractAst, Expr, FunctionAst, Statement, StatementKind};
+
+/// Read-only callbacks for `walk_contract`. Every method defaults to doing nothing, so an analysis
+/// only overrides the node kinds it cares about.
+pub trait Visitor {
+    /// A contract constant, visited before any function. Its value is walked afterwards.
+    fn visit_constant(&mut self, _name: &str, _value: &Expr) {}
+
+    fn visit_function(&mut self, _function: &FunctionAst) {}
+
+    /// Every statement, including those nested in `if` and `for` bodies.
+    fn visit_statement(&mut self, _statement: &Statement) {}
+
+    /// Every expression node, parents before their children.
+    fn visit_expr(&mut self, _expr: &Expr) {}
+}
+
+/// Collects the name of every `Expr::Identifier` visited, in visiting order.
+#[derive(Default)]
+pub struct Identifiers(pub Vec<String>);
+
+impl Visitor for Identifiers {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Identifier(name) = expr {
+            self.0.push(name.clone());
+        }
+    }
+}
+
+/// Walks constants (by name), then functions in declaration order, calling `visitor` on every node.
+pub fn walk_contract<V: Visitor + ?Sized>(contract: &ContractAst, visitor: &mut V) {
+    let mut constants: Vec<_> = contract.constants.iter().collect();
+    constants.sort_by(|(left, _), (right, _)| left.cmp(right));
+    for (name, value) in constants {
+        visitor.visit_constant(name, value);
+        walk_expr(value, visitor);
+    }
+    for function in &contract.functions {
+        walk_function(function, visitor);
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(function: &FunctionAst, visitor: &mut V) {
+    visitor.visit_function(function);
+    walk_statements(&function.body, visitor);
+}
+
+pub fn walk_statements<V: Visitor + ?Sized>(statements: &[Statement], visitor: &mut V) {
+    for statement in statements {
+        walk_statement(statement, visitor);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(statement: &Statement, visitor: &mut V) {
+    visitor.visit_statement(statement);
+    match &statement.kind {
+        StatementKind::Console { args } => {
+            for arg in args {
+                match arg {
+                    ConsoleArg::Identifier(name) => walk_expr(&Expr::Identifier(name.clone()), visitor),
+                    ConsoleArg::Literal(expr) => walk_expr(expr, visitor),
+                }
+            }
+        }
+        _ => statement_exprs(statement).into_iter().for_each(|expr| walk_expr(expr, visitor)),
+    }
+    match &statement.kind {
+        StatementKind::If { then_branch, else_branch, .. } => {
+            walk_statements(then_branch, visitor);
+            if let Some(else_branch) = else_branch {
+                walk_statements(else_branch, visitor);
+            }
+        }
+        StatementKind::For { body, .. } => walk_statements(body, visitor),
+        _ => {}
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(expr: &Expr, visitor: &mut V) {
+    visitor.visit_expr(expr);
+    expr_children(expr).into_iter().for_each(|child| walk_expr(child, visitor));
+}
+
+/// The expressions `statement` itself evaluates, in source order. Nested `if` and `for` bodies are
+/// left out, and so are `console.log` identifiers, which are not `Expr` nodes.
+pub fn statement_exprs(statement: &Statement) -> Vec<&Expr> {
+    match &statement.kind {
+        StatementKind::VariableDefinition { expr, .. } => expr.iter().collect(),
+        StatementKind::Let { expr, .. }
+        | StatementKind::TupleAssignment { expr, .. }
+        | StatementKind::ArrayPush { expr, .. }
+        | StatementKind::Assign { expr, .. }
+        | StatementKind::TimeOp { expr, .. }
+        | StatementKind::Require { expr, .. }
+        | StatementKind::Yield { expr }
+        | StatementKind::WarnIf { condition: expr, .. } => vec![expr],
+        StatementKind::FunctionCall { args, .. } | StatementKind::FunctionCallAssign { args, .. } | StatementKind::Log { args } => {
+            args.iter().collect()
+        }
+        StatementKind::Return { exprs } => exprs.iter().collect(),
+        StatementKind::If { condition, .. } => vec![condition],
+        StatementKind::For { start, end, .. } => vec![start, end],
+        StatementKind::Console { args } => args
+            .iter()
+            .filter_map(|arg| match arg {
+                ConsoleArg::Identifier(_) => None,
+                ConsoleArg::Literal(expr) => Some(expr),
+            })
+            .collect(),
+    }
+}
+
+/// The direct subexpressions of `expr`, in the order `walk_expr` visits them.
+pub fn expr_children(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::Int(_)
+        | Expr::Bool(_)
+        | Expr::Bytes(_)
+        | Expr::String(_)
+        | Expr::Identifier(_)
+        | Expr::Nullary(_)
+        | Expr::Decimal { .. } => Vec::new(),
+        Expr::Array(items) | Expr::Call { args: items, .. } | Expr::New { args: items, .. } => items.iter().collect(),
+        Expr::Split { source, index, .. } | Expr::ArrayIndex { source, index } => vec![&**source, &**index],
+        Expr::Slice { source, start, end } => vec![&**source, &**start, &**end],
+        Expr::Unary { expr, .. } => vec![&**expr],
+        Expr::Binary { left, right, .. } => vec![&**left, &**right],
+        Expr::IfElse { condition, then_expr, else_expr, .. } => vec![&**condition, &**then_expr, &**else_expr],
+        Expr::Introspection { index, .. } => vec![&**index],
+        Expr::Map { entries, default, .. } => entries.iter().flat_map(|(key, value)| [key, value]).chain(default.as_deref()).collect(),
+    }
+}
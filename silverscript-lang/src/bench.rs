@@ -0,0 +1,163 @@
+//! Micro-benchmarks of parse, compile and trace throughput over a few representative contracts.
+//!
+//! Only built with the `bench` feature, so the default build stays lean. `silverc --bench` prints
+//! the report for the bundled samples.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use crate::ast::{Expr, parse_contract_ast};
+use crate::compiler::{CompileOptions, compile_contract};
+use crate::debug::simulate::{TraceMeta, record_trace};
+
+/// A contract with the inputs needed to compile it and trace one of its entrypoints.
+#[derive(Debug, Clone)]
+pub struct BenchSample {
+    pub name: &'static str,
+    pub source: &'static str,
+    pub function: &'static str,
+    pub ctor_args: Vec<Expr>,
+    pub args: Vec<Expr>,
+    /// Value of the spent UTXO, for samples that read `input.value`.
+    pub utxo_value: Option<u64>,
+}
+
+/// Operations per second of each phase for one sample.
+///
+/// The trace phase is the whole `record_trace` pipeline: parsing, compiling with debug info and
+/// executing, so it is always the slowest of the three.
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub sample: &'static str,
+    pub parse_per_sec: f64,
+    pub compile_per_sec: f64,
+    pub trace_per_sec: f64,
+}
+
+const BASIC: &str = r#"
+contract Basic(int threshold) {
+    entrypoint function spend(int value) {
+        require(value >= threshold);
+    }
+}
+"#;
+
+const SUM_SERIES: &str = r#"
+contract SumSeries(int maxIterations) {
+    entrypoint function main(int n) {
+        require(n >= 0);
+        require(n <= maxIterations);
+        int s = 0;
+        for(i, 1, maxIterations + 1) {
+            if(i <= n) {
+                s = s + i;
+            }
+        }
+        require(s == n * (n + 1) / 2);
+    }
+}
+"#;
+
+const SPLITTER: &str = r#"
+contract Splitter(bytes4 tag) {
+    entrypoint function check(bytes payload, int cut) {
+        require(payload.length > cut);
+        bytes prefix = payload.slice(0, cut);
+        require(prefix == tag);
+        log("rest", payload.slice(cut, payload.length));
+    }
+
+    entrypoint function other(int a) {
+        require(a == 1);
+    }
+}
+"#;
+
+const KEEP_VALUE: &str = r#"
+contract KeepValue(int fee) {
+    entrypoint function spend() {
+        require(tx.outputs[0].value >= input.value - fee);
+        require(tx.outputs[0].lockingBytecode == input.lockingBytecode);
+    }
+}
+"#;
+
+/// Small contracts covering plain checks, unrolled loops, byte handling with logs, and introspection.
+pub fn bundled_samples() -> Vec<BenchSample> {
+    vec![
+        BenchSample {
+            name: "basic",
+            source: BASIC,
+            function: "spend",
+            ctor_args: vec![Expr::Int(10)],
+            args: vec![Expr::Int(42)],
+            utxo_value: None,
+        },
+        BenchSample {
+            name: "sum_series",
+            source: SUM_SERIES,
+            function: "main",
+            ctor_args: vec![Expr::Int(16)],
+            args: vec![Expr::Int(12)],
+            utxo_value: None,
+        },
+        BenchSample {
+            name: "splitter",
+            source: SPLITTER,
+            function: "check",
+            ctor_args: vec![Expr::Bytes(vec![0xde, 0xad, 0xbe, 0xef])],
+            args: vec![Expr::Bytes(vec![0xde, 0xad, 0xbe, 0xef, 0x01, 0x02]), Expr::Int(4)],
+            utxo_value: None,
+        },
+        BenchSample {
+            name: "keep_value",
+            source: KEEP_VALUE,
+            function: "spend",
+            ctor_args: vec![Expr::Int(0)],
+            args: vec![],
+            utxo_value: Some(100_000),
+        },
+    ]
+}
+
+/// Runs each phase of every sample `iterations` times. A sample that fails to compile or trace is
+/// an error rather than a fast but meaningless number.
+pub fn run_bench(samples: &[BenchSample], iterations: usize) -> Result<Vec<BenchResult>, String> {
+    samples.iter().map(|sample| bench_sample(sample, iterations.max(1))).collect()
+}
+
+fn bench_sample(sample: &BenchSample, iterations: usize) -> Result<BenchResult, String> {
+    let options = CompileOptions::default();
+    let meta = TraceMeta {
+        source: sample.source.to_string(),
+        function: sample.function.to_string(),
+        ctor_args: sample.ctor_args.clone(),
+        args: sample.args.clone(),
+        utxo_value: sample.utxo_value,
+        ..Default::default()
+    };
+    let trace = record_trace(meta.clone()).map_err(|err| format!("{}: {err}", sample.name))?;
+    if let Some(err) = trace.error {
+        return Err(format!("{}: sample spend fails: {err}", sample.name));
+    }
+
+    let parse_per_sec = ops_per_sec(iterations, || {
+        black_box(parse_contract_ast(sample.source).is_ok());
+    });
+    let compile_per_sec = ops_per_sec(iterations, || {
+        black_box(compile_contract(sample.source, &sample.ctor_args, options).is_ok());
+    });
+    let trace_per_sec = ops_per_sec(iterations, || {
+        black_box(record_trace(meta.clone()).is_ok());
+    });
+    Ok(BenchResult { sample: sample.name, parse_per_sec, compile_per_sec, trace_per_sec })
+}
+
+fn ops_per_sec(iterations: usize, mut op: impl FnMut()) -> f64 {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        op();
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    if elapsed > 0.0 { iterations as f64 / elapsed } else { f64::INFINITY }
+}
@@ -7,11 +7,11 @@ use silverscript_lang::ast::Expr;
 #[command(
     name = "sil-debug",
     about = "Debug a SilverScript contract",
-    after_help = "Examples:\n  # constructor (int x, int y), function hello(int a, int b)\n  sil-debug if_statement.sil --function hello --ctor-arg 3 --ctor-arg 10 --arg 1 --arg 2\n\nValue formats:\n  int:        123 (or 0x7b)\n  bool:       true|false\n  string:     hello (shell quoting handles spaces)\n  bytes*:     0xdeadbeef"
+    after_help = "Examples:\n  # constructor (int x, int y), function hello(int a, int b)\n  sil-debug if_statement.sil --function hello --ctor-arg 3 --ctor-arg 10 --arg 1 --arg 2\n\nValue formats:\n  int:        123 (or 0x7b)\n  bool:       true|false\n  string:     hello (shell quoting handles spaces)\n  bytes*:     0xdeadbeef (bare hex is accepted unless --strict-hex)\n  arrays:     [\"0x01\",\"0x02\"] or 0x01,0x02 (one value per element)\n\nPrefixes (override the default for the declared type):\n  hex:ff      byte-typed params; hex even with --strict-hex\n  num:42      int and byte-typed params; bytesN gets the N-byte little-endian script number (NUM2BIN)"
 )]
 pub struct DebugCliArgs {
-    #[arg(value_name = "contract.sil")]
-    pub script_path: String,
+    #[arg(value_name = "contract.sil", required_unless_present = "replay_path")]
+    pub script_path: Option<String>,
     #[arg(long = "no-selector")]
     pub without_selector: bool,
     #[arg(short = 'f', long = "function")]
@@ -20,6 +20,34 @@ pub struct DebugCliArgs {
     pub raw_ctor_args: Vec<String>,
     #[arg(short = 'a', long = "arg", value_name = "value", allow_hyphen_values = true)]
     pub raw_args: Vec<String>,
+    /// Record a non-interactive execution trace to this JSON file and exit.
+    #[arg(long = "save-trace", value_name = "trace.json")]
+    pub save_trace_path: Option<String>,
+    /// Record a trace like `--save-trace`, but write it as a standalone HTML viewer page.
+    #[arg(long = "out-html", value_name = "trace.html")]
+    pub out_html_path: Option<String>,
+    /// Debug info kept in a recorded trace: `lines` keeps one mapping per source line and drops locals
+    /// and logs, which keeps traces of large contracts small.
+    #[arg(long = "debug-level", value_name = "level", value_parser = ["full", "lines"], default_value = "full")]
+    pub debug_level: String,
+    /// Recompile and re-run a saved trace, reporting whether the outcome still matches.
+    #[arg(long = "replay", value_name = "trace.json", conflicts_with_all = ["save_trace_path", "out_html_path"])]
+    pub replay_path: Option<String>,
+    /// Run the same function and args against a second contract and report the first differing step.
+    #[arg(long = "diff-trace", value_name = "other.sil", conflicts_with_all = ["save_trace_path", "out_html_path", "replay_path"])]
+    pub diff_trace_path: Option<String>,
+    /// Require a `0x` prefix on every byte-typed argument instead of accepting bare hex.
+    #[arg(long = "strict-hex")]
+    pub strict_hex: bool,
+    /// Compile for and run on an engine without covenant support, to test fallback paths.
+    #[arg(long = "no-covenants")]
+    pub no_covenants: bool,
+    /// Keep the states before the last <depth> opcodes so `back` can step backwards in the REPL.
+    #[arg(long = "journal", value_name = "depth")]
+    pub journal_depth: Option<usize>,
+    /// Suppress informational output; errors are still reported on stderr.
+    #[arg(short = 'q', long = "quiet")]
+    pub quiet: bool,
 }
 
 pub fn parse_cli_args_or_help(bin_name: &str) -> Result<Option<DebugCliArgs>, Box<dyn Error>> {
@@ -46,9 +74,23 @@ fn parse_int_arg(raw: &str) -> Result<i64, Box<dyn Error>> {
     Ok(cleaned.parse::<i64>()?)
 }
 
-fn parse_hex_bytes(raw: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+/// Decimal such as `1.50` for a `@scale` param; the compiler scales it to the param's scale.
+fn parse_decimal_arg(raw: &str) -> Result<Expr, Box<dyn Error>> {
+    let cleaned = raw.replace('_', "");
+    let (whole, fraction) = cleaned.split_once('.').expect("checked by caller");
+    if fraction.is_empty() || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(format!("invalid decimal '{raw}'").into());
+    }
+    Ok(Expr::Decimal { digits: format!("{whole}{fraction}").parse()?, scale: fraction.len() as u32 })
+}
+
+fn parse_hex_bytes(raw: &str, strict_hex: bool) -> Result<Vec<u8>, Box<dyn Error>> {
     let trimmed = raw.trim();
-    let hex_str = trimmed.strip_prefix("0x").or_else(|| trimmed.strip_prefix("0X")).unwrap_or(trimmed);
+    let hex_str = match trimmed.strip_prefix("0x").or_else(|| trimmed.strip_prefix("0X")) {
+        Some(hex_str) => hex_str,
+        None if strict_hex => return Err(format!("byte argument '{trimmed}' must start with 0x (--strict-hex)").into()),
+        None => trimmed,
+    };
     if hex_str.is_empty() {
         return Ok(vec![]);
     }
@@ -59,31 +101,95 @@ fn parse_hex_bytes(raw: &str) -> Result<Vec<u8>, Box<dyn Error>> {
     Ok(decoded)
 }
 
-pub fn parse_typed_arg(type_name: &str, raw: &str) -> Result<Expr, Box<dyn Error>> {
-    // Support array inputs until the LSP exists by allowing:
-    // - JSON arrays: [1,2,3] or ["0x01","0x02"]
-    // - raw hex bytes: 0x... (treated as encoded array bytes)
+fn is_byte_type(type_name: &str) -> bool {
+    matches!(type_name, "bytes" | "byte" | "sig" | "datasig" | "pubkey")
+        || type_name.strip_prefix("bytes").is_some_and(|size| size.parse::<usize>().is_ok())
+}
+
+/// Little-endian sign-magnitude script number; with `size`, zero-padded to exactly that many bytes
+/// with the sign bit moved to the last byte, matching `OpNum2Bin`.
+fn encode_script_num(value: i64, size: Option<usize>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    let mut magnitude = value.unsigned_abs();
+    while magnitude > 0 {
+        bytes.push((magnitude & 0xff) as u8);
+        magnitude >>= 8;
+    }
+    let needs_sign_byte = bytes.last().is_some_and(|last| last & 0x80 != 0);
+    match size {
+        Some(size) => {
+            if bytes.len() + needs_sign_byte as usize > size {
+                return Err(format!("{value} does not fit in bytes{size}").into());
+            }
+            bytes.resize(size, 0);
+        }
+        None if needs_sign_byte => bytes.push(0),
+        None => {}
+    }
+    if value < 0 {
+        *bytes.last_mut().expect("negative values are non-empty") |= 0x80;
+    }
+    Ok(bytes)
+}
+
+/// Parses a CLI value for `type_name`. With `strict_hex`, byte-typed values must carry a `0x` prefix.
+///
+/// A `hex:` or `num:` prefix overrides the default reading of a byte-typed value: `hex:ff` is
+/// always hex, and `num:42` is the script number encoding of 42, padded to the declared width for
+/// `bytesN` as `OpNum2Bin` would. `num:` is also accepted for `int`.
+///
+/// Array values are given as a JSON array (`[1,2,3]`, `["0x01","0x02"]`) or a comma-separated
+/// list (`0x01,0x02`); each element is parsed as the element type. A single value without commas
+/// is taken as the already-encoded array bytes.
+pub fn parse_typed_arg(type_name: &str, raw: &str, strict_hex: bool) -> Result<Expr, Box<dyn Error>> {
     if let Some(element_type) = type_name.strip_suffix("[]") {
         let trimmed = raw.trim();
-        if trimmed.starts_with('[') {
+        let elements: Vec<String> = if trimmed.starts_with('[') {
             let values = serde_json::from_str::<Vec<serde_json::Value>>(trimmed)?;
-            let mut out = Vec::with_capacity(values.len());
-            for v in values {
-                let expr = match v {
-                    serde_json::Value::Number(n) => Expr::Int(n.as_i64().ok_or("invalid int in array")?),
-                    serde_json::Value::Bool(b) => Expr::Bool(b),
-                    serde_json::Value::String(s) => parse_typed_arg(element_type, &s)?,
-                    _ => return Err("unsupported array element (expected number/bool/string)".into()),
-                };
-                out.push(expr);
+            let mut elements = Vec::with_capacity(values.len());
+            for (index, value) in values.into_iter().enumerate() {
+                elements.push(match value {
+                    serde_json::Value::Number(n) => n.to_string(),
+                    serde_json::Value::Bool(b) => b.to_string(),
+                    serde_json::Value::String(s) => s,
+                    _ => return Err(format!("{type_name} element {index}: expected number, bool or string").into()),
+                });
             }
-            return Ok(Expr::Array(out));
+            elements
+        } else if trimmed.contains(',') {
+            trimmed.split(',').map(|element| element.trim().to_string()).collect()
+        } else {
+            return Ok(Expr::Bytes(parse_hex_bytes(trimmed, strict_hex)?));
+        };
+        let mut out = Vec::with_capacity(elements.len());
+        for (index, element) in elements.iter().enumerate() {
+            let expr = parse_typed_arg(element_type, element, strict_hex)
+                .map_err(|err| format!("{type_name} element {index} ('{element}'): {err}"))?;
+            out.push(expr);
         }
-        // If not JSON, accept hex bytes for already-encoded arrays.
-        return Ok(Expr::Bytes(parse_hex_bytes(trimmed)?));
+        return Ok(Expr::Array(out));
+    }
+
+    if let Some(hex) = raw.strip_prefix("hex:") {
+        if !is_byte_type(type_name) {
+            return Err(format!("hex: prefix is only valid for byte-typed params, not {type_name}").into());
+        }
+        return parse_typed_arg(type_name, &format!("0x{}", hex.trim()), strict_hex);
+    }
+    if let Some(number) = raw.strip_prefix("num:") {
+        let value = parse_int_arg(number.trim())?;
+        return match type_name {
+            "int" => Ok(Expr::Int(value)),
+            "bytes" => Ok(Expr::Bytes(encode_script_num(value, None)?)),
+            other => match other.strip_prefix("bytes").and_then(|v| v.parse::<usize>().ok()) {
+                Some(size) => Ok(Expr::Bytes(encode_script_num(value, Some(size))?)),
+                None => Err(format!("num: prefix is only valid for int, bytes and bytesN params, not {other}").into()),
+            },
+        };
     }
 
     match type_name {
+        "int" if raw.contains('.') => parse_decimal_arg(raw),
         "int" => Ok(Expr::Int(parse_int_arg(raw)?)),
         "bool" => match raw {
             "true" => Ok(Expr::Bool(true)),
@@ -91,10 +197,17 @@ pub fn parse_typed_arg(type_name: &str, raw: &str) -> Result<Expr, Box<dyn Error
             _ => Err(format!("invalid bool '{raw}' (expected true/false)").into()),
         },
         "string" => Ok(Expr::String(raw.to_string())),
-        "bytes" | "byte" | "pubkey" | "sig" | "datasig" => Ok(Expr::Bytes(parse_hex_bytes(raw)?)),
+        "pubkey" => {
+            let bytes = parse_hex_bytes(raw, strict_hex)?;
+            if bytes.len() != 32 {
+                return Err(format!("pubkey expects 32 bytes, got {}", bytes.len()).into());
+            }
+            Ok(Expr::Bytes(bytes))
+        }
+        "bytes" | "byte" | "sig" | "datasig" => Ok(Expr::Bytes(parse_hex_bytes(raw, strict_hex)?)),
         other => {
             if let Some(size) = other.strip_prefix("bytes").and_then(|v| v.parse::<usize>().ok()) {
-                let bytes = parse_hex_bytes(raw)?;
+                let bytes = parse_hex_bytes(raw, strict_hex)?;
                 if bytes.len() != size {
                     return Err(format!("{other} expects {size} bytes, got {}", bytes.len()).into());
                 }
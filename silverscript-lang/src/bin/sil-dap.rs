@@ -0,0 +1,511 @@
+//! Debug Adapter Protocol bridge: lets an IDE drive a `DebugSession` over stdin/stdout.
+//!
+//! The adapter debugs one spend per session. `launch` takes the contract path, the function and
+//! its arguments as strings in the same formats `sil-debug` accepts, and the session stops at the
+//! first statement once configuration is done.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+
+use kaspa_consensus_core::hashing::sighash::SigHashReusedValuesUnsync;
+use kaspa_txscript::caches::Cache;
+use kaspa_txscript::{EngineCtx, EngineFlags};
+use kaspa_txscript_errors::TxScriptError;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use silverscript_lang::ast::parse_contract_ast;
+use silverscript_lang::compiler::{CompileOptions, CompiledContract, compile_contract};
+use silverscript_lang::debug::session::{DebugEngine, DebugSession, SessionState};
+
+// Only the argument parsing is shared with sil-debug; its clap definitions go unused here.
+#[allow(dead_code)]
+mod common;
+
+/// Spends run on a single thread.
+const THREAD_ID: u64 = 1;
+const LOCALS_REFERENCE: u64 = 1;
+const CONSTANTS_REFERENCE: u64 = 2;
+const STACK_REFERENCE: u64 = 3;
+/// Largest message body the adapter will buffer; anything bigger is skipped.
+const MAX_MESSAGE_SIZE: usize = 4 << 20;
+
+/// `launch` request arguments.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LaunchConfig {
+    program: String,
+    /// Defaults to the first function in the ABI.
+    function: Option<String>,
+    #[serde(default)]
+    ctor_args: Vec<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    no_covenants: bool,
+    /// Stop at the first statement instead of running to the first breakpoint.
+    #[serde(default = "default_stop_on_entry")]
+    stop_on_entry: bool,
+}
+
+fn default_stop_on_entry() -> bool {
+    true
+}
+
+/// A compiled contract and the sigscript selecting the launched function, ready to debug.
+struct Launch {
+    config: LaunchConfig,
+    source: String,
+    compiled: CompiledContract,
+    sigscript: Vec<u8>,
+}
+
+/// Builds the spend the same way `sil-debug` does: constructor args baked into the script, function
+/// args pushed by the sigscript.
+fn prepare_launch(config: LaunchConfig) -> Result<Launch, Box<dyn Error>> {
+    let source = fs::read_to_string(&config.program)?;
+    let parsed_contract = parse_contract_ast(&source)?;
+    if parsed_contract.params.len() != config.ctor_args.len() {
+        return Err(format!(
+            "constructor expects {} arguments, got {}: {}",
+            parsed_contract.params.len(),
+            config.ctor_args.len(),
+            parsed_contract.constructor_signature()
+        )
+        .into());
+    }
+    let mut ctor_args = Vec::with_capacity(config.ctor_args.len());
+    for (param, raw) in parsed_contract.params.iter().zip(config.ctor_args.iter()) {
+        ctor_args.push(common::parse_typed_arg(&param.type_name, raw, false)?);
+    }
+
+    let compile_opts = CompileOptions { record_debug_infos: true, covenants_disabled: config.no_covenants, ..Default::default() };
+    let compiled = compile_contract(&source, &ctor_args, compile_opts)?;
+    let default_name = compiled.abi.first().map(|entry| entry.name.clone()).ok_or("contract has no functions")?;
+    let function_name = config.function.clone().unwrap_or(default_name);
+    let entry = compiled
+        .abi
+        .iter()
+        .find(|entry| entry.name == function_name)
+        .ok_or_else(|| format!("function '{function_name}' not found"))?;
+    if entry.inputs.len() != config.args.len() {
+        return Err(entry.arg_count_mismatch(config.args.len()).into());
+    }
+    let mut args = Vec::with_capacity(config.args.len());
+    for (input, raw) in entry.inputs.iter().zip(config.args.iter()) {
+        args.push(common::parse_typed_arg(&input.type_name, raw, false)?);
+    }
+    let sigscript = compiled.build_sig_script(&function_name, args)?;
+    Ok(Launch { config, source, compiled, sigscript })
+}
+
+/// Reads and writes `Content-Length` framed DAP messages.
+struct Connection<R, W> {
+    reader: R,
+    writer: W,
+    seq: u64,
+}
+
+impl<R: BufRead, W: Write> Connection<R, W> {
+    /// Returns `None` once the client closes its end. Oversized or malformed messages are answered
+    /// with an error response and skipped.
+    fn read_message(&mut self) -> io::Result<Option<Value>> {
+        loop {
+            let mut length = None;
+            loop {
+                let mut line = String::new();
+                if self.reader.read_line(&mut line)? == 0 {
+                    return Ok(None);
+                }
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(value) = line.strip_prefix("Content-Length:") {
+                    length = value.trim().parse::<usize>().ok();
+                }
+            }
+            let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header"))?;
+            if length > MAX_MESSAGE_SIZE {
+                io::copy(&mut (&mut self.reader).take(length as u64), &mut io::sink())?;
+                self.reject(format!("message of {length} bytes exceeds the {MAX_MESSAGE_SIZE} byte limit"))?;
+                continue;
+            }
+            let mut body = vec![0u8; length];
+            self.reader.read_exact(&mut body)?;
+            match serde_json::from_slice(&body) {
+                Ok(message) => return Ok(Some(message)),
+                Err(err) => self.reject(format!("malformed message: {err}"))?,
+            }
+        }
+    }
+
+    /// Answers a message that could not be read as a request, so it has no `seq` to refer to.
+    fn reject(&mut self, message: String) -> io::Result<()> {
+        self.respond(&json!({ "seq": 0, "command": "" }), Err(message))
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        let body = message.to_string();
+        write!(self.writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        self.writer.flush()
+    }
+
+    fn respond(&mut self, request: &Value, result: Result<Value, String>) -> io::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+        self.send(response)
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn output(&mut self, category: &str, text: String) -> io::Result<()> {
+        self.event("output", json!({ "category": category, "output": format!("{text}\n") }))
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsConditionalBreakpoints": true,
+        "supportsEvaluateForHovers": true,
+        "supportsRestartRequest": true,
+        "supportsSteppingGranularity": true,
+    })
+}
+
+/// Handles requests until a `launch` succeeds. Returns `None` if the client disconnects first.
+fn await_launch<R: BufRead, W: Write>(connection: &mut Connection<R, W>) -> Result<Option<Launch>, Box<dyn Error>> {
+    while let Some(request) = connection.read_message()? {
+        match request["command"].as_str().unwrap_or_default() {
+            "initialize" => connection.respond(&request, Ok(capabilities()))?,
+            "launch" => {
+                let launch = serde_json::from_value::<LaunchConfig>(request["arguments"].clone())
+                    .map_err(|err| -> Box<dyn Error> { err.into() })
+                    .and_then(prepare_launch);
+                match launch {
+                    Ok(launch) => {
+                        connection.respond(&request, Ok(json!({})))?;
+                        return Ok(Some(launch));
+                    }
+                    Err(err) => connection.respond(&request, Err(format!("launch failed: {err}")))?,
+                }
+            }
+            "disconnect" | "terminate" => {
+                connection.respond(&request, Ok(json!({})))?;
+                return Ok(None);
+            }
+            command => connection.respond(&request, Err(format!("'{command}' requires a launched program")))?,
+        }
+    }
+    Ok(None)
+}
+
+/// Drives a launched session. The client configures breakpoints after the `initialized` event and
+/// starts execution with `configurationDone`.
+struct Adapter<'s, 'a> {
+    session: &'s mut DebugSession<'a>,
+    config: LaunchConfig,
+    reported_logs: usize,
+    reported_warnings: usize,
+}
+
+/// Why `Adapter::serve` returned.
+enum Served {
+    Disconnected,
+    /// `restart` recompiled the program; the caller starts a new session from it.
+    Restart(Launch),
+}
+
+/// How a run or step ended.
+enum Stop {
+    At(&'static str),
+    Finished,
+    Failed(String),
+}
+
+impl Stop {
+    fn after(result: Result<Option<SessionState>, TxScriptError>, reason: &'static str) -> Self {
+        match result {
+            Ok(Some(_)) => Stop::At(reason),
+            Ok(None) => Stop::Finished,
+            Err(err) => Stop::Failed(err.to_string()),
+        }
+    }
+}
+
+impl Adapter<'_, '_> {
+    /// A restarted session keeps the client's configuration, so it starts running right away instead
+    /// of waiting for `configurationDone`.
+    fn serve<R: BufRead, W: Write>(&mut self, connection: &mut Connection<R, W>, restarted: bool) -> io::Result<Served> {
+        if restarted {
+            let stop = self.start();
+            self.report(connection, stop)?;
+        } else {
+            connection.event("initialized", json!({}))?;
+        }
+        while let Some(request) = connection.read_message()? {
+            let command = request["command"].as_str().unwrap_or_default().to_string();
+            let arguments = &request["arguments"];
+            let stop = match command.as_str() {
+                "configurationDone" => {
+                    connection.respond(&request, Ok(json!({})))?;
+                    self.start()
+                }
+                "next" | "stepIn" | "stepOut" | "continue" => {
+                    let body = if command == "continue" { json!({ "allThreadsContinued": true }) } else { json!({}) };
+                    connection.respond(&request, Ok(body))?;
+                    let by_opcode = arguments["granularity"] == "instruction";
+                    match command.as_str() {
+                        "next" | "stepIn" if by_opcode => Stop::after(self.session.step_opcode(), "step"),
+                        "next" => Stop::after(self.session.step_over(), "step"),
+                        "stepIn" => Stop::after(self.session.step_into(), "step"),
+                        "stepOut" => Stop::after(self.session.step_out(), "step"),
+                        _ => Stop::after(self.session.continue_to_breakpoint(), "breakpoint"),
+                    }
+                }
+                "restart" => match self.relaunch(arguments) {
+                    Ok(launch) => {
+                        connection.respond(&request, Ok(json!({})))?;
+                        return Ok(Served::Restart(launch));
+                    }
+                    Err(message) => {
+                        connection.respond(&request, Err(format!("restart failed: {message}")))?;
+                        continue;
+                    }
+                },
+                "disconnect" | "terminate" => {
+                    connection.respond(&request, Ok(json!({})))?;
+                    return Ok(Served::Disconnected);
+                }
+                _ => {
+                    let result = self.handle(&command, arguments);
+                    connection.respond(&request, result)?;
+                    continue;
+                }
+            };
+            self.report(connection, stop)?;
+        }
+        Ok(Served::Disconnected)
+    }
+
+    fn start(&mut self) -> Stop {
+        if self.config.stop_on_entry { Stop::At("entry") } else { Stop::after(self.session.continue_to_breakpoint(), "breakpoint") }
+    }
+
+    /// Recompiles the program, from the updated launch configuration if the client sends one. On
+    /// failure, e.g. a source edit that no longer compiles, the current session carries on.
+    fn relaunch(&self, arguments: &Value) -> Result<Launch, String> {
+        let config = match arguments.get("arguments") {
+            Some(config) => serde_json::from_value(config.clone()).map_err(|err| err.to_string())?,
+            None => self.config.clone(),
+        };
+        prepare_launch(config).map_err(|err| err.to_string())
+    }
+
+    /// The breakpoints and their conditions, to carry over to a restarted session.
+    fn breakpoints(&self) -> Vec<(u32, Option<String>)> {
+        let lines = self.session.breakpoints();
+        lines.into_iter().map(|line| (line, self.session.breakpoint_condition(line).map(str::to_string))).collect()
+    }
+
+    /// Requests that only inspect or configure the session.
+    fn handle(&mut self, command: &str, arguments: &Value) -> Result<Value, String> {
+        match command {
+            "initialize" => Ok(capabilities()),
+            "setBreakpoints" => Ok(self.set_breakpoints(arguments)),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "spend" }] })),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(self.scopes(arguments["frameId"].as_u64().unwrap_or_default())),
+            "variables" => self.variables(arguments["variablesReference"].as_u64().unwrap_or_default()),
+            "evaluate" => {
+                let expression = arguments["expression"].as_str().unwrap_or_default();
+                let watch = self.session.eval_expression(expression)?;
+                let result = self.session.format_watch(&watch);
+                Ok(json!({ "result": result, "type": watch.type_name, "variablesReference": 0 }))
+            }
+            command => Err(format!("unsupported request '{command}'")),
+        }
+    }
+
+    /// Forwards new log and warning output, then tells the client where execution stopped. A failed
+    /// opcode ends the session, since the engine cannot step past it.
+    fn report<R: BufRead, W: Write>(&mut self, connection: &mut Connection<R, W>, stop: Stop) -> io::Result<()> {
+        for entry in self.session.logs().iter().skip(self.reported_logs) {
+            connection.output("console", format!("[log] line {}: {}", entry.line, entry.values.join(" ")))?;
+        }
+        self.reported_logs = self.session.logs().len();
+        for warning in self.session.warnings().iter().skip(self.reported_warnings) {
+            connection.output("console", format!("[warn] line {}: {}", warning.line, warning.message))?;
+        }
+        self.reported_warnings = self.session.warnings().len();
+
+        match stop {
+            Stop::At(reason) => {
+                connection.event("stopped", json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }))
+            }
+            Stop::Finished => {
+                connection.event("terminated", json!({}))?;
+                connection.event("exited", json!({ "exitCode": 0 }))
+            }
+            Stop::Failed(message) => {
+                connection.output("stderr", format!("script failed: {message}"))?;
+                connection.event("terminated", json!({}))?;
+                connection.event("exited", json!({ "exitCode": 1 }))
+            }
+        }
+    }
+
+    /// Replaces every breakpoint; the adapter debugs a single source file.
+    fn set_breakpoints(&mut self, arguments: &Value) -> Value {
+        for line in self.session.breakpoints() {
+            self.session.clear_breakpoint(line);
+        }
+        let requested = arguments["breakpoints"].as_array().cloned().unwrap_or_default();
+        let breakpoints: Vec<Value> = requested
+            .iter()
+            .map(|breakpoint| {
+                let line = breakpoint["line"].as_u64().unwrap_or_default() as u32;
+                let result = match breakpoint["condition"].as_str().filter(|condition| !condition.trim().is_empty()) {
+                    Some(condition) => self.session.set_conditional_breakpoint(line, condition.to_string()),
+                    None if self.session.add_breakpoint(line) => Ok(()),
+                    None => Err(format!("no statement at line {line}")),
+                };
+                match result {
+                    Ok(()) => json!({ "verified": true, "line": line }),
+                    Err(message) => json!({ "verified": false, "line": line, "message": message }),
+                }
+            })
+            .collect();
+        json!({ "breakpoints": breakpoints })
+    }
+
+    /// Innermost frame first. `call_stack` names the inlined callees but not their call sites, so
+    /// only the innermost frame carries a position.
+    fn stack_trace(&self) -> Value {
+        let mut names = vec![self.session.current_function_name().unwrap_or("main").to_string()];
+        names.extend(self.session.call_stack());
+        let span = self.session.current_span();
+        let frames: Vec<Value> = names
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(id, name)| {
+                let (line, column) = match span {
+                    Some(span) if id == 0 => (span.line, span.col),
+                    _ => (0, 0),
+                };
+                json!({ "id": id, "name": name, "line": line, "column": column, "source": { "path": self.config.program } })
+            })
+            .collect();
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    fn scopes(&self, frame_id: u64) -> Value {
+        if frame_id != 0 {
+            return json!({ "scopes": [] });
+        }
+        json!({ "scopes": [
+            { "name": "Locals", "variablesReference": LOCALS_REFERENCE, "expensive": false },
+            { "name": "Constants", "variablesReference": CONSTANTS_REFERENCE, "expensive": false },
+            { "name": "Stack", "variablesReference": STACK_REFERENCE, "expensive": false },
+        ] })
+    }
+
+    fn variables(&self, reference: u64) -> Result<Value, String> {
+        let variables: Vec<Value> = match reference {
+            LOCALS_REFERENCE | CONSTANTS_REFERENCE => {
+                let (sequence, frame_id) =
+                    self.session.current_location().map(|mapping| (mapping.sequence, mapping.frame_id)).unwrap_or_default();
+                self.session
+                    .list_variables_at_sequence(sequence, frame_id)?
+                    .into_iter()
+                    .filter(|var| var.is_constant == (reference == CONSTANTS_REFERENCE))
+                    .map(|var| {
+                        let value = self.session.format_variable(&var);
+                        json!({ "name": var.name, "value": value, "type": var.type_name, "variablesReference": 0 })
+                    })
+                    .collect()
+            }
+            STACK_REFERENCE => {
+                let stack = self.session.stack();
+                stack
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(index, item)| json!({ "name": format!("[{index}]"), "value": item, "variablesReference": 0 }))
+                    .collect()
+            }
+            _ => return Err(format!("unknown variables reference {reference}")),
+        };
+        Ok(json!({ "variables": variables }))
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let stdin = io::stdin();
+    let mut connection = Connection { reader: stdin.lock(), writer: io::stdout(), seq: 0 };
+    let Some(mut launch) = await_launch(&mut connection)? else {
+        return Ok(());
+    };
+
+    let sig_cache = Cache::new(10_000);
+    let reused_values = SigHashReusedValuesUnsync::new();
+    let mut breakpoints = Vec::new();
+    let mut restarted = false;
+    loop {
+        let (served, kept_breakpoints) = {
+            let ctx = EngineCtx::new(&sig_cache).with_reused(&reused_values);
+            let engine = DebugEngine::new(ctx, EngineFlags { covenants_enabled: !launch.config.no_covenants });
+            let debug_info = launch.compiled.debug_info.clone();
+            // `launch` has already been answered, so a spend that cannot start ends the debug session
+            // with `terminated` instead.
+            let started = DebugSession::full(&launch.sigscript, &launch.compiled.script, &launch.source, debug_info, engine)
+                .and_then(|mut session| session.run_to_first_executed_statement().map(|_| session));
+            let mut session = match started {
+                Ok(session) => session,
+                Err(err) => {
+                    connection.output("stderr", format!("failed to start the spend: {err}"))?;
+                    connection.event("terminated", json!({}))?;
+                    return Ok(());
+                }
+            };
+            // Lines may have moved since the breakpoints were set; the ones that no longer hit a
+            // statement are dropped.
+            for (line, condition) in breakpoints {
+                match condition {
+                    Some(condition) => {
+                        let _ = session.set_conditional_breakpoint(line, condition);
+                    }
+                    None => {
+                        session.add_breakpoint(line);
+                    }
+                }
+            }
+
+            let mut adapter = Adapter { session: &mut session, config: launch.config.clone(), reported_logs: 0, reported_warnings: 0 };
+            (adapter.serve(&mut connection, restarted)?, adapter.breakpoints())
+        };
+        match served {
+            Served::Disconnected => return Ok(()),
+            Served::Restart(next) => {
+                launch = next;
+                breakpoints = kept_breakpoints;
+                restarted = true;
+            }
+        }
+    }
+}
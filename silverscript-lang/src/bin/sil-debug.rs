@@ -7,7 +7,12 @@ use kaspa_txscript::{EngineCtx, EngineFlags};
 
 use silverscript_lang::ast::parse_contract_ast;
 use silverscript_lang::compiler::{CompileOptions, compile_contract};
+use silverscript_lang::debug::DebugLevel;
+use silverscript_lang::debug::html::trace_to_html;
 use silverscript_lang::debug::session::{DebugEngine, DebugSession};
+use silverscript_lang::debug::simulate::{
+    ExecutionTrace, TraceMeta, TraceStep, first_divergence, record_trace, record_trace_at_level, replay_trace,
+};
 
 mod common;
 
@@ -41,13 +46,7 @@ fn show_vars(session: &DebugSession<'_>) {
             } else {
                 for var in variables {
                     let constant_suffix = if var.is_constant { " (const)" } else { "" };
-                    println!(
-                        "{}{} ({}) = {}",
-                        var.name,
-                        constant_suffix,
-                        var.type_name,
-                        session.format_value(&var.type_name, &var.value)
-                    );
+                    println!("{}{} ({}) = {}", var.name, constant_suffix, var.type_name, session.format_variable(&var));
                 }
             }
         }
@@ -60,9 +59,36 @@ fn show_step_view(session: &DebugSession<'_>) {
     show_vars(session);
 }
 
+fn report_invariant_violations(session: &DebugSession<'_>, reported: &mut usize) {
+    for violation in session.invariant_violations().iter().skip(*reported) {
+        println!("Invariant violated at line {}: {} ({})", violation.line, violation.condition, violation.reason);
+    }
+    *reported = session.invariant_violations().len();
+}
+
+fn report_logs(session: &DebugSession<'_>, reported: &mut usize) {
+    for entry in session.logs().iter().skip(*reported) {
+        println!("[log] line {}: {}", entry.line, entry.values.join(" "));
+    }
+    *reported = session.logs().len();
+}
+
+fn report_warnings(session: &DebugSession<'_>, reported: &mut usize) {
+    for warning in session.warnings().iter().skip(*reported) {
+        println!("[warn] line {}: {}", warning.line, warning.message);
+    }
+    *reported = session.warnings().len();
+}
+
 fn run_repl(session: &mut DebugSession<'_>) -> Result<(), kaspa_txscript_errors::TxScriptError> {
     let stdin = io::stdin();
+    let mut reported_violations = 0;
+    let mut reported_logs = 0;
+    let mut reported_warnings = 0;
     loop {
+        report_invariant_violations(session, &mut reported_violations);
+        report_logs(session, &mut reported_logs);
+        report_warnings(session, &mut reported_warnings);
         print!("{PROMPT}");
         io::stdout().flush().ok();
 
@@ -107,6 +133,10 @@ fn run_repl(session: &mut DebugSession<'_>) -> Result<(), kaspa_txscript_errors:
                     break;
                 }
             },
+            "back" => match session.step_back() {
+                Some(_) => show_step_view(session),
+                None => println!("Nothing to step back to; start with --journal <depth> to record history."),
+            },
             "c" | "continue" => match session.continue_to_breakpoint()? {
                 Some(_) => show_step_view(session),
                 None => {
@@ -114,24 +144,44 @@ fn run_repl(session: &mut DebugSession<'_>) -> Result<(), kaspa_txscript_errors:
                     break;
                 }
             },
+            "fail" | "run-to-failure" => {
+                match session.run_to_failure() {
+                    Some(failure) => {
+                        show_step_view(session);
+                        let statement = failure.statement.map(|text| format!(" at `{text}`")).unwrap_or_default();
+                        println!("Failed{statement}: {}", failure.message);
+                    }
+                    None => println!("Done. No check failed."),
+                }
+                break;
+            }
             "b" | "break" => {
                 if let Some(arg) = parts.next() {
-                    match arg.parse::<u32>() {
-                        Ok(line) => {
+                    let condition = cmd.split_once(" if ").map(|(_, condition)| condition.trim().to_string());
+                    match (arg.parse::<u32>(), condition) {
+                        (Ok(line), Some(condition)) => match session.set_conditional_breakpoint(line, condition) {
+                            Ok(()) => println!("Conditional breakpoint set at line {line}"),
+                            Err(err) => println!("ERROR: {err}"),
+                        },
+                        (Ok(line), None) => {
                             if session.add_breakpoint(line) {
                                 println!("Breakpoint set at line {line}");
                             } else {
                                 println!("Warning: no statement at line {line}, breakpoint not set");
                             }
                         }
-                        Err(_) => println!("Invalid line number."),
+                        (Err(_), _) => println!("Invalid line number."),
                     }
                 } else {
                     let lines = session.breakpoints();
                     if lines.is_empty() {
                         println!("No breakpoints set.");
                     } else {
-                        println!("Breakpoints: {}", lines.iter().map(|line| line.to_string()).collect::<Vec<_>>().join(", "));
+                        let describe = |line: &u32| match session.breakpoint_condition(*line) {
+                            Some(condition) => format!("{line} if {condition}"),
+                            None => line.to_string(),
+                        };
+                        println!("Breakpoints: {}", lines.iter().map(describe).collect::<Vec<_>>().join(", "));
                     }
                 }
             }
@@ -142,13 +192,7 @@ fn run_repl(session: &mut DebugSession<'_>) -> Result<(), kaspa_txscript_errors:
                     match session.variable_by_name(name) {
                         Ok(var) => {
                             let constant_suffix = if var.is_constant { " (const)" } else { "" };
-                            println!(
-                                "{}{} ({}) = {}",
-                                var.name,
-                                constant_suffix,
-                                var.type_name,
-                                session.format_value(&var.type_name, &var.value)
-                            );
+                            println!("{}{} ({}) = {}", var.name, constant_suffix, var.type_name, session.format_variable(&var));
                         }
                         Err(err) => println!("ERROR: {err}"),
                     }
@@ -156,30 +200,93 @@ fn run_repl(session: &mut DebugSession<'_>) -> Result<(), kaspa_txscript_errors:
                     println!("Usage: print <name>");
                 }
             }
+            "eval" | "e" => {
+                let source = cmd.split_once(char::is_whitespace).map(|(_, rest)| rest.trim()).unwrap_or("");
+                if source.is_empty() {
+                    println!("Usage: eval <expression>");
+                } else {
+                    match session.eval_expression(source) {
+                        Ok(watch) => {
+                            println!("{source} ({}) = {}", watch.type_name, session.format_watch(&watch))
+                        }
+                        Err(err) => println!("ERROR: {err}"),
+                    }
+                }
+            }
             "stack" => show_stack(session),
             "q" | "quit" => break,
             "help" | "h" | "?" => {
                 println!(
-                    "Commands: next/over (n), step/into (s), step opcode (si), finish/out, continue (c), break (b <line>), list (l), vars, print <name>, stack, quit (q)"
+                    "Commands: next/over (n), step/into (s), step opcode (si), back, finish/out, continue (c), fail, break (b <line> [if <expr>]), list (l), vars, print <name>, eval <expr>, stack, quit (q)"
                 )
             }
             _ => println!(
-                "Commands: next/over (n), step/into (s), step opcode (si), finish/out, continue (c), break (b <line>), list (l), vars, print <name>, stack, quit (q)"
+                "Commands: next/over (n), step/into (s), step opcode (si), back, finish/out, continue (c), fail, break (b <line> [if <expr>]), list (l), vars, print <name>, eval <expr>, stack, quit (q)"
             ),
         }
     }
     Ok(())
 }
 
+fn replay(trace_path: &str, quiet: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let saved: ExecutionTrace = serde_json::from_str(&fs::read_to_string(trace_path)?)?;
+    let report = replay_trace(&saved)?;
+    if report.matches {
+        if !quiet {
+            println!("Replay matches saved trace ({} steps).", saved.steps.len());
+        }
+        return Ok(());
+    }
+
+    eprintln!("Replay diverges from saved trace.");
+    if let Some(index) = report.divergence {
+        match saved.steps.get(index) {
+            Some(step) => eprintln!("First divergent step: {index} (expected {} at byte {})", step.opcode, step.byte_offset),
+            None => eprintln!("First divergent step: {index} (saved trace ended)"),
+        }
+    }
+    let describe = |error: &Option<String>| error.clone().unwrap_or_else(|| "passed".to_string());
+    eprintln!("Expected outcome: {}", describe(&report.expected_error));
+    eprintln!("Actual outcome: {}", describe(&report.actual_error));
+    Err("replay diverged".into())
+}
+
+fn diff_traces(left: &ExecutionTrace, right: &ExecutionTrace) -> Result<(), Box<dyn std::error::Error>> {
+    let describe_step = |step: &Option<TraceStep>| match step {
+        Some(step) => format!("{} at byte {} (stack: [{}])", step.opcode, step.byte_offset, step.stack.join(", ")),
+        None => "<trace ended>".to_string(),
+    };
+    match first_divergence(left, right) {
+        Some(divergence) => {
+            match left.source_steps.get(divergence.step_index).or(right.source_steps.get(divergence.step_index)) {
+                Some(step) => println!("Traces diverge at source step {} (line {}):", divergence.step_index, step.span.line),
+                None => println!("Traces diverge at source step {}:", divergence.step_index),
+            }
+            println!("  left:  {}", describe_step(&divergence.left));
+            println!("  right: {}", describe_step(&divergence.right));
+        }
+        None => println!("Traces run the same {} source steps.", left.source_steps.len()),
+    }
+    let describe_outcome = |error: &Option<String>| error.clone().unwrap_or_else(|| "passed".to_string());
+    println!("Left outcome: {}", describe_outcome(&left.error));
+    println!("Right outcome: {}", describe_outcome(&right.error));
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let Some(cli) = common::parse_cli_args_or_help("sil-debug")? else {
         return Ok(());
     };
-    let script_path = cli.script_path;
+    if let Some(replay_path) = cli.replay_path {
+        return replay(&replay_path, cli.quiet);
+    }
+    let script_path = cli.script_path.ok_or("missing contract path")?;
     let without_selector = cli.without_selector;
     let function_name = cli.function_name;
     let raw_ctor_args = cli.raw_ctor_args;
     let raw_args = cli.raw_args;
+    let strict_hex = cli.strict_hex;
+    let covenants_disabled = cli.no_covenants;
 
     let source = fs::read_to_string(&script_path)?;
     let parsed_contract = parse_contract_ast(&source)?;
@@ -190,15 +297,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     if parsed_contract.params.len() != raw_ctor_args.len() {
-        return Err(format!("constructor expects {} arguments, got {}", parsed_contract.params.len(), raw_ctor_args.len()).into());
+        return Err(format!(
+            "constructor expects {} arguments, got {}: {}",
+            parsed_contract.params.len(),
+            raw_ctor_args.len(),
+            parsed_contract.constructor_signature()
+        )
+        .into());
     }
 
     let mut ctor_args = Vec::with_capacity(raw_ctor_args.len());
     for (param, raw) in parsed_contract.params.iter().zip(raw_ctor_args.iter()) {
-        ctor_args.push(common::parse_typed_arg(&param.type_name, raw)?);
+        ctor_args.push(common::parse_typed_arg(&param.type_name, raw, strict_hex)?);
     }
 
-    let compile_opts = CompileOptions { record_debug_infos: true, ..Default::default() };
+    let compile_opts = CompileOptions { record_debug_infos: true, covenants_disabled, ..Default::default() };
     let compiled = compile_contract(&source, &ctor_args, compile_opts)?;
     let debug_info = compiled.debug_info.clone();
 
@@ -206,7 +319,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let reused_values = SigHashReusedValuesUnsync::new();
     let ctx = EngineCtx::new(&sig_cache).with_reused(&reused_values);
 
-    let flags = EngineFlags { covenants_enabled: true };
+    let flags = EngineFlags { covenants_enabled: !covenants_disabled };
     let engine = DebugEngine::new(ctx, flags);
 
     // Seed the stack like a real spend: run sigscript pushes before locking script.
@@ -219,19 +332,74 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .ok_or_else(|| format!("function '{selected_name}' not found"))?;
 
     if entry.inputs.len() != raw_args.len() {
-        return Err(format!("function '{selected_name}' expects {} arguments, got {}", entry.inputs.len(), raw_args.len()).into());
+        return Err(entry.arg_count_mismatch(raw_args.len()).into());
     }
 
     let mut typed_args = Vec::with_capacity(raw_args.len());
     for (input, raw) in entry.inputs.iter().zip(raw_args.iter()) {
-        typed_args.push(common::parse_typed_arg(&input.type_name, raw)?);
+        typed_args.push(common::parse_typed_arg(&input.type_name, raw, strict_hex)?);
+    }
+
+    if cli.save_trace_path.is_some() || cli.out_html_path.is_some() {
+        let meta = TraceMeta {
+            source: source.clone(),
+            function: selected_name,
+            ctor_args,
+            args: typed_args,
+            covenants_disabled,
+            ..Default::default()
+        };
+        let level = if cli.debug_level == "lines" { DebugLevel::Lines } else { DebugLevel::Full };
+        let trace = record_trace_at_level(meta, level)?;
+        let mut saved = Vec::new();
+        if let Some(trace_path) = cli.save_trace_path {
+            fs::write(&trace_path, serde_json::to_string_pretty(&trace)?)?;
+            saved.push(trace_path);
+        }
+        if let Some(html_path) = cli.out_html_path {
+            fs::write(&html_path, trace_to_html(&trace)?)?;
+            saved.push(html_path);
+        }
+        let saved = saved.join(" and ");
+        match &trace.error {
+            _ if cli.quiet => {}
+            Some(err) => println!("Saved trace ({} steps, failed: {err}) to {saved}", trace.steps.len()),
+            None => println!("Saved trace ({} steps, passed) to {saved}", trace.steps.len()),
+        }
+        return Ok(());
+    }
+
+    if let Some(other_path) = cli.diff_trace_path {
+        let other_source = fs::read_to_string(&other_path)?;
+        let left = record_trace(TraceMeta {
+            source: source.clone(),
+            function: selected_name.clone(),
+            ctor_args: ctor_args.clone(),
+            args: typed_args.clone(),
+            covenants_disabled,
+            ..Default::default()
+        })?;
+        let right = record_trace(TraceMeta {
+            source: other_source,
+            function: selected_name,
+            ctor_args,
+            args: typed_args,
+            covenants_disabled,
+            ..Default::default()
+        })?;
+        return diff_traces(&left, &right);
     }
 
     // Always seed: even in --no-selector mode the function params must be pushed.
     let sigscript = compiled.build_sig_script(&selected_name, typed_args)?;
     let mut session = DebugSession::full(&sigscript, &compiled.script, &source, debug_info, engine)?;
+    if let Some(depth) = cli.journal_depth {
+        session.enable_journal(depth);
+    }
 
-    println!("Stepping through {} bytes of script", compiled.script.len());
+    if !cli.quiet {
+        println!("Stepping through {} bytes of script", compiled.script.len());
+    }
     session.run_to_first_executed_statement()?;
     show_source_context(&session);
     run_repl(&mut session)?;
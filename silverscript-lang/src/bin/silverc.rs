@@ -1,9 +1,46 @@
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use silverscript_lang::ast::Expr;
-use silverscript_lang::compiler::{CompileOptions, compile_contract};
+use silverscript_lang::ast::{Expr, parse_contract_ast};
+use silverscript_lang::compiler::callgraph::CallGraph;
+use silverscript_lang::compiler::selectors::{SelectorLock, check_selector_lock};
+use silverscript_lang::compiler::{CompileOptions, compile_contract, compile_entrypoint, contract_abi, entrypoint_abi};
+use silverscript_lang::debug::opcodes::opcode_by_name;
+use silverscript_lang::debug::simulate::run_test_vectors;
+use silverscript_lang::examples::{example, examples};
+use silverscript_lang::solidity::{parse_solidity_signatures, silverscript_stub};
+
+const USAGE: &str = concat!(
+    "usage: silverc <src.sil> [--constructor-args ctor.json] [--hardened] [--no-covenants] [-o dst.json]\n",
+    "       [--embed-source-hash] [--optimize] [--only-function name] [--abi | --callgraph] [--selectors-lock path]\n",
+    "       [--deny OP_CAT,OP_CHECKMULTISIG]\n",
+    "       silverc init <name> [--kind basic|p2pkh]\n",
+    "       silverc import-solidity <src.sol> [--contract name] [-o dst.sil]\n",
+    "       silverc test <src.sil>\n",
+    "       silverc examples [name]\n",
+    "       silverc --bench [--iterations n]"
+);
+
+/// Contract written by `silverc init --kind basic`; `{name}` is replaced with the contract name.
+const BASIC_TEMPLATE: &str = r#"pragma silverscript ^0.1.0;
+
+contract {name}(int threshold) {
+    entrypoint function spend(int value) {
+        require(value >= threshold);
+    }
+}
+"#;
+
+/// Contract written by `silverc init --kind p2pkh`.
+const P2PKH_TEMPLATE: &str = r#"pragma silverscript ^0.1.0;
+
+contract {name}(pubkey owner) {
+    entrypoint function spend(sig ownerSig) {
+        require(checkSig(ownerSig, owner));
+    }
+}
+"#;
 
 fn main() {
     if let Err(err) = run() {
@@ -15,12 +52,36 @@ fn main() {
 fn run() -> Result<(), String> {
     let args = env::args().skip(1).collect::<Vec<_>>();
     if args.is_empty() {
-        return Err("usage: silverc <src.sil> [--constructor-args ctor.json] [-o dst.json]".to_string());
+        return Err(USAGE.to_string());
+    }
+    if args[0] == "init" {
+        return init_project(&args[1..]);
+    }
+    if args[0] == "import-solidity" {
+        return import_solidity(&args[1..]);
+    }
+    if args[0] == "--bench" {
+        return bench(&args[1..]);
+    }
+    if args[0] == "test" {
+        return run_tests(&args[1..]);
+    }
+    if args[0] == "examples" {
+        return print_examples(&args[1..]);
     }
 
     let mut src: Option<String> = None;
     let mut ctor_args_path: Option<String> = None;
     let mut out_path: Option<String> = None;
+    let mut lock_path: Option<String> = None;
+    let mut only_function: Option<String> = None;
+    let mut hardened = false;
+    let mut covenants_disabled = false;
+    let mut embed_source_hash = false;
+    let mut optimize = false;
+    let mut denied: Vec<u8> = Vec::new();
+    let mut abi_only = false;
+    let mut callgraph_only = false;
 
     let mut i = 0;
     while i < args.len() {
@@ -30,6 +91,47 @@ fn run() -> Result<(), String> {
                 ctor_args_path = Some(value.clone());
                 i += 2;
             }
+            "--hardened" => {
+                hardened = true;
+                i += 1;
+            }
+            "--no-covenants" => {
+                covenants_disabled = true;
+                i += 1;
+            }
+            "--embed-source-hash" => {
+                embed_source_hash = true;
+                i += 1;
+            }
+            "--optimize" => {
+                optimize = true;
+                i += 1;
+            }
+            "--deny" => {
+                let value = args.get(i + 1).ok_or_else(|| "--deny requires a comma-separated list of opcodes".to_string())?;
+                for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                    denied.push(opcode_by_name(name).ok_or_else(|| format!("unknown opcode in --deny: {name}"))?);
+                }
+                i += 2;
+            }
+            "--only-function" => {
+                let value = args.get(i + 1).ok_or_else(|| "--only-function requires a function name".to_string())?;
+                only_function = Some(value.clone());
+                i += 2;
+            }
+            "--selectors-lock" => {
+                let value = args.get(i + 1).ok_or_else(|| "--selectors-lock requires a path".to_string())?;
+                lock_path = Some(value.clone());
+                i += 2;
+            }
+            "--callgraph" => {
+                callgraph_only = true;
+                i += 1;
+            }
+            "--abi" => {
+                abi_only = true;
+                i += 1;
+            }
             "-o" => {
                 let value = args.get(i + 1).ok_or_else(|| "-o requires a path".to_string())?;
                 out_path = Some(value.clone());
@@ -51,15 +153,51 @@ fn run() -> Result<(), String> {
     let src = src.ok_or_else(|| "missing source file".to_string())?;
     let source = fs::read_to_string(&src).map_err(|err| format!("failed to read {src}: {err}"))?;
 
-    let constructor_args = if let Some(path) = ctor_args_path {
-        let json = fs::read_to_string(&path).map_err(|err| format!("failed to read {path}: {err}"))?;
+    let constructor_args = if let Some(path) = &ctor_args_path {
+        let json = fs::read_to_string(path).map_err(|err| format!("failed to read {path}: {err}"))?;
         serde_json::from_str::<Vec<Expr>>(&json).map_err(|err| format!("failed to parse constructor args {path}: {err}"))?
     } else {
         Vec::new()
     };
 
-    let compiled =
-        compile_contract(&source, &constructor_args, CompileOptions::default()).map_err(|err| format!("compile error: {err}"))?;
+    if let Some(path) = &lock_path {
+        check_lock_file(&source, path)?;
+    }
+
+    if callgraph_only {
+        let contract = parse_contract_ast(&source).map_err(|err| format!("compile error: {err}"))?;
+        let output_path = out_path.map(PathBuf::from).unwrap_or_else(|| default_output_path(&src).with_extension("dot"));
+        let dot = CallGraph::build(&contract).to_dot();
+        fs::write(&output_path, dot).map_err(|err| format!("failed to write {}: {err}", output_path.display()))?;
+        return Ok(());
+    }
+
+    if abi_only {
+        let ctor_args = ctor_args_path.is_some().then_some(constructor_args.as_slice());
+        let abi = match &only_function {
+            Some(name) => entrypoint_abi(&source, ctor_args, name),
+            None => contract_abi(&source, ctor_args),
+        }
+        .map_err(|err| format!("compile error: {err}"))?;
+        let output_path = out_path.map(PathBuf::from).unwrap_or_else(|| default_output_path(&src).with_extension("abi.json"));
+        let json = serde_json::to_string_pretty(&abi).map_err(|err| format!("failed to serialize output: {err}"))?;
+        fs::write(&output_path, json).map_err(|err| format!("failed to write {}: {err}", output_path.display()))?;
+        return Ok(());
+    }
+
+    // Debug info locates denied opcodes in the source; it is dropped again before writing.
+    let record_debug_infos = !denied.is_empty();
+    let options =
+        CompileOptions { hardened, covenants_disabled, embed_source_hash, optimize, record_debug_infos, ..Default::default() };
+    let mut compiled = match &only_function {
+        Some(name) => compile_entrypoint(&source, &constructor_args, options, name),
+        None => compile_contract(&source, &constructor_args, options),
+    }
+    .map_err(|err| format!("compile error: {err}"))?;
+    if record_debug_infos {
+        compiled.check_denied_opcodes(&denied).map_err(|err| format!("compile error: {err}"))?;
+        compiled.debug_info = None;
+    }
 
     let output_path = match out_path {
         Some(path) => PathBuf::from(path),
@@ -72,6 +210,216 @@ fn run() -> Result<(), String> {
     Ok(())
 }
 
+/// Runs the `@test` vectors of a contract, failing if any spend does not pass or fail as expected.
+fn run_tests(args: &[String]) -> Result<(), String> {
+    let [src] = args else {
+        return Err(USAGE.to_string());
+    };
+    let source = fs::read_to_string(src).map_err(|err| format!("failed to read {src}: {err}"))?;
+    let outcomes = run_test_vectors(&source)?;
+    if outcomes.is_empty() {
+        println!("no @test vectors in {src}");
+        return Ok(());
+    }
+    for outcome in &outcomes {
+        let status = if outcome.matches() { "ok" } else { "FAILED" };
+        let expected = if outcome.expect_pass { "pass" } else { "fail" };
+        let actual = outcome.error.as_deref().map_or("passed".to_string(), |err| format!("failed: {err}"));
+        println!("{status:<6} {} @test #{} (expect {expected}): {actual}", outcome.function, outcome.index);
+    }
+    let mismatched = outcomes.iter().filter(|outcome| !outcome.matches()).count();
+    if mismatched > 0 {
+        return Err(format!("{mismatched} of {} @test vectors did not behave as expected", outcomes.len()));
+    }
+    Ok(())
+}
+
+/// Lists the bundled example contracts, or prints the source of the named one.
+fn print_examples(args: &[String]) -> Result<(), String> {
+    match args {
+        [] => {
+            for example in examples() {
+                println!("{:<10} {} (try `{}`)", example.name, example.description, example.function);
+            }
+            Ok(())
+        }
+        [name] => {
+            let example = example(name).ok_or_else(|| {
+                let names = examples().iter().map(|example| example.name).collect::<Vec<_>>();
+                format!("unknown example '{name}' (expected one of: {})", names.join(", "))
+            })?;
+            print!("{}", example.source);
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+/// Prints parse, compile and trace throughput for the bundled benchmark samples.
+#[cfg(feature = "bench")]
+fn bench(args: &[String]) -> Result<(), String> {
+    use silverscript_lang::bench::{bundled_samples, run_bench};
+
+    let iterations = match args {
+        [] => 200,
+        [flag, value] if flag == "--iterations" => {
+            value.parse::<usize>().map_err(|_| format!("--iterations expects a positive number, got '{value}'"))?
+        }
+        _ => return Err(USAGE.to_string()),
+    };
+    let results = run_bench(&bundled_samples(), iterations)?;
+    println!("{:<12} {:>14} {:>14} {:>14}", "sample", "parse/s", "compile/s", "trace/s");
+    for result in results {
+        println!(
+            "{:<12} {:>14.0} {:>14.0} {:>14.0}",
+            result.sample, result.parse_per_sec, result.compile_per_sec, result.trace_per_sec
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "bench"))]
+fn bench(_args: &[String]) -> Result<(), String> {
+    Err("silverc was built without benchmarks; rebuild with `--features bench`".to_string())
+}
+
+/// Verifies selectors against the lock file at `path`, creating it or appending new entrypoints as needed.
+fn check_lock_file(source: &str, path: &str) -> Result<(), String> {
+    let lock = match fs::read_to_string(path) {
+        Ok(json) => {
+            serde_json::from_str::<SelectorLock>(&json).map_err(|err| format!("failed to parse selector lock {path}: {err}"))?
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => SelectorLock::new(),
+        Err(err) => return Err(format!("failed to read {path}: {err}")),
+    };
+    let contract = parse_contract_ast(source).map_err(|err| format!("compile error: {err}"))?;
+    let updated = check_selector_lock(&contract, &lock).map_err(|err| format!("compile error: {err}"))?;
+    if updated != lock {
+        let json = serde_json::to_string_pretty(&updated).map_err(|err| format!("failed to serialize selector lock: {err}"))?;
+        fs::write(path, json).map_err(|err| format!("failed to write {path}: {err}"))?;
+    }
+    Ok(())
+}
+
+/// Scaffolds `<name>/` with `<name>.sil`, the matching `args.json` constructor args and a README.
+fn init_project(args: &[String]) -> Result<(), String> {
+    let mut name: Option<&str> = None;
+    let mut kind = "basic";
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--kind" => {
+                kind = args.get(i + 1).map(String::as_str).ok_or_else(|| "--kind requires a value".to_string())?;
+                i += 2;
+            }
+            value if value.starts_with('-') => return Err(format!("unknown option: {value}")),
+            value => {
+                if name.is_some() {
+                    return Err("only one project name is supported".to_string());
+                }
+                name = Some(value);
+                i += 1;
+            }
+        }
+    }
+    let name = name.ok_or_else(|| USAGE.to_string())?;
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(format!("invalid project name '{name}': use letters, digits, '_' or '-'"));
+    }
+    let contract_name = contract_name(name);
+    if !contract_name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(format!("invalid project name '{name}': must start with a letter"));
+    }
+
+    let (template, ctor_args) = match kind {
+        "basic" => (BASIC_TEMPLATE, vec![Expr::Int(0)]),
+        "p2pkh" => (P2PKH_TEMPLATE, vec![Expr::Bytes(vec![0u8; 32])]),
+        other => return Err(format!("unknown template kind '{other}' (expected basic or p2pkh)")),
+    };
+
+    let dir = Path::new(name);
+    if dir.exists() {
+        return Err(format!("{} already exists", dir.display()));
+    }
+    fs::create_dir_all(dir).map_err(|err| format!("failed to create {}: {err}", dir.display()))?;
+
+    let source_file = format!("{name}.sil");
+    let json = serde_json::to_string_pretty(&ctor_args).map_err(|err| format!("failed to serialize constructor args: {err}"))?;
+    let readme = format!(
+        "# {contract_name}\n\n\
+         Compile with the constructor args in `args.json`:\n\n\
+         ```sh\nsilverc {source_file} --constructor-args args.json\n```\n"
+    );
+    let files = [
+        (source_file.clone(), template.replace("{name}", &contract_name)),
+        ("args.json".to_string(), json),
+        ("README.md".to_string(), readme),
+    ];
+    for (file, contents) in files {
+        let path = dir.join(file);
+        fs::write(&path, contents).map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Writes a silverscript stub with the signatures of a Solidity contract or interface.
+fn import_solidity(args: &[String]) -> Result<(), String> {
+    let mut src: Option<&str> = None;
+    let mut contract: Option<&str> = None;
+    let mut out_path: Option<&str> = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--contract" => {
+                contract = Some(args.get(i + 1).map(String::as_str).ok_or_else(|| "--contract requires a name".to_string())?);
+                i += 2;
+            }
+            "-o" => {
+                out_path = Some(args.get(i + 1).map(String::as_str).ok_or_else(|| "-o requires a path".to_string())?);
+                i += 2;
+            }
+            value if value.starts_with('-') => return Err(format!("unknown option: {value}")),
+            value => {
+                if src.is_some() {
+                    return Err("only one source file is supported".to_string());
+                }
+                src = Some(value);
+                i += 1;
+            }
+        }
+    }
+    let src = src.ok_or_else(|| USAGE.to_string())?;
+    let source = fs::read_to_string(src).map_err(|err| format!("failed to read {src}: {err}"))?;
+    let contracts = parse_solidity_signatures(&source).map_err(|err| format!("failed to read Solidity signatures: {err}"))?;
+    let selected = match contract {
+        Some(name) => {
+            contracts.iter().find(|candidate| candidate.name == name).ok_or_else(|| format!("no contract named '{name}' in {src}"))?
+        }
+        None => match contracts.as_slice() {
+            [only] => only,
+            [] => return Err(format!("no contract or interface found in {src}")),
+            _ => {
+                let names: Vec<&str> = contracts.iter().map(|candidate| candidate.name.as_str()).collect();
+                return Err(format!("{src} declares several contracts ({}); pick one with --contract", names.join(", ")));
+            }
+        },
+    };
+    let output_path = out_path.map(PathBuf::from).unwrap_or_else(|| Path::new(src).with_extension("sil"));
+    fs::write(&output_path, silverscript_stub(selected)).map_err(|err| format!("failed to write {}: {err}", output_path.display()))
+}
+
+/// `my-vault` and `my_vault` become `MyVault`.
+fn contract_name(project: &str) -> String {
+    project
+        .split(['-', '_'])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map(|first| first.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+        })
+        .collect()
+}
+
 fn default_output_path(src: &str) -> PathBuf {
     if let Some(stripped) = src.strip_suffix(".sil") {
         PathBuf::from(format!("{stripped}.json"))
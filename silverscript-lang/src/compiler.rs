@@ -1,22 +1,37 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use kaspa_consensus_core::tx::ScriptPublicKey;
 use kaspa_txscript::opcodes::codes::*;
+use kaspa_txscript::pay_to_script_hash_script;
 use kaspa_txscript::script_builder::{ScriptBuilder, ScriptBuilderError};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::ast::visit::{Identifiers, Visitor, walk_contract, walk_expr, walk_function};
 use crate::ast::{
-    BinaryOp, ConsoleArg, ContractAst, Expr, FunctionAst, IntrospectionKind, NullaryOp, SourceSpan, SplitPart, Statement,
-    StatementKind, TimeVar, UnaryOp, parse_contract_ast,
+    AnnotationAst, BinaryOp, ConsoleArg, ContractAst, Expr, FunctionAst, IntrospectionKind, NullaryOp, SourceSpan, SplitPart,
+    Statement, StatementKind, TimeVar, UnaryOp, format_signature, parse_contract_ast,
 };
-use crate::debug::DebugInfo;
 use crate::debug::labels::synthetic;
+use crate::debug::opcodes::{canonical_disassembly_token, disassemble, opcode_name};
+use crate::debug::{DebugInfo, DebugLevel, MappingKind};
 use crate::parser::Rule;
 use chrono::NaiveDateTime;
 
+mod arg_layout;
+pub mod artifact;
+pub mod callgraph;
 mod debug_recording;
+mod decimals;
+mod fold;
+pub mod mass;
+pub mod multisig;
+mod peephole;
+pub mod selectors;
+pub mod test_vectors;
 
-use debug_recording::{DebugSink, FunctionDebugRecorder, record_synthetic_range};
+use callgraph::CallGraph;
+use debug_recording::{DebugSink, FunctionDebugRecorder, TernaryRange, record_synthetic_range};
 
 #[derive(Debug, Error)]
 pub enum CompilerError {
@@ -26,12 +41,32 @@ pub enum CompilerError {
     Unsupported(String),
     #[error("invalid literal: {0}")]
     InvalidLiteral(String),
+    #[error("literal out of range at line {}, col {}: {literal} does not fit in a script number", .span.line, .span.col)]
+    LiteralOutOfRange { literal: String, span: SourceSpan },
+    #[error("invalid escape sequence at line {}, col {}: {escape}", .span.line, .span.col)]
+    InvalidEscape { escape: String, span: SourceSpan },
+    #[error("invalid pragma at line {}, col {}: {reason}", .span.line, .span.col)]
+    InvalidPragma { reason: String, span: SourceSpan },
+    #[error("invalid for loop bound{}: {reason}", describe_location(.span))]
+    InvalidLoopBound { reason: String, span: Option<SourceSpan> },
     #[error("undefined identifier: {0}")]
     UndefinedIdentifier(String),
     #[error("cyclic identifier reference: {0}")]
     CyclicIdentifier(String),
+    #[error("recursive call cycle: {}", .cycle.join(" -> "))]
+    RecursiveCall { cycle: Vec<String>, spans: Vec<SourceSpan> },
     #[error("script build error: {0}")]
     ScriptBuild(#[from] ScriptBuilderError),
+    #[error("script uses denied opcode {opcode}{}", describe_location(.span))]
+    DeniedOpcode { opcode: String, span: Option<SourceSpan> },
+    #[error(
+        "script does not match expectScript at instruction {}: expected {expected}, got {actual}\n  \
+         expected: {expected_script}\n  actual:   {actual_script}",
+        .index + 1
+    )]
+    UnexpectedScript { index: usize, expected: String, actual: String, expected_script: String, actual_script: String },
+    #[error("function '{function}' exceeds @maxMass budget: computed mass {computed}, allowed {allowed}")]
+    MassBudgetExceeded { function: String, computed: u64, allowed: u64 },
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -39,22 +74,127 @@ pub struct CompileOptions {
     pub allow_yield: bool,
     pub allow_entrypoint_return: bool,
     pub record_debug_infos: bool,
+    /// How much of the recorded debug info to keep; ignored without `record_debug_infos`.
+    pub debug_level: DebugLevel,
+    /// Emits runtime overflow checks for int arithmetic, bounds checks before byte slicing and a
+    /// clean-stack assertion at the end of every entrypoint, at the cost of a larger script.
+    pub hardened: bool,
+    /// Targets an engine running with covenants disabled; `@covenantOnly` requires are left out.
+    pub covenants_disabled: bool,
+    /// Appends `blake2b(source)` behind an untaken `OpReturn` so the deployed script commits to its source.
+    /// Costs `SOURCE_HASH_METADATA_SIZE` bytes and never executes.
+    pub embed_source_hash: bool,
+    /// Leaves out `require`s whose condition is a constant that always holds, pushes the value of int
+    /// and bool expressions whose operands are all literals or constants instead of computing it, and
+    /// collapses redundant opcode pairs (see `peephole`).
+    pub optimize: bool,
+}
+
+/// Bytes `CompileOptions::embed_source_hash` adds: `OpFalse OpIf OpReturn <32-byte hash> OpEndIf`.
+pub const SOURCE_HASH_METADATA_SIZE: usize = 37;
+
+/// Largest script the engine accepts (`MAX_SCRIPTS_SIZE` in txscript); a longer P2SH redeem script can never be spent.
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+
+/// blake2b-256 of a contract source, as embedded by `CompileOptions::embed_source_hash`.
+pub fn source_hash(source: &str) -> [u8; 32] {
+    blake2b_256(source.as_bytes())
+}
+
+/// blake2b-256 of a 32-byte x-only public key, the value `pubkeyHash(pk)` computes on chain.
+/// Wallets should derive stored P2PKH hashes with this so they match the contract.
+pub fn pubkey_hash(pubkey: &[u8; 32]) -> [u8; 32] {
+    blake2b_256(pubkey)
+}
+
+/// Unkeyed blake2b with a 32-byte digest, the hash behind `OpBlake2b`.
+pub(crate) fn blake2b_256(data: &[u8]) -> [u8; 32] {
+    let hash = blake2b_simd::Params::new().hash_length(32).hash(data);
+    hash.as_bytes().try_into().expect("hash length is 32")
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FunctionInputAbi {
     pub name: String,
     pub type_name: String,
+    /// Fractional digits of a `@scale` param; decimal arguments are scaled to it before being pushed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FunctionAbiEntry {
     pub name: String,
     pub inputs: Vec<FunctionInputAbi>,
+    /// Unconditional `require`s of the entrypoint body, rendered as source.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preconditions: Vec<String>,
+}
+
+impl FunctionAbiEntry {
+    /// The entrypoint as written, e.g. `spend(pubkey owner, sig s)`.
+    pub fn signature(&self) -> String {
+        format_signature(&self.name, self.inputs.iter().map(|input| (input.type_name.as_str(), input.name.as_str())))
+    }
+
+    /// Error for a call with `got` arguments, spelling out the expected signature.
+    pub fn arg_count_mismatch(&self, got: usize) -> String {
+        format!("function '{}' expects {} arguments, got {got}: {}", self.name, self.inputs.len(), self.signature())
+    }
 }
 
 pub type FunctionAbi = Vec<FunctionAbiEntry>;
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectorAbiEntry {
+    pub name: String,
+    pub inputs: Vec<FunctionInputAbi>,
+    /// Value the sigscript pushes to select this entrypoint; `None` when the contract has a single entrypoint.
+    pub selector: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preconditions: Vec<String>,
+}
+
+/// Interface of a contract, derived without building sigscripts or executing anything.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractAbi {
+    pub contract_name: String,
+    pub entrypoints: Vec<SelectorAbiEntry>,
+    /// Set when the source parses but does not compile; the entrypoints then come from the parsed outline.
+    pub compile_error: Option<String>,
+    pub dispatch: DispatchAbi,
+}
+
+/// How a sigscript reaches an entrypoint. Arguments are always pushed in param order, first param
+/// first, using minimal pushes; this describes what, if anything, follows them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DispatchAbi {
+    /// `None` for a single-entrypoint contract, whose sigscripts push only the arguments.
+    pub selector: Option<SelectorDispatch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectorDispatch {
+    pub position: SelectorPosition,
+    pub encoding: SelectorEncoding,
+    /// Every selector that reaches an entrypoint, ascending; any other value fails the script.
+    pub valid_selectors: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectorPosition {
+    /// Pushed after the last argument, so it is the top stack item when the lockscript starts.
+    AfterArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectorEncoding {
+    /// Minimally encoded script number: `OP_0`, `OP_1`..`OP_16`, or a data push for larger values.
+    ScriptNum,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CompiledContract {
     pub contract_name: String,
@@ -62,12 +202,261 @@ pub struct CompiledContract {
     pub ast: ContractAst,
     pub abi: FunctionAbi,
     pub without_selector: bool,
+    /// One flag per constructor param: whether it is referenced by code reachable from an entrypoint.
+    #[serde(default)]
+    pub used_ctor_params: Vec<bool>,
+    pub debug_info: Option<DebugInfo>,
+}
+
+impl CompiledContract {
+    /// Serializes the whole contract (script, AST, ABI, flags and debug info) so another process can
+    /// build sigscripts and traces from it without the compiler re-parsing the source.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Reads a contract written by `to_json`.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// The script itself as a locking script of the given version, the form the debugger and
+    /// `verify_auto_signed` spend.
+    pub fn to_script_public_key(&self, version: u16) -> ScriptPublicKey {
+        ScriptPublicKey::new(version, self.script.clone().into())
+    }
+
+    /// blake2b-256 of the script, the hash a pay-to-script-hash locking script commits to.
+    pub fn script_hash(&self) -> [u8; 32] {
+        blake2b_256(&self.script)
+    }
+
+    /// A version 0 pay-to-script-hash locking script committing to the script. Spending it needs the
+    /// script pushed as the last item of the sigscript. Scripts that could never be spent through it,
+    /// an empty script or one over `MAX_SCRIPT_SIZE`, are rejected.
+    pub fn to_p2sh_script_public_key(&self) -> Result<ScriptPublicKey, CompilerError> {
+        if self.script.is_empty() {
+            return Err(CompilerError::Unsupported("cannot build a p2sh script for an empty script".to_string()));
+        }
+        if self.script.len() > MAX_SCRIPT_SIZE {
+            return Err(CompilerError::Unsupported(format!(
+                "cannot build a p2sh script for a {} byte script; scripts are limited to {MAX_SCRIPT_SIZE} bytes",
+                self.script.len()
+            )));
+        }
+        Ok(pay_to_script_hash_script(&self.script))
+    }
+
+    /// Fails on the first opcode of the script that is in `denied`, for deployments that forbid some
+    /// opcodes. The error carries the span of the statement that emitted it when debug info was
+    /// recorded; opcodes the compiler adds itself, such as selector dispatch, have no span.
+    pub fn check_denied_opcodes(&self, denied: &[u8]) -> Result<(), CompilerError> {
+        let mut offset = 0usize;
+        for (opcode, size) in mass::instructions(&self.script) {
+            if denied.contains(&opcode) {
+                let span = self.debug_info.as_ref().and_then(|debug_info| statement_span_at(debug_info, offset));
+                let opcode = opcode_name(opcode).map(str::to_string).unwrap_or_else(|| format!("0x{opcode:02x}"));
+                return Err(CompilerError::DeniedOpcode { opcode, span });
+            }
+            offset += size;
+        }
+        Ok(())
+    }
+
+    /// Size, worst-case mass and signature operation count of the compiled script.
+    pub fn metrics(&self) -> CompileMetrics {
+        CompileMetrics::of(&self.script)
+    }
+
+    /// Non-fatal diagnostics about the contract, such as constructor params that are never used.
+    pub fn warnings(&self) -> Vec<String> {
+        contract_warnings(&self.ast, &self.used_ctor_params)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompileMetrics {
+    pub script_size: usize,
+    pub mass: u64,
+    pub sig_ops: u64,
+}
+
+impl CompileMetrics {
+    fn of(script: &[u8]) -> Self {
+        Self { script_size: script.len(), mass: mass::estimate_script_mass(script), sig_ops: mass::count_sig_ops(script) }
+    }
+}
+
+fn contract_warnings(contract: &ContractAst, used_ctor_params: &[bool]) -> Vec<String> {
+    let mut warnings: Vec<String> = contract
+        .params
+        .iter()
+        .zip(used_ctor_params.iter())
+        .filter(|(_, used)| !**used)
+        .map(|(param, _)| format!("constructor param '{}' is never used", param.name))
+        .collect();
+    let mut no_op_requires = NoOpRequires::default();
+    walk_contract(contract, &mut no_op_requires);
+    warnings.extend(no_op_requires.warnings);
+    warnings
+}
+
+/// All outputs of a compilation, gathered for tooling that wants a single object to consume.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompileResult {
+    pub contract_name: String,
+    pub script: Vec<u8>,
+    pub ast: ContractAst,
+    pub abi: FunctionAbi,
+    pub without_selector: bool,
+    #[serde(default)]
+    pub used_ctor_params: Vec<bool>,
+    pub warnings: Vec<String>,
+    pub metrics: CompileMetrics,
     pub debug_info: Option<DebugInfo>,
 }
 
+impl From<CompileResult> for CompiledContract {
+    fn from(result: CompileResult) -> Self {
+        Self {
+            contract_name: result.contract_name,
+            script: result.script,
+            ast: result.ast,
+            abi: result.abi,
+            without_selector: result.without_selector,
+            used_ctor_params: result.used_ctor_params,
+            debug_info: result.debug_info,
+        }
+    }
+}
+
+/// What `compile` builds a contract from.
+#[derive(Debug, Clone, Copy)]
+pub enum CompileInput<'a> {
+    Source(&'a str),
+    /// A contract with no source text, e.g. one read from JSON; its debug info has an empty `source`
+    /// and it cannot embed a source hash.
+    Ast(&'a ContractAst),
+    /// A contract already parsed from the given source.
+    Parsed(&'a ContractAst, &'a str),
+}
+
+impl<'a> From<&'a str> for CompileInput<'a> {
+    fn from(source: &'a str) -> Self {
+        Self::Source(source)
+    }
+}
+
+impl<'a> From<&'a ContractAst> for CompileInput<'a> {
+    fn from(contract: &'a ContractAst) -> Self {
+        Self::Ast(contract)
+    }
+}
+
+fn describe_location(span: &Option<SourceSpan>) -> String {
+    span.map(|span| format!(" at line {}, col {}", span.line, span.col)).unwrap_or_default()
+}
+
+/// Span of the narrowest statement whose bytecode includes `offset`.
+fn statement_span_at(debug_info: &DebugInfo, offset: usize) -> Option<SourceSpan> {
+    debug_info
+        .mappings
+        .iter()
+        .filter(|mapping| matches!(mapping.kind, MappingKind::Statement {}))
+        .filter(|mapping| mapping.bytecode_start <= offset && offset < mapping.bytecode_end)
+        .filter_map(|mapping| Some((mapping.bytecode_end - mapping.bytecode_start, mapping.span?)))
+        .min_by_key(|(width, _)| *width)
+        .map(|(_, span)| span)
+}
+
 pub fn compile_contract(source: &str, constructor_args: &[Expr], options: CompileOptions) -> Result<CompiledContract, CompilerError> {
+    compile(source, constructor_args, options).map(CompiledContract::from)
+}
+
+/// Returns the ABI of `source` with selector indices.
+///
+/// Only parse errors are fatal. When constructor args are given (or the contract takes none) the
+/// contract is also compiled and any compile error is reported in `compile_error` alongside the outline.
+pub fn contract_abi(source: &str, constructor_args: Option<&[Expr]>) -> Result<ContractAbi, CompilerError> {
     let contract = parse_contract_ast(source)?;
-    compile_contract_impl(&contract, constructor_args, options, Some(source))
+    contract_abi_impl(contract, source, constructor_args)
+}
+
+/// Like `compile_contract`, but keeps only the entrypoint `function_name` and the functions it calls.
+///
+/// The result is a single-entrypoint script without selector dispatch, for deployments that only
+/// ever spend through one path.
+pub fn compile_entrypoint(
+    source: &str,
+    constructor_args: &[Expr],
+    options: CompileOptions,
+    function_name: &str,
+) -> Result<CompiledContract, CompilerError> {
+    let contract = strip_to_entrypoint(&parse_contract_ast(source)?, function_name)?;
+    compile(CompileInput::Parsed(&contract, source), constructor_args, options).map(CompiledContract::from)
+}
+
+/// `contract_abi` of the contract `compile_entrypoint` builds for `function_name`.
+pub fn entrypoint_abi(source: &str, constructor_args: Option<&[Expr]>, function_name: &str) -> Result<ContractAbi, CompilerError> {
+    let contract = strip_to_entrypoint(&parse_contract_ast(source)?, function_name)?;
+    contract_abi_impl(contract, source, constructor_args)
+}
+
+/// Returns `contract` reduced to the entrypoint `function_name` and its transitive callees, in declaration order.
+/// Callees that are themselves entrypoints are kept as plain functions so the result has a single entrypoint.
+pub fn strip_to_entrypoint(contract: &ContractAst, function_name: &str) -> Result<ContractAst, CompilerError> {
+    match contract.functions.iter().find(|func| func.name == function_name) {
+        Some(func) if func.entrypoint => {}
+        Some(_) => return Err(CompilerError::Unsupported(format!("function '{function_name}' is not an entrypoint"))),
+        None => return Err(CompilerError::Unsupported(format!("function '{function_name}' not found"))),
+    }
+    let graph = CallGraph::build(contract);
+    let reachable = graph.reachable_from(function_name);
+    let functions = contract
+        .functions
+        .iter()
+        .filter(|func| reachable.contains(&func.name.as_str()))
+        .map(|func| FunctionAst { entrypoint: func.name == function_name, ..func.clone() })
+        .collect();
+    // The expectation describes the full contract's script, not the stripped one.
+    Ok(ContractAst {
+        name: contract.name.clone(),
+        params: contract.params.clone(),
+        constants: contract.constants.clone(),
+        functions,
+        expected_script: None,
+    })
+}
+
+fn contract_abi_impl(contract: ContractAst, source: &str, constructor_args: Option<&[Expr]>) -> Result<ContractAbi, CompilerError> {
+    let compile_args = constructor_args.or(contract.params.is_empty().then_some(&[][..]));
+    let compile_error = compile_args
+        .and_then(|args| compile(CompileInput::Parsed(&contract, source), args, CompileOptions::default()).err())
+        .map(|err| err.to_string());
+
+    let abi = build_function_abi(&contract);
+    let without_selector = abi.len() == 1;
+    let selectors = selectors::entrypoint_selectors(&contract)?;
+    let mut valid_selectors: Vec<i64> = selectors.iter().map(|(_, selector)| *selector).collect();
+    valid_selectors.sort_unstable();
+    let dispatch = DispatchAbi {
+        selector: (!without_selector).then_some(SelectorDispatch {
+            position: SelectorPosition::AfterArgs,
+            encoding: SelectorEncoding::ScriptNum,
+            valid_selectors,
+        }),
+    };
+    let entrypoints = abi
+        .into_iter()
+        .zip(selectors)
+        .map(|(entry, (_, selector))| SelectorAbiEntry {
+            name: entry.name,
+            inputs: entry.inputs,
+            selector: (!without_selector).then_some(selector),
+            preconditions: entry.preconditions,
+        })
+        .collect();
+    Ok(ContractAbi { contract_name: contract.name, entrypoints, compile_error, dispatch })
 }
 
 pub fn compile_contract_ast(
@@ -75,15 +464,36 @@ pub fn compile_contract_ast(
     constructor_args: &[Expr],
     options: CompileOptions,
 ) -> Result<CompiledContract, CompilerError> {
-    compile_contract_impl(contract, constructor_args, options, None)
+    compile(contract, constructor_args, options).map(CompiledContract::from)
 }
 
-fn compile_contract_impl(
+/// Like `compile_contract` for a contract already parsed from `source`, so callers can time or
+/// inspect the parse separately while keeping source-dependent outputs such as debug info.
+pub fn compile_parsed_contract(
     contract: &ContractAst,
+    source: &str,
     constructor_args: &[Expr],
     options: CompileOptions,
-    source: Option<&str>,
 ) -> Result<CompiledContract, CompilerError> {
+    compile(CompileInput::Parsed(contract, source), constructor_args, options).map(CompiledContract::from)
+}
+
+/// Compiles a contract and returns every output in a single `CompileResult`. The other `compile_*`
+/// functions all go through here.
+pub fn compile<'a>(
+    input: impl Into<CompileInput<'a>>,
+    constructor_args: &[Expr],
+    options: CompileOptions,
+) -> Result<CompileResult, CompilerError> {
+    let parsed;
+    let (contract, source) = match input.into() {
+        CompileInput::Source(source) => {
+            parsed = parse_contract_ast(source)?;
+            (&parsed, Some(source))
+        }
+        CompileInput::Ast(contract) => (contract, None),
+        CompileInput::Parsed(contract, source) => (contract, Some(source)),
+    };
     if contract.functions.is_empty() {
         return Err(CompilerError::Unsupported("contract has no functions".to_string()));
     }
@@ -94,16 +504,45 @@ fn compile_contract_impl(
     }
 
     if contract.params.len() != constructor_args.len() {
-        return Err(CompilerError::Unsupported("constructor argument count mismatch".to_string()));
+        return Err(CompilerError::Unsupported(format!(
+            "constructor expects {} arguments, got {}: {}",
+            contract.params.len(),
+            constructor_args.len(),
+            contract.constructor_signature()
+        )));
     }
 
+    // Built before lowering so preconditions keep decimal literals as written.
+    let abi = build_function_abi(contract);
+    let lowered = decimals::lower_contract(contract)?;
+    let contract = &lowered;
+    let constructor_args = contract
+        .params
+        .iter()
+        .zip(constructor_args)
+        .map(|(param, value)| decimals::scale_arg(value.clone(), param.scale, &param.name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let constructor_args = constructor_args.as_slice();
+
     for (param, value) in contract.params.iter().zip(constructor_args.iter()) {
         if !expr_matches_type(value, &param.type_name) {
             return Err(CompilerError::Unsupported(format!("constructor argument '{}' expects {}", param.name, param.type_name)));
         }
     }
 
+    for func in &contract.functions {
+        validate_function_annotations(func)?;
+    }
+    CallGraph::build(contract).check_acyclic()?;
+
+    for (name, value) in &contract.constants {
+        if let Expr::Map { key_type, value_type, entries, default } = value {
+            validate_map(name, key_type, value_type, entries, default.as_deref())?;
+        }
+    }
+
     let without_selector = entrypoint_functions.len() == 1;
+    let selectors = selectors::entrypoint_selectors(contract)?;
 
     let mut constants = contract.constants.clone();
     for (param, value) in contract.params.iter().zip(constructor_args.iter()) {
@@ -113,7 +552,7 @@ fn compile_contract_impl(
     let functions_map = contract.functions.iter().cloned().map(|func| (func.name.clone(), func)).collect::<HashMap<_, _>>();
     let function_order =
         contract.functions.iter().enumerate().map(|(index, func)| (func.name.clone(), index)).collect::<HashMap<_, _>>();
-    let abi = build_function_abi(contract);
+    let used_ctor_params = used_constructor_params(contract);
     let uses_script_size = contract_uses_script_size(contract);
     let mut script_size = if uses_script_size { Some(100i64) } else { None };
 
@@ -141,16 +580,20 @@ fn compile_contract_impl(
             let compiled = compiled_entrypoints
                 .first()
                 .ok_or_else(|| CompilerError::Unsupported("contract has no entrypoint functions".to_string()))?;
+            check_max_mass(compiled, &compiled.script)?;
             recorder.record_compiled_function(&compiled.name, compiled.script.len(), &compiled.debug, 0);
             compiled.script.clone()
         } else {
             let mut builder = ScriptBuilder::new();
             let total = compiled_entrypoints.len();
+            // Guards and `OpElse`s a spend passes through before reaching the entrypoint being dispatched.
+            let mut dispatched_prefix = Vec::new();
 
-            for (index, compiled) in compiled_entrypoints.iter().enumerate() {
+            for (index, (compiled, (_, selector))) in compiled_entrypoints.iter().zip(&selectors).enumerate() {
+                let guard_start = builder.script().len();
                 record_synthetic_range(&mut builder, &mut recorder, synthetic::DISPATCHER_GUARD, |builder| {
                     builder.add_op(OpDup)?;
-                    builder.add_i64(index as i64)?;
+                    builder.add_i64(*selector)?;
                     builder.add_op(OpNumEqual)?;
                     builder.add_op(OpIf)?;
                     builder.add_op(OpDrop)?;
@@ -158,6 +601,13 @@ fn compile_contract_impl(
                 })?;
 
                 let func_start = builder.script().len();
+                let guard = builder.script()[guard_start..func_start].to_vec();
+                let path =
+                    [&dispatched_prefix[..], &guard[..], &compiled.script[..], &[OpElse][..], &vec![OpEndIf; total][..]].concat();
+                check_max_mass(compiled, &path)?;
+                dispatched_prefix.extend(guard);
+                dispatched_prefix.push(OpElse);
+
                 builder.add_ops(&compiled.script)?;
                 recorder.record_compiled_function(&compiled.name, compiled.script.len(), &compiled.debug, func_start);
 
@@ -181,28 +631,39 @@ fn compile_contract_impl(
 
             builder.drain()
         };
+        let script = if options.embed_source_hash { append_source_hash(script, source, &mut recorder)? } else { script };
 
         if !uses_script_size {
-            let debug_info = recorder.into_debug_info(source.unwrap_or_default().to_string());
-            return Ok(CompiledContract {
+            check_expected_script(contract, &script)?;
+            let debug_info =
+                recorder.into_debug_info(source.unwrap_or_default().to_string()).map(|info| info.at_level(options.debug_level));
+            return Ok(CompileResult {
                 contract_name: contract.name.clone(),
+                metrics: CompileMetrics::of(&script),
                 script,
                 ast: contract.clone(),
                 abi,
                 without_selector,
+                warnings: contract_warnings(contract, &used_ctor_params),
+                used_ctor_params,
                 debug_info,
             });
         }
 
         let actual_size = script.len() as i64;
         if Some(actual_size) == script_size {
-            let debug_info = recorder.into_debug_info(source.unwrap_or_default().to_string());
-            return Ok(CompiledContract {
+            check_expected_script(contract, &script)?;
+            let debug_info =
+                recorder.into_debug_info(source.unwrap_or_default().to_string()).map(|info| info.at_level(options.debug_level));
+            return Ok(CompileResult {
                 contract_name: contract.name.clone(),
+                metrics: CompileMetrics::of(&script),
                 script,
                 ast: contract.clone(),
                 abi,
                 without_selector,
+                warnings: contract_warnings(contract, &used_ctor_params),
+                used_ctor_params,
                 debug_info,
             });
         }
@@ -212,11 +673,89 @@ fn compile_contract_impl(
     Err(CompilerError::Unsupported("script size did not stabilize".to_string()))
 }
 
+/// Compares the final script with the contract's `expectScript`, if any, instruction by instruction.
+fn check_expected_script(contract: &ContractAst, script: &[u8]) -> Result<(), CompilerError> {
+    let Some(expected) = &contract.expected_script else {
+        return Ok(());
+    };
+    let expected = expected
+        .split_whitespace()
+        .map(|token| {
+            canonical_disassembly_token(token)
+                .ok_or_else(|| CompilerError::Unsupported(format!("unknown opcode '{token}' in expectScript")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let actual_script = disassemble(script);
+    let actual: Vec<&str> = actual_script.split_whitespace().collect();
+    let mismatch =
+        (0..expected.len().max(actual.len())).find(|&index| expected.get(index).map(String::as_str) != actual.get(index).copied());
+    let Some(index) = mismatch else {
+        return Ok(());
+    };
+    let describe = |token: Option<&str>| token.unwrap_or("end of script").to_string();
+    Err(CompilerError::UnexpectedScript {
+        index,
+        expected: describe(expected.get(index).map(String::as_str)),
+        actual: describe(actual.get(index).copied()),
+        expected_script: expected.join(" "),
+        actual_script,
+    })
+}
+
+/// Appends the source hash in a branch that is never taken, leaving the script's behavior unchanged.
+fn append_source_hash(script: Vec<u8>, source: Option<&str>, recorder: &mut DebugSink) -> Result<Vec<u8>, CompilerError> {
+    let source = source.ok_or_else(|| CompilerError::Unsupported("embed_source_hash requires the contract source".to_string()))?;
+    let mut builder = ScriptBuilder::new();
+    builder.add_ops(&script)?;
+    record_synthetic_range(&mut builder, recorder, synthetic::SOURCE_HASH, |builder| {
+        builder.add_op(OpFalse)?;
+        builder.add_op(OpIf)?;
+        builder.add_op(OpReturn)?;
+        builder.add_data(&source_hash(source))?;
+        builder.add_op(OpEndIf)?;
+        Ok(())
+    })?;
+    Ok(builder.drain())
+}
+
 #[derive(Debug)]
 struct CompiledFunction {
     name: String,
     script: Vec<u8>,
     debug: FunctionDebugRecorder,
+    /// `@maxMass` budget, checked once the function is placed behind the dispatcher.
+    max_mass: Option<u64>,
+}
+
+/// Def-use analysis for constructor params over entrypoints, the functions they call and contract constants.
+/// A function param with the same name shadows the constructor param inside that function.
+fn used_constructor_params(contract: &ContractAst) -> Vec<bool> {
+    let graph = CallGraph::build(contract);
+    let reached: HashSet<&str> =
+        contract.functions.iter().filter(|func| func.entrypoint).flat_map(|func| graph.reachable_from(&func.name)).collect();
+    let mut constant_uses = Identifiers::default();
+    contract.constants.values().for_each(|value| walk_expr(value, &mut constant_uses));
+    let function_uses: Vec<(&FunctionAst, Identifiers)> = contract
+        .functions
+        .iter()
+        .filter(|func| reached.contains(func.name.as_str()))
+        .map(|func| {
+            let mut uses = Identifiers::default();
+            walk_function(func, &mut uses);
+            (func, uses)
+        })
+        .collect();
+
+    contract
+        .params
+        .iter()
+        .map(|param| {
+            constant_uses.0.contains(&param.name)
+                || function_uses
+                    .iter()
+                    .any(|(func, uses)| !func.params.iter().any(|shadow| shadow.name == param.name) && uses.0.contains(&param.name))
+        })
+        .collect()
 }
 
 fn contract_uses_script_size(contract: &ContractAst) -> bool {
@@ -229,6 +768,7 @@ fn contract_uses_script_size(contract: &ContractAst) -> bool {
 fn statement_uses_script_size(stmt: &Statement) -> bool {
     match &stmt.kind {
         StatementKind::VariableDefinition { expr, .. } => expr.as_ref().is_some_and(expr_uses_script_size),
+        StatementKind::Let { expr, .. } => expr_uses_script_size(expr),
         StatementKind::TupleAssignment { expr, .. } => expr_uses_script_size(expr),
         StatementKind::ArrayPush { expr, .. } => expr_uses_script_size(expr),
         StatementKind::FunctionCall { args, .. } => args.iter().any(expr_uses_script_size),
@@ -249,12 +789,20 @@ fn statement_uses_script_size(stmt: &Statement) -> bool {
         StatementKind::Console { args, .. } => {
             args.iter().any(|arg| matches!(arg, ConsoleArg::Literal(e) if expr_uses_script_size(e)))
         }
+        StatementKind::Log { args } => args.iter().any(expr_uses_script_size),
+        StatementKind::WarnIf { condition, .. } => expr_uses_script_size(condition),
     }
 }
 
 fn expr_uses_script_size(expr: &Expr) -> bool {
     match expr {
-        Expr::Int(_) | Expr::Bool(_) | Expr::Bytes(_) | Expr::String(_) | Expr::Identifier(_) => false,
+        Expr::Int(_)
+        | Expr::Bool(_)
+        | Expr::Bytes(_)
+        | Expr::String(_)
+        | Expr::Identifier(_)
+        | Expr::Map { .. }
+        | Expr::Decimal { .. } => false,
         Expr::Array(items) => items.iter().any(expr_uses_script_size),
         Expr::Call { args, .. } | Expr::New { args, .. } => args.iter().any(expr_uses_script_size),
         Expr::Split { source, index, .. } => expr_uses_script_size(source) || expr_uses_script_size(index),
@@ -264,7 +812,7 @@ fn expr_uses_script_size(expr: &Expr) -> bool {
         Expr::ArrayIndex { source, index } => expr_uses_script_size(source) || expr_uses_script_size(index),
         Expr::Unary { expr, .. } => expr_uses_script_size(expr),
         Expr::Binary { left, right, .. } => expr_uses_script_size(left) || expr_uses_script_size(right),
-        Expr::IfElse { condition, then_expr, else_expr } => {
+        Expr::IfElse { condition, then_expr, else_expr, .. } => {
             expr_uses_script_size(condition) || expr_uses_script_size(then_expr) || expr_uses_script_size(else_expr)
         }
         Expr::Nullary(op) => matches!(op, NullaryOp::ThisScriptSize | NullaryOp::ThisScriptSizeDataPrefix),
@@ -301,13 +849,10 @@ fn array_literal_matches_type(values: &[Expr], type_name: &str) -> bool {
     match element_type {
         "int" => values.iter().all(|value| matches!(value, Expr::Int(_))),
         "byte" => values.iter().all(|value| matches!(value, Expr::Bytes(bytes) if bytes.len() == 1)),
-        _ => {
-            if let Some(size) = element_type.strip_prefix("bytes").and_then(|v| v.parse::<usize>().ok()) {
-                values.iter().all(|value| matches!(value, Expr::Bytes(bytes) if bytes.len() == size))
-            } else {
-                false
-            }
-        }
+        _ => match fixed_type_size(element_type) {
+            Some(size) => values.iter().all(|value| matches!(value, Expr::Bytes(bytes) if bytes.len() == size as usize)),
+            None => false,
+        },
     }
 }
 
@@ -321,8 +866,27 @@ fn build_function_abi(contract: &ContractAst) -> FunctionAbi {
             inputs: func
                 .params
                 .iter()
-                .map(|param| FunctionInputAbi { name: param.name.clone(), type_name: param.type_name.clone() })
+                .map(|param| FunctionInputAbi { name: param.name.clone(), type_name: param.type_name.clone(), scale: param.scale })
                 .collect(),
+            preconditions: function_preconditions(&func.body),
+        })
+        .collect()
+}
+
+/// Conditions every successful spend of a function satisfies: its top-level `require`s and time checks.
+/// Checks nested under `if` or `for` only hold on some paths and are left out.
+fn function_preconditions(body: &[Statement]) -> Vec<String> {
+    body.iter()
+        .filter_map(|stmt| match &stmt.kind {
+            StatementKind::Require { expr, .. } => Some(expr.to_string()),
+            StatementKind::TimeOp { tx_var, expr, .. } => {
+                let var = match tx_var {
+                    TimeVar::ThisAge => "this.age",
+                    TimeVar::TxTime => "tx.time",
+                };
+                Some(format!("{var} >= {expr}"))
+            }
+            _ => None,
         })
         .collect()
 }
@@ -339,6 +903,7 @@ fn fixed_type_size(type_name: &str) -> Option<i64> {
     match type_name {
         "int" => Some(8),
         "byte" => Some(1),
+        "pubkey" => Some(32),
         _ => type_name.strip_prefix("bytes").and_then(|v| v.parse::<i64>().ok()),
     }
 }
@@ -347,6 +912,50 @@ fn array_element_size(type_name: &str) -> Option<i64> {
     array_element_type(type_name).and_then(fixed_type_size)
 }
 
+/// Type used to decode a `log()` argument in the debugger.
+fn log_arg_type(expr: &Expr, env: &HashMap<String, Expr>, types: &HashMap<String, String>) -> String {
+    match expr {
+        Expr::String(_) => "string".to_string(),
+        Expr::Bool(_) => "bool".to_string(),
+        Expr::Identifier(name) if types.contains_key(name) => types[name].clone(),
+        _ if expr_is_bytes(expr, env, types) => "bytes".to_string(),
+        _ => "int".to_string(),
+    }
+}
+
+/// Type of a `let` binding: comparisons and logical operators are `bool`, a variable keeps its
+/// declared type, `asPubkey`/`asSig` give `pubkey`/`sig`, and anything else is `bytes` or `int` by
+/// how it compiles.
+fn inferred_type(expr: &Expr, env: &HashMap<String, Expr>, types: &HashMap<String, String>) -> String {
+    match expr {
+        Expr::Binary { op: BinaryOp::Or | BinaryOp::And, .. } | Expr::Unary { op: UnaryOp::Not, .. } => "bool".to_string(),
+        Expr::Binary { op: BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge, .. } => {
+            "bool".to_string()
+        }
+        Expr::IfElse { then_expr, .. } => inferred_type(then_expr, env, types),
+        _ => match retyped_type(expr) {
+            Some(type_name) => type_name.to_string(),
+            None => log_arg_type(expr, env, types),
+        },
+    }
+}
+
+/// Arms of a source ternary must have the same type; an unsized `bytes` value also goes with a sized one.
+fn check_ternary_arms(
+    then_expr: &Expr,
+    else_expr: &Expr,
+    env: &HashMap<String, Expr>,
+    types: &HashMap<String, String>,
+) -> Result<(), CompilerError> {
+    let then_type = inferred_type(then_expr, env, types);
+    let else_type = inferred_type(else_expr, env, types);
+    let unsized_bytes = (then_type == "bytes" || else_type == "bytes") && is_bytes_type(&then_type) && is_bytes_type(&else_type);
+    if then_type != else_type && !unsized_bytes {
+        return Err(CompilerError::Unsupported(format!("ternary arms must have the same type, got {then_type} and {else_type}")));
+    }
+    Ok(())
+}
+
 fn contains_return(stmt: &Statement) -> bool {
     match &stmt.kind {
         StatementKind::Return { .. } => true,
@@ -414,7 +1023,10 @@ fn expr_matches_type_with_env(expr: &Expr, type_name: &str, types: &HashMap<Stri
     match expr {
         Expr::Identifier(name) => types.get(name).is_some_and(|t| t == type_name),
         Expr::Array(values) => is_array_type(type_name) && array_literal_matches_type(values, type_name),
-        _ => expr_matches_type(expr, type_name),
+        _ => match retyped_type(expr) {
+            Some(retyped) => retyped == type_name,
+            None => expr_matches_type(expr, type_name),
+        },
     }
 }
 
@@ -427,8 +1039,57 @@ fn expr_matches_return_type(expr: &Expr, type_name: &str, types: &HashMap<String
     }
 }
 
+/// One push of a sigscript: an argument, or the trailing entrypoint selector (named `selector`, type `int`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigScriptPush {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// The push opcode and its data, as hex.
+    pub push_hex: String,
+}
+
 impl CompiledContract {
     pub fn build_sig_script(&self, function_name: &str, args: Vec<Expr>) -> Result<Vec<u8>, CompilerError> {
+        let (function, args) = self.checked_sig_script_args(function_name, args)?;
+        let mut builder = ScriptBuilder::new();
+        for (input, arg) in function.inputs.iter().zip(args) {
+            push_function_arg(&mut builder, input, arg)?;
+        }
+        if !self.without_selector {
+            let selector = function_branch_index(&self.ast, function_name)?;
+            builder.add_i64(selector)?;
+        }
+        Ok(builder.drain())
+    }
+
+    /// The pushes `build_sig_script` concatenates, one per argument in order, then the selector.
+    pub fn sig_script_pushes(&self, function_name: &str, args: Vec<Expr>) -> Result<Vec<SigScriptPush>, CompilerError> {
+        let (function, args) = self.checked_sig_script_args(function_name, args)?;
+        let mut pushes = Vec::with_capacity(args.len() + 1);
+        for (input, arg) in function.inputs.iter().zip(args) {
+            let mut builder = ScriptBuilder::new();
+            push_function_arg(&mut builder, input, arg)?;
+            pushes.push(SigScriptPush {
+                name: input.name.clone(),
+                type_name: input.type_name.clone(),
+                push_hex: faster_hex::hex_string(&builder.drain()),
+            });
+        }
+        if !self.without_selector {
+            let selector = function_branch_index(&self.ast, function_name)?;
+            let push = ScriptBuilder::new().add_i64(selector)?.drain();
+            pushes.push(SigScriptPush {
+                name: "selector".to_string(),
+                type_name: "int".to_string(),
+                push_hex: faster_hex::hex_string(&push),
+            });
+        }
+        Ok(pushes)
+    }
+
+    /// Looks up `function_name`, scales decimal arguments and checks them against the declared params.
+    fn checked_sig_script_args(&self, function_name: &str, args: Vec<Expr>) -> Result<(&FunctionAbiEntry, Vec<Expr>), CompilerError> {
         let function = self
             .abi
             .iter()
@@ -436,47 +1097,70 @@ impl CompiledContract {
             .ok_or_else(|| CompilerError::Unsupported(format!("function '{}' not found", function_name)))?;
 
         if function.inputs.len() != args.len() {
-            return Err(CompilerError::Unsupported(format!(
-                "function '{}' expects {} arguments",
-                function_name,
-                function.inputs.len()
-            )));
+            return Err(CompilerError::Unsupported(function.arg_count_mismatch(args.len())));
         }
 
+        let args = function
+            .inputs
+            .iter()
+            .zip(args)
+            .map(|(input, arg)| decimals::scale_arg(arg, input.scale, &input.name))
+            .collect::<Result<Vec<_>, _>>()?;
         for (input, arg) in function.inputs.iter().zip(args.iter()) {
             if !expr_matches_type(arg, &input.type_name) {
                 return Err(CompilerError::Unsupported(format!("function argument '{}' expects {}", input.name, input.type_name)));
             }
         }
+        Ok((function, args))
+    }
 
-        let mut builder = ScriptBuilder::new();
-        for (input, arg) in function.inputs.iter().zip(args) {
-            if is_array_type(&input.type_name) {
-                match arg {
-                    Expr::Array(values) => {
-                        let bytes = encode_array_literal(&values, &input.type_name)?;
-                        builder.add_data(&bytes)?;
-                    }
-                    Expr::Bytes(value) => {
-                        builder.add_data(&value)?;
-                    }
-                    _ => {
-                        return Err(CompilerError::Unsupported(format!(
-                            "function argument '{}' expects {}",
-                            input.name, input.type_name
-                        )));
-                    }
-                }
-            } else {
-                push_sigscript_arg(&mut builder, arg)?;
-            }
+    /// Like `build_sig_script`, but takes arguments by parameter name instead of by position.
+    pub fn build_sig_script_named(&self, function_name: &str, args: &BTreeMap<String, Expr>) -> Result<Vec<u8>, CompilerError> {
+        let args = self.positional_args(function_name, args)?;
+        self.build_sig_script(function_name, args)
+    }
+
+    /// Orders `args` by the declared parameters of `function_name`. Every parameter must be named
+    /// exactly once, and names that are not parameters are rejected.
+    pub fn positional_args(&self, function_name: &str, args: &BTreeMap<String, Expr>) -> Result<Vec<Expr>, CompilerError> {
+        let function = self
+            .abi
+            .iter()
+            .find(|entry| entry.name == function_name)
+            .ok_or_else(|| CompilerError::Unsupported(format!("function '{}' not found", function_name)))?;
+
+        if let Some(unknown) = args.keys().find(|name| !function.inputs.iter().any(|input| &input.name == *name)) {
+            return Err(CompilerError::Unsupported(format!("function '{}' has no parameter '{}'", function_name, unknown)));
         }
-        if !self.without_selector {
-            let selector = function_branch_index(&self.ast, function_name)?;
-            builder.add_i64(selector)?;
+        function
+            .inputs
+            .iter()
+            .map(|input| {
+                args.get(&input.name).cloned().ok_or_else(|| {
+                    CompilerError::Unsupported(format!("function '{}' is missing argument '{}'", function_name, input.name))
+                })
+            })
+            .collect()
+    }
+}
+
+fn push_function_arg(builder: &mut ScriptBuilder, input: &FunctionInputAbi, arg: Expr) -> Result<(), CompilerError> {
+    if !is_array_type(&input.type_name) {
+        return push_sigscript_arg(builder, arg);
+    }
+    match arg {
+        Expr::Array(values) => {
+            let bytes = encode_array_literal(&values, &input.type_name)?;
+            builder.add_data(&bytes)?;
+        }
+        Expr::Bytes(value) => {
+            builder.add_data(&value)?;
+        }
+        _ => {
+            return Err(CompilerError::Unsupported(format!("function argument '{}' expects {}", input.name, input.type_name)));
         }
-        Ok(builder.drain())
     }
+    Ok(())
 }
 
 fn push_sigscript_arg(builder: &mut ScriptBuilder, arg: Expr) -> Result<(), CompilerError> {
@@ -525,9 +1209,8 @@ fn encode_array_literal(values: &[Expr], type_name: &str) -> Result<Vec<u8>, Com
             }
         }
         _ => {
-            let size = element_type
-                .strip_prefix("bytes")
-                .and_then(|v| v.parse::<usize>().ok())
+            let size = fixed_type_size(element_type)
+                .map(|size| size as usize)
                 .ok_or_else(|| CompilerError::Unsupported("array element type must have known size".to_string()))?;
             for value in values {
                 let Expr::Bytes(bytes) = value else {
@@ -544,15 +1227,18 @@ fn encode_array_literal(values: &[Expr], type_name: &str) -> Result<Vec<u8>, Com
 }
 
 pub fn function_branch_index(contract: &ContractAst, function_name: &str) -> Result<i64, CompilerError> {
-    contract
-        .functions
-        .iter()
-        .filter(|func| func.entrypoint)
-        .position(|func| func.name == function_name)
-        .map(|index| index as i64)
+    selectors::entrypoint_selectors(contract)?
+        .into_iter()
+        .find(|(name, _)| name == function_name)
+        .map(|(_, selector)| selector)
         .ok_or_else(|| CompilerError::Unsupported(format!("function '{function_name}' not found")))
 }
 
+/// Stack slot an entrypoint reads its `index`-th param from, counted from the top once the selector is consumed.
+fn param_stack_depth(index: usize, param_count: usize) -> i64 {
+    (param_count - 1 - index) as i64
+}
+
 fn compile_function(
     function: &FunctionAst,
     function_index: usize,
@@ -574,7 +1260,7 @@ fn compile_function(
         if is_array_type(&param.type_name) && array_element_size(&param.type_name).is_none() {
             return Err(CompilerError::Unsupported(format!("array element type must have known size: {}", param.type_name)));
         }
-        params.insert(param.name.clone(), (param_count - 1 - index) as i64);
+        params.insert(param.name.clone(), param_stack_depth(index, param_count));
         types.insert(param.name.clone(), param.type_name.clone());
     }
 
@@ -597,6 +1283,7 @@ fn compile_function(
             function_index,
             script_size,
             inline_frame_counter: 1,
+            ternaries: Vec::new(),
         };
         body_compiler.compile_function_body(function, &mut env, &params, &mut types)?
     };
@@ -610,21 +1297,127 @@ fn compile_function(
             for _ in 0..param_count {
                 builder.add_op(OpDrop)?;
             }
+            if options.hardened {
+                emit_clean_stack_check(&mut builder, 0)?;
+            }
             builder.add_op(OpTrue)?;
         } else {
             let mut stack_depth = 0i64;
             for expr in &yields {
-                compile_expr(expr, &env, &params, &types, &mut builder, options, &mut HashSet::new(), &mut stack_depth, script_size)?;
-            }
-            for _ in 0..param_count {
-                builder.add_i64(yield_count as i64)?;
+                compile_expr(
+                    expr,
+                    &env,
+                    &params,
+                    &types,
+                    &mut builder,
+                    options,
+                    &mut HashSet::new(),
+                    &mut stack_depth,
+                    &mut Vec::new(),
+                    script_size,
+                )?;
+            }
+            for _ in 0..param_count {
+                builder.add_i64(yield_count as i64)?;
                 builder.add_op(OpRoll)?;
                 builder.add_op(OpDrop)?;
             }
+            if options.hardened {
+                emit_clean_stack_check(&mut builder, yield_count as i64)?;
+            }
+        }
+    }
+
+    let script = builder.drain();
+    let script = if options.optimize { peephole::optimize(script, &mut recorder) } else { script };
+    let max_mass = function.annotations.iter().find(|annotation| annotation.name == "maxMass").map(max_mass_budget).transpose()?;
+
+    Ok(CompiledFunction { name: function.name.clone(), script, debug: recorder, max_mass })
+}
+
+/// Enforces `@maxMass` on `path`, the instructions a spend of the entrypoint executes once it is
+/// dispatched: the selector guards tried before it, its own script and the closing `OpElse`/`OpEndIf`s.
+fn check_max_mass(compiled: &CompiledFunction, path: &[u8]) -> Result<(), CompilerError> {
+    let Some(allowed) = compiled.max_mass else {
+        return Ok(());
+    };
+    let computed = mass::estimate_script_mass(path);
+    if computed > allowed {
+        return Err(CompilerError::MassBudgetExceeded { function: compiled.name.clone(), computed, allowed });
+    }
+    Ok(())
+}
+
+/// Map keys and values must be literals of the declared types, and keys must be distinct.
+fn validate_map(
+    name: &str,
+    key_type: &str,
+    value_type: &str,
+    entries: &[(Expr, Expr)],
+    default: Option<&Expr>,
+) -> Result<(), CompilerError> {
+    if entries.is_empty() && default.is_none() {
+        return Err(CompilerError::Unsupported(format!("map '{name}' has no entries")));
+    }
+    for (index, (key, value)) in entries.iter().enumerate() {
+        if !expr_matches_type(key, key_type) {
+            return Err(CompilerError::Unsupported(format!("map '{name}' keys must be constant {key_type} literals")));
+        }
+        if !expr_matches_type(value, value_type) {
+            return Err(CompilerError::Unsupported(format!("map '{name}' values must be constant {value_type} literals")));
+        }
+        if entries[..index].iter().any(|(previous, _)| previous == key) {
+            return Err(CompilerError::Unsupported(format!("map '{name}' has duplicate key {}", describe_map_key(key))));
+        }
+    }
+    if default.is_some_and(|value| !expr_matches_type(value, value_type)) {
+        return Err(CompilerError::Unsupported(format!("map '{name}' default must be a constant {value_type} literal")));
+    }
+    Ok(())
+}
+
+fn describe_map_key(key: &Expr) -> String {
+    match key {
+        Expr::Int(value) => value.to_string(),
+        Expr::Bool(value) => value.to_string(),
+        Expr::Bytes(bytes) => format!("0x{}", faster_hex::hex_string(bytes)),
+        Expr::String(value) => format!("\"{value}\""),
+        _ => format!("{key:?}"),
+    }
+}
+
+fn validate_function_annotations(function: &FunctionAst) -> Result<(), CompilerError> {
+    for annotation in &function.annotations {
+        match annotation.name.as_str() {
+            "maxMass" => {
+                if !function.entrypoint {
+                    return Err(CompilerError::Unsupported("@maxMass is only supported on entrypoint functions".to_string()));
+                }
+                max_mass_budget(annotation)?;
+            }
+            "selector" => {
+                if !function.entrypoint {
+                    return Err(CompilerError::Unsupported("@selector is only supported on entrypoint functions".to_string()));
+                }
+                selectors::selector_pin(annotation)?;
+            }
+            "test" => {
+                if !function.entrypoint {
+                    return Err(CompilerError::Unsupported("@test is only supported on entrypoint functions".to_string()));
+                }
+                test_vectors::test_vector(annotation)?;
+            }
+            other => return Err(CompilerError::Unsupported(format!("unknown annotation '@{other}'"))),
         }
     }
+    Ok(())
+}
 
-    Ok(CompiledFunction { name: function.name.clone(), script: builder.drain(), debug: recorder })
+fn max_mass_budget(annotation: &AnnotationAst) -> Result<u64, CompilerError> {
+    match annotation.args.as_slice() {
+        [Expr::Int(value)] if *value >= 0 => Ok(*value as u64),
+        _ => Err(CompilerError::Unsupported("@maxMass expects a single non-negative integer".to_string())),
+    }
 }
 
 struct FunctionBodyCompiler<'a> {
@@ -637,6 +1430,8 @@ struct FunctionBodyCompiler<'a> {
     function_index: usize,
     script_size: Option<i64>,
     inline_frame_counter: u32,
+    /// Source ternaries compiled so far; each statement claims the ones it compiled once it is recorded.
+    ternaries: Vec<TernaryRange>,
 }
 
 impl<'a> FunctionBodyCompiler<'a> {
@@ -692,6 +1487,7 @@ impl<'a> FunctionBodyCompiler<'a> {
                 self.options,
                 &mut HashSet::new(),
                 &mut stack_depth,
+                &mut self.ternaries,
                 self.script_size,
             )?;
             self.builder.add_op(OpDrop)?;
@@ -709,7 +1505,11 @@ impl<'a> FunctionBodyCompiler<'a> {
         yields: &mut Vec<Expr>,
     ) -> Result<(), CompilerError> {
         let start = self.builder.script().len();
+        let ternaries = self.ternaries.len();
         let mut variables = Vec::new();
+        if self.options.optimize {
+            self.debug_recorder.record_folds(stmt, env);
+        }
 
         match &stmt.kind {
             StatementKind::VariableDefinition { type_name, name, expr, .. } => {
@@ -737,11 +1537,27 @@ impl<'a> FunctionBodyCompiler<'a> {
                     let expr = expr
                         .clone()
                         .ok_or_else(|| CompilerError::Unsupported("variable definition requires initializer".to_string()))?;
+                    if is_bytes_type(type_name) {
+                        let declared = fixed_type_size(type_name).map(|size| size as usize);
+                        if let (Some(declared), Some(actual)) = (declared, static_bytes_len(&expr, env, types)) {
+                            if declared != actual {
+                                return Err(CompilerError::Unsupported(format!(
+                                    "variable '{name}' is declared {type_name} but its value is {actual} bytes"
+                                )));
+                            }
+                        }
+                    }
                     self.debug_recorder.variable_update(env, &mut variables, name, type_name, expr.clone())?;
                     env.insert(name.clone(), expr);
                     types.insert(name.clone(), type_name.clone());
                 }
             }
+            StatementKind::Let { name, expr } => {
+                let type_name = inferred_type(expr, env, types);
+                self.debug_recorder.variable_update(env, &mut variables, name, &type_name, expr.clone())?;
+                env.insert(name.clone(), expr.clone());
+                types.insert(name.clone(), type_name);
+            }
             StatementKind::ArrayPush { name, expr, .. } => {
                 let array_type = types.get(name).ok_or_else(|| CompilerError::UndefinedIdentifier(name.clone()))?;
                 if !is_array_type(array_type) {
@@ -755,7 +1571,7 @@ impl<'a> FunctionBodyCompiler<'a> {
                     Expr::Call { name: "bytes8".to_string(), args: vec![expr.clone()] }
                 } else if element_type == "byte" {
                     Expr::Call { name: "bytes1".to_string(), args: vec![expr.clone()] }
-                } else if element_type.starts_with("bytes") {
+                } else if element_type.starts_with("bytes") || element_type == "pubkey" {
                     if expr_is_bytes(expr, env, types) {
                         expr.clone()
                     } else {
@@ -770,6 +1586,8 @@ impl<'a> FunctionBodyCompiler<'a> {
                 self.debug_recorder.variable_update(env, &mut variables, name, array_type, updated.clone())?;
                 env.insert(name.clone(), updated);
             }
+            StatementKind::Require { covenant_only: true, .. } if self.options.covenants_disabled => {}
+            StatementKind::Require { expr, .. } if self.options.optimize && literal_condition(expr) == Some(true) => {}
             StatementKind::Require { expr, .. } => {
                 let mut stack_depth = 0i64;
                 compile_expr(
@@ -781,18 +1599,32 @@ impl<'a> FunctionBodyCompiler<'a> {
                     self.options,
                     &mut HashSet::new(),
                     &mut stack_depth,
+                    &mut self.ternaries,
                     self.script_size,
                 )?;
                 self.builder.add_op(OpVerify)?;
+                self.debug_recorder.record_check(stmt, expr, start, self.builder.script().len());
             }
             StatementKind::TimeOp { tx_var, expr, .. } => {
-                compile_time_op_statement(tx_var, expr, env, params, types, self.builder, self.options, self.script_size)?;
+                compile_time_op_statement(
+                    tx_var,
+                    expr,
+                    env,
+                    params,
+                    types,
+                    self.builder,
+                    self.options,
+                    &mut self.ternaries,
+                    self.script_size,
+                )?;
             }
-            StatementKind::If { condition, then_branch, else_branch, .. } => {
-                self.compile_if_statement(condition, then_branch, else_branch.as_deref(), env, params, types, yields)?;
+            StatementKind::If { condition, then_branch, else_branch, header_span } => {
+                let else_branch = else_branch.as_deref();
+                let header_span = header_span.or(stmt.span);
+                variables = self.compile_if_statement(condition, then_branch, else_branch, env, params, types, yields, header_span)?;
             }
-            StatementKind::For { ident, start, end, body, .. } => {
-                self.compile_for_statement(ident, start, end, body, env, params, types, yields, stmt.span)?;
+            StatementKind::For { ident, start, end, body, header_span } => {
+                self.compile_for_statement(ident, start, end, body, env, params, types, yields, header_span.or(stmt.span))?;
             }
             StatementKind::Yield { expr, .. } => {
                 let mut visiting = HashSet::new();
@@ -813,6 +1645,89 @@ impl<'a> FunctionBodyCompiler<'a> {
                 }
                 _ => return Err(CompilerError::Unsupported("tuple assignment only supports split()".to_string())),
             },
+            StatementKind::FunctionCall { name, args, .. } if name == "requireCovenant" && !self.functions.contains_key(name) => {
+                let condition = require_covenant_condition(args)?;
+                let mut stack_depth = 0i64;
+                compile_expr(
+                    &condition,
+                    env,
+                    params,
+                    types,
+                    self.builder,
+                    self.options,
+                    &mut HashSet::new(),
+                    &mut stack_depth,
+                    &mut self.ternaries,
+                    self.script_size,
+                )?;
+                self.builder.add_op(OpVerify)?;
+            }
+            StatementKind::FunctionCall { name, args, .. }
+                if name == "requireAllOutputsMatch" && !self.functions.contains_key(name) =>
+            {
+                let script = match args.as_slice() {
+                    [] => Expr::Nullary(NullaryOp::ActiveBytecode),
+                    [script] if expr_is_bytes(script, env, types) => script.clone(),
+                    _ => {
+                        return Err(CompilerError::Unsupported(
+                            "requireAllOutputsMatch() expects no arguments or a single locking script".to_string(),
+                        ));
+                    }
+                };
+                add_all_outputs_match(&script, env, params, types, self.builder, self.options, &mut self.ternaries, self.script_size)?;
+            }
+            StatementKind::FunctionCall { name, args, .. } if name == "requireLength" && !self.functions.contains_key(name) => {
+                let [value, length] = args.as_slice() else {
+                    return Err(CompilerError::Unsupported("requireLength() expects a byte string and a length".to_string()));
+                };
+                if !expr_is_bytes(value, env, types) {
+                    return Err(CompilerError::Unsupported("requireLength() expects a byte string as its first argument".to_string()));
+                }
+                let static_length = resolve_expr(length.clone(), env, &mut HashSet::new());
+                if let (Some(len), Ok(Expr::Int(expected))) = (static_bytes_len(value, env, types), static_length) {
+                    if len as i64 != expected {
+                        return Err(CompilerError::Unsupported(format!(
+                            "requireLength() always fails: the value is {len} bytes, not {expected}"
+                        )));
+                    }
+                }
+                // `value size length` -> `value`, failing unless the sizes match; then drop the value.
+                let mut stack_depth = 0i64;
+                compile_expr(
+                    value,
+                    env,
+                    params,
+                    types,
+                    self.builder,
+                    self.options,
+                    &mut HashSet::new(),
+                    &mut stack_depth,
+                    &mut self.ternaries,
+                    self.script_size,
+                )?;
+                self.builder.add_op(OpSize)?;
+                stack_depth += 1;
+                compile_expr(
+                    length,
+                    env,
+                    params,
+                    types,
+                    self.builder,
+                    self.options,
+                    &mut HashSet::new(),
+                    &mut stack_depth,
+                    &mut self.ternaries,
+                    self.script_size,
+                )?;
+                self.builder.add_op(OpNumEqualVerify)?;
+                let condition = Expr::Binary {
+                    op: BinaryOp::Eq,
+                    left: Box::new(Expr::Call { name: "length".to_string(), args: vec![value.clone()] }),
+                    right: Box::new(length.clone()),
+                };
+                self.debug_recorder.record_check(stmt, &condition, start, self.builder.script().len());
+                self.builder.add_op(OpDrop)?;
+            }
             StatementKind::FunctionCall { name, args, .. } => {
                 self.compile_inline_call_and_discard_returns(name, args, params, types, env, stmt.span)?;
             }
@@ -890,12 +1805,27 @@ impl<'a> FunctionBodyCompiler<'a> {
                 }
             }
             StatementKind::Console { .. } => {}
+            StatementKind::Log { args } => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    self.debug_recorder.variable_update(env, &mut values, "", &log_arg_type(arg, env, types), arg.clone())?;
+                }
+                self.debug_recorder.record_log(stmt, start, values, None);
+                return Ok(());
+            }
+            StatementKind::WarnIf { condition, message } => {
+                let mut values = Vec::with_capacity(1);
+                self.debug_recorder.variable_update(env, &mut values, "", "bool", condition.clone())?;
+                self.debug_recorder.record_log(stmt, start, values, Some(message.clone()));
+                return Ok(());
+            }
         }
 
         let end = self.builder.script().len();
         // Record updates at the end of the statement so variables reflect post-statement state
         // when the debugger is paused at the next byte offset.
         self.debug_recorder.record_statement_updates(stmt, start, end, variables);
+        self.debug_recorder.record_ternaries(self.ternaries.split_off(ternaries));
         Ok(())
     }
 
@@ -993,6 +1923,7 @@ impl<'a> FunctionBodyCompiler<'a> {
                 function_index: callee_index,
                 script_size: self.script_size,
                 inline_frame_counter: self.inline_frame_counter,
+                ternaries: Vec::new(),
             };
             let yields = callee_compiler.compile_function_body(function, env, caller_params, types)?;
             (yields, callee_compiler.inline_frame_counter)
@@ -1016,7 +1947,9 @@ impl<'a> FunctionBodyCompiler<'a> {
         params: &HashMap<String, i64>,
         types: &mut HashMap<String, String>,
         yields: &mut Vec<Expr>,
-    ) -> Result<(), CompilerError> {
+        header_span: Option<SourceSpan>,
+    ) -> Result<Vec<(String, String, Expr)>, CompilerError> {
+        let header_start = self.builder.script().len();
         let mut stack_depth = 0i64;
         compile_expr(
             condition,
@@ -1027,27 +1960,42 @@ impl<'a> FunctionBodyCompiler<'a> {
             self.options,
             &mut HashSet::new(),
             &mut stack_depth,
+            &mut self.ternaries,
             self.script_size,
         )?;
         self.builder.add_op(OpIf)?;
+        self.debug_recorder.record_condition(header_span, header_start, self.builder.script().len());
 
         let original_env = env.clone();
         let mut then_env = original_env.clone();
         let mut then_types = types.clone();
+        let scope = self.debug_recorder.open_scope();
         self.compile_block(then_branch, &mut then_env, params, &mut then_types, yields)?;
+        self.debug_recorder.close_scope(scope);
 
         let mut else_env = original_env.clone();
         if let Some(else_branch) = else_branch {
             self.builder.add_op(OpElse)?;
             let mut else_types = types.clone();
+            let scope = self.debug_recorder.open_scope();
             self.compile_block(else_branch, &mut else_env, params, &mut else_types, yields)?;
+            self.debug_recorder.close_scope(scope);
         }
 
         self.builder.add_op(OpEndIf)?;
 
         let resolved_condition = resolve_expr(condition.clone(), &original_env, &mut HashSet::new())?;
         merge_env_after_if(env, &original_env, &then_env, &else_env, &resolved_condition);
-        Ok(())
+
+        // Branch updates end with their branch; outer variables a branch changed get their merged value.
+        let mut changed: Vec<&String> = original_env.keys().filter(|name| env.get(*name) != original_env.get(*name)).collect();
+        changed.sort();
+        let mut variables = Vec::new();
+        for name in changed {
+            let type_name = types.get(name).map_or("unknown", String::as_str);
+            self.debug_recorder.variable_update(env, &mut variables, name, type_name, env[name].clone())?;
+        }
+        Ok(variables)
     }
 
     fn compile_block(
@@ -1074,10 +2022,19 @@ impl<'a> FunctionBodyCompiler<'a> {
         params: &HashMap<String, i64>,
         types: &mut HashMap<String, String>,
         yields: &mut Vec<Expr>,
-        span: Option<SourceSpan>,
+        header_span: Option<SourceSpan>,
     ) -> Result<(), CompilerError> {
-        let start = eval_const_int(start_expr, self.contract_constants)?;
-        let end = eval_const_int(end_expr, self.contract_constants)?;
+        // Bounds must fold to integers so the body can be unrolled; point at the header when they do not.
+        let bound = |expr: &Expr| {
+            eval_const_int(expr, self.contract_constants).map_err(|err| match err {
+                CompilerError::Unsupported(reason) | CompilerError::InvalidLiteral(reason) => {
+                    CompilerError::InvalidLoopBound { reason, span: header_span }
+                }
+                other => other,
+            })
+        };
+        let start = bound(start_expr)?;
+        let end = bound(end_expr)?;
         if end < start {
             return Err(CompilerError::Unsupported("for loop end must be >= start".to_string()));
         }
@@ -1090,7 +2047,9 @@ impl<'a> FunctionBodyCompiler<'a> {
             let index_expr = Expr::Int(value);
             env.insert(name.clone(), index_expr.clone());
             let bytecode_offset = self.builder.script().len();
-            self.debug_recorder.record_virtual_updates(span, bytecode_offset, vec![(name.clone(), "int".to_string(), index_expr)]);
+            // One step per iteration, at the header, so the debugger shows which unrolled pass is running.
+            let counter = vec![(name.clone(), "int".to_string(), index_expr)];
+            self.debug_recorder.record_virtual_updates(header_span, bytecode_offset, counter);
             self.compile_block(body, env, params, types, yields)?;
         }
 
@@ -1135,12 +2094,62 @@ fn merge_env_after_if(
                     condition: Box::new(condition.clone()),
                     then_expr: Box::new(then_expr.clone()),
                     else_expr: Box::new(else_expr.clone()),
+                    spans: None,
                 },
             );
         }
     }
 }
 
+/// `requireCovenant(i)` holds when output `i` is locked by the same script as the input being spent.
+fn require_covenant_condition(args: &[Expr]) -> Result<Expr, CompilerError> {
+    let [index] = args else {
+        return Err(CompilerError::Unsupported("requireCovenant() expects a single output index".to_string()));
+    };
+    Ok(Expr::Binary {
+        op: BinaryOp::Eq,
+        left: Box::new(Expr::Introspection { kind: IntrospectionKind::OutputLockingBytecode, index: Box::new(index.clone()) }),
+        right: Box::new(Expr::Nullary(NullaryOp::ActiveBytecode)),
+    })
+}
+
+/// Most outputs `requireAllOutputsMatch()` checks. A spend with more fails rather than leaving outputs unchecked.
+pub const MAX_MATCHED_OUTPUTS: i64 = 8;
+
+/// Fails unless every output is locked by `script`. Like `tx.fee`, the check is unrolled over
+/// `MAX_MATCHED_OUTPUTS` slots, each guarded by the output count.
+fn add_all_outputs_match(
+    script: &Expr,
+    env: &HashMap<String, Expr>,
+    params: &HashMap<String, i64>,
+    types: &HashMap<String, String>,
+    builder: &mut ScriptBuilder,
+    options: CompileOptions,
+    ternaries: &mut Vec<TernaryRange>,
+    script_size: Option<i64>,
+) -> Result<(), CompilerError> {
+    builder.add_op(OpTxOutputCount)?;
+    builder.add_i64(MAX_MATCHED_OUTPUTS)?;
+    builder.add_op(OpLessThanOrEqual)?;
+    builder.add_op(OpVerify)?;
+    for index in 0..MAX_MATCHED_OUTPUTS {
+        builder.add_op(OpTxOutputCount)?;
+        builder.add_i64(index)?;
+        builder.add_op(OpGreaterThan)?;
+        builder.add_op(OpIf)?;
+        let condition = Expr::Binary {
+            op: BinaryOp::Eq,
+            left: Box::new(Expr::Introspection { kind: IntrospectionKind::OutputLockingBytecode, index: Box::new(Expr::Int(index)) }),
+            right: Box::new(script.clone()),
+        };
+        let mut stack_depth = 0i64;
+        compile_expr(&condition, env, params, types, builder, options, &mut HashSet::new(), &mut stack_depth, ternaries, script_size)?;
+        builder.add_op(OpVerify)?;
+        builder.add_op(OpEndIf)?;
+    }
+    Ok(())
+}
+
 fn compile_time_op_statement(
     tx_var: &TimeVar,
     expr: &Expr,
@@ -1149,10 +2158,11 @@ fn compile_time_op_statement(
     types: &HashMap<String, String>,
     builder: &mut ScriptBuilder,
     options: CompileOptions,
+    ternaries: &mut Vec<TernaryRange>,
     script_size: Option<i64>,
 ) -> Result<(), CompilerError> {
     let mut stack_depth = 0i64;
-    compile_expr(expr, env, params, types, builder, options, &mut HashSet::new(), &mut stack_depth, script_size)?;
+    compile_expr(expr, env, params, types, builder, options, &mut HashSet::new(), &mut stack_depth, ternaries, script_size)?;
 
     match tx_var {
         TimeVar::ThisAge => {
@@ -1178,45 +2188,144 @@ pub fn compile_debug_expr(
     let env = HashMap::new();
     let mut builder = ScriptBuilder::new();
     let mut stack_depth = 0i64;
-    compile_expr(expr, &env, params, types, &mut builder, CompileOptions::default(), &mut HashSet::new(), &mut stack_depth, None)?;
+    compile_expr(
+        expr,
+        &env,
+        params,
+        types,
+        &mut builder,
+        CompileOptions::default(),
+        &mut HashSet::new(),
+        &mut stack_depth,
+        &mut Vec::new(),
+        None,
+    )?;
     Ok(builder.drain())
 }
 
+/// Compiles a debugger watch expression. Unlike `compile_debug_expr`, identifiers may also name
+/// locals and constants, which `env` maps to their resolved expressions. Returns the bytecode and
+/// the type to decode its result as.
+pub fn compile_watch_expr(
+    expr: &Expr,
+    env: &HashMap<String, Expr>,
+    params: &HashMap<String, i64>,
+    types: &HashMap<String, String>,
+) -> Result<(Vec<u8>, String), CompilerError> {
+    let mut builder = ScriptBuilder::new();
+    let mut stack_depth = 0i64;
+    compile_expr(
+        expr,
+        env,
+        params,
+        types,
+        &mut builder,
+        CompileOptions::default(),
+        &mut HashSet::new(),
+        &mut stack_depth,
+        &mut Vec::new(),
+        None,
+    )?;
+    Ok((builder.drain(), inferred_type(expr, env, types)))
+}
+
+/// Value of a boolean condition built only from literals, or `None` if it depends on anything else.
+/// Identifiers are never folded, so a local shadowing a constant cannot make a check disappear.
+fn literal_condition(expr: &Expr) -> Option<bool> {
+    let no_constants = HashMap::new();
+    match expr {
+        Expr::Bool(value) => Some(*value),
+        Expr::Unary { op: UnaryOp::Not, expr } => literal_condition(expr).map(|value| !value),
+        Expr::Binary { op: BinaryOp::And, left, right } => match (literal_condition(left), literal_condition(right)) {
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            (Some(true), Some(true)) => Some(true),
+            _ => None,
+        },
+        Expr::Binary { op: BinaryOp::Or, left, right } => match (literal_condition(left), literal_condition(right)) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        },
+        Expr::Binary { op, left, right } => {
+            if let (Ok(lhs), Ok(rhs)) = (eval_const_int(left, &no_constants), eval_const_int(right, &no_constants)) {
+                return match op {
+                    BinaryOp::Eq => Some(lhs == rhs),
+                    BinaryOp::Ne => Some(lhs != rhs),
+                    BinaryOp::Lt => Some(lhs < rhs),
+                    BinaryOp::Le => Some(lhs <= rhs),
+                    BinaryOp::Gt => Some(lhs > rhs),
+                    BinaryOp::Ge => Some(lhs >= rhs),
+                    _ => None,
+                };
+            }
+            let literal = |expr: &Expr| matches!(expr, Expr::Bool(_) | Expr::Bytes(_) | Expr::String(_));
+            match op {
+                BinaryOp::Eq if literal(left) && literal(right) => Some(left == right),
+                BinaryOp::Ne if literal(left) && literal(right) => Some(left != right),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Warns about `require`s whose condition always holds.
+#[derive(Default)]
+struct NoOpRequires {
+    warnings: Vec<String>,
+}
+
+impl Visitor for NoOpRequires {
+    fn visit_statement(&mut self, stmt: &Statement) {
+        match &stmt.kind {
+            StatementKind::Require { expr, .. } if literal_condition(expr) == Some(true) => {
+                let location = stmt.span.map(|span| format!(" on line {}", span.line)).unwrap_or_default();
+                self.warnings.push(format!("require({expr}){location} always passes and only adds bytes; remove it"));
+            }
+            _ => {}
+        }
+    }
+}
+
 fn eval_const_int(expr: &Expr, constants: &HashMap<String, Expr>) -> Result<i64, CompilerError> {
     match expr {
         Expr::Int(value) => Ok(*value),
         Expr::Identifier(name) => match constants.get(name) {
             Some(value) => eval_const_int(value, constants),
-            None => Err(CompilerError::Unsupported("for loop bounds must be constant integers".to_string())),
+            None => Err(CompilerError::Unsupported("expected a constant integer expression".to_string())),
         },
-        Expr::Unary { op: UnaryOp::Neg, expr } => Ok(-eval_const_int(expr, constants)?),
-        Expr::Unary { .. } => Err(CompilerError::Unsupported("for loop bounds must be constant integers".to_string())),
+        Expr::Unary { op: UnaryOp::Neg, expr } => eval_const_int(expr, constants)?.checked_neg().ok_or_else(const_overflow),
+        Expr::Unary { .. } => Err(CompilerError::Unsupported("expected a constant integer expression".to_string())),
         Expr::Binary { op, left, right } => {
             let lhs = eval_const_int(left, constants)?;
             let rhs = eval_const_int(right, constants)?;
             match op {
-                BinaryOp::Add => Ok(lhs + rhs),
-                BinaryOp::Sub => Ok(lhs - rhs),
-                BinaryOp::Mul => Ok(lhs * rhs),
+                BinaryOp::Add => lhs.checked_add(rhs).ok_or_else(const_overflow),
+                BinaryOp::Sub => lhs.checked_sub(rhs).ok_or_else(const_overflow),
+                BinaryOp::Mul => lhs.checked_mul(rhs).ok_or_else(const_overflow),
                 BinaryOp::Div => {
                     if rhs == 0 {
-                        return Err(CompilerError::InvalidLiteral("division by zero in for loop bounds".to_string()));
+                        return Err(CompilerError::InvalidLiteral("division by zero in constant expression".to_string()));
                     }
-                    Ok(lhs / rhs)
+                    lhs.checked_div(rhs).ok_or_else(const_overflow)
                 }
                 BinaryOp::Mod => {
                     if rhs == 0 {
-                        return Err(CompilerError::InvalidLiteral("modulo by zero in for loop bounds".to_string()));
+                        return Err(CompilerError::InvalidLiteral("modulo by zero in constant expression".to_string()));
                     }
-                    Ok(lhs % rhs)
+                    lhs.checked_rem(rhs).ok_or_else(const_overflow)
                 }
-                _ => Err(CompilerError::Unsupported("for loop bounds must be constant integers".to_string())),
+                _ => Err(CompilerError::Unsupported("expected a constant integer expression".to_string())),
             }
         }
-        _ => Err(CompilerError::Unsupported("for loop bounds must be constant integers".to_string())),
+        _ => Err(CompilerError::Unsupported("expected a constant integer expression".to_string())),
     }
 }
 
+fn const_overflow() -> CompilerError {
+    CompilerError::InvalidLiteral("integer overflow in constant expression".to_string())
+}
+
 fn resolve_expr(expr: Expr, env: &HashMap<String, Expr>, visiting: &mut HashSet<String>) -> Result<Expr, CompilerError> {
     resolve_expr_internal(expr, env, visiting, true)
 }
@@ -1259,10 +2368,11 @@ fn resolve_expr_internal(
             left: Box::new(resolve_expr_internal(*left, env, visiting, preserve_inline_args)?),
             right: Box::new(resolve_expr_internal(*right, env, visiting, preserve_inline_args)?),
         }),
-        Expr::IfElse { condition, then_expr, else_expr } => Ok(Expr::IfElse {
+        Expr::IfElse { condition, then_expr, else_expr, spans } => Ok(Expr::IfElse {
             condition: Box::new(resolve_expr_internal(*condition, env, visiting, preserve_inline_args)?),
             then_expr: Box::new(resolve_expr_internal(*then_expr, env, visiting, preserve_inline_args)?),
             else_expr: Box::new(resolve_expr_internal(*else_expr, env, visiting, preserve_inline_args)?),
+            spans,
         }),
         Expr::Array(values) => {
             let mut resolved = Vec::with_capacity(values.len());
@@ -1337,15 +2447,22 @@ fn replace_identifier(expr: &Expr, target: &str, replacement: &Expr) -> Expr {
             source: Box::new(replace_identifier(source, target, replacement)),
             index: Box::new(replace_identifier(index, target, replacement)),
         },
-        Expr::IfElse { condition, then_expr, else_expr } => Expr::IfElse {
+        Expr::IfElse { condition, then_expr, else_expr, spans } => Expr::IfElse {
             condition: Box::new(replace_identifier(condition, target, replacement)),
             then_expr: Box::new(replace_identifier(then_expr, target, replacement)),
             else_expr: Box::new(replace_identifier(else_expr, target, replacement)),
+            spans: *spans,
         },
         Expr::Introspection { kind, index } => {
             Expr::Introspection { kind: *kind, index: Box::new(replace_identifier(index, target, replacement)) }
         }
-        Expr::Int(_) | Expr::Bool(_) | Expr::Bytes(_) | Expr::String(_) | Expr::Nullary(_) => expr.clone(),
+        Expr::Int(_)
+        | Expr::Bool(_)
+        | Expr::Bytes(_)
+        | Expr::String(_)
+        | Expr::Nullary(_)
+        | Expr::Map { .. }
+        | Expr::Decimal { .. } => expr.clone(),
     }
 }
 
@@ -1364,9 +2481,15 @@ fn compile_expr(
     options: CompileOptions,
     visiting: &mut HashSet<String>,
     stack_depth: &mut i64,
+    ternaries: &mut Vec<TernaryRange>,
     script_size: Option<i64>,
 ) -> Result<(), CompilerError> {
     let scope = CompilationScope { env, params, types };
+    if options.optimize {
+        if let Some(value) = fold::fold_constant(expr, env) {
+            return compile_expr(&value, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size);
+        }
+    }
     match expr {
         Expr::Int(value) => {
             builder.add_i64(*value)?;
@@ -1393,7 +2516,7 @@ fn compile_expr(
                 return Err(CompilerError::CyclicIdentifier(name.clone()));
             }
             if let Some(expr) = env.get(name) {
-                compile_expr(expr, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                compile_expr(expr, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                 visiting.remove(name);
                 return Ok(());
             }
@@ -1407,27 +2530,40 @@ fn compile_expr(
             visiting.remove(name);
             Err(CompilerError::UndefinedIdentifier(name.clone()))
         }
-        Expr::IfElse { condition, then_expr, else_expr } => {
-            compile_expr(condition, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+        Expr::IfElse { condition, then_expr, else_expr, spans } => {
+            if spans.is_some() {
+                check_ternary_arms(then_expr, else_expr, env, types)?;
+            }
+            let start = builder.script().len();
+            compile_expr(condition, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
             builder.add_op(OpIf)?;
             *stack_depth -= 1;
             let depth_before = *stack_depth;
-            compile_expr(then_expr, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+            let then_start = builder.script().len();
+            compile_expr(then_expr, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+            let then_end = builder.script().len();
             builder.add_op(OpElse)?;
             *stack_depth = depth_before;
-            compile_expr(else_expr, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+            let else_start = builder.script().len();
+            compile_expr(else_expr, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+            let else_end = builder.script().len();
             builder.add_op(OpEndIf)?;
             *stack_depth = depth_before + 1;
+            if let Some(spans) = spans.filter(|_| options.record_debug_infos) {
+                ternaries.push(TernaryRange { spans, start, then_start, then_end, else_start, else_end });
+            }
             Ok(())
         }
         Expr::Array(_) => Err(CompilerError::Unsupported("array literals are only supported in LockingBytecodeNullData".to_string())),
         Expr::Call { name, args } => match name.as_str() {
-            "OpSha256" => compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpSHA256, script_size),
+            "OpSha256" => {
+                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, ternaries, OpSHA256, script_size)
+            }
             "sha256" => {
                 if args.len() != 1 {
                     return Err(CompilerError::Unsupported("sha256() expects a single argument".to_string()));
                 }
-                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                 builder.add_op(OpSHA256)?;
                 Ok(())
             }
@@ -1451,25 +2587,87 @@ fn compile_expr(
                 *stack_depth += 1;
                 Ok(())
             }
-            "OpTxSubnetId" => {
-                compile_opcode_call(name, args, 0, &scope, builder, options, visiting, stack_depth, OpTxSubnetId, script_size)
-            }
-            "OpTxGas" => compile_opcode_call(name, args, 0, &scope, builder, options, visiting, stack_depth, OpTxGas, script_size),
-            "OpTxPayloadLen" => {
-                compile_opcode_call(name, args, 0, &scope, builder, options, visiting, stack_depth, OpTxPayloadLen, script_size)
-            }
-            "OpTxPayloadSubstr" => {
-                compile_opcode_call(name, args, 2, &scope, builder, options, visiting, stack_depth, OpTxPayloadSubstr, script_size)
-            }
-            "OpOutpointTxId" => {
-                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpOutpointTxId, script_size)
-            }
-            "OpOutpointIndex" => {
-                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpOutpointIndex, script_size)
-            }
-            "OpTxInputScriptSigLen" => {
-                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpTxInputScriptSigLen, script_size)
+            "OpTxSubnetId" => compile_opcode_call(
+                name,
+                args,
+                0,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpTxSubnetId,
+                script_size,
+            ),
+            "OpTxGas" => {
+                compile_opcode_call(name, args, 0, &scope, builder, options, visiting, stack_depth, ternaries, OpTxGas, script_size)
             }
+            "OpTxPayloadLen" => compile_opcode_call(
+                name,
+                args,
+                0,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpTxPayloadLen,
+                script_size,
+            ),
+            "OpTxPayloadSubstr" => compile_opcode_call(
+                name,
+                args,
+                2,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpTxPayloadSubstr,
+                script_size,
+            ),
+            "OpOutpointTxId" => compile_opcode_call(
+                name,
+                args,
+                1,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpOutpointTxId,
+                script_size,
+            ),
+            "OpOutpointIndex" => compile_opcode_call(
+                name,
+                args,
+                1,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpOutpointIndex,
+                script_size,
+            ),
+            "OpTxInputScriptSigLen" => compile_opcode_call(
+                name,
+                args,
+                1,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpTxInputScriptSigLen,
+                script_size,
+            ),
             "OpTxInputScriptSigSubstr" => compile_opcode_call(
                 name,
                 args,
@@ -1479,64 +2677,209 @@ fn compile_expr(
                 options,
                 visiting,
                 stack_depth,
+                ternaries,
                 OpTxInputScriptSigSubstr,
                 script_size,
             ),
-            "OpTxInputSeq" => {
-                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpTxInputSeq, script_size)
-            }
-            "OpTxInputIsCoinbase" => {
-                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpTxInputIsCoinbase, script_size)
-            }
-            "OpTxInputSpkLen" => {
-                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpTxInputSpkLen, script_size)
-            }
-            "OpTxInputSpkSubstr" => {
-                compile_opcode_call(name, args, 3, &scope, builder, options, visiting, stack_depth, OpTxInputSpkSubstr, script_size)
-            }
-            "OpTxOutputSpkLen" => {
-                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpTxOutputSpkLen, script_size)
-            }
-            "OpTxOutputSpkSubstr" => {
-                compile_opcode_call(name, args, 3, &scope, builder, options, visiting, stack_depth, OpTxOutputSpkSubstr, script_size)
-            }
-            "OpAuthOutputCount" => {
-                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpAuthOutputCount, script_size)
-            }
-            "OpAuthOutputIdx" => {
-                compile_opcode_call(name, args, 2, &scope, builder, options, visiting, stack_depth, OpAuthOutputIdx, script_size)
-            }
-            "OpInputCovenantId" => {
-                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpInputCovenantId, script_size)
-            }
-            "OpCovInputCount" => {
-                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpCovInputCount, script_size)
-            }
-            "OpCovInputIdx" => {
-                compile_opcode_call(name, args, 2, &scope, builder, options, visiting, stack_depth, OpCovInputIdx, script_size)
-            }
-            "OpCovOutCount" => {
-                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpCovOutCount, script_size)
-            }
-            "OpCovOutputIdx" => {
-                compile_opcode_call(name, args, 2, &scope, builder, options, visiting, stack_depth, OpCovOutputIdx, script_size)
-            }
-            "OpNum2Bin" => compile_opcode_call(name, args, 2, &scope, builder, options, visiting, stack_depth, OpNum2Bin, script_size),
-            "OpBin2Num" => compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpBin2Num, script_size),
-            "OpChainblockSeqCommit" => {
-                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, OpChainblockSeqCommit, script_size)
-            }
-            "bytes" => {
-                if args.is_empty() || args.len() > 2 {
-                    return Err(CompilerError::Unsupported("bytes() expects one or two arguments".to_string()));
-                }
-                if args.len() == 2 {
-                    compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
-                    compile_expr(&args[1], env, params, types, builder, options, visiting, stack_depth, script_size)?;
-                    builder.add_op(OpNum2Bin)?;
-                    *stack_depth -= 1;
-                    return Ok(());
-                }
+            "OpTxInputSeq" => compile_opcode_call(
+                name,
+                args,
+                1,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpTxInputSeq,
+                script_size,
+            ),
+            "OpTxInputIsCoinbase" => compile_opcode_call(
+                name,
+                args,
+                1,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpTxInputIsCoinbase,
+                script_size,
+            ),
+            "OpTxInputSpkLen" => compile_opcode_call(
+                name,
+                args,
+                1,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpTxInputSpkLen,
+                script_size,
+            ),
+            "OpTxInputSpkSubstr" => compile_opcode_call(
+                name,
+                args,
+                3,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpTxInputSpkSubstr,
+                script_size,
+            ),
+            "OpTxOutputSpkLen" => compile_opcode_call(
+                name,
+                args,
+                1,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpTxOutputSpkLen,
+                script_size,
+            ),
+            "OpTxOutputSpkSubstr" => compile_opcode_call(
+                name,
+                args,
+                3,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpTxOutputSpkSubstr,
+                script_size,
+            ),
+            "OpAuthOutputCount" => compile_opcode_call(
+                name,
+                args,
+                1,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpAuthOutputCount,
+                script_size,
+            ),
+            "OpAuthOutputIdx" => compile_opcode_call(
+                name,
+                args,
+                2,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpAuthOutputIdx,
+                script_size,
+            ),
+            "OpInputCovenantId" => compile_opcode_call(
+                name,
+                args,
+                1,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpInputCovenantId,
+                script_size,
+            ),
+            "OpCovInputCount" => compile_opcode_call(
+                name,
+                args,
+                1,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpCovInputCount,
+                script_size,
+            ),
+            "OpCovInputIdx" => compile_opcode_call(
+                name,
+                args,
+                2,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpCovInputIdx,
+                script_size,
+            ),
+            "OpCovOutCount" => compile_opcode_call(
+                name,
+                args,
+                1,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpCovOutCount,
+                script_size,
+            ),
+            "OpCovOutputIdx" => compile_opcode_call(
+                name,
+                args,
+                2,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpCovOutputIdx,
+                script_size,
+            ),
+            "OpNum2Bin" => {
+                compile_opcode_call(name, args, 2, &scope, builder, options, visiting, stack_depth, ternaries, OpNum2Bin, script_size)
+            }
+            "OpBin2Num" => {
+                compile_opcode_call(name, args, 1, &scope, builder, options, visiting, stack_depth, ternaries, OpBin2Num, script_size)
+            }
+            "OpChainblockSeqCommit" => compile_opcode_call(
+                name,
+                args,
+                1,
+                &scope,
+                builder,
+                options,
+                visiting,
+                stack_depth,
+                ternaries,
+                OpChainblockSeqCommit,
+                script_size,
+            ),
+            "bytes" => {
+                if args.is_empty() || args.len() > 2 {
+                    return Err(CompilerError::Unsupported("bytes() expects one or two arguments".to_string()));
+                }
+                if args.len() == 2 {
+                    compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                    compile_expr(&args[1], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                    builder.add_op(OpNum2Bin)?;
+                    *stack_depth -= 1;
+                    return Ok(());
+                }
                 match &args[0] {
                     Expr::String(value) => {
                         builder.add_data(value.as_bytes())?;
@@ -1550,10 +2893,21 @@ fn compile_expr(
                             return Ok(());
                         }
                         if expr_is_bytes(&args[0], env, types) {
-                            compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                            compile_expr(
+                                &args[0],
+                                env,
+                                params,
+                                types,
+                                builder,
+                                options,
+                                visiting,
+                                stack_depth,
+                                ternaries,
+                                script_size,
+                            )?;
                             return Ok(());
                         }
-                        compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                        compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                         builder.add_i64(8)?;
                         *stack_depth += 1;
                         builder.add_op(OpNum2Bin)?;
@@ -1562,10 +2916,32 @@ fn compile_expr(
                     }
                     _ => {
                         if expr_is_bytes(&args[0], env, types) {
-                            compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                            compile_expr(
+                                &args[0],
+                                env,
+                                params,
+                                types,
+                                builder,
+                                options,
+                                visiting,
+                                stack_depth,
+                                ternaries,
+                                script_size,
+                            )?;
                             Ok(())
                         } else {
-                            compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                            compile_expr(
+                                &args[0],
+                                env,
+                                params,
+                                types,
+                                builder,
+                                options,
+                                visiting,
+                                stack_depth,
+                                ternaries,
+                                script_size,
+                            )?;
                             builder.add_i64(8)?;
                             *stack_depth += 1;
                             builder.add_op(OpNum2Bin)?;
@@ -1579,10 +2955,29 @@ fn compile_expr(
                 if args.len() != 1 {
                     return Err(CompilerError::Unsupported("length() expects a single argument".to_string()));
                 }
+                let is_number = match &args[0] {
+                    Expr::Int(_) | Expr::Bool(_) => true,
+                    Expr::Identifier(name) => matches!(types.get(name).map(String::as_str), Some("int" | "bool")),
+                    _ => false,
+                };
+                if is_number {
+                    return Err(CompilerError::Unsupported("length() expects a byte string or an array".to_string()));
+                }
                 if let Expr::Identifier(name) = &args[0] {
                     if let Some(type_name) = types.get(name) {
                         if let Some(element_size) = array_element_size(type_name) {
-                            compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                            compile_expr(
+                                &args[0],
+                                env,
+                                params,
+                                types,
+                                builder,
+                                options,
+                                visiting,
+                                stack_depth,
+                                ternaries,
+                                script_size,
+                            )?;
                             builder.add_op(OpSize)?;
                             builder.add_op(OpSwap)?;
                             builder.add_op(OpDrop)?;
@@ -1594,7 +2989,7 @@ fn compile_expr(
                         }
                     }
                 }
-                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                 builder.add_op(OpSize)?;
                 Ok(())
             }
@@ -1602,14 +2997,14 @@ fn compile_expr(
                 if args.len() != 1 {
                     return Err(CompilerError::Unsupported("int() expects a single argument".to_string()));
                 }
-                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                 Ok(())
             }
             "sig" | "pubkey" | "datasig" => {
                 if args.len() != 1 {
                     return Err(CompilerError::Unsupported(format!("{name}() expects a single argument")));
                 }
-                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                 Ok(())
             }
             name if name.starts_with("bytes") => {
@@ -1620,7 +3015,7 @@ fn compile_expr(
                 if args.len() != 1 {
                     return Err(CompilerError::Unsupported(format!("{name}() expects a single argument")));
                 }
-                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                 builder.add_i64(size)?;
                 *stack_depth += 1;
                 builder.add_op(OpNum2Bin)?;
@@ -1631,24 +3026,188 @@ fn compile_expr(
                 if args.len() != 1 {
                     return Err(CompilerError::Unsupported("blake2b() expects a single argument".to_string()));
                 }
-                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                builder.add_op(OpBlake2b)?;
+                Ok(())
+            }
+            "pubkeyHash" => {
+                if args.len() != 1 {
+                    return Err(CompilerError::Unsupported("pubkeyHash() expects a single argument".to_string()));
+                }
+                check_signature_arg(name, "argument", "pubkey", &args[0], types)?;
+                if matches!(&args[0], Expr::Bytes(bytes) if bytes.len() != 32) {
+                    return Err(CompilerError::Unsupported("pubkeyHash() expects a 32-byte x-only public key".to_string()));
+                }
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                 builder.add_op(OpBlake2b)?;
                 Ok(())
             }
+            "asPubkey" | "asSig" => {
+                if args.len() != 1 {
+                    return Err(CompilerError::Unsupported(format!("{name}() expects a single argument")));
+                }
+                let source_type = match &args[0] {
+                    Expr::Identifier(arg) => types.get(arg).map(String::as_str),
+                    _ => None,
+                };
+                let from_bytes = match source_type {
+                    Some(type_name) => type_name.starts_with("bytes") && !is_array_type(type_name),
+                    None => expr_is_bytes(&args[0], env, types),
+                };
+                if !from_bytes {
+                    return Err(CompilerError::Unsupported(format!(
+                        "{name}() expects a bytes or bytesN value, got {}",
+                        source_type.unwrap_or("a non-byte expression")
+                    )));
+                }
+                let (min_len, max_len) = retyped_len_range(name);
+                if let Some(len) = static_bytes_len(&args[0], env, types) {
+                    if len < min_len || len > max_len {
+                        return Err(CompilerError::Unsupported(format!("{name}() cannot convert a {len}-byte value")));
+                    }
+                }
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                // `value size`; the size is checked and dropped, leaving the value.
+                builder.add_op(OpSize)?;
+                *stack_depth += 1;
+                if min_len == max_len {
+                    builder.add_i64(min_len as i64)?;
+                    builder.add_op(OpNumEqualVerify)?;
+                } else {
+                    builder.add_i64(min_len as i64)?;
+                    builder.add_i64(max_len as i64 + 1)?;
+                    builder.add_op(OpWithin)?;
+                    builder.add_op(OpVerify)?;
+                }
+                *stack_depth -= 1;
+                Ok(())
+            }
+            "head" | "tail" => {
+                if args.len() != 2 {
+                    return Err(CompilerError::Unsupported(format!("{name}() expects 2 arguments")));
+                }
+                if !expr_is_bytes(&args[0], env, types) {
+                    return Err(CompilerError::Unsupported(format!("{name}() expects a byte string as its first argument")));
+                }
+                let static_count = resolve_expr(args[1].clone(), env, &mut HashSet::new());
+                if let (Some(len), Ok(Expr::Int(count))) = (static_bytes_len(&args[0], env, types), static_count) {
+                    if count < 0 || count as usize > len {
+                        return Err(CompilerError::Unsupported(format!("{name}() takes {count} bytes of a {len}-byte value")));
+                    }
+                }
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                builder.add_op(OpSize)?;
+                *stack_depth += 1;
+                compile_expr(&args[1], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                // Unlike a plain split, the count is always checked: `source size count` with `0 <= count <= size`.
+                emit_index_bounds_check(builder)?;
+                if name == "head" {
+                    builder.add_op(OpNip)?;
+                    builder.add_i64(0)?;
+                    builder.add_op(OpSwap)?;
+                } else {
+                    builder.add_op(OpOver)?;
+                    builder.add_op(OpSwap)?;
+                    builder.add_op(OpSub)?;
+                    builder.add_op(OpSwap)?;
+                }
+                builder.add_op(OpSubstr)?;
+                *stack_depth -= 2;
+                Ok(())
+            }
+            "xorBytes" => {
+                if args.len() != 2 {
+                    return Err(CompilerError::Unsupported("xorBytes() expects 2 arguments".to_string()));
+                }
+                if !expr_is_bytes(&args[0], env, types) || !expr_is_bytes(&args[1], env, types) {
+                    return Err(CompilerError::Unsupported("xorBytes() expects byte string arguments".to_string()));
+                }
+                let static_lengths = (static_bytes_len(&args[0], env, types), static_bytes_len(&args[1], env, types));
+                if let (Some(left), Some(right)) = static_lengths {
+                    if left != right {
+                        return Err(CompilerError::Unsupported(format!(
+                            "xorBytes() expects byte strings of equal length, got {left} and {right}"
+                        )));
+                    }
+                }
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                compile_expr(&args[1], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                if !matches!(static_lengths, (Some(_), Some(_))) {
+                    // Lengths are only known at runtime: require size(a) == size(b) before XORing.
+                    for _ in 0..2 {
+                        builder.add_op(OpOver)?;
+                        builder.add_op(OpSize)?;
+                        builder.add_op(OpNip)?;
+                    }
+                    builder.add_op(OpNumEqualVerify)?;
+                }
+                builder.add_op(OpXor)?;
+                *stack_depth -= 1;
+                Ok(())
+            }
+            "encodeLP" => {
+                let width = length_prefix_width(name, args, env)?;
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                // [data] -> [data, num2bin(size, width)] -> [prefix ++ data]
+                builder.add_op(OpSize)?;
+                builder.add_i64(width)?;
+                *stack_depth += 2;
+                builder.add_op(OpNum2Bin)?;
+                builder.add_op(OpSwap)?;
+                builder.add_op(OpCat)?;
+                *stack_depth -= 2;
+                Ok(())
+            }
+            "decodeLP" => {
+                let width = length_prefix_width(name, args, env)?;
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                // [encoded] -> [encoded, len] -> [encoded, width, width + len] -> [payload]
+                builder.add_op(OpDup)?;
+                builder.add_i64(0)?;
+                builder.add_i64(width)?;
+                *stack_depth += 3;
+                builder.add_op(OpSubstr)?;
+                builder.add_op(OpBin2Num)?;
+                *stack_depth -= 2;
+                builder.add_i64(width)?;
+                builder.add_op(OpTuck)?;
+                *stack_depth += 2;
+                builder.add_op(OpAdd)?;
+                builder.add_op(OpSubstr)?;
+                *stack_depth -= 3;
+                Ok(())
+            }
+            "tweakPubkey" => {
+                if args.len() != 2 {
+                    return Err(CompilerError::Unsupported("tweakPubkey() expects 2 arguments".to_string()));
+                }
+                // Kaspa script has no point-addition opcode, so a tweaked key cannot be derived on-chain.
+                Err(CompilerError::Unsupported(
+                    "tweakPubkey() is not supported: the Kaspa script engine has no public key tweak opcode".to_string(),
+                ))
+            }
             "checkSig" => {
                 if args.len() != 2 {
                     return Err(CompilerError::Unsupported("checkSig() expects 2 arguments".to_string()));
                 }
-                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
-                compile_expr(&args[1], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                check_signature_arg(name, "first argument", "sig", &args[0], types)?;
+                check_signature_arg(name, "second argument", "pubkey", &args[1], types)?;
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                compile_expr(&args[1], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                 builder.add_op(OpCheckSig)?;
                 *stack_depth -= 1;
                 Ok(())
             }
             "checkDataSig" => {
+                if args.len() != 3 {
+                    return Err(CompilerError::Unsupported("checkDataSig() expects 3 arguments".to_string()));
+                }
+                check_signature_arg(name, "first argument", "datasig", &args[0], types)?;
+                check_signature_arg(name, "second argument", "bytes", &args[1], types)?;
+                check_signature_arg(name, "third argument", "pubkey", &args[2], types)?;
                 // TODO: Remove this stub
                 for arg in args {
-                    compile_expr(arg, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                    compile_expr(arg, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                 }
                 for _ in 0..args.len() {
                     builder.add_op(OpDrop)?;
@@ -1658,6 +3217,31 @@ fn compile_expr(
                 *stack_depth += 1;
                 Ok(())
             }
+            "checkMultiSig" => {
+                let [Expr::Array(signatures), Expr::Array(pubkeys)] = args.as_slice() else {
+                    return Err(CompilerError::Unsupported(
+                        "checkMultiSig() expects an array literal of signatures and one of public keys".to_string(),
+                    ));
+                };
+                multisig::check_counts(signatures.len(), pubkeys.len())?;
+                for signature in signatures {
+                    check_signature_arg(name, "signature list", "sig", signature, types)?;
+                }
+                for pubkey in pubkeys {
+                    check_signature_arg(name, "public key list", "pubkey", pubkey, types)?;
+                }
+                // <sig_1> .. <sig_m> <m> <pubkey_1> .. <pubkey_n> <n>, with no dummy element; see `multisig`.
+                for items in [signatures, pubkeys] {
+                    for item in items {
+                        compile_expr(item, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                    }
+                    builder.add_i64(items.len() as i64)?;
+                    *stack_depth += 1;
+                }
+                builder.add_op(OpCheckMultiSig)?;
+                *stack_depth -= (signatures.len() + pubkeys.len() + 2) as i64 - 1;
+                Ok(())
+            }
             _ => Err(CompilerError::Unsupported(format!("unknown function call: {name}"))),
         },
         Expr::New { name, args } => match name.as_str() {
@@ -1674,7 +3258,7 @@ fn compile_expr(
                 if args.len() != 1 {
                     return Err(CompilerError::Unsupported("LockingBytecodeP2PK expects a single pubkey argument".to_string()));
                 }
-                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                 builder.add_data(&[0x00, 0x00, OpData32])?;
                 *stack_depth += 1;
                 builder.add_op(OpSwap)?;
@@ -1690,7 +3274,7 @@ fn compile_expr(
                 if args.len() != 1 {
                     return Err(CompilerError::Unsupported("LockingBytecodeP2SH expects a single bytes32 argument".to_string()));
                 }
-                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                 builder.add_data(&[0x00, 0x00])?;
                 *stack_depth += 1;
                 builder.add_data(&[OpBlake2b])?;
@@ -1716,7 +3300,7 @@ fn compile_expr(
                         "LockingBytecodeP2SHFromRedeemScript expects a single redeem_script argument".to_string(),
                     ));
                 }
-                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                compile_expr(&args[0], env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
                 builder.add_op(OpBlake2b)?;
                 builder.add_data(&[0x00, 0x00])?;
                 *stack_depth += 1;
@@ -1740,31 +3324,53 @@ fn compile_expr(
             _ => Err(CompilerError::Unsupported(format!("unknown constructor: {name}"))),
         },
         Expr::Unary { op, expr } => {
-            compile_expr(expr, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+            compile_expr(expr, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
             match op {
                 UnaryOp::Not => builder.add_op(OpNot)?,
                 UnaryOp::Neg => builder.add_op(OpNegate)?,
             };
             Ok(())
         }
+        Expr::Binary { op: BinaryOp::In, left, right } => {
+            compile_membership(left, right, &scope, builder, options, visiting, stack_depth, ternaries, script_size)
+        }
+        // `a && b` runs as `a ? b : false` and `a || b` as `a ? true : b`, so the right operand (and
+        // any `checkSig` in it) only executes when the left one does not settle the result. Each
+        // operand goes through `Op0NotEqual` first: like `OpBoolAnd`/`OpBoolOr`, any nonzero number
+        // counts as true, `OpIf` only ever sees 0 or 1, and the result is 0 or 1.
+        Expr::Binary { op: op @ (BinaryOp::And | BinaryOp::Or), left, right } => {
+            compile_expr(left, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+            builder.add_op(Op0NotEqual)?;
+            builder.add_op(OpIf)?;
+            *stack_depth -= 1;
+            let depth_before = *stack_depth;
+            if *op == BinaryOp::And {
+                compile_expr(right, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                builder.add_op(Op0NotEqual)?;
+                builder.add_op(OpElse)?;
+                builder.add_op(OpFalse)?;
+            } else {
+                builder.add_op(OpTrue)?;
+                builder.add_op(OpElse)?;
+                compile_expr(right, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                builder.add_op(Op0NotEqual)?;
+            }
+            builder.add_op(OpEndIf)?;
+            *stack_depth = depth_before + 1;
+            Ok(())
+        }
         Expr::Binary { op, left, right } => {
             let bytes_eq =
                 matches!(op, BinaryOp::Eq | BinaryOp::Ne) && (expr_is_bytes(left, env, types) || expr_is_bytes(right, env, types));
             let bytes_add = matches!(op, BinaryOp::Add) && (expr_is_bytes(left, env, types) || expr_is_bytes(right, env, types));
             if bytes_add {
-                compile_concat_operand(left, env, params, types, builder, options, visiting, stack_depth, script_size)?;
-                compile_concat_operand(right, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                compile_concat_operand(left, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                compile_concat_operand(right, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
             } else {
-                compile_expr(left, env, params, types, builder, options, visiting, stack_depth, script_size)?;
-                compile_expr(right, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                compile_expr(left, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                compile_expr(right, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
             }
             match op {
-                BinaryOp::Or => {
-                    builder.add_op(OpBoolOr)?;
-                }
-                BinaryOp::And => {
-                    builder.add_op(OpBoolAnd)?;
-                }
                 BinaryOp::BitOr => {
                     builder.add_op(OpOr)?;
                 }
@@ -1801,13 +3407,22 @@ fn compile_expr(
                     if bytes_add {
                         builder.add_op(OpCat)?;
                     } else {
+                        if options.hardened {
+                            emit_add_overflow_check(builder, false)?;
+                        }
                         builder.add_op(OpAdd)?;
                     }
                 }
                 BinaryOp::Sub => {
+                    if options.hardened {
+                        emit_add_overflow_check(builder, true)?;
+                    }
                     builder.add_op(OpSub)?;
                 }
                 BinaryOp::Mul => {
+                    if options.hardened {
+                        emit_mul_overflow_check(builder)?;
+                    }
                     builder.add_op(OpMul)?;
                 }
                 BinaryOp::Div => {
@@ -1816,15 +3431,25 @@ fn compile_expr(
                 BinaryOp::Mod => {
                     builder.add_op(OpMod)?;
                 }
+                BinaryOp::In => unreachable!("membership is compiled by compile_membership"),
+                BinaryOp::And | BinaryOp::Or => unreachable!("logical operators short-circuit above"),
             }
             *stack_depth -= 1;
             Ok(())
         }
         Expr::Split { source, index, part } => {
-            compile_expr(source, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+            compile_expr(source, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
             match part {
                 SplitPart::Left => {
-                    compile_expr(index, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                    compile_expr(index, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                    if options.hardened {
+                        builder.add_op(OpOver)?;
+                        builder.add_op(OpSize)?;
+                        builder.add_op(OpNip)?;
+                        builder.add_op(OpOver)?;
+                        emit_index_bounds_check(builder)?;
+                        builder.add_op(Op2Drop)?;
+                    }
                     builder.add_i64(0)?;
                     *stack_depth += 1;
                     builder.add_op(OpSwap)?;
@@ -1834,7 +3459,10 @@ fn compile_expr(
                 SplitPart::Right => {
                     builder.add_op(OpSize)?;
                     *stack_depth += 1;
-                    compile_expr(index, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+                    compile_expr(index, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+                    if options.hardened {
+                        emit_index_bounds_check(builder)?;
+                    }
                     builder.add_op(OpSwap)?;
                     builder.add_op(OpSubstr)?;
                     *stack_depth -= 2;
@@ -1843,6 +3471,9 @@ fn compile_expr(
             Ok(())
         }
         Expr::ArrayIndex { source, index } => {
+            if let Some(map) = map_literal(source, env) {
+                return compile_map_lookup(index, map, &scope, builder, options, visiting, stack_depth, ternaries, script_size);
+            }
             let resolved_source = match source.as_ref() {
                 Expr::Identifier(_) => source.as_ref().clone(),
                 _ => resolve_expr(*source.clone(), env, visiting)?,
@@ -1860,8 +3491,8 @@ fn compile_expr(
             };
             let element_size = fixed_type_size(element_type)
                 .ok_or_else(|| CompilerError::Unsupported("array element type must have known size".to_string()))?;
-            compile_expr(&resolved_source, env, params, types, builder, options, visiting, stack_depth, script_size)?;
-            compile_expr(index, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+            compile_expr(&resolved_source, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+            compile_expr(index, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
             builder.add_i64(element_size)?;
             *stack_depth += 1;
             builder.add_op(OpMul)?;
@@ -1872,6 +3503,9 @@ fn compile_expr(
             *stack_depth += 1;
             builder.add_op(OpAdd)?;
             *stack_depth -= 1;
+            if options.hardened {
+                emit_slice_bounds_check(builder)?;
+            }
             builder.add_op(OpSubstr)?;
             *stack_depth -= 2;
             if element_type == "int" {
@@ -1880,9 +3514,18 @@ fn compile_expr(
             Ok(())
         }
         Expr::Slice { source, start, end } => {
-            compile_expr(source, env, params, types, builder, options, visiting, stack_depth, script_size)?;
-            compile_expr(start, env, params, types, builder, options, visiting, stack_depth, script_size)?;
-            compile_expr(end, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+            let static_end = resolve_expr(*end.clone(), env, &mut HashSet::new());
+            if let (Some(len), Ok(Expr::Int(end))) = (static_bytes_len(source, env, types), static_end) {
+                if end < 0 || end as usize > len {
+                    return Err(CompilerError::Unsupported(format!("slice end {end} is outside a {len}-byte value")));
+                }
+            }
+            compile_expr(source, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+            compile_expr(start, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+            compile_expr(end, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+            if options.hardened {
+                emit_slice_bounds_check(builder)?;
+            }
 
             builder.add_op(Op2Dup)?;
             *stack_depth += 2;
@@ -1932,12 +3575,18 @@ fn compile_expr(
                 NullaryOp::TxLockTime => {
                     builder.add_op(OpTxLockTime)?;
                 }
+                NullaryOp::TxFee => {
+                    add_tx_fee(builder)?;
+                }
             }
             *stack_depth += 1;
             Ok(())
         }
+        Expr::Map { .. } => Err(CompilerError::Unsupported("maps can only be used in a lookup like table[key]".to_string())),
+        Expr::Decimal { .. } => Err(CompilerError::Unsupported("decimal literal was not lowered to a scaled int".to_string())),
         Expr::Introspection { kind, index } => {
-            compile_expr(index, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+            check_introspection_index(*kind, index, env, types)?;
+            compile_expr(index, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
             match kind {
                 IntrospectionKind::InputValue => {
                     builder.add_op(OpTxInputAmount)?;
@@ -1957,6 +3606,368 @@ fn compile_expr(
     }
 }
 
+/// The introspection opcodes read the index from the stack, so `tx.inputs[i]` and `tx.outputs[i]`
+/// take any int expression. Indexes that can never be valid are rejected here: negative constants,
+/// and byte strings or bools.
+fn check_introspection_index(
+    kind: IntrospectionKind,
+    index: &Expr,
+    env: &HashMap<String, Expr>,
+    types: &HashMap<String, String>,
+) -> Result<(), CompilerError> {
+    let list = match kind {
+        IntrospectionKind::InputValue | IntrospectionKind::InputLockingBytecode => "tx.inputs",
+        IntrospectionKind::OutputValue | IntrospectionKind::OutputLockingBytecode => "tx.outputs",
+    };
+    let resolved = resolve_expr(index.clone(), env, &mut HashSet::new()).unwrap_or_else(|_| index.clone());
+    let is_bool = match &resolved {
+        Expr::Bool(_) => true,
+        Expr::Identifier(name) => types.get(name).is_some_and(|type_name| type_name == "bool"),
+        _ => false,
+    };
+    match eval_const_int(&resolved, &HashMap::new()) {
+        Ok(value) if value < 0 => Err(CompilerError::Unsupported(format!("{list} index must not be negative, got {value}"))),
+        _ if is_bool || expr_is_bytes(&resolved, env, types) => {
+            Err(CompilerError::Unsupported(format!("{list} index must be an int, got {index}")))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Lowers `key in [a, b, ...]` to an OR of equality checks against the constant set.
+///
+/// The key is evaluated once and kept below an accumulator: `key OpDup a OpEqual (OpOver b OpEqual OpBoolOr)* OpNip`.
+fn compile_membership(
+    key: &Expr,
+    set: &Expr,
+    scope: &CompilationScope,
+    builder: &mut ScriptBuilder,
+    options: CompileOptions,
+    visiting: &mut HashSet<String>,
+    stack_depth: &mut i64,
+    ternaries: &mut Vec<TernaryRange>,
+    script_size: Option<i64>,
+) -> Result<(), CompilerError> {
+    let CompilationScope { env, params, types } = *scope;
+    let mut resolving = HashSet::new();
+    let elements = match resolve_expr(set.clone(), env, &mut resolving)? {
+        Expr::Array(elements) => elements,
+        _ => return Err(CompilerError::Unsupported("in operator requires a compile-time constant array".to_string())),
+    };
+    if elements.is_empty() {
+        return Err(CompilerError::Unsupported("in operator requires a non-empty array".to_string()));
+    }
+
+    let key_is_bytes = expr_is_bytes(key, env, types);
+    for element in &elements {
+        let matches_key = match element {
+            Expr::Bytes(_) | Expr::String(_) => key_is_bytes,
+            Expr::Int(_) | Expr::Bool(_) => !key_is_bytes,
+            _ => return Err(CompilerError::Unsupported("in operator requires a compile-time constant array".to_string())),
+        };
+        if !matches_key {
+            return Err(CompilerError::Unsupported("in operator array elements must match the left operand type".to_string()));
+        }
+    }
+    let equal_op = if key_is_bytes { OpEqual } else { OpNumEqual };
+
+    compile_expr(key, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+    for (index, element) in elements.iter().enumerate() {
+        builder.add_op(if index == 0 { OpDup } else { OpOver })?;
+        *stack_depth += 1;
+        compile_expr(element, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+        builder.add_op(equal_op)?;
+        *stack_depth -= 1;
+        if index > 0 {
+            builder.add_op(OpBoolOr)?;
+            *stack_depth -= 1;
+        }
+    }
+    builder.add_op(OpNip)?;
+    *stack_depth -= 1;
+    Ok(())
+}
+
+/// Hardened mode: with `a b` on top, verifies that `a + b` (or `a - b`) stays within the script number range.
+/// The stack is left unchanged.
+fn emit_add_overflow_check(builder: &mut ScriptBuilder, subtract: bool) -> Result<(), CompilerError> {
+    builder.add_op(Op2Dup)?;
+    builder.add_op(OpDup)?;
+    builder.add_i64(0)?;
+    // a + b can only overflow upwards when b > 0; a - b when b < 0.
+    builder.add_op(if subtract { OpLessThan } else { OpGreaterThan })?;
+    builder.add_op(OpIf)?;
+    builder.add_i64(i64::MAX)?;
+    if subtract {
+        builder.add_op(OpAdd)?;
+    } else {
+        builder.add_op(OpSwap)?;
+        builder.add_op(OpSub)?;
+    }
+    builder.add_op(OpLessThanOrEqual)?;
+    builder.add_op(OpElse)?;
+    builder.add_i64(-i64::MAX)?;
+    if subtract {
+        builder.add_op(OpAdd)?;
+    } else {
+        builder.add_op(OpSwap)?;
+        builder.add_op(OpSub)?;
+    }
+    builder.add_op(OpGreaterThanOrEqual)?;
+    builder.add_op(OpEndIf)?;
+    builder.add_op(OpVerify)?;
+    Ok(())
+}
+
+/// Hardened mode: with `a b` on top, verifies `|a| <= MAX / |b|` so that `a * b` cannot overflow.
+/// The stack is left unchanged.
+fn emit_mul_overflow_check(builder: &mut ScriptBuilder) -> Result<(), CompilerError> {
+    builder.add_op(Op2Dup)?;
+    builder.add_op(OpAbs)?;
+    builder.add_op(OpSwap)?;
+    builder.add_op(OpAbs)?;
+    builder.add_op(OpOver)?;
+    builder.add_i64(0)?;
+    builder.add_op(OpGreaterThan)?;
+    builder.add_op(OpIf)?;
+    builder.add_op(OpSwap)?;
+    builder.add_i64(i64::MAX)?;
+    builder.add_op(OpSwap)?;
+    builder.add_op(OpDiv)?;
+    builder.add_op(OpLessThanOrEqual)?;
+    builder.add_op(OpVerify)?;
+    builder.add_op(OpElse)?;
+    builder.add_op(Op2Drop)?;
+    builder.add_op(OpEndIf)?;
+    Ok(())
+}
+
+/// Hardened mode: with `len index` on top, verifies `0 <= index <= len`. The stack is left unchanged.
+fn emit_index_bounds_check(builder: &mut ScriptBuilder) -> Result<(), CompilerError> {
+    builder.add_op(OpDup)?;
+    builder.add_i64(0)?;
+    builder.add_op(OpGreaterThanOrEqual)?;
+    builder.add_op(OpVerify)?;
+    builder.add_op(Op2Dup)?;
+    builder.add_op(OpGreaterThanOrEqual)?;
+    builder.add_op(OpVerify)?;
+    Ok(())
+}
+
+/// Hardened mode: with `source start end` on top, verifies `0 <= start <= end <= source.length`.
+/// The stack is left unchanged.
+fn emit_slice_bounds_check(builder: &mut ScriptBuilder) -> Result<(), CompilerError> {
+    builder.add_op(OpOver)?;
+    builder.add_i64(0)?;
+    builder.add_op(OpGreaterThanOrEqual)?;
+    builder.add_op(OpVerify)?;
+    builder.add_op(Op2Dup)?;
+    builder.add_op(OpLessThanOrEqual)?;
+    builder.add_op(OpVerify)?;
+    builder.add_i64(2)?;
+    builder.add_op(OpPick)?;
+    builder.add_op(OpSize)?;
+    builder.add_op(OpNip)?;
+    builder.add_op(OpOver)?;
+    builder.add_op(OpGreaterThanOrEqual)?;
+    builder.add_op(OpVerify)?;
+    Ok(())
+}
+
+/// Hardened mode: verifies that exactly `expected` items remain on the stack.
+fn emit_clean_stack_check(builder: &mut ScriptBuilder, expected: i64) -> Result<(), CompilerError> {
+    builder.add_op(OpDepth)?;
+    builder.add_i64(expected)?;
+    builder.add_op(OpNumEqualVerify)?;
+    Ok(())
+}
+
+/// Returns the map literal an index expression reads from, if its source names a `map`.
+fn map_literal<'e>(source: &'e Expr, env: &'e HashMap<String, Expr>) -> Option<&'e Expr> {
+    match source {
+        Expr::Map { .. } => Some(source),
+        Expr::Identifier(name) => env.get(name).filter(|value| matches!(value, Expr::Map { .. })),
+        _ => None,
+    }
+}
+
+/// Lowers `table[key]` to a chain of equality-guarded selects ending in the default value:
+/// `key (OpDup k OpEqual OpIf OpDrop v OpElse)* OpDrop default OpEndIf*`.
+///
+/// A map without a default fails the script when the key is missing.
+fn compile_map_lookup(
+    key: &Expr,
+    map: &Expr,
+    scope: &CompilationScope,
+    builder: &mut ScriptBuilder,
+    options: CompileOptions,
+    visiting: &mut HashSet<String>,
+    stack_depth: &mut i64,
+    ternaries: &mut Vec<TernaryRange>,
+    script_size: Option<i64>,
+) -> Result<(), CompilerError> {
+    let CompilationScope { env, params, types } = *scope;
+    let Expr::Map { key_type, entries, default, .. } = map else {
+        return Err(CompilerError::Unsupported("map lookup requires a map".to_string()));
+    };
+    let key_is_bytes = expr_is_bytes(key, env, types);
+    if key_is_bytes != is_bytes_type(key_type) {
+        return Err(CompilerError::Unsupported(format!("map lookup key must be of type {key_type}")));
+    }
+    let equal_op = if key_is_bytes { OpEqual } else { OpNumEqual };
+
+    compile_expr(key, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
+    // Branch bodies only push literals, so they are compiled against a scratch depth; the net effect is key -> value.
+    let mut literal_depth = 0i64;
+    for (entry_key, entry_value) in entries {
+        builder.add_op(OpDup)?;
+        compile_expr(entry_key, env, params, types, builder, options, visiting, &mut literal_depth, ternaries, script_size)?;
+        builder.add_op(equal_op)?;
+        builder.add_op(OpIf)?;
+        builder.add_op(OpDrop)?;
+        compile_expr(entry_value, env, params, types, builder, options, visiting, &mut literal_depth, ternaries, script_size)?;
+        builder.add_op(OpElse)?;
+    }
+    builder.add_op(OpDrop)?;
+    match default {
+        Some(value) => {
+            compile_expr(value, env, params, types, builder, options, visiting, &mut literal_depth, ternaries, script_size)?
+        }
+        None => {
+            builder.add_op(OpFalse)?;
+            builder.add_op(OpVerify)?;
+        }
+    }
+    for _ in entries {
+        builder.add_op(OpEndIf)?;
+    }
+    Ok(())
+}
+
+/// Most inputs, and most outputs, `tx.fee` sums. A spend with more fails rather than under-counting.
+pub const MAX_TX_FEE_ENTRIES: i64 = 8;
+
+/// Pushes `tx.fee`: the sum of input amounts minus the sum of output amounts.
+///
+/// Script has no loops, so both sums are unrolled over `MAX_TX_FEE_ENTRIES` slots, each guarded
+/// by the input or output count.
+fn add_tx_fee(builder: &mut ScriptBuilder) -> Result<(), CompilerError> {
+    for count_op in [OpTxInputCount, OpTxOutputCount] {
+        builder.add_op(count_op)?;
+        builder.add_i64(MAX_TX_FEE_ENTRIES)?;
+        builder.add_op(OpLessThanOrEqual)?;
+        builder.add_op(OpVerify)?;
+    }
+    builder.add_i64(0)?;
+    for (count_op, amount_op, fold_op) in [(OpTxInputCount, OpTxInputAmount, OpAdd), (OpTxOutputCount, OpTxOutputAmount, OpSub)] {
+        for index in 0..MAX_TX_FEE_ENTRIES {
+            builder.add_op(count_op)?;
+            builder.add_i64(index)?;
+            builder.add_op(OpGreaterThan)?;
+            builder.add_op(OpIf)?;
+            builder.add_i64(index)?;
+            builder.add_op(amount_op)?;
+            builder.add_op(fold_op)?;
+            builder.add_op(OpEndIf)?;
+        }
+    }
+    Ok(())
+}
+
+/// Byte lengths `asPubkey` and `asSig` accept: an x-only key, and a Schnorr signature with or
+/// without its sighash type byte.
+fn retyped_len_range(conversion: &str) -> (usize, usize) {
+    if conversion == "asPubkey" { (32, 32) } else { (64, 65) }
+}
+
+/// Type of an `asPubkey`/`asSig` conversion; `None` for any other expression.
+fn retyped_type(expr: &Expr) -> Option<&'static str> {
+    match expr {
+        Expr::Call { name, .. } if name == "asPubkey" => Some("pubkey"),
+        Expr::Call { name, .. } if name == "asSig" => Some("sig"),
+        _ => None,
+    }
+}
+
+/// Checks an argument of `checkSig`, `checkDataSig` or `checkMultiSig` against the `expected` kind.
+/// Plain byte strings fit any kind; numbers, arrays and variables declared as another kind do not,
+/// and the `bytes` message of `checkDataSig` takes any byte string.
+fn check_signature_arg(
+    function: &str,
+    position: &str,
+    expected: &str,
+    expr: &Expr,
+    types: &HashMap<String, String>,
+) -> Result<(), CompilerError> {
+    let actual = match expr {
+        Expr::Int(_) => "int",
+        Expr::Bool(_) => "bool",
+        Expr::String(_) => "string",
+        Expr::Array(_) => "array",
+        Expr::Identifier(name) => match types.get(name) {
+            Some(type_name) => type_name.as_str(),
+            None => return Ok(()),
+        },
+        _ => match retyped_type(expr) {
+            Some(type_name) => type_name,
+            None => return Ok(()),
+        },
+    };
+    let plain_bytes = !is_array_type(actual) && (actual == "byte" || actual.starts_with("bytes"));
+    let fits = match expected {
+        "bytes" => is_bytes_type(actual) && !is_array_type(actual),
+        _ => actual == expected || plain_bytes,
+    };
+    if fits {
+        return Ok(());
+    }
+    Err(CompilerError::Unsupported(format!("{function}() expects a {expected} for its {position}, got {actual}")))
+}
+
+/// Widest length prefix `encodeLP`/`decodeLP` accept; a script number is at most 8 bytes.
+const MAX_LENGTH_PREFIX_WIDTH: i64 = 8;
+
+/// Prefix width argument of `encodeLP`/`decodeLP`, which must be known at compile time.
+fn length_prefix_width(name: &str, args: &[Expr], env: &HashMap<String, Expr>) -> Result<i64, CompilerError> {
+    if args.len() != 2 {
+        return Err(CompilerError::Unsupported(format!("{name}() expects 2 arguments")));
+    }
+    match resolve_expr(args[1].clone(), env, &mut HashSet::new())? {
+        Expr::Int(width) if (1..=MAX_LENGTH_PREFIX_WIDTH).contains(&width) => Ok(width),
+        _ => Err(CompilerError::Unsupported(format!(
+            "{name}() expects a constant prefix width between 1 and {MAX_LENGTH_PREFIX_WIDTH}"
+        ))),
+    }
+}
+
+/// Byte length of `expr` when it is known at compile time: literals, fixed-size types, constant
+/// slices of those, `head`/`tail` with a constant count, and concatenations of two such values
+/// (`bytes32 + bytes32` is 64 bytes).
+fn static_bytes_len(expr: &Expr, env: &HashMap<String, Expr>, types: &HashMap<String, String>) -> Option<usize> {
+    let resolved = resolve_expr(expr.clone(), env, &mut HashSet::new()).ok()?;
+    match resolved {
+        Expr::Bytes(bytes) => Some(bytes.len()),
+        Expr::String(value) => Some(value.len()),
+        Expr::Identifier(name) => match types.get(&name).map(String::as_str) {
+            Some(type_name) if type_name != "int" && !is_array_type(type_name) => fixed_type_size(type_name).map(|size| size as usize),
+            _ => None,
+        },
+        Expr::Binary { op: BinaryOp::Add, left, right } if expr_is_bytes(&left, env, types) && expr_is_bytes(&right, env, types) => {
+            Some(static_bytes_len(&left, env, types)? + static_bytes_len(&right, env, types)?)
+        }
+        Expr::Slice { start, end, .. } => match (*start, *end) {
+            (Expr::Int(start), Expr::Int(end)) if 0 <= start && start <= end => Some((end - start) as usize),
+            _ => None,
+        },
+        Expr::Call { name, .. } if name == "asPubkey" => Some(32),
+        Expr::Call { name, args } if matches!(name.as_str(), "head" | "tail") && args.len() == 2 => match &args[1] {
+            Expr::Int(count) if *count >= 0 => Some(*count as usize),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 fn expr_is_bytes(expr: &Expr, env: &HashMap<String, Expr>, types: &HashMap<String, String>) -> bool {
     let mut visiting = HashSet::new();
     expr_is_bytes_inner(expr, env, types, &mut visiting)
@@ -1980,7 +3991,15 @@ fn expr_is_bytes_inner(
             matches!(
                 name.as_str(),
                 "bytes"
+                    | "xorBytes"
+                    | "head"
+                    | "tail"
+                    | "encodeLP"
+                    | "decodeLP"
                     | "blake2b"
+                    | "pubkeyHash"
+                    | "asPubkey"
+                    | "asSig"
                     | "sha256"
                     | "OpSha256"
                     | "OpTxSubnetId"
@@ -1999,7 +4018,7 @@ fn expr_is_bytes_inner(
         Expr::Binary { op: BinaryOp::Add, left, right } => {
             expr_is_bytes_inner(left, env, types, visiting) || expr_is_bytes_inner(right, env, types, visiting)
         }
-        Expr::IfElse { condition: _, then_expr, else_expr } => {
+        Expr::IfElse { then_expr, else_expr, .. } => {
             expr_is_bytes_inner(then_expr, env, types, visiting) && expr_is_bytes_inner(else_expr, env, types, visiting)
         }
         Expr::Introspection { kind, .. } => {
@@ -2007,11 +4026,16 @@ fn expr_is_bytes_inner(
         }
         Expr::Nullary(NullaryOp::ActiveBytecode) => true,
         Expr::Nullary(NullaryOp::ThisScriptSizeDataPrefix) => true,
-        Expr::ArrayIndex { source, .. } => match source.as_ref() {
-            Expr::Identifier(name) => {
-                types.get(name).and_then(|type_name| array_element_type(type_name)).map(|element| element != "int").unwrap_or(false)
-            }
-            _ => false,
+        Expr::ArrayIndex { source, .. } => match map_literal(source, env) {
+            Some(Expr::Map { value_type, .. }) => is_bytes_type(value_type),
+            _ => match source.as_ref() {
+                Expr::Identifier(name) => types
+                    .get(name)
+                    .and_then(|type_name| array_element_type(type_name))
+                    .map(|element| element != "int")
+                    .unwrap_or(false),
+                _ => false,
+            },
         },
         Expr::Identifier(name) => {
             if !visiting.insert(name.clone()) {
@@ -2039,6 +4063,7 @@ fn compile_opcode_call(
     options: CompileOptions,
     visiting: &mut HashSet<String>,
     stack_depth: &mut i64,
+    ternaries: &mut Vec<TernaryRange>,
     opcode: u8,
     script_size: Option<i64>,
 ) -> Result<(), CompilerError> {
@@ -2046,7 +4071,7 @@ fn compile_opcode_call(
         return Err(CompilerError::Unsupported(format!("{name}() expects {expected_args} argument(s)")));
     }
     for arg in args {
-        compile_expr(arg, scope.env, scope.params, scope.types, builder, options, visiting, stack_depth, script_size)?;
+        compile_expr(arg, scope.env, scope.params, scope.types, builder, options, visiting, stack_depth, ternaries, script_size)?;
     }
     builder.add_op(opcode)?;
     *stack_depth += 1 - expected_args as i64;
@@ -2062,9 +4087,10 @@ fn compile_concat_operand(
     options: CompileOptions,
     visiting: &mut HashSet<String>,
     stack_depth: &mut i64,
+    ternaries: &mut Vec<TernaryRange>,
     script_size: Option<i64>,
 ) -> Result<(), CompilerError> {
-    compile_expr(expr, env, params, types, builder, options, visiting, stack_depth, script_size)?;
+    compile_expr(expr, env, params, types, builder, options, visiting, stack_depth, ternaries, script_size)?;
     if !expr_is_bytes(expr, env, types) {
         builder.add_i64(1)?;
         *stack_depth += 1;
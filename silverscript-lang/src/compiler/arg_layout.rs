@@ -0,0 +1,113 @@
+use kaspa_consensus_core::hashing::sighash::SigHashReusedValuesUnsync;
+use kaspa_txscript::caches::Cache;
+use kaspa_txscript::script_builder::ScriptBuilder;
+use kaspa_txscript::{EngineCtx, EngineFlags, TxScriptEngine, parse_script};
+
+use crate::ast::Expr;
+use crate::debug::session::{DebugEngine, DebugReused, DebugTx};
+
+use super::{
+    CompiledContract, CompilerError, FunctionInputAbi, array_element_type, function_branch_index, param_stack_depth, push_function_arg,
+};
+
+impl CompiledContract {
+    /// Checks, for every entrypoint, that `build_sig_script` leaves each argument in the stack slot
+    /// the compiled script reads that param from, with the selector on top when there is one.
+    pub fn verify_arg_layout(&self) -> Result<(), CompilerError> {
+        for entry in &self.abi {
+            let args: Vec<Expr> =
+                entry.inputs.iter().enumerate().map(|(index, input)| probe_arg(&input.type_name, index)).collect::<Result<_, _>>()?;
+            let sigscript = self.build_sig_script(&entry.name, args.clone())?;
+            self.verify_sig_script_layout(&entry.name, &args, &sigscript)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that `sigscript` leaves `args` where the compiled `function_name` expects them.
+    pub fn verify_sig_script_layout(&self, function_name: &str, args: &[Expr], sigscript: &[u8]) -> Result<(), CompilerError> {
+        let entry = self
+            .abi
+            .iter()
+            .find(|entry| entry.name == function_name)
+            .ok_or_else(|| CompilerError::Unsupported(format!("function '{function_name}' not found")))?;
+        if entry.inputs.len() != args.len() {
+            return Err(CompilerError::Unsupported(entry.arg_count_mismatch(args.len())));
+        }
+
+        let mut stack = pushed_stack(sigscript)?;
+        if !self.without_selector {
+            let selector = function_branch_index(&self.ast, function_name)?;
+            let mut builder = ScriptBuilder::new();
+            builder.add_i64(selector)?;
+            let expected = pushed_stack(&builder.drain())?;
+            if stack.last() != expected.last() {
+                return Err(CompilerError::Unsupported(format!(
+                    "sigscript for '{function_name}' does not end with selector {selector}"
+                )));
+            }
+            stack.pop();
+        }
+        if stack.len() != args.len() {
+            return Err(CompilerError::Unsupported(format!(
+                "sigscript for '{function_name}' pushes {} items for {} params",
+                stack.len(),
+                args.len()
+            )));
+        }
+
+        for (index, (input, arg)) in entry.inputs.iter().zip(args).enumerate() {
+            let depth = param_stack_depth(index, args.len()) as usize;
+            let expected = pushed_item(input, arg.clone())?;
+            if stack[stack.len() - 1 - depth] != expected {
+                return Err(CompilerError::Unsupported(format!(
+                    "param '{}' of '{function_name}' is read from stack slot {depth} but the sigscript pushes another value there",
+                    input.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Distinct placeholder value of `type_name` for the `index`-th param.
+fn probe_arg(type_name: &str, index: usize) -> Result<Expr, CompilerError> {
+    let fill = (index % 255) as u8 + 1;
+    if let Some(element_type) = array_element_type(type_name) {
+        return Ok(Expr::Array(vec![probe_arg(element_type, index)?, probe_arg(element_type, index + 1)?]));
+    }
+    Ok(match type_name {
+        "int" => Expr::Int(1000 + index as i64),
+        "bool" => Expr::Bool(index % 2 == 0),
+        "string" => Expr::String(format!("arg{index}")),
+        "bytes" => Expr::Bytes(vec![fill; 2]),
+        "byte" => Expr::Bytes(vec![fill]),
+        "pubkey" => Expr::Bytes(vec![fill; 32]),
+        "sig" | "datasig" => Expr::Bytes(vec![fill; 65]),
+        other => match other.strip_prefix("bytes").and_then(|size| size.parse::<usize>().ok()) {
+            Some(size) => Expr::Bytes(vec![fill; size]),
+            None => return Err(CompilerError::Unsupported(format!("no layout probe for type '{other}'"))),
+        },
+    })
+}
+
+/// Stack item the sigscript push for `arg` produces.
+fn pushed_item(input: &FunctionInputAbi, arg: Expr) -> Result<Vec<u8>, CompilerError> {
+    let mut builder = ScriptBuilder::new();
+    push_function_arg(&mut builder, input, arg)?;
+    pushed_stack(&builder.drain())?
+        .pop()
+        .ok_or_else(|| CompilerError::Unsupported(format!("argument '{}' pushes nothing", input.name)))
+}
+
+/// Runs a push-only script and returns the resulting stack, bottom first.
+fn pushed_stack(script: &[u8]) -> Result<Vec<Vec<u8>>, CompilerError> {
+    let sig_cache = Cache::new(0);
+    let reused_values = SigHashReusedValuesUnsync::new();
+    let mut engine: DebugEngine<'_> =
+        TxScriptEngine::new(EngineCtx::new(&sig_cache).with_reused(&reused_values), EngineFlags { covenants_enabled: true });
+    for opcode in parse_script::<DebugTx<'_>, DebugReused>(script) {
+        let opcode = opcode.map_err(|err| CompilerError::Unsupported(format!("invalid sigscript: {err}")))?;
+        engine.execute_opcode(opcode).map_err(|err| CompilerError::Unsupported(format!("invalid sigscript: {err}")))?;
+    }
+    Ok(engine.stacks().dstack.iter().cloned().collect())
+}
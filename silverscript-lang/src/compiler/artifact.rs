@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Serialize};
+
+use crate::ast::{AnnotationAst, ContractAst, Expr, FunctionAst, ParamAst};
+
+use super::{CompiledContract, FunctionAbiEntry, FunctionInputAbi, selectors};
+
+/// On-disk form of a compiled contract, shaped like a CashScript artifact so wallet libraries that
+/// read those can be adapted to it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Artifact {
+    pub contract_name: String,
+    pub constructor_inputs: Vec<ArtifactInput>,
+    pub abi: Vec<ArtifactFunction>,
+    /// The locking script as hex.
+    pub bytecode: String,
+    /// Source the contract was compiled from; empty when it was compiled without debug info.
+    pub source: String,
+    pub compiler_version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactInput {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactFunction {
+    pub name: String,
+    pub inputs: Vec<ArtifactInput>,
+    /// Value the sigscript pushes after the arguments; `None` when the contract has a single entrypoint.
+    pub selector_index: Option<i64>,
+}
+
+impl From<&FunctionInputAbi> for ArtifactInput {
+    fn from(input: &FunctionInputAbi) -> Self {
+        Self { name: input.name.clone(), type_name: input.type_name.clone(), scale: input.scale }
+    }
+}
+
+impl From<&ArtifactInput> for FunctionInputAbi {
+    fn from(input: &ArtifactInput) -> Self {
+        Self { name: input.name.clone(), type_name: input.type_name.clone(), scale: input.scale }
+    }
+}
+
+impl From<&ArtifactInput> for ParamAst {
+    fn from(input: &ArtifactInput) -> Self {
+        Self { type_name: input.type_name.clone(), name: input.name.clone(), scale: input.scale }
+    }
+}
+
+impl CompiledContract {
+    /// The contract as a CashScript-style JSON artifact (see `Artifact`).
+    pub fn to_artifact_json(&self) -> String {
+        serde_json::to_string_pretty(&self.artifact()).expect("artifact serializes")
+    }
+
+    /// The artifact `to_artifact_json` writes. Selector indices are left out for a single-entrypoint
+    /// contract, whose sigscripts push only the arguments.
+    pub fn artifact(&self) -> Artifact {
+        let selectors: HashMap<String, i64> = selectors::entrypoint_selectors(&self.ast).unwrap_or_default().into_iter().collect();
+        let abi = self
+            .abi
+            .iter()
+            .map(|entry| ArtifactFunction {
+                name: entry.name.clone(),
+                inputs: entry.inputs.iter().map(ArtifactInput::from).collect(),
+                selector_index: if self.without_selector { None } else { selectors.get(&entry.name).copied() },
+            })
+            .collect();
+        Artifact {
+            contract_name: self.contract_name.clone(),
+            constructor_inputs: self
+                .ast
+                .params
+                .iter()
+                .map(|param| ArtifactInput { name: param.name.clone(), type_name: param.type_name.clone(), scale: param.scale })
+                .collect(),
+            abi,
+            bytecode: faster_hex::hex_string(&self.script),
+            source: self.debug_info.as_ref().map(|debug_info| debug_info.source.clone()).unwrap_or_default(),
+            compiler_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Reads an artifact written by `to_artifact_json` without re-parsing its source.
+    ///
+    /// The result can build sigscripts: each entrypoint is rebuilt with an empty body and its
+    /// selector pinned. It carries no debug info, and every constructor param counts as used.
+    pub fn from_artifact_json(json: &str) -> Result<Self, serde_json::Error> {
+        let artifact: Artifact = serde_json::from_str(json)?;
+        let mut script = vec![0u8; artifact.bytecode.len() / 2];
+        if artifact.bytecode.len() % 2 != 0 || faster_hex::hex_decode(artifact.bytecode.as_bytes(), &mut script).is_err() {
+            return Err(serde_json::Error::custom("artifact bytecode is not valid hex"));
+        }
+
+        let functions = artifact
+            .abi
+            .iter()
+            .map(|function| FunctionAst {
+                name: function.name.clone(),
+                params: function.inputs.iter().map(ParamAst::from).collect(),
+                entrypoint: true,
+                return_types: Vec::new(),
+                annotations: function
+                    .selector_index
+                    .map(|selector| AnnotationAst {
+                        name: "selector".to_string(),
+                        args: vec![Expr::Int(selector)],
+                        named_args: Vec::new(),
+                    })
+                    .into_iter()
+                    .collect(),
+                body: Vec::new(),
+            })
+            .collect();
+        let abi = artifact
+            .abi
+            .iter()
+            .map(|function| FunctionAbiEntry {
+                name: function.name.clone(),
+                inputs: function.inputs.iter().map(FunctionInputAbi::from).collect(),
+                preconditions: Vec::new(),
+            })
+            .collect();
+        let params: Vec<ParamAst> = artifact.constructor_inputs.iter().map(ParamAst::from).collect();
+        Ok(Self {
+            contract_name: artifact.contract_name.clone(),
+            script,
+            used_ctor_params: vec![true; params.len()],
+            ast: ContractAst { name: artifact.contract_name, params, constants: HashMap::new(), functions, expected_script: None },
+            abi,
+            without_selector: artifact.abi.iter().all(|function| function.selector_index.is_none()),
+            debug_info: None,
+        })
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::{ContractAst, SourceSpan, Statement, StatementKind};
+
+use super::CompilerError;
+
+/// A call from one contract function to another; `span` is the calling statement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallEdge {
+    pub caller: String,
+    pub callee: String,
+    pub span: Option<SourceSpan>,
+}
+
+/// Static call graph between the functions of a contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallGraph {
+    pub contract_name: String,
+    /// Function names in declaration order, paired with whether the function is an entrypoint.
+    pub functions: Vec<(String, bool)>,
+    /// Calls in source order; a function calling the same callee twice yields two edges.
+    pub edges: Vec<CallEdge>,
+}
+
+impl CallGraph {
+    pub fn build(contract: &ContractAst) -> Self {
+        let functions: Vec<_> = contract.functions.iter().map(|func| (func.name.clone(), func.entrypoint)).collect();
+        let names: HashSet<&str> = functions.iter().map(|(name, _)| name.as_str()).collect();
+        let mut edges = Vec::new();
+        for func in &contract.functions {
+            collect_calls(&func.name, &func.body, &names, &mut edges);
+        }
+        Self { contract_name: contract.name.clone(), functions, edges }
+    }
+
+    /// Returns the first call cycle found, as the edges walked from the first function on the cycle back to itself.
+    pub fn find_cycle(&self) -> Option<Vec<&CallEdge>> {
+        let mut finished = HashSet::new();
+        for (name, _) in &self.functions {
+            let mut path = Vec::new();
+            if let Some(cycle) = self.visit(name, &mut path, &mut finished) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    fn visit<'a>(&'a self, name: &str, path: &mut Vec<&'a CallEdge>, finished: &mut HashSet<&'a str>) -> Option<Vec<&'a CallEdge>> {
+        if finished.contains(name) {
+            return None;
+        }
+        for edge in self.edges.iter().filter(|edge| edge.caller == name) {
+            if let Some(start) = path.iter().position(|visited| visited.caller == edge.callee) {
+                let mut cycle = path[start..].to_vec();
+                cycle.push(edge);
+                return Some(cycle);
+            }
+            if edge.callee == name {
+                return Some(vec![edge]);
+            }
+            path.push(edge);
+            let cycle = self.visit(&edge.callee, path, finished);
+            path.pop();
+            if cycle.is_some() {
+                return cycle;
+            }
+        }
+        if let Some((function, _)) = self.functions.iter().find(|(function, _)| function == name) {
+            finished.insert(function.as_str());
+        }
+        None
+    }
+
+    /// Returns `root` followed by every function it transitively calls, in the order they are first reached.
+    pub fn reachable_from(&self, root: &str) -> Vec<&str> {
+        let mut reached: Vec<&str> = self.functions.iter().filter(|(name, _)| name == root).map(|(name, _)| name.as_str()).collect();
+        let mut index = 0;
+        while index < reached.len() {
+            for edge in self.edges.iter().filter(|edge| edge.caller == reached[index]) {
+                if !reached.contains(&edge.callee.as_str()) {
+                    reached.push(&edge.callee);
+                }
+            }
+            index += 1;
+        }
+        reached
+    }
+
+    /// Errors with `RecursiveCall` if any function can reach itself, since calls are inlined.
+    pub fn check_acyclic(&self) -> Result<(), CompilerError> {
+        let Some(cycle) = self.find_cycle() else {
+            return Ok(());
+        };
+        let mut names: Vec<String> = cycle.iter().map(|edge| edge.caller.clone()).collect();
+        names.push(cycle[0].caller.clone());
+        let spans = cycle.iter().filter_map(|edge| edge.span).collect();
+        Err(CompilerError::RecursiveCall { cycle: names, spans })
+    }
+
+    /// Renders the graph in Graphviz DOT format; entrypoints are drawn as boxes.
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!("digraph \"{}\" {{\n", self.contract_name);
+        for (name, entrypoint) in &self.functions {
+            let shape = if *entrypoint { "box" } else { "ellipse" };
+            dot.push_str(&format!("    \"{name}\" [shape={shape}];\n"));
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!("    \"{}\" -> \"{}\";\n", edge.caller, edge.callee));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn collect_calls(caller: &str, body: &[Statement], functions: &HashSet<&str>, edges: &mut Vec<CallEdge>) {
+    for stmt in body {
+        match &stmt.kind {
+            StatementKind::FunctionCall { name, .. } | StatementKind::FunctionCallAssign { name, .. } => {
+                if functions.contains(name.as_str()) {
+                    edges.push(CallEdge { caller: caller.to_string(), callee: name.clone(), span: stmt.span });
+                }
+            }
+            StatementKind::If { then_branch, else_branch, .. } => {
+                collect_calls(caller, then_branch, functions, edges);
+                if let Some(else_branch) = else_branch {
+                    collect_calls(caller, else_branch, functions, edges);
+                }
+            }
+            StatementKind::For { body, .. } => collect_calls(caller, body, functions, edges),
+            _ => {}
+        }
+    }
+}
@@ -2,13 +2,13 @@ use std::collections::{HashMap, HashSet};
 
 use kaspa_txscript::script_builder::ScriptBuilder;
 
-use crate::ast::{Expr, FunctionAst, ParamAst, SourceSpan, Statement};
+use crate::ast::{Expr, FunctionAst, ParamAst, SourceSpan, Statement, TernarySpans};
 use crate::debug::{
-    DebugConstantMapping, DebugEvent, DebugEventKind, DebugFunctionRange, DebugInfo, DebugParamMapping, DebugRecorder,
-    DebugVariableUpdate,
+    DebugCheck, DebugConstantMapping, DebugEvent, DebugEventKind, DebugFold, DebugFunctionRange, DebugInfo, DebugLogArg,
+    DebugLogPoint, DebugParamMapping, DebugRecorder, DebugVariableUpdate,
 };
 
-use super::{CompilerError, resolve_expr_for_debug};
+use super::{CompilerError, fold, resolve_expr_for_debug};
 
 pub(super) fn record_synthetic_range(
     builder: &mut ScriptBuilder,
@@ -23,6 +23,18 @@ pub(super) fn record_synthetic_range(
     Ok(())
 }
 
+/// Bytecode of one compiled source ternary: `condition OpIf then OpElse else OpEndIf` spans
+/// `start..end`, with each arm between its `then_*` and `else_*` offsets.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct TernaryRange {
+    pub spans: TernarySpans,
+    pub start: usize,
+    pub then_start: usize,
+    pub then_end: usize,
+    pub else_start: usize,
+    pub else_end: usize,
+}
+
 /// Per-function debug recorder active during function compilation.
 /// Records params, statements, and variable updates for a single function.
 #[derive(Debug, Default)]
@@ -32,6 +44,9 @@ pub struct FunctionDebugRecorder {
     events: Vec<DebugEvent>,
     variable_updates: Vec<DebugVariableUpdate>,
     param_mappings: Vec<DebugParamMapping>,
+    log_points: Vec<DebugLogPoint>,
+    folds: Vec<DebugFold>,
+    checks: Vec<DebugCheck>,
     next_seq: u32,
     call_depth: u32,
     frame_id: u32,
@@ -100,6 +115,7 @@ impl FunctionDebugRecorder {
                 type_name: param.type_name.clone(),
                 stack_index: (param_count - 1 - index) as i64,
                 function: function.name.clone(),
+                scale: param.scale,
             });
         }
     }
@@ -109,6 +125,43 @@ impl FunctionDebugRecorder {
         self.push_event(bytecode_start, bytecode_start + bytecode_len, stmt.span, kind)
     }
 
+    /// Records the `if (...)` header as its own step, so stepping stops on the condition before a branch runs.
+    pub fn record_condition(&mut self, span: Option<SourceSpan>, bytecode_start: usize, bytecode_end: usize) -> Option<u32> {
+        self.push_event(bytecode_start, bytecode_end, span, DebugEventKind::Statement {})
+    }
+
+    /// Records a ternary and each arm as steps of their own, after the statement that compiled them.
+    /// Only the taken arm executes, so stepping stops on it and skips the other.
+    pub fn record_ternaries(&mut self, ternaries: Vec<TernaryRange>) {
+        let mut steps = Vec::with_capacity(ternaries.len() * 3);
+        for ternary in ternaries {
+            let TernaryRange { spans, start, then_start, then_end, else_start, else_end } = ternary;
+            steps.push((start, else_end + 1, spans.expr));
+            steps.push((then_start, then_end, spans.then_expr));
+            steps.push((else_start, else_end, spans.else_expr));
+        }
+        // Nested ternaries are noted innermost first; steps have to follow the bytecode instead.
+        steps.sort_by_key(|&(start, end, _)| (start, std::cmp::Reverse(end)));
+        for (start, end, span) in steps {
+            self.push_event(start, end, Some(span), DebugEventKind::Statement {});
+        }
+    }
+
+    /// Marks where a branch's variable updates start; pass the result to `close_scope` at its end.
+    pub fn open_scope(&self) -> usize {
+        self.variable_updates.len()
+    }
+
+    /// Ends the visibility of every update recorded since `open_scope` at the branch's last step.
+    pub fn close_scope(&mut self, first_update: usize) {
+        let Some(last_sequence) = self.next_seq.checked_sub(1) else {
+            return;
+        };
+        for update in &mut self.variable_updates[first_update..] {
+            update.scope_end.get_or_insert(last_sequence);
+        }
+    }
+
     pub fn record_virtual_step(&mut self, span: Option<SourceSpan>, bytecode_offset: usize) -> Option<u32> {
         self.push_event(bytecode_offset, bytecode_offset, span, DebugEventKind::Virtual {})
     }
@@ -136,6 +189,71 @@ impl FunctionDebugRecorder {
         }
     }
 
+    /// Records a `log` statement as a virtual step plus a log point evaluated by the debugger. A
+    /// `warnIf` passes its message as `warning` and its condition as the only arg.
+    pub fn record_log(
+        &mut self,
+        stmt: &Statement,
+        bytecode_offset: usize,
+        args: Vec<(String, String, Expr)>,
+        warning: Option<String>,
+    ) {
+        if let Some(sequence) = self.record_statement(stmt, bytecode_offset, 0) {
+            self.log_points.push(DebugLogPoint {
+                bytecode_offset,
+                span: stmt.span,
+                function: self.function_name.clone(),
+                args: args.into_iter().map(|(_, type_name, expr)| DebugLogArg { type_name, expr }).collect(),
+                warning,
+                sequence,
+                frame_id: self.frame_id,
+                scope_end: None,
+            });
+        }
+    }
+
+    /// Moves every recorded bytecode offset through `remap`, after the function's script was rewritten.
+    pub fn remap_offsets(&mut self, remap: impl Fn(usize) -> usize) {
+        for event in &mut self.events {
+            event.bytecode_start = remap(event.bytecode_start);
+            event.bytecode_end = remap(event.bytecode_end);
+        }
+        for update in &mut self.variable_updates {
+            update.bytecode_offset = remap(update.bytecode_offset);
+        }
+        for log_point in &mut self.log_points {
+            log_point.bytecode_offset = remap(log_point.bytecode_offset);
+        }
+        for check in &mut self.checks {
+            check.bytecode_start = remap(check.bytecode_start);
+            check.bytecode_end = remap(check.bytecode_end);
+        }
+    }
+
+    /// Records a compiled `require` whose bytecode is `bytecode_start..bytecode_end`.
+    pub fn record_check(&mut self, stmt: &Statement, condition: &Expr, bytecode_start: usize, bytecode_end: usize) {
+        if !self.enabled {
+            return;
+        }
+        self.checks.push(DebugCheck {
+            span: stmt.span,
+            function: self.function_name.clone(),
+            condition: condition.to_string(),
+            bytecode_start,
+            bytecode_end,
+        });
+    }
+
+    /// Notes the expressions of `stmt` that constant folding replaces with their value.
+    pub fn record_folds(&mut self, stmt: &Statement, env: &HashMap<String, Expr>) {
+        if !self.enabled {
+            return;
+        }
+        for (expr, value) in fold::folded_exprs(stmt, env) {
+            self.folds.push(DebugFold { span: stmt.span, function: self.function_name.clone(), expr, value });
+        }
+    }
+
     pub fn record_inline_param_updates(
         &mut self,
         function: &FunctionAst,
@@ -189,9 +307,18 @@ impl FunctionDebugRecorder {
         for mut update in updates {
             if let Some(merged_seq) = seq_map.get(&update.sequence) {
                 update.sequence = *merged_seq;
+                update.scope_end = update.scope_end.and_then(|end| seq_map.get(&end).copied());
                 self.variable_updates.push(update);
             }
         }
+
+        for log_point in &inline.log_points {
+            if let Some(merged_seq) = seq_map.get(&log_point.sequence) {
+                self.log_points.push(DebugLogPoint { sequence: *merged_seq, ..log_point.clone() });
+            }
+        }
+        self.folds.extend(inline.folds.iter().cloned());
+        self.checks.extend(inline.checks.iter().cloned());
     }
 
     pub(super) fn record_variable_updates(
@@ -214,6 +341,7 @@ impl FunctionDebugRecorder {
                 function: self.function_name.clone(),
                 sequence,
                 frame_id: self.frame_id,
+                scope_end: None,
             });
         }
     }
@@ -266,6 +394,7 @@ impl DebugSink {
                 name: param.name.clone(),
                 type_name: param.type_name.clone(),
                 value: value.clone(),
+                scale: param.scale,
             });
         }
     }
@@ -296,6 +425,24 @@ impl DebugSink {
         let seq_base = rec.reserve_sequence_block(debug.sequence_count());
         emit_events_with_offset(&debug.events, offset, seq_base, rec);
         emit_variable_updates_with_offset(&debug.variable_updates, offset, seq_base, rec);
+        for log_point in &debug.log_points {
+            rec.record_log_point(DebugLogPoint {
+                bytecode_offset: log_point.bytecode_offset + offset,
+                sequence: seq_base.saturating_add(log_point.sequence),
+                ..log_point.clone()
+            });
+        }
+        for fold in &debug.folds {
+            rec.record_fold(fold.clone());
+        }
+        for check in &debug.checks {
+            rec.record_check(DebugCheck {
+                function: name.to_string(),
+                bytecode_start: check.bytecode_start + offset,
+                bytecode_end: check.bytecode_end + offset,
+                ..check.clone()
+            });
+        }
         rec.record_function(DebugFunctionRange { name: name.to_string(), bytecode_start: offset, bytecode_end: offset + script_len });
         record_param_mappings(&debug.param_mappings, rec);
     }
@@ -333,6 +480,7 @@ fn emit_variable_updates_with_offset(updates: &[DebugVariableUpdate], offset: us
             function: update.function.clone(),
             sequence: seq_base.saturating_add(update.sequence),
             frame_id: update.frame_id,
+            scope_end: update.scope_end.map(|end| seq_base.saturating_add(end)),
         });
     }
 }
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+
+use crate::ast::{BinaryOp, ConsoleArg, ContractAst, Expr, ParamAst, Statement, StatementKind, UnaryOp};
+
+use super::CompilerError;
+
+/// Scale carried by an int-valued expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scale {
+    /// Fixed by a `@scale` param: other operands must match it exactly.
+    Fixed(u32),
+    /// Taken from a decimal literal, which may be padded with trailing zeros to match a larger scale.
+    Literal(u32),
+}
+
+impl Scale {
+    fn digits(self) -> u32 {
+        match self {
+            Scale::Fixed(scale) | Scale::Literal(scale) => scale,
+        }
+    }
+}
+
+type Scales = HashMap<String, Scale>;
+
+/// A lowered expression and the scale of its value, if it has one.
+type Lowered = (Expr, Option<Scale>);
+
+/// Rewrites decimal literals into scaled ints and checks that scaled values are only combined with
+/// values of the same scale. `@scale` params need no rewriting: callers already pass them scaled.
+pub(super) fn lower_contract(contract: &ContractAst) -> Result<ContractAst, CompilerError> {
+    let callee_params = contract.functions.iter().map(|func| (func.name.as_str(), func.params.as_slice())).collect();
+    let lowering = Lowering { callee_params };
+
+    let mut lowered = contract.clone();
+    let mut contract_scales = Scales::new();
+    for (name, value) in lowered.constants.iter_mut() {
+        let (expr, scale) = lowering.expr(value, &Scales::new())?;
+        *value = expr;
+        if let Some(scale) = scale {
+            contract_scales.insert(name.clone(), scale);
+        }
+    }
+    declare_params(&mut contract_scales, &contract.params);
+
+    for func in &mut lowered.functions {
+        let mut scales = contract_scales.clone();
+        declare_params(&mut scales, &func.params);
+        func.body = lowering.block(&func.body, &scales)?;
+    }
+    Ok(lowered)
+}
+
+/// Converts a decimal argument for a param with the given scale into the scaled int the script expects.
+pub(super) fn scale_arg(arg: Expr, scale: Option<u32>, name: &str) -> Result<Expr, CompilerError> {
+    let Expr::Decimal { digits, scale: places } = arg else {
+        return Ok(arg);
+    };
+    match scale {
+        Some(scale) if places <= scale => rescale(digits, scale - places).map(Expr::Int),
+        Some(scale) => Err(CompilerError::Unsupported(format!(
+            "argument '{name}' has {places} fractional digits but is declared @scale({scale})"
+        ))),
+        None => Err(CompilerError::Unsupported(format!("argument '{name}' is a decimal but its param has no @scale"))),
+    }
+}
+
+fn declare_params(scales: &mut Scales, params: &[ParamAst]) {
+    for param in params {
+        match param.scale {
+            Some(scale) => scales.insert(param.name.clone(), Scale::Fixed(scale)),
+            None => scales.remove(&param.name),
+        };
+    }
+}
+
+fn rescale(digits: i64, extra_places: u32) -> Result<i64, CompilerError> {
+    10i64
+        .checked_pow(extra_places)
+        .and_then(|factor| digits.checked_mul(factor))
+        .ok_or_else(|| CompilerError::Unsupported(format!("decimal literal {digits} overflows when rescaled")))
+}
+
+struct Lowering<'a> {
+    callee_params: HashMap<&'a str, &'a [ParamAst]>,
+}
+
+impl Lowering<'_> {
+    fn block(&self, body: &[Statement], scales: &Scales) -> Result<Vec<Statement>, CompilerError> {
+        let mut scales = scales.clone();
+        body.iter().map(|stmt| self.statement(stmt, &mut scales)).collect()
+    }
+
+    fn statement(&self, stmt: &Statement, scales: &mut Scales) -> Result<Statement, CompilerError> {
+        let plain = |expr: &Expr| self.expr(expr, scales).map(|(expr, _)| expr);
+        let kind = match &stmt.kind {
+            StatementKind::VariableDefinition { type_name, modifiers, name, expr } => {
+                let (expr, scale) = match expr {
+                    Some(expr) => {
+                        let (expr, scale) = self.expr(expr, scales)?;
+                        (Some(expr), scale)
+                    }
+                    None => (None, None),
+                };
+                match scale {
+                    Some(scale) => scales.insert(name.clone(), scale),
+                    None => scales.remove(name),
+                };
+                StatementKind::VariableDefinition {
+                    type_name: type_name.clone(),
+                    modifiers: modifiers.clone(),
+                    name: name.clone(),
+                    expr,
+                }
+            }
+            StatementKind::Let { name, expr } => {
+                let (expr, scale) = self.expr(expr, scales)?;
+                match scale {
+                    Some(scale) => scales.insert(name.clone(), scale),
+                    None => scales.remove(name),
+                };
+                StatementKind::Let { name: name.clone(), expr }
+            }
+            StatementKind::Assign { name, expr } => {
+                let target = (Expr::Identifier(name.clone()), scales.get(name).copied());
+                let (_, expr, _) = unify("'='", target, self.expr(expr, scales)?)?;
+                StatementKind::Assign { name: name.clone(), expr }
+            }
+            StatementKind::FunctionCall { name, args } => {
+                StatementKind::FunctionCall { name: name.clone(), args: self.call_args(name, args, scales)? }
+            }
+            StatementKind::FunctionCallAssign { bindings, name, args } => {
+                let args = self.call_args(name, args, scales)?;
+                for binding in bindings {
+                    scales.remove(&binding.name);
+                }
+                StatementKind::FunctionCallAssign { bindings: bindings.clone(), name: name.clone(), args }
+            }
+            StatementKind::TupleAssignment { left_type, left_name, right_type, right_name, expr } => {
+                let expr = plain(expr)?;
+                scales.remove(left_name);
+                scales.remove(right_name);
+                StatementKind::TupleAssignment {
+                    left_type: left_type.clone(),
+                    left_name: left_name.clone(),
+                    right_type: right_type.clone(),
+                    right_name: right_name.clone(),
+                    expr,
+                }
+            }
+            StatementKind::ArrayPush { name, expr } => StatementKind::ArrayPush { name: name.clone(), expr: plain(expr)? },
+            StatementKind::TimeOp { tx_var, expr, message } => {
+                StatementKind::TimeOp { tx_var: *tx_var, expr: plain(expr)?, message: message.clone() }
+            }
+            StatementKind::Require { expr, message, covenant_only } => {
+                StatementKind::Require { expr: plain(expr)?, message: message.clone(), covenant_only: *covenant_only }
+            }
+            StatementKind::If { condition, then_branch, else_branch, header_span } => StatementKind::If {
+                condition: plain(condition)?,
+                then_branch: self.block(then_branch, scales)?,
+                else_branch: else_branch.as_ref().map(|branch| self.block(branch, scales)).transpose()?,
+                header_span: *header_span,
+            },
+            StatementKind::For { ident, start, end, body, header_span } => {
+                let mut body_scales = scales.clone();
+                body_scales.remove(ident);
+                let body = self.block(body, &body_scales)?;
+                StatementKind::For { ident: ident.clone(), start: plain(start)?, end: plain(end)?, body, header_span: *header_span }
+            }
+            StatementKind::Yield { expr } => StatementKind::Yield { expr: plain(expr)? },
+            StatementKind::Return { exprs } => StatementKind::Return { exprs: exprs.iter().map(plain).collect::<Result<_, _>>()? },
+            StatementKind::Console { args } => StatementKind::Console {
+                args: args
+                    .iter()
+                    .map(|arg| match arg {
+                        ConsoleArg::Literal(expr) => plain(expr).map(ConsoleArg::Literal),
+                        ConsoleArg::Identifier(name) => Ok(ConsoleArg::Identifier(name.clone())),
+                    })
+                    .collect::<Result<_, _>>()?,
+            },
+            StatementKind::Log { args } => StatementKind::Log { args: args.iter().map(plain).collect::<Result<_, _>>()? },
+            StatementKind::WarnIf { condition, message } => {
+                StatementKind::WarnIf { condition: plain(condition)?, message: message.clone() }
+            }
+        };
+        Ok(Statement { span: stmt.span, kind })
+    }
+
+    /// Lowers call arguments, matching each one against the scale of the callee's param. Builtins
+    /// take their arguments as plain ints.
+    fn call_args(&self, callee: &str, args: &[Expr], scales: &Scales) -> Result<Vec<Expr>, CompilerError> {
+        let params = self.callee_params.get(callee);
+        let mut lowered = Vec::with_capacity(args.len());
+        for (index, arg) in args.iter().enumerate() {
+            let value = self.expr(arg, scales)?;
+            match params.and_then(|params| params.get(index)) {
+                Some(param) => {
+                    let target = (Expr::Identifier(param.name.clone()), param.scale.map(Scale::Fixed));
+                    lowered.push(unify(&format!("argument '{}' of '{callee}'", param.name), target, value)?.1);
+                }
+                None => lowered.push(value.0),
+            }
+        }
+        Ok(lowered)
+    }
+
+    fn expr(&self, expr: &Expr, scales: &Scales) -> Result<Lowered, CompilerError> {
+        let plain = |expr: &Expr| self.expr(expr, scales).map(|(expr, _)| Box::new(expr));
+        let all = |exprs: &[Expr]| exprs.iter().map(|expr| Ok(*plain(expr)?)).collect::<Result<Vec<_>, CompilerError>>();
+        Ok(match expr {
+            Expr::Decimal { digits, scale } => (Expr::Int(*digits), Some(Scale::Literal(*scale))),
+            Expr::Identifier(name) => (expr.clone(), scales.get(name).copied()),
+            Expr::Int(_) | Expr::Bool(_) | Expr::Bytes(_) | Expr::String(_) | Expr::Nullary(_) => (expr.clone(), None),
+            Expr::Unary { op: UnaryOp::Neg, expr: inner } => {
+                let (inner, scale) = self.expr(inner, scales)?;
+                match inner {
+                    Expr::Int(value) if scale.is_some() => (Expr::Int(-value), scale),
+                    inner => (Expr::Unary { op: UnaryOp::Neg, expr: Box::new(inner) }, scale),
+                }
+            }
+            Expr::Unary { op, expr } => (Expr::Unary { op: *op, expr: plain(expr)? }, None),
+            Expr::Binary { op, left, right } => {
+                let left = self.expr(left, scales)?;
+                let right = self.expr(right, scales)?;
+                self.binary(*op, left, right)?
+            }
+            Expr::IfElse { condition, then_expr, else_expr, spans } => {
+                let condition = plain(condition)?;
+                let (then_expr, else_expr, scale) = unify("'?:'", self.expr(then_expr, scales)?, self.expr(else_expr, scales)?)?;
+                (Expr::IfElse { condition, then_expr: Box::new(then_expr), else_expr: Box::new(else_expr), spans: *spans }, scale)
+            }
+            Expr::Array(items) => (Expr::Array(all(items)?), None),
+            Expr::Call { name, args } => (Expr::Call { name: name.clone(), args: self.call_args(name, args, scales)? }, None),
+            Expr::New { name, args } => (Expr::New { name: name.clone(), args: all(args)? }, None),
+            Expr::Split { source, index, part } => (Expr::Split { source: plain(source)?, index: plain(index)?, part: *part }, None),
+            Expr::Slice { source, start, end } => {
+                (Expr::Slice { source: plain(source)?, start: plain(start)?, end: plain(end)? }, None)
+            }
+            Expr::ArrayIndex { source, index } => (Expr::ArrayIndex { source: plain(source)?, index: plain(index)? }, None),
+            Expr::Introspection { kind, index } => (Expr::Introspection { kind: *kind, index: plain(index)? }, None),
+            Expr::Map { key_type, value_type, entries, default } => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| Ok((*plain(key)?, *plain(value)?)))
+                    .collect::<Result<Vec<_>, CompilerError>>()?;
+                let default = default.as_deref().map(plain).transpose()?;
+                (Expr::Map { key_type: key_type.clone(), value_type: value_type.clone(), entries, default }, None)
+            }
+        })
+    }
+
+    fn binary(&self, op: BinaryOp, left: Lowered, right: Lowered) -> Result<Lowered, CompilerError> {
+        let symbol = op.symbol();
+        let binary = |left: Expr, right: Expr| Expr::Binary { op, left: Box::new(left), right: Box::new(right) };
+        match op {
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mod => {
+                let (left, right, scale) = unify(&format!("'{symbol}'"), left, right)?;
+                Ok((binary(left, right), scale))
+            }
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                let (left, right, _) = unify(&format!("'{symbol}'"), left, right)?;
+                Ok((binary(left, right), None))
+            }
+            BinaryOp::Mul => match (left.1, right.1) {
+                (Some(_), Some(_)) => Err(CompilerError::Unsupported(
+                    "multiplying two scaled values changes the scale; divide by a power of ten explicitly".to_string(),
+                )),
+                (scale, None) | (None, scale) => Ok((binary(left.0, right.0), scale)),
+            },
+            BinaryOp::Div => match (left.1, right.1) {
+                (scale, None) => Ok((binary(left.0, right.0), scale)),
+                // A ratio of two values with the same scale is unscaled.
+                (Some(_), Some(_)) => {
+                    let (left, right, _) = unify("'/'", left, right)?;
+                    Ok((binary(left, right), None))
+                }
+                (None, Some(scale)) => Err(CompilerError::Unsupported(format!(
+                    "cannot divide an unscaled value by a value with scale {}",
+                    scale.digits()
+                ))),
+            },
+            _ => Ok((binary(left.0, right.0), None)),
+        }
+    }
+}
+
+/// Brings two operands to a common scale, padding decimals with fewer fractional digits, and
+/// returns them with the shared scale. `what` names the operation in errors.
+fn unify(what: &str, left: Lowered, right: Lowered) -> Result<(Expr, Expr, Option<Scale>), CompilerError> {
+    let mismatch = |detail: String| CompilerError::Unsupported(format!("mixed scales in {what}: {detail}"));
+    match (left, right) {
+        ((left, None), (right, None)) => Ok((left, right, None)),
+        ((left, Some(scale)), (right, None)) | ((left, None), (right, Some(scale))) => {
+            // Zero reads the same at every scale.
+            if matches!(left, Expr::Int(0)) || matches!(right, Expr::Int(0)) {
+                return Ok((left, right, Some(scale)));
+            }
+            Err(mismatch(format!("a value with scale {} and an unscaled int; write the int as a decimal literal", scale.digits())))
+        }
+        ((_, Some(Scale::Fixed(a))), (_, Some(Scale::Fixed(b)))) if a != b => {
+            Err(mismatch(format!("scale {a} and scale {b}; rescale one side explicitly")))
+        }
+        ((left, Some(a)), (right, Some(b))) => {
+            let scale = match (a, b) {
+                (Scale::Fixed(scale), _) | (_, Scale::Fixed(scale)) => Scale::Fixed(scale),
+                (a, b) => Scale::Literal(a.digits().max(b.digits())),
+            };
+            let target = scale.digits();
+            let widen = |expr: Expr, from: u32| match expr {
+                expr if from == target => Ok(expr),
+                _ if from > target => Err(mismatch(format!("a decimal has {from} fractional digits but the scale is {target}"))),
+                Expr::Int(digits) => rescale(digits, target - from).map(Expr::Int),
+                expr => {
+                    let factor = Expr::Int(rescale(1, target - from)?);
+                    Ok(Expr::Binary { op: BinaryOp::Mul, left: Box::new(expr), right: Box::new(factor) })
+                }
+            };
+            Ok((widen(left, a.digits())?, widen(right, b.digits())?, Some(scale)))
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::visit::{expr_children, statement_exprs};
+use crate::ast::{BinaryOp, Expr, Statement, UnaryOp};
+
+use super::resolve_expr;
+
+/// The literal `expr` evaluates to, when every operand is an int or bool literal once constants and
+/// locals in `env` are substituted. Used by `CompileOptions::optimize` to push the result instead of
+/// computing it on the stack.
+///
+/// Folding follows the script number rules: an operation that would overflow the 8-byte range, or
+/// divide by zero, is left to fail at run time as it would without optimization.
+pub(super) fn fold_constant(expr: &Expr, env: &HashMap<String, Expr>) -> Option<Expr> {
+    if !matches!(expr, Expr::Unary { .. } | Expr::Binary { .. }) {
+        return None;
+    }
+    let resolved = resolve_expr(expr.clone(), env, &mut HashSet::new()).ok()?;
+    evaluate(&resolved)
+}
+
+fn evaluate(expr: &Expr) -> Option<Expr> {
+    match expr {
+        Expr::Int(value) => script_num(*value),
+        Expr::Bool(value) => Some(Expr::Bool(*value)),
+        Expr::Unary { op, expr } => match (op, evaluate(expr)?) {
+            (UnaryOp::Neg, Expr::Int(value)) => script_num(value.checked_neg()?),
+            (UnaryOp::Not, Expr::Bool(value)) => Some(Expr::Bool(!value)),
+            _ => None,
+        },
+        Expr::Binary { op, left, right } => match (evaluate(left)?, evaluate(right)?) {
+            (Expr::Int(left), Expr::Int(right)) => fold_int(*op, left, right),
+            (Expr::Bool(left), Expr::Bool(right)) => match op {
+                BinaryOp::And => Some(Expr::Bool(left && right)),
+                BinaryOp::Or => Some(Expr::Bool(left || right)),
+                BinaryOp::Eq => Some(Expr::Bool(left == right)),
+                BinaryOp::Ne => Some(Expr::Bool(left != right)),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn fold_int(op: BinaryOp, left: i64, right: i64) -> Option<Expr> {
+    let value = match op {
+        BinaryOp::Add => left.checked_add(right)?,
+        BinaryOp::Sub => left.checked_sub(right)?,
+        BinaryOp::Mul => left.checked_mul(right)?,
+        BinaryOp::Div if right != 0 => left.checked_div(right)?,
+        BinaryOp::Mod if right != 0 => left.checked_rem(right)?,
+        BinaryOp::Eq => return Some(Expr::Bool(left == right)),
+        BinaryOp::Ne => return Some(Expr::Bool(left != right)),
+        BinaryOp::Lt => return Some(Expr::Bool(left < right)),
+        BinaryOp::Le => return Some(Expr::Bool(left <= right)),
+        BinaryOp::Gt => return Some(Expr::Bool(left > right)),
+        BinaryOp::Ge => return Some(Expr::Bool(left >= right)),
+        _ => return None,
+    };
+    script_num(value)
+}
+
+/// `i64::MIN` needs a ninth byte as a script number, so it is never produced by folding.
+fn script_num(value: i64) -> Option<Expr> {
+    (value != i64::MIN).then_some(Expr::Int(value))
+}
+
+/// The outermost subexpressions of `stmt` that `fold_constant` replaces, as `(source, value)`.
+/// Nested statement bodies are left out; they are reported when they are compiled.
+pub(super) fn folded_exprs(stmt: &Statement, env: &HashMap<String, Expr>) -> Vec<(String, Expr)> {
+    let mut folds = Vec::new();
+    for expr in statement_exprs(stmt) {
+        collect_folds(expr, env, &mut folds);
+    }
+    folds
+}
+
+/// Reports `expr` if it folds, and otherwise looks for folds among its children.
+fn collect_folds(expr: &Expr, env: &HashMap<String, Expr>, folds: &mut Vec<(String, Expr)>) {
+    match fold_constant(expr, env) {
+        Some(value) => folds.push((expr.to_string(), value)),
+        None => expr_children(expr).into_iter().for_each(|child| collect_folds(child, env, folds)),
+    }
+}
@@ -0,0 +1,61 @@
+use kaspa_txscript::opcodes::codes::*;
+
+/// Mass charged for every serialized script byte (consensus `mass_per_tx_byte`).
+pub const MASS_PER_SCRIPT_BYTE: u64 = 1;
+/// Mass charged for every signature operation (consensus `mass_per_sig_op`).
+pub const MASS_PER_SIG_OP: u64 = 1000;
+/// Most keys `OpCheckMultiSig` accepts, also the worst case assumed for multisig opcodes when the
+/// count is not known statically.
+pub const MAX_MULTISIG_KEYS: usize = 20;
+
+/// Static worst-case mass of a script: every opcode is assumed to execute, so both branches of
+/// conditionals are counted.
+pub fn estimate_script_mass(script: &[u8]) -> u64 {
+    instructions(script).fold(0u64, |mass, (opcode, size)| {
+        mass.saturating_add(size as u64 * MASS_PER_SCRIPT_BYTE).saturating_add(opcode_sig_ops(opcode) * MASS_PER_SIG_OP)
+    })
+}
+
+/// Worst-case number of signature operations in a script.
+pub fn count_sig_ops(script: &[u8]) -> u64 {
+    instructions(script).fold(0u64, |sig_ops, (opcode, _)| sig_ops.saturating_add(opcode_sig_ops(opcode)))
+}
+
+/// Number of signature operations charged for an opcode.
+pub fn opcode_sig_ops(opcode: u8) -> u64 {
+    match opcode {
+        OpCheckSig | OpCheckSigVerify | OpCheckSigECDSA => 1,
+        OpCheckMultiSig | OpCheckMultiSigVerify | OpCheckMultiSigECDSA => MAX_MULTISIG_KEYS as u64,
+        _ => 0,
+    }
+}
+
+/// Walks a script yielding `(opcode, serialized size)` for every instruction.
+pub(crate) fn instructions(script: &[u8]) -> impl Iterator<Item = (u8, usize)> + '_ {
+    let mut offset = 0usize;
+    std::iter::from_fn(move || {
+        let opcode = *script.get(offset)?;
+        let size = instruction_size(script, offset);
+        offset = offset.saturating_add(size);
+        Some((opcode, size))
+    })
+}
+
+/// Serialized size of the instruction starting at `offset`, including any push payload.
+fn instruction_size(script: &[u8], offset: usize) -> usize {
+    let opcode = script[offset];
+    let read_len = |width: usize| -> usize {
+        let start = offset + 1;
+        let Some(bytes) = script.get(start..start + width) else {
+            return script.len() - start.min(script.len());
+        };
+        bytes.iter().rev().fold(0usize, |acc, byte| (acc << 8) | *byte as usize)
+    };
+    match opcode {
+        OpData1..=OpData75 => 1 + opcode as usize,
+        OpPushData1 => 2 + read_len(1),
+        OpPushData2 => 3 + read_len(2),
+        OpPushData4 => 5 + read_len(4),
+        _ => 1,
+    }
+}
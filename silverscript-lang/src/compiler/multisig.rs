@@ -0,0 +1,60 @@
+//! Stack layout of `OpCheckMultiSig`, for builders that assemble multisig spends by hand.
+//!
+//! Kaspa's `OpCheckMultiSig` pops exactly what it uses. Bitcoin has an off-by-one bug that pops one
+//! extra item, so Bitcoin sigscripts start with a dummy `OP_0`. Kaspa has no such dummy element, and
+//! a sigscript that pushes one leaves an extra item on the stack, which fails the clean-stack rule.
+//!
+//! The locking script is `<m> <pubkey_1> .. <pubkey_n> <n> OpCheckMultiSig`. The sigscript pushes
+//! exactly `m` signatures in the same order as the keys they belong to. The engine walks keys and
+//! signatures forward together, so a signature never matches a key that comes before the previous
+//! match. From bottom to top, the stack the opcode sees is:
+//!
+//! ```text
+//! <sig_1> .. <sig_m> <m> <pubkey_1> .. <pubkey_n> <n>
+//! ```
+
+use kaspa_txscript::opcodes::codes::OpCheckMultiSig;
+use kaspa_txscript::script_builder::ScriptBuilder;
+
+use super::CompilerError;
+use super::mass::MAX_MULTISIG_KEYS;
+
+/// Builds `<required> <pubkeys..> <n> OpCheckMultiSig`.
+pub fn multisig_locking_script(required: usize, pubkeys: &[Vec<u8>]) -> Result<Vec<u8>, CompilerError> {
+    check_counts(required, pubkeys.len())?;
+    let mut builder = ScriptBuilder::new();
+    builder.add_i64(required as i64)?;
+    for pubkey in pubkeys {
+        builder.add_data(pubkey)?;
+    }
+    builder.add_i64(pubkeys.len() as i64)?;
+    builder.add_op(OpCheckMultiSig)?;
+    Ok(builder.drain())
+}
+
+/// Builds the sigscript that unlocks a `required`-of-`signatures.len()` multisig.
+///
+/// `signatures` has one slot per key, in key order; `Some` holds that key's signature. Exactly
+/// `required` slots must be filled. The signatures are pushed in key order with no dummy element.
+pub fn multisig_sigscript_layout(required: usize, signatures: &[Option<Vec<u8>>]) -> Result<Vec<u8>, CompilerError> {
+    check_counts(required, signatures.len())?;
+    let provided = signatures.iter().flatten().count();
+    if provided != required {
+        return Err(CompilerError::Unsupported(format!("multisig needs exactly {required} signatures, got {provided}")));
+    }
+    let mut builder = ScriptBuilder::new();
+    for signature in signatures.iter().flatten() {
+        builder.add_data(signature)?;
+    }
+    Ok(builder.drain())
+}
+
+pub(super) fn check_counts(required: usize, keys: usize) -> Result<(), CompilerError> {
+    if keys == 0 || keys > MAX_MULTISIG_KEYS {
+        return Err(CompilerError::Unsupported(format!("multisig takes 1 to {MAX_MULTISIG_KEYS} keys, got {keys}")));
+    }
+    if required == 0 || required > keys {
+        return Err(CompilerError::Unsupported(format!("multisig threshold {required} must be between 1 and {keys}")));
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use kaspa_txscript::opcodes::codes::*;
+
+use super::debug_recording::FunctionDebugRecorder;
+use super::mass::instructions;
+
+enum Rewrite {
+    Remove,
+    Fuse(u8),
+}
+
+fn rewrite(first: u8, second: u8) -> Option<Rewrite> {
+    match (first, second) {
+        (OpDup, OpDrop) | (OpSwap, OpSwap) => Some(Rewrite::Remove),
+        (first, OpDrop) if is_push(first) => Some(Rewrite::Remove),
+        (OpEqual, OpVerify) => Some(Rewrite::Fuse(OpEqualVerify)),
+        (OpNumEqual, OpVerify) => Some(Rewrite::Fuse(OpNumEqualVerify)),
+        (OpCheckSig, OpVerify) => Some(Rewrite::Fuse(OpCheckSigVerify)),
+        (OpCheckMultiSig, OpVerify) => Some(Rewrite::Fuse(OpCheckMultiSigVerify)),
+        _ => None,
+    }
+}
+
+/// Data pushes and small-int opcodes; `OpReserved` sits in the range but fails when executed.
+fn is_push(opcode: u8) -> bool {
+    opcode <= Op16 && opcode != OpReserved
+}
+
+/// Rewrites adjacent opcode pairs that `CompileOptions::optimize` collapses:
+///
+/// | Pattern | Becomes |
+/// |---------|---------|
+/// | `OpDup OpDrop`, `OpSwap OpSwap`, `<push> OpDrop` | nothing |
+/// | `OpEqual OpVerify` | `OpEqualVerify` |
+/// | `OpNumEqual OpVerify` | `OpNumEqualVerify` |
+/// | `OpCheckSig OpVerify` | `OpCheckSigVerify` |
+/// | `OpCheckMultiSig OpVerify` | `OpCheckMultiSigVerify` |
+///
+/// Scripts have no jump offsets, so dropping bytes never breaks control flow, and a pair is always
+/// inside a single branch. Each pass rewrites non-overlapping pairs; passes repeat until nothing
+/// changes, so `<push> <push> OpDrop OpDrop` disappears too.
+///
+/// The offsets `debug` holds are moved to match; a removed instruction's offsets point at whatever
+/// follows it.
+pub(super) fn optimize(mut script: Vec<u8>, debug: &mut FunctionDebugRecorder) -> Vec<u8> {
+    loop {
+        let (rewritten, offsets) = rewrite_pass(&script);
+        if rewritten.len() == script.len() {
+            return script;
+        }
+        debug.remap_offsets(|offset| offsets.get(offset).copied().unwrap_or(rewritten.len()));
+        script = rewritten;
+    }
+}
+
+/// One pass over `script`. Returns the new script and, for every old byte offset up to and
+/// including the end of the script, its new offset.
+fn rewrite_pass(script: &[u8]) -> (Vec<u8>, Vec<usize>) {
+    let mut starts = Vec::new();
+    let mut offset = 0usize;
+    for (opcode, size) in instructions(script) {
+        starts.push((offset, opcode, size));
+        offset += size;
+    }
+
+    let mut out = Vec::with_capacity(script.len());
+    let mut offsets = vec![0usize; script.len() + 1];
+    let mut index = 0;
+    while index < starts.len() {
+        let (start, opcode, size) = starts[index];
+        if let Some(&(next_start, next_opcode, next_size)) = starts.get(index + 1) {
+            if let Some(rewrite) = rewrite(opcode, next_opcode) {
+                let new_start = out.len();
+                if let Rewrite::Fuse(fused) = rewrite {
+                    out.push(fused);
+                }
+                offsets[start..next_start + next_size].fill(new_start);
+                index += 2;
+                continue;
+            }
+        }
+        for (delta, slot) in offsets[start..start + size].iter_mut().enumerate() {
+            *slot = out.len() + delta;
+        }
+        out.extend_from_slice(&script[start..start + size]);
+        index += 1;
+    }
+    offsets[script.len()] = out.len();
+    (out, offsets)
+}
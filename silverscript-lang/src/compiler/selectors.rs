@@ -0,0 +1,77 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::ast::{AnnotationAst, ContractAst, Expr};
+
+use super::CompilerError;
+
+/// Committed `selectors.lock` contents: entrypoint name to the selector deployed sigscripts push.
+pub type SelectorLock = BTreeMap<String, i64>;
+
+/// Selector of every entrypoint, in declaration order.
+///
+/// Entrypoints pinned with `@selector(n)` keep `n`; the rest take the lowest free values in
+/// declaration order, so a contract without pins keeps the plain `0..n` numbering.
+pub fn entrypoint_selectors(contract: &ContractAst) -> Result<Vec<(String, i64)>, CompilerError> {
+    let entrypoints: Vec<_> = contract.functions.iter().filter(|func| func.entrypoint).collect();
+    let mut pins = Vec::with_capacity(entrypoints.len());
+    let mut taken = HashSet::new();
+    for func in &entrypoints {
+        let pin = func.annotations.iter().find(|annotation| annotation.name == "selector").map(selector_pin).transpose()?;
+        match pin {
+            Some(value) if !taken.insert(value) => {
+                return Err(CompilerError::Unsupported(format!("@selector({value}) is pinned by more than one entrypoint")));
+            }
+            _ => pins.push(pin),
+        }
+    }
+
+    let mut next = 0i64;
+    let mut selectors = Vec::with_capacity(entrypoints.len());
+    for (func, pin) in entrypoints.iter().zip(pins) {
+        let selector = pin.unwrap_or_else(|| {
+            while taken.contains(&next) {
+                next += 1;
+            }
+            taken.insert(next);
+            next
+        });
+        selectors.push((func.name.clone(), selector));
+    }
+    Ok(selectors)
+}
+
+/// Checks the current selectors against a committed lock and returns the updated lock.
+///
+/// Locked entrypoints must keep their selector; new entrypoints are appended to the lock. A new
+/// entrypoint may not reuse the selector of a locked one that was removed, since sigscripts built
+/// for the old function would then reach the new one.
+pub fn check_selector_lock(contract: &ContractAst, lock: &SelectorLock) -> Result<SelectorLock, CompilerError> {
+    let selectors = entrypoint_selectors(contract)?;
+    let mut updated = lock.clone();
+    for (name, selector) in &selectors {
+        match lock.get(name) {
+            Some(locked) if locked != selector => {
+                return Err(CompilerError::Unsupported(format!(
+                    "selector of '{name}' changed from {locked} to {selector}; pin it with @selector({locked})"
+                )));
+            }
+            Some(_) => {}
+            None => {
+                if let Some((previous, _)) = lock.iter().find(|(_, locked)| *locked == selector) {
+                    return Err(CompilerError::Unsupported(format!(
+                        "new entrypoint '{name}' would reuse selector {selector} of removed '{previous}'; pin an unused @selector"
+                    )));
+                }
+                updated.insert(name.clone(), *selector);
+            }
+        }
+    }
+    Ok(updated)
+}
+
+pub(super) fn selector_pin(annotation: &AnnotationAst) -> Result<i64, CompilerError> {
+    match annotation.args.as_slice() {
+        [Expr::Int(value)] if *value >= 0 => Ok(*value),
+        _ => Err(CompilerError::Unsupported("@selector expects a single non-negative integer".to_string())),
+    }
+}
@@ -0,0 +1,48 @@
+//! `@test(args=[...], expect="pass")` vectors: inline examples of how an entrypoint behaves,
+//! checked by `silverc test`.
+
+use crate::ast::{AnnotationAst, Expr, FunctionAst};
+
+use super::CompilerError;
+
+/// One `@test` annotation of an entrypoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestVector {
+    /// From `ctor=[...]`; may be left out for contracts without constructor params.
+    pub ctor_args: Vec<Expr>,
+    pub args: Vec<Expr>,
+    /// `expect="pass"` or `expect="fail"`.
+    pub expect_pass: bool,
+}
+
+/// The `@test` vectors of `function`, in source order.
+pub fn test_vectors(function: &FunctionAst) -> Result<Vec<TestVector>, CompilerError> {
+    function.annotations.iter().filter(|annotation| annotation.name == "test").map(test_vector).collect()
+}
+
+pub(super) fn test_vector(annotation: &AnnotationAst) -> Result<TestVector, CompilerError> {
+    if !annotation.args.is_empty() {
+        return unsupported(r#"@test takes named arguments: args=[...], expect="pass" or "fail", optionally ctor=[...]"#);
+    }
+    let mut ctor_args = Vec::new();
+    let mut args = Vec::new();
+    let mut expect_pass = None;
+    for (key, value) in &annotation.named_args {
+        match (key.as_str(), value) {
+            ("args", Expr::Array(values)) => args = values.clone(),
+            ("ctor", Expr::Array(values)) => ctor_args = values.clone(),
+            ("expect", Expr::String(expect)) if expect == "pass" || expect == "fail" => expect_pass = Some(expect == "pass"),
+            ("args" | "ctor", _) => return unsupported(&format!("@test {key} must be an array literal")),
+            ("expect", _) => return unsupported(r#"@test expect must be "pass" or "fail""#),
+            (other, _) => return unsupported(&format!("unknown @test argument '{other}'")),
+        }
+    }
+    let Some(expect_pass) = expect_pass else {
+        return unsupported(r#"@test needs expect="pass" or expect="fail""#);
+    };
+    Ok(TestVector { ctor_args, args, expect_pass })
+}
+
+fn unsupported(message: &str) -> Result<TestVector, CompilerError> {
+    Err(CompilerError::Unsupported(message.to_string()))
+}
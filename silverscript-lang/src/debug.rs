@@ -1,7 +1,11 @@
-use crate::ast::{Expr, SourceSpan};
+use crate::ast::{CommentKind, Expr, SourceSpan, parse_comments};
+use crate::parser::{Rule, parse_source_file};
 use serde::{Deserialize, Serialize};
 
+pub mod html;
+pub mod opcodes;
 pub mod session;
+pub mod simulate;
 
 pub mod labels {
     pub mod synthetic {
@@ -11,9 +15,29 @@ pub mod labels {
         pub const DISPATCHER_ELSE: &str = "dispatcher.else";
         /// Closes all dispatcher if/else branches.
         pub const DISPATCHER_ENDIFS: &str = "dispatcher.endifs";
+        /// Unreachable `OpReturn` carrying the source hash (`CompileOptions::embed_source_hash`).
+        pub const SOURCE_HASH: &str = "metadata.source_hash";
     }
 }
 
+/// Runs `operation` on the engine, reporting a panic inside it as `engine panicked: ...` instead of
+/// unwinding into the caller. A malformed script must not take a long-running debugger down with it.
+///
+/// Guarded are every opcode a `DebugSession` executes (sigscript seeding and stepping), the shadow
+/// VM that evaluates variables and watches, and every opcode run by `simulate` for traces and spends.
+/// Parsing scripts and compiling contracts are not guarded. An engine that panicked is left in an
+/// unknown state, so a session refuses to step it again.
+pub(crate) fn guard_engine<T>(operation: impl FnOnce() -> T) -> Result<T, String> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(operation)).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "no message".to_string());
+        format!("engine panicked: {message}")
+    })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DebugEventKind {
     Statement {},
@@ -49,6 +73,9 @@ pub struct DebugRecorder {
     params: Vec<DebugParamMapping>,
     functions: Vec<DebugFunctionRange>,
     constants: Vec<DebugConstantMapping>,
+    log_points: Vec<DebugLogPoint>,
+    folds: Vec<DebugFold>,
+    checks: Vec<DebugCheck>,
     next_sequence: u32,
 }
 
@@ -73,6 +100,18 @@ impl DebugRecorder {
         self.constants.push(constant);
     }
 
+    pub fn record_log_point(&mut self, log_point: DebugLogPoint) {
+        self.log_points.push(log_point);
+    }
+
+    pub fn record_fold(&mut self, fold: DebugFold) {
+        self.folds.push(fold);
+    }
+
+    pub fn record_check(&mut self, check: DebugCheck) {
+        self.checks.push(check);
+    }
+
     pub fn next_sequence(&mut self) -> u32 {
         let sequence = self.next_sequence;
         self.next_sequence = self.next_sequence.saturating_add(1);
@@ -90,20 +129,39 @@ impl DebugRecorder {
     }
 
     pub fn into_debug_info(self, source: String) -> DebugInfo {
+        let mappings: Vec<DebugMapping> = self.events.into_iter().map(DebugMapping::from).collect();
+        let invariants = collect_invariants(&source, &mappings);
         DebugInfo {
             source,
-            mappings: self.events.into_iter().map(DebugMapping::from).collect(),
+            mappings,
             variable_updates: self.variable_updates,
             params: self.params,
             functions: self.functions,
             constants: self.constants,
+            invariants,
+            log_points: self.log_points,
+            folds: self.folds,
+            checks: self.checks,
         }
     }
 }
 
+/// How much debug metadata a compile with `record_debug_infos` keeps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugLevel {
+    /// Every mapping, variable update, log point and fold.
+    #[default]
+    Full,
+    /// One mapping per run of statements on a source line, plus params, constants, functions,
+    /// invariants and checks. Locals, `log` output and inlined call frames are unavailable, but the
+    /// debug info of a large contract stays small.
+    Lines,
+}
+
 /// Complete debug metadata attached to compiled contract.
 /// Contains everything needed to map bytecode execution back to source and evaluate variables.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DebugInfo {
     pub source: String,
     pub mappings: Vec<DebugMapping>,
@@ -111,18 +169,47 @@ pub struct DebugInfo {
     pub params: Vec<DebugParamMapping>,
     pub functions: Vec<DebugFunctionRange>,
     pub constants: Vec<DebugConstantMapping>,
+    #[serde(default)]
+    pub invariants: Vec<DebugInvariant>,
+    #[serde(default)]
+    pub log_points: Vec<DebugLogPoint>,
+    #[serde(default)]
+    pub folds: Vec<DebugFold>,
+    /// Every `require` in the script; a check's id is its index here.
+    #[serde(default)]
+    pub checks: Vec<DebugCheck>,
 }
 
 impl DebugInfo {
-    pub fn empty() -> Self {
-        Self {
-            source: String::new(),
-            mappings: Vec::new(),
-            variable_updates: Vec::new(),
-            params: Vec::new(),
-            functions: Vec::new(),
-            constants: Vec::new(),
+    /// Drops what `level` does not keep; see `DebugLevel`.
+    pub fn at_level(mut self, level: DebugLevel) -> Self {
+        if level == DebugLevel::Full {
+            return self;
+        }
+        let mut mappings: Vec<DebugMapping> = Vec::new();
+        let statements =
+            self.mappings.into_iter().filter(|mapping| matches!(mapping.kind, MappingKind::Statement {} | MappingKind::Virtual {}));
+        for mapping in statements {
+            let line = mapping.span.map(|span| span.line);
+            match mappings.last_mut() {
+                Some(last) if last.bytecode_end == mapping.bytecode_start && last.span.map(|span| span.line) == line => {
+                    last.bytecode_end = mapping.bytecode_end;
+                    if last.bytecode_end > last.bytecode_start {
+                        last.kind = MappingKind::Statement {};
+                    }
+                }
+                _ => mappings.push(mapping),
+            }
         }
+        self.mappings = mappings;
+        self.variable_updates.clear();
+        self.log_points.clear();
+        self.folds.clear();
+        self
+    }
+
+    pub fn empty() -> Self {
+        Self::default()
     }
 }
 
@@ -140,6 +227,10 @@ pub struct DebugVariableUpdate {
     pub sequence: u32,
     #[serde(default)]
     pub frame_id: u32,
+    /// Last sequence at which the update is visible, set for updates made inside an `if` branch.
+    /// Later steps see the value merged after the `if` instead, and branch locals disappear.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope_end: Option<u32>,
 }
 
 /// Maps function parameter to its stack position.
@@ -150,6 +241,9 @@ pub struct DebugParamMapping {
     pub type_name: String,
     pub stack_index: i64,
     pub function: String,
+    /// `@scale` of the param; the debugger shows its value as a decimal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
 }
 
 /// Bytecode range for a compiled function.
@@ -168,6 +262,63 @@ pub struct DebugConstantMapping {
     pub name: String,
     pub type_name: String,
     pub value: Expr,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
+}
+
+/// Stack invariant declared with a `/*@invariant: ...*/` comment.
+/// Checked by the debugger when execution reaches the first statement following the comment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugInvariant {
+    pub line: u32,
+    pub condition: String,
+    pub bytecode_offset: usize,
+}
+
+/// A `log(...)` statement. Emits no bytecode; the debugger evaluates `args` when execution
+/// reaches `bytecode_offset`. For a `warnIf(condition, message)`, `warning` holds the message and
+/// `args` the condition alone; the debugger records the warning when the condition is true.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugLogPoint {
+    pub bytecode_offset: usize,
+    pub span: Option<SourceSpan>,
+    pub function: String,
+    pub args: Vec<DebugLogArg>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    #[serde(default)]
+    pub sequence: u32,
+    #[serde(default)]
+    pub frame_id: u32,
+}
+
+/// Pre-resolved `log` argument, evaluated like a variable update.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugLogArg {
+    pub type_name: String,
+    pub expr: Expr,
+}
+
+/// An expression `CompileOptions::optimize` folded to a constant: the script pushes `value` where
+/// the statement at `span` has `expr`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugFold {
+    pub span: Option<SourceSpan>,
+    pub function: String,
+    pub expr: String,
+    pub value: Expr,
+}
+
+/// A `require` statement: `bytecode_start..bytecode_end` evaluates `condition` and verifies it.
+/// `function` is the entrypoint whose script holds the check, so a `require` in a function inlined
+/// into two entrypoints is listed twice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugCheck {
+    pub span: Option<SourceSpan>,
+    pub function: String,
+    pub condition: String,
+    pub bytecode_start: usize,
+    pub bytecode_end: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -218,3 +369,46 @@ impl From<DebugEvent> for DebugMapping {
         }
     }
 }
+
+const INVARIANT_MARKER: &str = "/*@invariant:";
+
+/// Extracts `/*@invariant: ...*/` comments from `source` and anchors each one to the earliest
+/// statement of the enclosing function that starts on or after the line where the comment ends.
+/// Markers inside strings or other comments are not invariants, nor is a comment outside a function.
+fn collect_invariants(source: &str, mappings: &[DebugMapping]) -> Vec<DebugInvariant> {
+    let (Ok(comments), Some(functions)) = (parse_comments(source), function_line_ranges(source)) else {
+        return Vec::new();
+    };
+    comments
+        .into_iter()
+        .filter(|comment| comment.kind == CommentKind::Block)
+        .filter_map(|comment| {
+            let condition = comment.text.strip_prefix(INVARIANT_MARKER)?.strip_suffix("*/")?.trim().to_string();
+            let span = comment.span;
+            let (_, function_end) = functions.iter().find(|(start, end)| *start <= span.line && span.end_line <= *end)?;
+            let (_, bytecode_offset) = mappings
+                .iter()
+                .filter(|mapping| matches!(mapping.kind, MappingKind::Statement {} | MappingKind::Virtual {}))
+                .filter_map(|mapping| mapping.span.map(|statement| (statement.line, mapping.bytecode_start)))
+                .filter(|(statement_line, _)| (span.end_line..=*function_end).contains(statement_line))
+                .min()?;
+            Some(DebugInvariant { line: span.line, condition, bytecode_offset })
+        })
+        .collect()
+}
+
+/// First and last line of every function definition in `source`.
+fn function_line_ranges(source: &str) -> Option<Vec<(u32, u32)>> {
+    let contract = parse_source_file(source).ok()?.next()?.into_inner().find(|pair| pair.as_rule() == Rule::contract_definition)?;
+    let functions = contract
+        .into_inner()
+        .filter(|pair| pair.as_rule() == Rule::contract_item)
+        .flat_map(|item| item.into_inner())
+        .filter(|pair| pair.as_rule() == Rule::function_definition)
+        .map(|function| {
+            let span = SourceSpan::from_span(function.as_span());
+            (span.line, span.end_line)
+        })
+        .collect();
+    Some(functions)
+}
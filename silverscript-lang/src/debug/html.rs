@@ -0,0 +1,18 @@
+//! Standalone HTML export of an [`ExecutionTrace`]: the viewer assets and the trace JSON bundled into one
+//! file that opens in any browser without a server.
+
+use crate::debug::simulate::ExecutionTrace;
+
+const VIEWER_HTML: &str = include_str!("html/viewer.html");
+const VIEWER_JS: &str = include_str!("html/viewer.js");
+const VIEWER_CSS: &str = include_str!("html/viewer.css");
+
+/// Renders `trace` as a self-contained page. The viewer reads the trace back from the embedded
+/// `<script type="application/json" id="trace-data">` element on load.
+pub fn trace_to_html(trace: &ExecutionTrace) -> Result<String, serde_json::Error> {
+    let json = serde_json::to_string(trace)?;
+    // Contract source ends up in the JSON; a literal `</script>` in it would close the data element early.
+    let json = json.replace("</", "<\\/");
+    // The trace goes in last so placeholder-like text inside it is never substituted.
+    Ok(VIEWER_HTML.replace("{{styles}}", VIEWER_CSS).replace("{{script}}", VIEWER_JS).replace("{{trace}}", &json))
+}
@@ -0,0 +1,89 @@
+(function () {
+  "use strict";
+
+  const trace = JSON.parse(document.getElementById("trace-data").textContent);
+  const steps = trace.steps || [];
+  const sourceSteps = trace.source_steps || [];
+  let current = 0;
+
+  function element(tag, text, className) {
+    const node = document.createElement(tag);
+    if (text !== undefined) node.textContent = text;
+    if (className) node.className = className;
+    return node;
+  }
+
+  function renderHeader() {
+    document.getElementById("title").textContent = trace.meta.function + " — SilverScript trace";
+    const outcome = document.getElementById("outcome");
+    outcome.textContent = trace.error ? "Failed: " + trace.error : "Passed";
+    outcome.className = trace.error ? "failed" : "passed";
+  }
+
+  function renderSource() {
+    const coverage = new Map((trace.line_coverage || []).map((entry) => [entry.line, entry.status]));
+    const list = document.getElementById("source");
+    trace.meta.source.split("\n").forEach((text, index) => {
+      const status = coverage.get(index + 1);
+      list.appendChild(element("li", text || " ", status === "unmapped" ? "" : status));
+    });
+  }
+
+  function renderSteps() {
+    const list = document.getElementById("steps");
+    steps.forEach((step, index) => {
+      const item = element("li", step.byte_offset + "  " + step.opcode, step.execution_index == null ? "skipped" : "");
+      item.addEventListener("click", () => select(index));
+      list.appendChild(item);
+    });
+  }
+
+  function fillStack(id, items) {
+    const list = document.getElementById(id);
+    list.replaceChildren(...(items || []).slice().reverse().map((item) => element("li", item === "" ? "(empty)" : item)));
+  }
+
+  function select(index) {
+    if (steps.length === 0) return;
+    current = Math.max(0, Math.min(index, steps.length - 1));
+    const step = steps[current];
+    const sourceStep = step.source_step == null ? null : sourceSteps[step.source_step];
+
+    document.querySelectorAll("#steps li").forEach((item, position) => {
+      item.classList.toggle("current", position === current);
+      const inStatement = sourceStep && position >= sourceStep.opcode_steps.start && position < sourceStep.opcode_steps.end;
+      item.classList.toggle("in-statement", Boolean(inStatement));
+    });
+    document.querySelectorAll("#source li").forEach((item, position) => {
+      const line = position + 1;
+      item.classList.toggle("current", Boolean(sourceStep && line >= sourceStep.span.line && line <= sourceStep.span.end_line));
+    });
+    document.querySelectorAll("#steps li")[current].scrollIntoView({ block: "nearest" });
+
+    fillStack("stack", step.stack);
+    fillStack("alt-stack", step.alt_stack);
+    document.getElementById("position").textContent = "step " + (current + 1) + " of " + steps.length;
+    document.getElementById("over").disabled = !sourceStep;
+  }
+
+  function stepOver() {
+    const step = steps[current];
+    const sourceStep = step && step.source_step != null ? sourceSteps[step.source_step] : null;
+    if (!sourceStep) return;
+    const target = sourceStep.step_over == null ? null : sourceSteps[sourceStep.step_over];
+    select(target ? target.opcode_steps.start : steps.length - 1);
+  }
+
+  document.getElementById("prev").addEventListener("click", () => select(current - 1));
+  document.getElementById("next").addEventListener("click", () => select(current + 1));
+  document.getElementById("over").addEventListener("click", stepOver);
+  document.addEventListener("keydown", (event) => {
+    if (event.key === "ArrowLeft") select(current - 1);
+    if (event.key === "ArrowRight") select(current + 1);
+  });
+
+  renderHeader();
+  renderSource();
+  renderSteps();
+  select(0);
+})();
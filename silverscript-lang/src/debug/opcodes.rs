@@ -0,0 +1,240 @@
+use kaspa_txscript::opcodes::codes::*;
+use serde::{Deserialize, Serialize};
+
+use crate::compiler::mass::instructions;
+
+/// Coarse opcode grouping for tooling that colors or filters opcodes without matching on mnemonics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpcodeCategory {
+    /// Data and small-number pushes.
+    Push,
+    /// Conditionals, `OpVerify`, `OpReturn` and no-ops.
+    Flow,
+    /// Stack shuffling, splicing, bitwise ops and byte equality.
+    Stack,
+    /// Numeric ops, comparisons and number/bytes conversion.
+    Arithmetic,
+    /// Hashing and signature checks.
+    Crypto,
+    /// Transaction, lock-time and covenant introspection.
+    Introspection,
+    /// Reserved or unassigned opcodes.
+    Other,
+}
+
+/// Category of the opcode with byte value `opcode`.
+pub fn opcode_category(opcode: u8) -> OpcodeCategory {
+    match opcode {
+        Op0 | OpData1..=OpData75 | OpPushData1 | OpPushData2 | OpPushData4 => OpcodeCategory::Push,
+        // Op1Negate, then Op1..=Op16; 0x50 is OpReserved.
+        0x4f | 0x51..=0x60 => OpcodeCategory::Push,
+        // OpNop through OpReturn.
+        0x61..=0x6a => OpcodeCategory::Flow,
+        OpNum2Bin | OpBin2Num => OpcodeCategory::Arithmetic,
+        // Alt-stack and stack manipulation, splice ops, bitwise ops, OpEqual/OpEqualVerify.
+        0x6b..=0x88 => OpcodeCategory::Stack,
+        // Op1Add through OpWithin.
+        0x8b..=0xa5 => OpcodeCategory::Arithmetic,
+        // Hashes and OpCheckSig/OpCheckMultiSig variants.
+        0xa6..=0xaf => OpcodeCategory::Crypto,
+        OpCheckLockTimeVerify
+        | OpCheckSequenceVerify
+        | OpTxVersion
+        | OpTxInputCount
+        | OpTxOutputCount
+        | OpTxLockTime
+        | OpTxSubnetId
+        | OpTxGas
+        | OpTxPayloadLen
+        | OpTxPayloadSubstr
+        | OpTxInputIndex
+        | OpOutpointTxId
+        | OpOutpointIndex
+        | OpTxInputScriptSigLen
+        | OpTxInputScriptSigSubstr
+        | OpTxInputSeq
+        | OpTxInputAmount
+        | OpTxInputSpk
+        | OpTxInputSpkLen
+        | OpTxInputSpkSubstr
+        | OpTxInputIsCoinbase
+        | OpTxOutputAmount
+        | OpTxOutputSpk
+        | OpTxOutputSpkLen
+        | OpTxOutputSpkSubstr
+        | OpInputCovenantId
+        | OpAuthOutputCount
+        | OpAuthOutputIdx
+        | OpCovInputCount
+        | OpCovInputIdx
+        | OpCovOutCount
+        | OpCovOutputIdx
+        | OpChainblockSeqCommit => OpcodeCategory::Introspection,
+        _ => OpcodeCategory::Other,
+    }
+}
+
+/// Opcodes the engine only accepts with `covenants_enabled`.
+pub fn is_covenant_opcode(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        OpInputCovenantId | OpAuthOutputCount | OpAuthOutputIdx | OpCovInputCount | OpCovInputIdx | OpCovOutCount | OpCovOutputIdx
+    )
+}
+
+/// Non-push opcodes by mnemonic, for `opcode_by_name` and `opcode_name`.
+const NAMED_OPCODES: &[(&str, u8)] = &[
+    ("OpNop", OpNop),
+    ("OpIf", OpIf),
+    ("OpElse", OpElse),
+    ("OpEndIf", OpEndIf),
+    ("OpVerify", OpVerify),
+    ("OpReturn", OpReturn),
+    ("OpToAltStack", OpToAltStack),
+    ("Op2Drop", Op2Drop),
+    ("Op2Dup", Op2Dup),
+    ("OpDepth", OpDepth),
+    ("OpDrop", OpDrop),
+    ("OpDup", OpDup),
+    ("OpNip", OpNip),
+    ("OpOver", OpOver),
+    ("OpPick", OpPick),
+    ("OpRoll", OpRoll),
+    ("OpSwap", OpSwap),
+    ("OpTuck", OpTuck),
+    ("OpCat", OpCat),
+    ("OpSubstr", OpSubstr),
+    ("OpSize", OpSize),
+    ("OpAnd", OpAnd),
+    ("OpOr", OpOr),
+    ("OpXor", OpXor),
+    ("OpEqual", OpEqual),
+    ("OpEqualVerify", OpEqualVerify),
+    ("Op1Add", Op1Add),
+    ("OpNegate", OpNegate),
+    ("OpAbs", OpAbs),
+    ("OpNot", OpNot),
+    ("OpAdd", OpAdd),
+    ("OpSub", OpSub),
+    ("OpMul", OpMul),
+    ("OpDiv", OpDiv),
+    ("OpMod", OpMod),
+    ("OpBoolAnd", OpBoolAnd),
+    ("OpBoolOr", OpBoolOr),
+    ("OpNumEqual", OpNumEqual),
+    ("OpNumEqualVerify", OpNumEqualVerify),
+    ("OpNumNotEqual", OpNumNotEqual),
+    ("OpLessThan", OpLessThan),
+    ("OpGreaterThan", OpGreaterThan),
+    ("OpLessThanOrEqual", OpLessThanOrEqual),
+    ("OpGreaterThanOrEqual", OpGreaterThanOrEqual),
+    ("OpWithin", OpWithin),
+    ("OpSHA256", OpSHA256),
+    ("OpBlake2b", OpBlake2b),
+    ("OpCheckSig", OpCheckSig),
+    ("OpCheckSigVerify", OpCheckSigVerify),
+    ("OpCheckSigECDSA", OpCheckSigECDSA),
+    ("OpCheckMultiSig", OpCheckMultiSig),
+    ("OpCheckMultiSigVerify", OpCheckMultiSigVerify),
+    ("OpCheckMultiSigECDSA", OpCheckMultiSigECDSA),
+    ("OpCheckLockTimeVerify", OpCheckLockTimeVerify),
+    ("OpCheckSequenceVerify", OpCheckSequenceVerify),
+    ("OpNum2Bin", OpNum2Bin),
+    ("OpBin2Num", OpBin2Num),
+    ("OpTxVersion", OpTxVersion),
+    ("OpTxInputCount", OpTxInputCount),
+    ("OpTxOutputCount", OpTxOutputCount),
+    ("OpTxLockTime", OpTxLockTime),
+    ("OpTxSubnetId", OpTxSubnetId),
+    ("OpTxGas", OpTxGas),
+    ("OpTxPayloadLen", OpTxPayloadLen),
+    ("OpTxPayloadSubstr", OpTxPayloadSubstr),
+    ("OpTxInputIndex", OpTxInputIndex),
+    ("OpOutpointTxId", OpOutpointTxId),
+    ("OpOutpointIndex", OpOutpointIndex),
+    ("OpTxInputScriptSigLen", OpTxInputScriptSigLen),
+    ("OpTxInputScriptSigSubstr", OpTxInputScriptSigSubstr),
+    ("OpTxInputSeq", OpTxInputSeq),
+    ("OpTxInputAmount", OpTxInputAmount),
+    ("OpTxInputSpk", OpTxInputSpk),
+    ("OpTxInputSpkLen", OpTxInputSpkLen),
+    ("OpTxInputSpkSubstr", OpTxInputSpkSubstr),
+    ("OpTxInputIsCoinbase", OpTxInputIsCoinbase),
+    ("OpTxOutputAmount", OpTxOutputAmount),
+    ("OpTxOutputSpk", OpTxOutputSpk),
+    ("OpTxOutputSpkLen", OpTxOutputSpkLen),
+    ("OpTxOutputSpkSubstr", OpTxOutputSpkSubstr),
+    ("OpInputCovenantId", OpInputCovenantId),
+    ("OpAuthOutputCount", OpAuthOutputCount),
+    ("OpAuthOutputIdx", OpAuthOutputIdx),
+    ("OpCovInputCount", OpCovInputCount),
+    ("OpCovInputIdx", OpCovInputIdx),
+    ("OpCovOutCount", OpCovOutCount),
+    ("OpCovOutputIdx", OpCovOutputIdx),
+    ("OpChainblockSeqCommit", OpChainblockSeqCommit),
+];
+
+/// Byte value of the non-push opcode called `name`. Case and underscores are ignored and the `Op`
+/// prefix is optional, so `OP_CHECKMULTISIG`, `OpCheckMultiSig` and `checkmultisig` all match.
+pub fn opcode_by_name(name: &str) -> Option<u8> {
+    let wanted = normalize_opcode_name(name);
+    NAMED_OPCODES.iter().find(|(known, _)| normalize_opcode_name(known) == wanted).map(|(_, opcode)| *opcode)
+}
+
+/// Mnemonic of the opcode with byte value `opcode`, as `opcode_by_name` accepts it.
+pub fn opcode_name(opcode: u8) -> Option<&'static str> {
+    NAMED_OPCODES.iter().find(|(_, known)| *known == opcode).map(|(name, _)| *name)
+}
+
+/// Space-separated mnemonics of `script`: `Op0`, `Op1Negate` and `Op1`..`Op16` for small-number
+/// pushes, `0x`-prefixed hex for data pushes, `opcode_name` for the rest and `<0xNN>` for unassigned bytes.
+pub fn disassemble(script: &[u8]) -> String {
+    let mut offset = 0usize;
+    let tokens: Vec<String> = instructions(script)
+        .map(|(opcode, size)| {
+            let instruction = &script[offset..(offset + size).min(script.len())];
+            offset += size;
+            let header = match opcode {
+                OpPushData1 => 2,
+                OpPushData2 => 3,
+                OpPushData4 => 5,
+                _ => 1,
+            };
+            match opcode {
+                Op0 => "Op0".to_string(),
+                0x4f => "Op1Negate".to_string(),
+                0x51..=0x60 => format!("Op{}", opcode - 0x50),
+                OpData1..=OpData75 | OpPushData1 | OpPushData2 | OpPushData4 => {
+                    format!("0x{}", faster_hex::hex_string(instruction.get(header..).unwrap_or_default()))
+                }
+                _ => opcode_name(opcode).map(str::to_string).unwrap_or_else(|| format!("<0x{opcode:02x}>")),
+            }
+        })
+        .collect();
+    tokens.join(" ")
+}
+
+/// A hand-written disassembly token in the form `disassemble` prints it, or `None` if it names no
+/// opcode. Names are matched as in `opcode_by_name`; `OP_FALSE`/`OP_TRUE` and bare numbers up to 16
+/// are the small-number pushes, and hex is lowercased.
+pub fn canonical_disassembly_token(token: &str) -> Option<String> {
+    if let Some(hex) = token.strip_prefix("0x") {
+        let valid = hex.len() % 2 == 0 && hex.bytes().all(|byte| byte.is_ascii_hexdigit());
+        return valid.then(|| format!("0x{}", hex.to_ascii_lowercase()));
+    }
+    match normalize_opcode_name(token).as_str() {
+        "false" | "0" => Some("Op0".to_string()),
+        "true" => Some("Op1".to_string()),
+        "1negate" => Some("Op1Negate".to_string()),
+        name => match name.parse::<u8>() {
+            Ok(number @ 1..=16) => Some(format!("Op{number}")),
+            _ => opcode_by_name(token).and_then(opcode_name).map(str::to_string),
+        },
+    }
+}
+
+fn normalize_opcode_name(name: &str) -> String {
+    let name = name.replace('_', "").to_ascii_lowercase();
+    name.strip_prefix("op").map(str::to_string).unwrap_or(name)
+}
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use kaspa_consensus_core::hashing::sighash::SigHashReusedValuesUnsync;
 use kaspa_consensus_core::tx::PopulatedTransaction;
@@ -7,9 +7,15 @@ use kaspa_txscript::script_builder::ScriptBuilder;
 use kaspa_txscript::{DynOpcodeImplementation, EngineCtx, EngineFlags, TxScriptEngine, parse_script};
 use serde::{Deserialize, Serialize};
 
-use crate::ast::{Expr, SourceSpan};
-use crate::compiler::compile_debug_expr;
-use crate::debug::{DebugFunctionRange, DebugInfo, DebugMapping, DebugParamMapping, DebugVariableUpdate, MappingKind};
+use crate::ast::visit::{Identifiers, Visitor, walk_expr};
+use crate::ast::{Expr, SourceSpan, format_decimal, parse_expression_ast};
+use crate::compiler::{compile_debug_expr, compile_watch_expr};
+use crate::debug::opcodes::{OpcodeCategory, opcode_category};
+use crate::debug::simulate::final_stack_status;
+use crate::debug::{
+    DebugFunctionRange, DebugInfo, DebugInvariant, DebugLogArg, DebugLogPoint, DebugMapping, DebugParamMapping, DebugVariableUpdate,
+    MappingKind, guard_engine,
+};
 
 fn encode_hex(bytes: &[u8]) -> String {
     faster_hex::hex_string(bytes)
@@ -55,6 +61,17 @@ pub struct Variable {
     pub value: DebugValue,
     pub is_constant: bool,
     pub origin: VariableOrigin,
+    /// `@scale` the variable was declared with; `format_variable` renders its value as a decimal.
+    pub scale: Option<u32>,
+}
+
+/// Result of `DebugSession::eval_expression`, typed so `format_value` can render it.
+#[derive(Debug, Clone)]
+pub struct WatchValue {
+    pub type_name: String,
+    pub value: DebugValue,
+    /// `@scale` of a watched variable; `format_watch` renders its value as a decimal.
+    pub scale: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
@@ -72,9 +89,47 @@ pub struct SourceContext {
 #[derive(Debug, Clone)]
 pub struct SessionState {
     pub pc: usize,
+    /// Position of the last opcode in true execution order, or `None` if it was skipped inside an untaken branch.
+    pub execution_index: Option<usize>,
     pub opcode: Option<String>,
     pub mapping: Option<DebugMapping>,
     pub stack: Vec<String>,
+    /// Invariants that failed at the current position.
+    pub invariant_violations: Vec<InvariantViolation>,
+}
+
+/// An `@invariant` annotation that did not hold when execution reached its statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantViolation {
+    pub line: u32,
+    pub condition: String,
+    pub pc: usize,
+    pub reason: String,
+}
+
+/// Values printed by a `log(...)` statement when execution reached it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub line: u32,
+    pub pc: usize,
+    pub values: Vec<String>,
+}
+
+/// A `warnIf(condition, message)` whose condition was true when execution reached it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeWarning {
+    pub line: u32,
+    pub pc: usize,
+    pub message: String,
+}
+
+/// Where `run_to_failure` stopped: the failing opcode, or the end of the script when the final stack check failed.
+#[derive(Debug, Clone)]
+pub struct ScriptFailure {
+    pub state: SessionState,
+    /// Source text of the statement that failed, e.g. `require(a > b);`.
+    pub statement: Option<String>,
+    pub message: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +142,9 @@ pub struct StackSnapshot {
 pub struct OpcodeMeta {
     pub index: usize,
     pub byte_offset: usize,
+    /// Opcode byte value, e.g. `0x93` for `OpAdd`.
+    pub opcode: u8,
+    pub category: OpcodeCategory,
     pub display: String,
     pub mapping: Option<DebugMapping>,
 }
@@ -104,6 +162,42 @@ pub struct DebugSession<'a> {
     uses_sequence_order: bool,
     source_lines: Vec<String>,
     breakpoints: HashSet<u32>,
+    /// Predicates of conditional breakpoints, keyed by line; lines missing here always stop.
+    breakpoint_conditions: HashMap<u32, String>,
+    invariant_violations: Vec<InvariantViolation>,
+    logs: Vec<LogEntry>,
+    warnings: Vec<RuntimeWarning>,
+    executed_opcodes: usize,
+    last_execution_index: Option<usize>,
+    journal: Option<Journal>,
+    /// Set once an opcode failed or the engine panicked; every later step reports it again, since the
+    /// failing opcode has been consumed.
+    engine_failure: Option<String>,
+}
+
+/// Opt-in history for `DebugSession::step_back`: the state before each of the last `depth` executed
+/// opcodes. The engine cannot be rewound, so while `cursor` points into `entries` the session shows
+/// that recorded state, and stepping forward walks the journal until it reaches the live engine again.
+struct Journal {
+    depth: usize,
+    entries: VecDeque<StepSnapshot>,
+    /// Entry currently shown; `None` at the live position.
+    cursor: Option<usize>,
+    /// The live position, set aside while rewound.
+    head: Option<StepSnapshot>,
+}
+
+/// What a step changes: the position, the stacks, and the current source step, which together
+/// determine the visible variable bindings.
+#[derive(Clone)]
+struct StepSnapshot {
+    pc: usize,
+    current_step_index: Option<usize>,
+    last_execution_index: Option<usize>,
+    executed_opcodes: usize,
+    executing: bool,
+    dstack: Vec<Vec<u8>>,
+    astack: Vec<Vec<u8>>,
 }
 
 struct ShadowParamValue {
@@ -176,7 +270,7 @@ impl<'a> DebugSession<'a> {
             source_mappings.sort_by_key(|mapping| (mapping.bytecode_start, mapping.bytecode_end));
         }
 
-        Ok(Self {
+        let mut session = Self {
             engine,
             opcodes,
             op_displays,
@@ -189,21 +283,135 @@ impl<'a> DebugSession<'a> {
             uses_sequence_order,
             source_lines,
             breakpoints: HashSet::new(),
-        })
+            breakpoint_conditions: HashMap::new(),
+            invariant_violations: Vec::new(),
+            logs: Vec::new(),
+            warnings: Vec::new(),
+            executed_opcodes: 0,
+            last_execution_index: None,
+            journal: None,
+            engine_failure: None,
+        };
+        session.check_invariants();
+        session.check_log_points();
+        Ok(session)
     }
 
-    /// Executes a single opcode and advances the program counter.
+    /// Executes a single opcode and advances the program counter. After `step_back` it replays the
+    /// journal instead, until the session is back at the live position.
     pub fn step_opcode(&mut self) -> Result<Option<SessionState>, kaspa_txscript_errors::TxScriptError> {
+        if let Some(journal) = self.journal.as_mut() {
+            if let Some(cursor) = journal.cursor {
+                let next = if cursor + 1 < journal.entries.len() {
+                    journal.cursor = Some(cursor + 1);
+                    journal.entries[cursor + 1].clone()
+                } else {
+                    journal.cursor = None;
+                    journal.head.take().expect("rewound journal keeps the live position")
+                };
+                self.restore_snapshot(next);
+                return Ok(Some(self.state()));
+            }
+        }
+        if let Some(message) = &self.engine_failure {
+            return Err(kaspa_txscript_errors::TxScriptError::InvalidState(message.clone()));
+        }
         if self.pc >= self.opcodes.len() {
             return Ok(None);
         }
 
+        let snapshot = self.journal.is_some().then(|| self.capture_snapshot());
         let opcode = self.opcodes[self.pc].take().expect("opcode already executed");
-        self.engine.execute_opcode(opcode)?;
+        let executing = self.engine.is_executing();
+        match guard_engine(|| self.engine.execute_opcode(opcode)) {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => {
+                self.engine_failure = Some(err.to_string());
+                return Err(err);
+            }
+            Err(message) => {
+                self.engine_failure = Some(message.clone());
+                return Err(kaspa_txscript_errors::TxScriptError::InvalidState(message));
+            }
+        }
+        if let (Some(journal), Some(snapshot)) = (self.journal.as_mut(), snapshot) {
+            if journal.entries.len() == journal.depth {
+                journal.entries.pop_front();
+            }
+            journal.entries.push_back(snapshot);
+        }
         self.pc += 1;
+        self.last_execution_index = executing.then_some(self.executed_opcodes);
+        if executing {
+            self.executed_opcodes += 1;
+        }
+        self.check_invariants();
+        self.check_log_points();
         Ok(Some(self.state()))
     }
 
+    /// Starts recording the state before each executed opcode so `step_back` can return to it. Only
+    /// the last `depth` states are kept; each holds a copy of both stacks, so a full journal roughly
+    /// doubles the memory of a recorded trace.
+    pub fn enable_journal(&mut self, depth: usize) {
+        self.journal = Some(Journal { depth: depth.max(1), entries: VecDeque::new(), cursor: None, head: None });
+    }
+
+    /// Returns to the state before the last executed opcode, or `None` when journaling is off or the
+    /// journal holds nothing older. Stepping forward afterwards replays the journal.
+    pub fn step_back(&mut self) -> Option<SessionState> {
+        let live = self.journal.as_ref()?.cursor.is_none().then(|| self.capture_snapshot());
+        let journal = self.journal.as_mut()?;
+        let index = match journal.cursor {
+            Some(cursor) => cursor.checked_sub(1)?,
+            None => journal.entries.len().checked_sub(1)?,
+        };
+        if live.is_some() {
+            journal.head = live;
+        }
+        journal.cursor = Some(index);
+        let snapshot = journal.entries[index].clone();
+        self.restore_snapshot(snapshot);
+        Some(self.state())
+    }
+
+    fn capture_snapshot(&self) -> StepSnapshot {
+        let (dstack, astack) = self.with_stacks(|dstack, astack| (dstack.to_vec(), astack.to_vec()));
+        StepSnapshot {
+            pc: self.pc,
+            current_step_index: self.current_step_index,
+            last_execution_index: self.last_execution_index,
+            executed_opcodes: self.executed_opcodes,
+            executing: self.is_executing(),
+            dstack,
+            astack,
+        }
+    }
+
+    fn restore_snapshot(&mut self, snapshot: StepSnapshot) {
+        self.pc = snapshot.pc;
+        self.current_step_index = snapshot.current_step_index;
+        self.last_execution_index = snapshot.last_execution_index;
+        self.executed_opcodes = snapshot.executed_opcodes;
+    }
+
+    /// The journal entry shown instead of the engine after `step_back`.
+    fn rewound_snapshot(&self) -> Option<&StepSnapshot> {
+        let journal = self.journal.as_ref()?;
+        journal.entries.get(journal.cursor?)
+    }
+
+    /// Hands `f` the data and alt stacks of the position the session shows.
+    fn with_stacks<R>(&self, f: impl FnOnce(&[Vec<u8>], &[Vec<u8>]) -> R) -> R {
+        match self.rewound_snapshot() {
+            Some(snapshot) => f(&snapshot.dstack, &snapshot.astack),
+            None => {
+                let stacks = self.engine.stacks();
+                f(&stacks.dstack, &stacks.astack)
+            }
+        }
+    }
+
     /// Step into: advance to next source step regardless of call depth.
     pub fn step_into(&mut self) -> Result<Option<SessionState>, kaspa_txscript_errors::TxScriptError> {
         self.step_with_depth_predicate(|_, _| true)
@@ -263,7 +471,7 @@ impl<'a> DebugSession<'a> {
                 return Ok(false);
             }
 
-            if mapping_matches_offset(&target, offset) && self.engine.is_executing() {
+            if mapping_matches_offset(&target, offset) && self.is_executing() {
                 return Ok(true);
             }
 
@@ -285,7 +493,7 @@ impl<'a> DebugSession<'a> {
                 return Ok(());
             }
             let offset = self.current_byte_offset();
-            if self.engine.is_executing() {
+            if self.is_executing() {
                 let found = self
                     .source_mappings
                     .iter()
@@ -320,24 +528,90 @@ impl<'a> DebugSession<'a> {
         }
     }
 
+    /// Runs until an opcode fails or the script ends. Returns the failure, or `None` if the script ended
+    /// with a single true value on the stack. The session cannot step further after a failing opcode.
+    pub fn run_to_failure(&mut self) -> Option<ScriptFailure> {
+        loop {
+            self.enter_statement_at_current_offset();
+            match self.step_opcode() {
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(err) => return Some(self.failure(err.to_string())),
+            }
+        }
+        let message = final_stack_status(&self.engine.stacks().dstack).rejection()?;
+        Some(self.failure(message))
+    }
+
+    /// Makes the innermost statement covering the next opcode the current step, so the location of a
+    /// failure points at the statement whose opcode failed rather than the last one stepped to.
+    fn enter_statement_at_current_offset(&mut self) {
+        if !self.is_executing() {
+            return;
+        }
+        let offset = self.current_byte_offset();
+        let innermost = self
+            .source_mappings
+            .iter()
+            .enumerate()
+            .filter(|(_, mapping)| self.is_steppable_mapping(mapping) && mapping_matches_offset(mapping, offset))
+            .min_by_key(|(_, mapping)| mapping.bytecode_end.saturating_sub(mapping.bytecode_start));
+        if let Some((index, _)) = innermost {
+            self.current_step_index = Some(index);
+        }
+    }
+
+    fn failure(&self, message: String) -> ScriptFailure {
+        let state = self.state();
+        let statement = state
+            .mapping
+            .as_ref()
+            .and_then(|mapping| mapping.span)
+            .and_then(|span| self.source_lines.get(span.line.checked_sub(1)? as usize))
+            .map(|line| line.trim().to_string());
+        ScriptFailure { state, statement, message }
+    }
+
     /// Returns the current execution state snapshot.
     pub fn state(&self) -> SessionState {
         let opcode = self.pc.checked_sub(1).and_then(|index| self.op_displays.get(index)).cloned();
-        SessionState { pc: self.pc, opcode, mapping: self.current_location(), stack: self.stack() }
+        let invariant_violations = self.invariant_violations.iter().filter(|violation| violation.pc == self.pc).cloned().collect();
+        SessionState {
+            pc: self.pc,
+            execution_index: self.last_execution_index,
+            opcode,
+            mapping: self.current_location(),
+            stack: self.stack(),
+            invariant_violations,
+        }
+    }
+
+    /// Returns every invariant violation observed so far in this session.
+    pub fn invariant_violations(&self) -> &[InvariantViolation] {
+        &self.invariant_violations
+    }
+
+    /// Returns every log entry produced so far in this session, in execution order.
+    pub fn logs(&self) -> &[LogEntry] {
+        &self.logs
+    }
+
+    /// Returns every `warnIf` that triggered so far in this session, in execution order.
+    pub fn warnings(&self) -> &[RuntimeWarning] {
+        &self.warnings
     }
 
     /// Returns true if the script engine is still running.
     pub fn is_executing(&self) -> bool {
-        self.engine.is_executing()
+        self.rewound_snapshot().map_or_else(|| self.engine.is_executing(), |snapshot| snapshot.executing)
     }
 
     /// Returns the current data and alt stack contents.
     pub fn stacks_snapshot(&self) -> StackSnapshot {
-        let stacks = self.engine.stacks();
-        StackSnapshot {
-            dstack: stacks.dstack.iter().map(|bytes| encode_hex(bytes)).collect(),
-            astack: stacks.astack.iter().map(|bytes| encode_hex(bytes)).collect(),
-        }
+        self.with_stacks(|dstack, astack| StackSnapshot {
+            dstack: dstack.iter().map(|bytes| encode_hex(bytes)).collect(),
+            astack: astack.iter().map(|bytes| encode_hex(bytes)).collect(),
+        })
     }
 
     /// Returns metadata for all opcodes (executed/pending status, byte offset).
@@ -345,9 +619,13 @@ impl<'a> DebugSession<'a> {
         (0..self.op_displays.len())
             .map(|index| {
                 let byte_offset = self.opcode_offsets.get(index).copied().unwrap_or(self.script_len);
+                let opcode = self.opcodes.get(index).and_then(|op| op.as_ref()).and_then(|op| op.serialize().first().copied());
+                let opcode = opcode.unwrap_or_default();
                 OpcodeMeta {
                     index,
                     byte_offset,
+                    opcode,
+                    category: opcode_category(opcode),
                     display: self.op_displays.get(index).cloned().unwrap_or_default(),
                     mapping: self.mapping_for_offset(byte_offset).cloned(),
                 }
@@ -395,10 +673,38 @@ impl<'a> DebugSession<'a> {
             .any(|mapping| mapping.span.is_some_and(|span| line >= span.line && line <= span.end_line));
         if valid {
             self.breakpoints.insert(line);
+            self.breakpoint_conditions.remove(&line);
         }
         valid
     }
 
+    /// Sets a breakpoint at `line` that only stops when `condition` evaluates to `true` in the frame
+    /// that reaches it (see `eval_expression`). The condition is parsed and its identifiers checked
+    /// here, so a typo fails now instead of never stopping.
+    pub fn set_conditional_breakpoint(&mut self, line: u32, condition: String) -> Result<(), String> {
+        let expr = self.parse_watch_expression(&condition)?;
+        let mut identifiers = Identifiers::default();
+        walk_expr(&expr, &mut identifiers);
+        let known = |name: &String| {
+            self.debug_info.params.iter().any(|param| param.name == *name)
+                || self.debug_info.variable_updates.iter().any(|update| update.name == *name)
+                || self.debug_info.constants.iter().any(|constant| constant.name == *name)
+        };
+        if let Some(name) = identifiers.0.iter().find(|name| !known(name)) {
+            return Err(format!("unknown variable '{name}' in breakpoint condition"));
+        }
+        if !self.add_breakpoint(line) {
+            return Err(format!("no statement at line {line}"));
+        }
+        self.breakpoint_conditions.insert(line, condition);
+        Ok(())
+    }
+
+    /// Condition of the breakpoint at `line`, if it is a conditional one.
+    pub fn breakpoint_condition(&self, line: u32) -> Option<&str> {
+        self.breakpoint_conditions.get(&line).map(String::as_str)
+    }
+
     /// Returns all currently set breakpoint line numbers.
     pub fn breakpoints(&self) -> Vec<u32> {
         let mut lines = self.breakpoints.iter().copied().collect::<Vec<_>>();
@@ -409,6 +715,7 @@ impl<'a> DebugSession<'a> {
     /// Removes the breakpoint at the given line number.
     pub fn clear_breakpoint(&mut self, line: u32) {
         self.breakpoints.remove(&line);
+        self.breakpoint_conditions.remove(&line);
     }
 
     // --- Variable inspection ---
@@ -442,6 +749,7 @@ impl<'a> DebugSession<'a> {
                 value,
                 is_constant: false,
                 origin: VariableOrigin::Local,
+                scale: None,
             });
             seen_names.insert(name.clone());
         }
@@ -457,6 +765,7 @@ impl<'a> DebugSession<'a> {
                 value,
                 is_constant: false,
                 origin: VariableOrigin::Param,
+                scale: param.scale,
             });
             seen_names.insert(param.name.clone());
         }
@@ -472,6 +781,7 @@ impl<'a> DebugSession<'a> {
                 value,
                 is_constant: true,
                 origin: VariableOrigin::Constant,
+                scale: constant.scale,
             });
             seen_names.insert(constant.name.clone());
         }
@@ -497,6 +807,7 @@ impl<'a> DebugSession<'a> {
                 value,
                 is_constant: false,
                 origin: VariableOrigin::Local,
+                scale: None,
             });
         }
 
@@ -508,6 +819,7 @@ impl<'a> DebugSession<'a> {
                 value,
                 is_constant: false,
                 origin: VariableOrigin::Param,
+                scale: param.scale,
             });
         }
 
@@ -520,20 +832,90 @@ impl<'a> DebugSession<'a> {
                 value,
                 is_constant: true,
                 origin: VariableOrigin::Constant,
+                scale: constant.scale,
             });
         }
 
         Err(format!("unknown variable '{name}'"))
     }
 
+    /// Evaluates a watch expression such as `y > 0` or `seed - fee` against the variables visible at
+    /// the current step. It runs on a shadow VM, so the session's engine is left untouched; calls to
+    /// contract functions and `new` are rejected because they would inline code into the watch.
+    pub fn eval_expression(&self, source: &str) -> Result<WatchValue, String> {
+        let expr = self.parse_watch_expression(source)?;
+        if let Expr::Identifier(name) = &expr {
+            let var = self.variable_by_name(name)?;
+            return Ok(WatchValue { type_name: var.type_name, value: var.value, scale: var.scale });
+        }
+
+        let function_name = self.current_function_name().ok_or_else(|| "No function context available".to_string())?;
+        let offset = self.current_byte_offset();
+        let (sequence, frame_id) = self.current_step_sequence_and_frame();
+        let include_current_sequence = self.include_current_sequence_updates(sequence, frame_id);
+        let var_updates = self.current_variable_updates(function_name, offset, sequence, frame_id, include_current_sequence);
+
+        // Same precedence as `variable_by_name`: locals, then params, then constants.
+        let mut env = HashMap::new();
+        let mut types = HashMap::new();
+        for constant in &self.debug_info.constants {
+            env.insert(constant.name.clone(), constant.value.clone());
+            types.insert(constant.name.clone(), constant.type_name.clone());
+        }
+        let params = self.shadow_param_values(function_name)?;
+        let mut param_indexes = HashMap::new();
+        for param in &params {
+            env.remove(&param.name);
+            param_indexes.insert(param.name.clone(), param.stack_index);
+            types.insert(param.name.clone(), param.type_name.clone());
+        }
+        for (name, update) in var_updates {
+            env.insert(name.clone(), update.expr.clone());
+            types.insert(name, update.type_name.clone());
+        }
+
+        let (bytecode, type_name) = compile_watch_expr(&expr, &env, &param_indexes, &types)
+            .map_err(|err| format!("failed to compile watch expression: {err}"))?;
+        let script = self.build_shadow_script(&params, &bytecode)?;
+        let value = decode_value_by_type(&type_name, self.execute_shadow_script(&script)?)?;
+        Ok(WatchValue { type_name, value, scale: None })
+    }
+
+    fn parse_watch_expression(&self, source: &str) -> Result<Expr, String> {
+        let expr = parse_expression_ast(source).map_err(|err| err.to_string())?;
+        let mut calls = ContractCalls { functions: &self.debug_info.functions, found: None };
+        walk_expr(&expr, &mut calls);
+        match calls.found {
+            Some(name) => Err(format!("watch expressions cannot call '{name}'")),
+            None => Ok(expr),
+        }
+    }
+
     // --- DebugValue formatting ---
+    /// Formats a variable's value for display, as a decimal when it was declared with `@scale`.
+    pub fn format_variable(&self, var: &Variable) -> String {
+        self.format_scaled(&var.type_name, var.scale, &var.value)
+    }
+
+    /// Formats the result of `eval_expression` for display, as `format_variable` does.
+    pub fn format_watch(&self, watch: &WatchValue) -> String {
+        self.format_scaled(&watch.type_name, watch.scale, &watch.value)
+    }
+
+    fn format_scaled(&self, type_name: &str, scale: Option<u32>, value: &DebugValue) -> String {
+        match (scale, value) {
+            (Some(scale), DebugValue::Int(number)) => format_decimal(*number, scale),
+            _ => self.format_value(type_name, value),
+        }
+    }
+
     /// Formats a debug value for display based on its type.
     pub fn format_value(&self, type_name: &str, value: &DebugValue) -> String {
         let element_type = type_name.strip_suffix("[]");
         match (type_name, value) {
             ("int", DebugValue::Int(number)) => number.to_string(),
             ("bool", DebugValue::Bool(value)) => value.to_string(),
-            ("string", DebugValue::String(value)) => value.clone(),
+            ("string", DebugValue::String(value)) => escape_control_chars(value),
             (_, DebugValue::Unknown(reason)) => {
                 if reason.trim().is_empty() {
                     "<unavailable>".to_string()
@@ -637,8 +1019,10 @@ impl<'a> DebugSession<'a> {
             if self.uses_sequence_order {
                 // For statement stops expose pre-state (< sequence). For virtual steps (no bytecode),
                 // same-sequence updates are treated as already materialized debugger state.
+                // Updates made inside an `if` branch go out of scope once the branch has ended.
                 update.frame_id == frame_id
                     && (update.sequence < sequence || (include_current_sequence && update.sequence == sequence))
+                    && update.scope_end.is_none_or(|end| sequence <= end)
             } else {
                 // Older debug info without sequence metadata falls back to opcode-offset snapshots.
                 update.bytecode_offset <= offset
@@ -723,14 +1107,81 @@ impl<'a> DebugSession<'a> {
         None
     }
 
+    /// A conditional breakpoint only hits when its condition is `true`; one that cannot be evaluated
+    /// yet, e.g. because a variable it reads is not assigned, does not stop.
     fn mapping_hits_breakpoint(&self, mapping: &DebugMapping) -> bool {
-        mapping.span.map(|span| (span.line..=span.end_line).any(|line| self.breakpoints.contains(&line))).unwrap_or(false)
+        let Some(span) = mapping.span else {
+            return false;
+        };
+        let hits = |line: u32| match self.breakpoint_conditions.get(&line) {
+            None => true,
+            Some(condition) => matches!(self.eval_expression(condition), Ok(WatchValue { value: DebugValue::Bool(true), .. })),
+        };
+        (span.line..=span.end_line).filter(|line| self.breakpoints.contains(line)).any(hits)
+    }
+
+    /// Evaluates invariants anchored at the opcode about to execute. Skipped branches are ignored.
+    fn check_invariants(&mut self) {
+        if !self.engine.is_executing() {
+            return;
+        }
+        let offset = self.current_byte_offset();
+        let depth = self.engine.stacks().dstack.len();
+        let violations: Vec<InvariantViolation> = self
+            .debug_info
+            .invariants
+            .iter()
+            .filter(|invariant| invariant.bytecode_offset == offset)
+            .filter_map(|invariant| {
+                let reason = match evaluate_invariant(invariant, depth) {
+                    Ok(true) => return None,
+                    Ok(false) => format!("stack depth is {depth}"),
+                    Err(err) => err,
+                };
+                Some(InvariantViolation { line: invariant.line, condition: invariant.condition.clone(), pc: self.pc, reason })
+            })
+            .collect();
+        self.invariant_violations.extend(violations);
+    }
+
+    /// Evaluates `log` and `warnIf` statements anchored at the opcode about to execute. Skipped branches are ignored.
+    fn check_log_points(&mut self) {
+        if !self.engine.is_executing() {
+            return;
+        }
+        let offset = self.current_byte_offset();
+        let mut entries = Vec::new();
+        let mut warnings = Vec::new();
+        for log_point in self.debug_info.log_points.iter().filter(|log_point| log_point.bytecode_offset == offset) {
+            let line = log_point.span.map(|span| span.line).unwrap_or_default();
+            match &log_point.warning {
+                None => entries.push(LogEntry { line, pc: self.pc, values: self.evaluate_log_point(log_point) }),
+                Some(message) => {
+                    let condition = log_point.args.first().map(|arg| self.evaluate_log_arg(&log_point.function, arg));
+                    if matches!(condition, Some(DebugValue::Bool(true))) {
+                        warnings.push(RuntimeWarning { line, pc: self.pc, message: message.clone() });
+                    }
+                }
+            }
+        }
+        self.logs.extend(entries);
+        self.warnings.extend(warnings);
+    }
+
+    fn evaluate_log_point(&self, log_point: &DebugLogPoint) -> Vec<String> {
+        log_point.args.iter().map(|arg| self.format_value(&arg.type_name, &self.evaluate_log_arg(&log_point.function, arg))).collect()
+    }
+
+    fn evaluate_log_arg(&self, function_name: &str, arg: &DebugLogArg) -> DebugValue {
+        match &arg.expr {
+            Expr::String(_) | Expr::Int(_) | Expr::Bool(_) | Expr::Bytes(_) => self.evaluate_constant(&arg.expr),
+            expr => self.evaluate_expr_with_shadow_vm(function_name, &arg.type_name, expr).unwrap_or_else(DebugValue::Unknown),
+        }
     }
 
     /// Returns the current main stack as hex-encoded strings.
     pub fn stack(&self) -> Vec<String> {
-        let stacks = self.engine.stacks();
-        stacks.dstack.iter().map(|bytes| encode_hex(bytes)).collect()
+        self.with_stacks(|dstack, _| dstack.iter().map(|bytes| encode_hex(bytes)).collect())
     }
 
     fn evaluate_update_with_shadow_vm(&self, function_name: &str, update: &DebugVariableUpdate) -> Result<DebugValue, String> {
@@ -789,7 +1240,7 @@ impl<'a> DebugSession<'a> {
             TxScriptEngine::new(EngineCtx::new(&sig_cache).with_reused(&reused_values), EngineFlags { covenants_enabled: true });
         for opcode in parse_script::<DebugTx<'_>, DebugReused>(script) {
             let opcode = opcode.map_err(|err| format!("failed to parse shadow script: {err}"))?;
-            engine.execute_opcode(opcode).map_err(|err| format!("failed to execute shadow script: {err}"))?;
+            guard_engine(|| engine.execute_opcode(opcode))?.map_err(|err| format!("failed to execute shadow script: {err}"))?;
         }
         engine.stacks().dstack.last().cloned().ok_or_else(|| "shadow VM produced an empty stack".to_string())
     }
@@ -813,14 +1264,33 @@ impl<'a> DebugSession<'a> {
         if index < 0 {
             return Err("negative stack index".to_string());
         }
-        let stacks = self.engine.stacks();
-        let stack = stacks.dstack;
-        let idx = index as usize;
-        if idx >= stack.len() {
-            return Err("stack index out of range".to_string());
+        self.with_stacks(|stack, _| {
+            let idx = index as usize;
+            if idx >= stack.len() {
+                return Err("stack index out of range".to_string());
+            }
+            let stack_index = stack.len() - 1 - idx;
+            Ok(stack.get(stack_index).cloned().unwrap_or_default())
+        })
+    }
+}
+
+/// Finds the first call in a watch expression that would inline a contract function or build a contract.
+struct ContractCalls<'a> {
+    functions: &'a [DebugFunctionRange],
+    found: Option<String>,
+}
+
+impl Visitor for ContractCalls<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if self.found.is_some() {
+            return;
+        }
+        match expr {
+            Expr::Call { name, .. } if self.functions.iter().any(|function| function.name == *name) => self.found = Some(name.clone()),
+            Expr::New { name, .. } => self.found = Some(name.clone()),
+            _ => {}
         }
-        let stack_index = stack.len() - 1 - idx;
-        Ok(stack.get(stack_index).cloned().unwrap_or_default())
     }
 }
 
@@ -830,10 +1300,16 @@ fn array_element_size(element_type: &str) -> Option<usize> {
         "int" => Some(8),
         "bool" => Some(1),
         "byte" => Some(1),
+        "pubkey" => Some(32),
         other => other.strip_prefix("bytes").and_then(|v| v.parse::<usize>().ok()),
     }
 }
 
+/// Keeps multi-line and escaped strings on one display line.
+fn escape_control_chars(value: &str) -> String {
+    value.chars().map(|ch| if ch.is_control() { ch.escape_default().to_string() } else { ch.to_string() }).collect()
+}
+
 /// Decodes raw bytes into a typed debug value based on the type name.
 fn decode_value_by_type(type_name: &str, bytes: Vec<u8>) -> Result<DebugValue, String> {
     match type_name {
@@ -885,10 +1361,30 @@ fn decode_i64(bytes: &[u8]) -> Result<i64, String> {
     Ok(value * sign)
 }
 
+/// Evaluates an invariant of the form `depth <op> <int>` against the current stack depth.
+fn evaluate_invariant(invariant: &DebugInvariant, depth: usize) -> Result<bool, String> {
+    let unsupported = || format!("unsupported invariant '{}'; expected `depth <op> <int>`", invariant.condition);
+    let rest = invariant.condition.trim().strip_prefix("depth").ok_or_else(unsupported)?.trim_start();
+    let (op, value) = ["==", "!=", "<=", ">=", "<", ">"]
+        .iter()
+        .find_map(|op| rest.strip_prefix(op).map(|value| (*op, value.trim())))
+        .ok_or_else(unsupported)?;
+    let expected: usize = value.parse().map_err(|_| unsupported())?;
+    Ok(match op {
+        "==" => depth == expected,
+        "!=" => depth != expected,
+        "<=" => depth <= expected,
+        ">=" => depth >= expected,
+        "<" => depth < expected,
+        _ => depth > expected,
+    })
+}
+
 /// Executes sigscript to seed the stack before debugging lockscript.
 fn seed_engine_with_sigscript(engine: &mut DebugEngine<'_>, sigscript: &[u8]) -> Result<(), kaspa_txscript_errors::TxScriptError> {
     for opcode in parse_script::<DebugTx<'_>, DebugReused>(sigscript) {
-        engine.execute_opcode(opcode?)?;
+        let opcode = opcode?;
+        guard_engine(|| engine.execute_opcode(opcode)).map_err(kaspa_txscript_errors::TxScriptError::InvalidState)??;
     }
     Ok(())
 }
@@ -905,6 +1401,14 @@ fn build_opcode_offsets(opcodes: &[Option<DebugOpcode<'_>>]) -> (Vec<usize>, usi
     (offsets, offset)
 }
 
+/// Script truthiness: any non-zero byte, ignoring a trailing sign bit (negative zero is false).
+pub(super) fn is_truthy(bytes: &[u8]) -> bool {
+    match bytes.split_last() {
+        None => false,
+        Some((last, rest)) => rest.iter().any(|byte| *byte != 0) || (*last & 0x7f) != 0,
+    }
+}
+
 fn mapping_matches_offset(mapping: &DebugMapping, offset: usize) -> bool {
     if mapping.bytecode_start == mapping.bytecode_end {
         offset == mapping.bytecode_start
@@ -930,12 +1434,16 @@ mod tests {
         let engine: DebugEngine<'static> =
             TxScriptEngine::new(EngineCtx::new(sig_cache).with_reused(reused_values), EngineFlags { covenants_enabled: true });
         let debug_info = DebugInfo {
-            source: String::new(),
-            mappings: vec![],
             variable_updates: updates,
             params,
             functions: vec![DebugFunctionRange { name: "f".to_string(), bytecode_start: 0, bytecode_end: 1 }],
-            constants: vec![DebugConstantMapping { name: "K".to_string(), type_name: "int".to_string(), value: Expr::Int(7) }],
+            constants: vec![DebugConstantMapping {
+                name: "K".to_string(),
+                type_name: "int".to_string(),
+                value: Expr::Int(7),
+                scale: None,
+            }],
+            ..Default::default()
         };
         DebugSession::full(sigscript, &[], "", Some(debug_info), engine)
     }
@@ -957,8 +1465,20 @@ mod tests {
 
         let session = make_session(
             vec![
-                DebugParamMapping { name: "a".to_string(), type_name: "int".to_string(), stack_index: 1, function: "f".to_string() },
-                DebugParamMapping { name: "b".to_string(), type_name: "int".to_string(), stack_index: 0, function: "f".to_string() },
+                DebugParamMapping {
+                    name: "a".to_string(),
+                    type_name: "int".to_string(),
+                    stack_index: 1,
+                    function: "f".to_string(),
+                    scale: None,
+                },
+                DebugParamMapping {
+                    name: "b".to_string(),
+                    type_name: "int".to_string(),
+                    stack_index: 0,
+                    function: "f".to_string(),
+                    scale: None,
+                },
             ],
             vec![],
             &sigscript,
@@ -986,7 +1506,13 @@ mod tests {
         let sigscript = sig_builder.drain();
 
         let session = make_session(
-            vec![DebugParamMapping { name: "a".to_string(), type_name: "int".to_string(), stack_index: 0, function: "f".to_string() }],
+            vec![DebugParamMapping {
+                name: "a".to_string(),
+                type_name: "int".to_string(),
+                stack_index: 0,
+                function: "f".to_string(),
+                scale: None,
+            }],
             vec![DebugVariableUpdate {
                 name: "x".to_string(),
                 type_name: "int".to_string(),
@@ -996,6 +1522,7 @@ mod tests {
                 function: "f".to_string(),
                 sequence: 0,
                 frame_id: 0,
+                scope_end: None,
             }],
             &sigscript,
         )
@@ -1005,4 +1532,19 @@ mod tests {
         let x = vars.into_iter().find(|var| var.name == "x").expect("x variable");
         assert!(matches!(x.value, DebugValue::Unknown(_)));
     }
+
+    #[test]
+    fn engine_panics_are_reported_and_stop_the_session() {
+        let err = guard_engine(|| -> usize { panic!("index {} out of range", 3) }).unwrap_err();
+        assert_eq!(err, "engine panicked: index 3 out of range");
+        assert_eq!(guard_engine(|| 7), Ok(7));
+
+        // No known script makes the engine panic, so the session is handed one as if it had.
+        let mut session = make_session(vec![], vec![], &[]).unwrap();
+        session.engine_failure = Some(err);
+        for _ in 0..2 {
+            let step = session.step_opcode().expect_err("a panicked engine is not stepped again").to_string();
+            assert!(step.contains("engine panicked: index 3 out of range"), "unexpected error: {step}");
+        }
+    }
 }
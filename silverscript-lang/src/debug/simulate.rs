@@ -0,0 +1,1108 @@
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Range;
+
+use kaspa_consensus_core::hashing::sighash::{SigHashReusedValuesUnsync, calc_schnorr_signature_hash};
+use kaspa_consensus_core::hashing::sighash_type::SIG_HASH_ALL;
+use kaspa_consensus_core::tx::{
+    MutableTransaction, ScriptPublicKey, Transaction, TransactionId, TransactionInput, TransactionOutpoint, TransactionOutput,
+    UtxoEntry, VerifiableTransaction,
+};
+use kaspa_txscript::caches::Cache;
+use kaspa_txscript::opcodes::codes::{OpCheckSig, OpCheckSigVerify, OpToAltStack, OpVerify};
+use kaspa_txscript::script_builder::ScriptBuilder;
+use kaspa_txscript::{EngineCtx, EngineFlags, TxScriptEngine, parse_script};
+use secp256k1::{Keypair, Message, Secp256k1, SecretKey};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::{Expr, SourceSpan, parse_contract_ast};
+use crate::compiler::test_vectors::test_vectors;
+use crate::compiler::{CompileOptions, CompiledContract, blake2b_256, compile_contract, compile_parsed_contract, mass};
+use crate::debug::opcodes::is_covenant_opcode;
+use crate::debug::session::{DebugEngine, DebugReused, DebugSession, DebugTx, LogEntry, RuntimeWarning, is_truthy};
+use crate::debug::{DebugInfo, DebugLevel, MappingKind, guard_engine};
+
+/// Upper bound on reduction passes so pathological contracts cannot loop forever.
+const MAX_REDUCTION_PASSES: usize = 64;
+
+/// Mock spend used by `verify_auto_signed` and `compare_session_with_engine`.
+const SIGNED_SPEND_TX: SpendTx<'static> = SpendTx { utxo_value: 100_000_000, output_values: &[], output_scripts: &[] };
+
+/// Largest stack item the engine accepts.
+const MAX_STACK_ITEM_SIZE: usize = 520;
+
+/// Result of shrinking a failing argument set.
+#[derive(Debug, Clone)]
+pub struct ReducedFailure {
+    pub args: Vec<Expr>,
+    pub failure: String,
+}
+
+/// Source and inputs needed to reproduce an execution.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TraceMeta {
+    pub source: String,
+    pub function: String,
+    #[serde(default)]
+    pub ctor_args: Vec<Expr>,
+    #[serde(default)]
+    pub args: Vec<Expr>,
+    /// Arguments by parameter name, as an alternative to `args`; only one of the two may be given.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub named_args: BTreeMap<String, Expr>,
+    /// Value of the UTXO being spent. When set, the spend runs as the only input of a transaction
+    /// paying back to the contract, so `input.value` and `input.lockingBytecode` have something to read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utxo_value: Option<u64>,
+    /// Values of the outputs of that transaction, which all pay back to the contract. Defaults to
+    /// one output of `utxo_value`; whatever the outputs leave of `utxo_value` is the fee.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_values: Vec<u64>,
+    /// Hex locking scripts of those outputs, in order. Outputs past the end of the list, or given an
+    /// empty string, pay back to the contract.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_scripts: Vec<String>,
+    /// Hex-encoded items pushed onto the alt stack before the sigscript runs, bottom first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alt_stack: Vec<String>,
+    /// Compiles for and runs on an engine without covenant support, to exercise fallback paths.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub covenants_disabled: bool,
+    /// Debug info level the trace was recorded at; a replay records at the same level so the
+    /// source steps line up.
+    #[serde(default)]
+    pub debug_level: DebugLevel,
+}
+
+impl TraceMeta {
+    /// The positional arguments for `function`, resolving `named_args` against the compiled ABI.
+    pub fn resolved_args(&self, compiled: &CompiledContract) -> Result<Vec<Expr>, String> {
+        if self.named_args.is_empty() {
+            return Ok(self.args.clone());
+        }
+        if !self.args.is_empty() {
+            return Err("give either args or named_args, not both".to_string());
+        }
+        compiled.positional_args(&self.function, &self.named_args).map_err(|err| err.to_string())
+    }
+
+    /// The transaction the spend runs in, or `None` to run without transaction context.
+    fn spend_tx(&self) -> Result<Option<SpendTx<'_>>, String> {
+        let Some(utxo_value) = self.utxo_value else {
+            if !self.output_values.is_empty() || !self.output_scripts.is_empty() {
+                return Err("output_values and output_scripts need a utxo_value to spend".to_string());
+            }
+            return Ok(None);
+        };
+        let paid = self.output_values.iter().try_fold(0u64, |total, value| total.checked_add(*value));
+        if paid.is_none_or(|paid| paid > utxo_value) {
+            return Err(format!("output_values pay more than the utxo_value of {utxo_value}"));
+        }
+        let output_count = self.output_values.len().max(1);
+        if self.output_scripts.len() > output_count {
+            return Err(format!("{} output_scripts given for {output_count} outputs", self.output_scripts.len()));
+        }
+        Ok(Some(SpendTx { utxo_value, output_values: &self.output_values, output_scripts: &self.output_scripts }))
+    }
+}
+
+/// A transaction with a single input spending `utxo_value`, and outputs of `output_values` (or one
+/// of `utxo_value` when empty) paying to `output_scripts`, or back to the contract.
+#[derive(Debug, Clone, Copy)]
+struct SpendTx<'a> {
+    utxo_value: u64,
+    output_values: &'a [u64],
+    output_scripts: &'a [String],
+}
+
+/// Lockscript state after one executed opcode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceStep {
+    pub byte_offset: usize,
+    /// Position in true execution order; `None` for opcodes skipped inside an untaken branch.
+    #[serde(default)]
+    pub execution_index: Option<usize>,
+    pub opcode: String,
+    pub stack: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alt_stack: Vec<String>,
+    /// Index into `ExecutionTrace::source_steps` of the statement this opcode ran for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_step: Option<usize>,
+}
+
+/// One visit to a source statement: the consecutive executed opcode steps its bytecode produced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceStep {
+    pub span: SourceSpan,
+    /// Inline call depth of the statement; 0 in the entrypoint body.
+    #[serde(default)]
+    pub call_depth: u32,
+    /// Indices into `ExecutionTrace::steps`.
+    pub opcode_steps: Range<usize>,
+    /// The source step `DebugSession::step_over` would stop at next: the first later one at the same
+    /// or a shallower call depth. `None` when the run ends first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_over: Option<usize>,
+}
+
+/// Recorded lockscript execution plus its terminal result (`error` is `None` on success).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    pub meta: TraceMeta,
+    pub steps: Vec<TraceStep>,
+    pub error: Option<String>,
+    /// One entry per source line, for shading executed and skipped code.
+    #[serde(default)]
+    pub line_coverage: Vec<LineCoverage>,
+    /// Statement-level view of `steps`; each opcode step names its entry in `source_step`.
+    #[serde(default)]
+    pub source_steps: Vec<SourceStep>,
+    /// Outcome of every `require` in the contract, keyed by its id in `DebugInfo::checks`.
+    #[serde(default)]
+    pub require_coverage: Vec<RequireCoverage>,
+    /// Entries produced by `log(...)` statements that executed.
+    #[serde(default)]
+    pub logs: Vec<LogEntry>,
+    /// `warnIf(...)` statements whose condition held; they never fail the run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<RuntimeWarning>,
+    /// Time spent producing this trace, phase by phase.
+    #[serde(default)]
+    pub timings: TraceTimings,
+}
+
+/// Wall-clock milliseconds spent in each phase of `record_trace`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraceTimings {
+    pub parse_ms: f64,
+    pub compile_ms: f64,
+    /// The recorded run of the spend.
+    pub execute_ms: f64,
+    /// The debug-session replay that evaluates `log` and `warnIf` statements; zero when the contract has none.
+    pub logs_ms: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineStatus {
+    /// At least one statement on the line ran.
+    Executed,
+    /// The line maps to bytecode that was never executed, e.g. an untaken branch.
+    Skipped,
+    /// No statement maps to the line (blank lines, comments, declarations).
+    Unmapped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineCoverage {
+    pub line: u32,
+    pub status: LineStatus,
+}
+
+/// Which outcomes a `require` took. Merge the entries of several traces with
+/// `merge_require_coverage` to see the checks a test suite never makes fail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequireCoverage {
+    /// Index into `DebugInfo::checks`.
+    pub check: usize,
+    /// Entrypoint whose script holds the check.
+    pub function: String,
+    pub span: Option<SourceSpan>,
+    pub condition: String,
+    pub seen_true: bool,
+    pub seen_false: bool,
+}
+
+impl RequireCoverage {
+    /// The check ran and always held, so nothing shows it can reject a spend.
+    pub fn never_false(&self) -> bool {
+        self.seen_true && !self.seen_false
+    }
+}
+
+/// First source step at which two traces differ, with the first differing opcode step inside it; a
+/// side is `None` when that trace ended earlier.
+#[derive(Debug, Clone)]
+pub struct TraceDivergence {
+    /// Index into both traces' `source_steps`.
+    pub step_index: usize,
+    pub left: Option<TraceStep>,
+    pub right: Option<TraceStep>,
+}
+
+/// Outcome of re-running a saved trace against the current compiler.
+#[derive(Debug, Clone)]
+pub struct ReplayReport {
+    pub matches: bool,
+    /// Index of the first step that differs, if the step sequences diverge.
+    pub divergence: Option<usize>,
+    pub expected_error: Option<String>,
+    pub actual_error: Option<String>,
+}
+
+/// Runs `function_name` with `args` against the compiled lockscript on a fresh engine.
+///
+/// No transaction context is attached, so this is only meaningful for contract logic that
+/// does not rely on introspection. Returns the failure reason if execution does not succeed.
+pub fn simulate(compiled: &CompiledContract, function_name: &str, args: Vec<Expr>) -> Result<(), String> {
+    execute(compiled, function_name, args, &[], true, None, None)
+}
+
+/// Like `simulate`, but says how a rejected spend failed. The outer error is for arguments that
+/// cannot be encoded into a sigscript at all.
+pub fn simulate_status(compiled: &CompiledContract, function_name: &str, args: Vec<Expr>) -> Result<SpendStatus, String> {
+    with_trace_engine(compiled, function_name, args, &[], true, None, |mut engine, sigscript| {
+        match run_spend(&mut engine, sigscript, &compiled.script, None) {
+            Ok(()) => SpendStatus::Valid,
+            Err(failure) => failure.status,
+        }
+    })
+}
+
+/// Whether a spend would be accepted. Consensus requires the scripts to leave exactly one item on
+/// the stack, and that item to be true.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendStatus {
+    Valid,
+    /// The top item is true, but `extra` more items are left below it.
+    InvalidDirtyStack {
+        extra: usize,
+    },
+    /// The single item left is false.
+    InvalidFalse,
+    /// Nothing is left on the stack.
+    InvalidEmptyStack,
+    /// An opcode failed before the end of the script.
+    ExecError(String),
+}
+
+impl SpendStatus {
+    pub fn is_valid(&self) -> bool {
+        *self == Self::Valid
+    }
+
+    /// Why the spend is rejected, in the words `simulate` reports it; `None` when it is valid.
+    pub fn rejection(&self) -> Option<String> {
+        match self {
+            Self::Valid => None,
+            Self::InvalidDirtyStack { extra } => {
+                Some(format!("{extra} extra stack entries at end of script execution; a valid spend leaves exactly one"))
+            }
+            Self::InvalidFalse => Some("false stack entry at end of script execution".to_string()),
+            Self::InvalidEmptyStack => Some("stack empty at end of script execution".to_string()),
+            Self::ExecError(reason) => Some(reason.clone()),
+        }
+    }
+}
+
+/// The end-of-script check consensus applies to the data stack, bottom first. A dirty stack is
+/// rejected whatever its top item is, as the engine does.
+pub fn final_stack_status(stack: &[Vec<u8>]) -> SpendStatus {
+    match stack {
+        [] => SpendStatus::InvalidEmptyStack,
+        [top] if is_truthy(top) => SpendStatus::Valid,
+        [_] => SpendStatus::InvalidFalse,
+        _ => SpendStatus::InvalidDirtyStack { extra: stack.len() - 1 },
+    }
+}
+
+/// Compiles `meta.source` at `meta.debug_level` and records every executed lockscript opcode.
+pub fn record_trace(meta: TraceMeta) -> Result<ExecutionTrace, String> {
+    let options = CompileOptions {
+        record_debug_infos: true,
+        debug_level: meta.debug_level,
+        covenants_disabled: meta.covenants_disabled,
+        ..Default::default()
+    };
+    let (contract, parse_ms) = timed(|| parse_contract_ast(&meta.source));
+    let contract = contract.map_err(|err| err.to_string())?;
+    let (compiled, compile_ms) = timed(|| compile_parsed_contract(&contract, &meta.source, &meta.ctor_args, options));
+    let compiled = compiled.map_err(|err| err.to_string())?;
+    let mut trace = trace_compiled(&compiled, meta)?;
+    trace.timings.parse_ms = parse_ms;
+    trace.timings.compile_ms = compile_ms;
+    Ok(trace)
+}
+
+/// Like `record_trace`, keeping only the debug info `level` allows. `DebugLevel::Lines` keeps the
+/// trace of a large contract small, at the cost of locals, logs and inlined call frames.
+pub fn record_trace_at_level(meta: TraceMeta, level: DebugLevel) -> Result<ExecutionTrace, String> {
+    record_trace(TraceMeta { debug_level: level, ..meta })
+}
+
+/// Records a trace of `meta.function` against an already compiled contract, e.g. one loaded with
+/// `CompiledContract::from_json`. `meta.source` is only used for line coverage and logs, and
+/// `meta.ctor_args` are ignored since they are baked into the script.
+pub fn trace_compiled(compiled: &CompiledContract, meta: TraceMeta) -> Result<ExecutionTrace, String> {
+    let alt_stack = decode_alt_stack(&meta.alt_stack)?;
+    let args = meta.resolved_args(compiled)?;
+    let spend_tx = meta.spend_tx()?;
+    let mut recording = Recording::default();
+    let covenants_enabled = !meta.covenants_disabled;
+    let (result, execute_ms) =
+        timed(|| execute(compiled, &meta.function, args, &alt_stack, covenants_enabled, spend_tx, Some(&mut recording)));
+    let (line_coverage, source_steps, require_coverage) = match &compiled.debug_info {
+        Some(debug_info) => (
+            line_coverage(&meta.source, debug_info, &recording.executed_offsets),
+            link_source_steps(&mut recording.steps, debug_info),
+            require_coverage(debug_info, &recording),
+        ),
+        None => Default::default(),
+    };
+    let ((logs, warnings), logs_ms) = timed(|| collect_logs(compiled, &meta, &alt_stack).unwrap_or_default());
+    let timings = TraceTimings { execute_ms, logs_ms, ..Default::default() };
+    Ok(ExecutionTrace {
+        meta,
+        steps: recording.steps,
+        error: result.err(),
+        line_coverage,
+        source_steps,
+        require_coverage,
+        logs,
+        warnings,
+        timings,
+    })
+}
+
+/// Runs `f` and returns its result with the milliseconds it took.
+#[cfg(not(target_arch = "wasm32"))]
+fn timed<T>(f: impl FnOnce() -> T) -> (T, f64) {
+    let start = std::time::Instant::now();
+    let value = f();
+    (value, start.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// Runs `f` and returns its result with the milliseconds it took; `Instant` is unavailable on wasm.
+#[cfg(target_arch = "wasm32")]
+fn timed<T>(f: impl FnOnce() -> T) -> (T, f64) {
+    let start = js_sys::Date::now();
+    let value = f();
+    (value, js_sys::Date::now() - start)
+}
+
+/// Replays the spend in a debug session to evaluate `log` and `warnIf` statements; entries up to a failure are kept.
+fn collect_logs(
+    compiled: &CompiledContract,
+    meta: &TraceMeta,
+    alt_stack: &[Vec<u8>],
+) -> Result<(Vec<LogEntry>, Vec<RuntimeWarning>), String> {
+    if compiled.debug_info.as_ref().is_none_or(|debug_info| debug_info.log_points.is_empty()) {
+        return Ok(Default::default());
+    }
+    let args = meta.resolved_args(compiled)?;
+    let covenants_enabled = !meta.covenants_disabled;
+    with_trace_engine(compiled, &meta.function, args, alt_stack, covenants_enabled, meta.spend_tx()?, |engine, sigscript| {
+        let mut session = DebugSession::full(sigscript, &compiled.script, &meta.source, compiled.debug_info.clone(), engine)
+            .map_err(|err| err.to_string())?;
+        while let Ok(Some(_)) = session.step_opcode() {}
+        Ok((session.logs().to_vec(), session.warnings().to_vec()))
+    })?
+}
+
+/// Recompiles and re-runs a saved trace, reporting whether the outcome and steps still match.
+pub fn replay_trace(saved: &ExecutionTrace) -> Result<ReplayReport, String> {
+    let replayed = record_trace(saved.meta.clone())?;
+    let divergence = first_differing_step(&saved.steps, &replayed.steps);
+    Ok(ReplayReport {
+        matches: divergence.is_none() && saved.error == replayed.error,
+        divergence,
+        expected_error: saved.error.clone(),
+        actual_error: replayed.error,
+    })
+}
+
+/// Walks the source steps of two traces in lockstep and returns the first one whose opcode steps differ.
+pub fn first_divergence(left: &ExecutionTrace, right: &ExecutionTrace) -> Option<TraceDivergence> {
+    fn opcode_steps(trace: &ExecutionTrace, index: usize) -> Option<&[TraceStep]> {
+        trace.source_steps.get(index).map(|step| &trace.steps[step.opcode_steps.clone()])
+    }
+    let step_index = (0..left.source_steps.len().max(right.source_steps.len()))
+        .find(|&index| opcode_steps(left, index) != opcode_steps(right, index))?;
+    let (left_steps, right_steps) =
+        (opcode_steps(left, step_index).unwrap_or_default(), opcode_steps(right, step_index).unwrap_or_default());
+    let offset = first_differing_step(left_steps, right_steps).unwrap_or_default();
+    Some(TraceDivergence { step_index, left: left_steps.get(offset).cloned(), right: right_steps.get(offset).cloned() })
+}
+
+/// Index of the first position where two step sequences differ, including one ending earlier.
+fn first_differing_step(left: &[TraceStep], right: &[TraceStep]) -> Option<usize> {
+    left.iter()
+        .zip(right.iter())
+        .position(|(l, r)| l != r)
+        .or_else(|| (left.len() != right.len()).then_some(left.len().min(right.len())))
+}
+
+/// Classifies every source line by whether its most specific statement mapping executed.
+///
+/// A line covered by several mappings (e.g. an `if` and a statement in its branch) is judged by
+/// the narrowest one, so lines inside an untaken branch report `Skipped` even though the
+/// enclosing `if` ran.
+pub fn line_coverage(source: &str, debug_info: &DebugInfo, executed_offsets: &HashSet<usize>) -> Vec<LineCoverage> {
+    let statements: Vec<_> = debug_info
+        .mappings
+        .iter()
+        .filter(|mapping| matches!(mapping.kind, MappingKind::Statement {} | MappingKind::Virtual {}))
+        .filter_map(|mapping| {
+            let span = mapping.span?;
+            let executed = if mapping.bytecode_start == mapping.bytecode_end {
+                executed_offsets.contains(&mapping.bytecode_start)
+            } else {
+                (mapping.bytecode_start..mapping.bytecode_end).any(|offset| executed_offsets.contains(&offset))
+            };
+            Some((span.line, span.end_line, executed))
+        })
+        .collect();
+
+    (1..=source.lines().count() as u32)
+        .map(|line| {
+            let covering: Vec<_> = statements.iter().filter(|(start, end, _)| *start <= line && line <= *end).collect();
+            let status = match covering.iter().map(|(start, end, _)| end - start).min() {
+                None => LineStatus::Unmapped,
+                Some(width) if covering.iter().any(|(start, end, executed)| end - start == width && *executed) => LineStatus::Executed,
+                Some(_) => LineStatus::Skipped,
+            };
+            LineCoverage { line, status }
+        })
+        .collect()
+}
+
+/// Groups executed `steps` into source steps and points each step at its group.
+///
+/// A step belongs to the narrowest statement mapping covering its byte offset, as in
+/// `line_coverage`. Consecutive executed steps of the same statement form one source step; skipped
+/// opcodes and opcodes outside any statement (such as selector dispatch) belong to none. Each
+/// source step then gets its `step_over` target, so clients can offer step-over on a recorded trace.
+fn link_source_steps(steps: &mut [TraceStep], debug_info: &DebugInfo) -> Vec<SourceStep> {
+    let statement_at = |offset: usize| {
+        debug_info
+            .mappings
+            .iter()
+            .enumerate()
+            .filter(|(_, mapping)| matches!(mapping.kind, MappingKind::Statement {}) && mapping.span.is_some())
+            .filter(|(_, mapping)| mapping.bytecode_start <= offset && offset < mapping.bytecode_end)
+            .min_by_key(|(_, mapping)| mapping.bytecode_end - mapping.bytecode_start)
+            .map(|(index, _)| index)
+    };
+
+    let mut source_steps: Vec<SourceStep> = Vec::new();
+    let mut current = None;
+    for (index, step) in steps.iter_mut().enumerate() {
+        let mapping = step.execution_index.and_then(|_| statement_at(step.byte_offset));
+        match mapping {
+            Some(mapping) if current == Some(mapping) => {
+                let last = source_steps.last_mut().expect("a source step is open");
+                last.opcode_steps.end = index + 1;
+            }
+            Some(mapping) => {
+                let mapping = &debug_info.mappings[mapping];
+                let span = mapping.span.expect("statement mappings are filtered by span");
+                let opcode_steps = index..index + 1;
+                source_steps.push(SourceStep { span, call_depth: mapping.call_depth, opcode_steps, step_over: None });
+            }
+            None => {}
+        }
+        current = mapping;
+        step.source_step = mapping.map(|_| source_steps.len() - 1);
+    }
+
+    for index in 0..source_steps.len() {
+        let depth = source_steps[index].call_depth;
+        source_steps[index].step_over = (index + 1..source_steps.len()).find(|&next| source_steps[next].call_depth <= depth);
+    }
+    source_steps
+}
+
+/// Outcome of each check in `debug_info` for one run. A check held when its last opcode executed;
+/// it failed when the opcode that stopped the run is part of it.
+fn require_coverage(debug_info: &DebugInfo, recording: &Recording) -> Vec<RequireCoverage> {
+    debug_info
+        .checks
+        .iter()
+        .enumerate()
+        .map(|(check, info)| {
+            let range = info.bytecode_start..info.bytecode_end;
+            let seen_false = recording.failed_offset.is_some_and(|offset| range.contains(&offset));
+            let last_opcode = recording.steps.iter().map(|step| step.byte_offset).filter(|offset| range.contains(offset)).max();
+            let seen_true = !seen_false && last_opcode.is_some_and(|offset| recording.executed_offsets.contains(&offset));
+            RequireCoverage {
+                check,
+                function: info.function.clone(),
+                span: info.span,
+                condition: info.condition.clone(),
+                seen_true,
+                seen_false,
+            }
+        })
+        .collect()
+}
+
+/// Combines the require coverage of several runs of one contract, such as a test suite's traces.
+/// A check counts as seen true or false if any run saw it so.
+pub fn merge_require_coverage<'a>(traces: impl IntoIterator<Item = &'a ExecutionTrace>) -> Vec<RequireCoverage> {
+    let mut merged: Vec<RequireCoverage> = Vec::new();
+    for trace in traces {
+        for coverage in &trace.require_coverage {
+            match merged.iter_mut().find(|merged| merged.check == coverage.check) {
+                Some(merged) => {
+                    merged.seen_true |= coverage.seen_true;
+                    merged.seen_false |= coverage.seen_false;
+                }
+                None => merged.push(coverage.clone()),
+            }
+        }
+    }
+    merged.sort_by_key(|coverage| coverage.check);
+    merged
+}
+
+/// Per-opcode data captured while recording a trace.
+#[derive(Default)]
+struct Recording {
+    steps: Vec<TraceStep>,
+    /// Byte offsets of opcodes reached while the engine was executing (not skipping a branch).
+    executed_offsets: HashSet<usize>,
+    /// Byte offset of the lockscript opcode that failed, if one did.
+    failed_offset: Option<usize>,
+}
+
+fn execute(
+    compiled: &CompiledContract,
+    function_name: &str,
+    args: Vec<Expr>,
+    alt_stack: &[Vec<u8>],
+    covenants_enabled: bool,
+    spend_tx: Option<SpendTx<'_>>,
+    recording: Option<&mut Recording>,
+) -> Result<(), String> {
+    let result = with_trace_engine(compiled, function_name, args, alt_stack, covenants_enabled, spend_tx, |mut engine, sigscript| {
+        run_spend(&mut engine, sigscript, &compiled.script, recording)
+    })?;
+    result.map_err(|failure| match failure.opcode {
+        Some(opcode) if !covenants_enabled && is_covenant_opcode(opcode) => {
+            format!("{} (covenant opcodes are unavailable with covenants disabled)", failure.reason)
+        }
+        _ => failure.reason,
+    })
+}
+
+/// Hands `run` an engine with `alt_stack` seeded and the sigscript for `args`. Without a
+/// `spend_tx` the engine has no transaction context; with one it runs that transaction's input.
+fn with_trace_engine<R>(
+    compiled: &CompiledContract,
+    function_name: &str,
+    args: Vec<Expr>,
+    alt_stack: &[Vec<u8>],
+    covenants_enabled: bool,
+    spend_tx: Option<SpendTx<'_>>,
+    run: impl FnOnce(DebugEngine<'_>, &[u8]) -> R,
+) -> Result<R, String> {
+    let sig_cache = Cache::new(0);
+    let reused_values = SigHashReusedValuesUnsync::new();
+    let ctx = EngineCtx::new(&sig_cache).with_reused(&reused_values);
+    let flags = EngineFlags { covenants_enabled };
+    let Some(spend_tx) = spend_tx else {
+        let sigscript = compiled.build_sig_script(function_name, args).map_err(|err| err.to_string())?;
+        let mut engine: DebugEngine<'_> = TxScriptEngine::new(ctx, flags);
+        seed_alt_stack(&mut engine, alt_stack)?;
+        return Ok(run(engine, &sigscript));
+    };
+    let spend = build_signed_spend(compiled, function_name, args, &[], spend_tx, &reused_values)?;
+    let verifiable = spend.tx.as_verifiable();
+    let mut engine: DebugEngine<'_> =
+        TxScriptEngine::from_transaction_input(&verifiable, &verifiable.inputs()[0], 0, &spend.utxo_entry, ctx, flags);
+    seed_alt_stack(&mut engine, alt_stack)?;
+    Ok(run(engine, &spend.sigscript))
+}
+
+/// Decodes the hex items of `TraceMeta::alt_stack`, rejecting malformed or oversized ones.
+fn decode_alt_stack(items: &[String]) -> Result<Vec<Vec<u8>>, String> {
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let hex = item.strip_prefix("0x").unwrap_or(item);
+            let mut bytes = vec![0u8; hex.len() / 2];
+            if hex.len() % 2 != 0 || faster_hex::hex_decode(hex.as_bytes(), &mut bytes).is_err() {
+                return Err(format!("alt stack item {index} is not valid hex: '{item}'"));
+            }
+            if bytes.len() > MAX_STACK_ITEM_SIZE {
+                return Err(format!("alt stack item {index} is {} bytes; the limit is {MAX_STACK_ITEM_SIZE}", bytes.len()));
+            }
+            Ok(bytes)
+        })
+        .collect()
+}
+
+/// Decodes entry `index` of `TraceMeta::output_scripts`.
+fn decode_output_script(index: usize, script: &str) -> Result<Vec<u8>, String> {
+    let hex = script.strip_prefix("0x").unwrap_or(script);
+    let mut bytes = vec![0u8; hex.len() / 2];
+    if hex.len() % 2 != 0 || faster_hex::hex_decode(hex.as_bytes(), &mut bytes).is_err() {
+        return Err(format!("output script {index} is not valid hex: '{script}'"));
+    }
+    Ok(bytes)
+}
+
+/// Pushes `items` onto the alt stack, bottom first, by moving each through the data stack.
+fn seed_alt_stack<'a>(engine: &mut DebugEngine<'a>, items: &[Vec<u8>]) -> Result<(), String> {
+    let mut builder = ScriptBuilder::new();
+    for item in items {
+        builder.add_data(item).map_err(|err| err.to_string())?;
+        builder.add_op(OpToAltStack).map_err(|err| err.to_string())?;
+    }
+    for opcode in parse_script::<DebugTx<'a>, DebugReused>(&builder.drain()) {
+        let opcode = opcode.map_err(|err| err.to_string())?;
+        guard_engine(|| engine.execute_opcode(opcode))?.map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+/// Why a spend failed, with the lockscript opcode it failed at (`None` for a failure outside the
+/// lockscript or in the final stack check) and the last opcode executed before that.
+struct SpendFailure {
+    reason: String,
+    opcode: Option<u8>,
+    previous: Option<u8>,
+    status: SpendStatus,
+}
+
+fn run_spend<'a>(
+    engine: &mut DebugEngine<'a>,
+    sigscript: &[u8],
+    script: &[u8],
+    mut recording: Option<&mut Recording>,
+) -> Result<(), SpendFailure> {
+    let failure = |reason: String, previous: Option<u8>| SpendFailure {
+        status: SpendStatus::ExecError(reason.clone()),
+        reason,
+        opcode: None,
+        previous,
+    };
+    for opcode in parse_script::<DebugTx<'a>, DebugReused>(sigscript) {
+        let opcode = opcode.map_err(|err| failure(err.to_string(), None))?;
+        guard_engine(|| engine.execute_opcode(opcode))
+            .map_err(|reason| failure(reason, None))?
+            .map_err(|err| failure(err.to_string(), None))?;
+    }
+
+    let mut byte_offset = 0usize;
+    let mut previous = None;
+    for opcode in parse_script::<DebugTx<'a>, DebugReused>(script) {
+        let opcode = opcode.map_err(|err| failure(err.to_string(), previous))?;
+        let display = format!("{opcode:?}");
+        let serialized = opcode.serialize();
+        let value = serialized.first().copied();
+        let executing = engine.is_executing();
+        if let Err(reason) = guard_engine(|| engine.execute_opcode(opcode)).and_then(|result| result.map_err(|err| err.to_string())) {
+            if let Some(recording) = recording.as_deref_mut() {
+                recording.failed_offset = Some(byte_offset);
+            }
+            return Err(SpendFailure { status: SpendStatus::ExecError(reason.clone()), reason, opcode: value, previous });
+        }
+        if executing {
+            previous = value;
+        }
+        if let Some(recording) = recording.as_deref_mut() {
+            // Script execution never revisits an offset, so the executed set doubles as a counter.
+            let execution_index = executing.then_some(recording.executed_offsets.len());
+            if executing {
+                recording.executed_offsets.insert(byte_offset);
+            }
+            let stacks = engine.stacks();
+            let stack = stacks.dstack.iter().map(|bytes| faster_hex::hex_string(bytes)).collect();
+            let alt_stack = stacks.astack.iter().map(|bytes| faster_hex::hex_string(bytes)).collect();
+            recording.steps.push(TraceStep { byte_offset, execution_index, opcode: display, stack, alt_stack, source_step: None });
+        }
+        byte_offset += serialized.len();
+    }
+
+    let status = final_stack_status(&engine.stacks().dstack);
+    match status.rejection() {
+        None => Ok(()),
+        Some(reason) => Err(SpendFailure { reason, opcode: None, previous, status }),
+    }
+}
+
+/// Outcome of a spend whose `sig` arguments were signed with real keys.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedSpendReport {
+    /// The spend succeeded with the injected signatures.
+    pub signature_valid: bool,
+    pub error: Option<String>,
+    /// The spend failed at a signature check rather than elsewhere in the contract logic, which
+    /// points at signing (wrong key or a different sighash) instead of the contract.
+    pub likely_sighash_mismatch: bool,
+}
+
+/// Signs every `sig` argument of `function_name` with the next of `keypairs`, in param order, over
+/// a one-input transaction spending the contract back to itself, then runs the spend against that
+/// transaction. The values passed for `sig` arguments are replaced.
+pub fn verify_auto_signed(
+    compiled: &CompiledContract,
+    function_name: &str,
+    args: Vec<Expr>,
+    keypairs: &[Keypair],
+) -> Result<SignedSpendReport, String> {
+    let entry = compiled
+        .abi
+        .iter()
+        .find(|entry| entry.name == function_name)
+        .ok_or_else(|| format!("function '{function_name}' not found"))?;
+    if entry.inputs.len() != args.len() {
+        return Err(entry.arg_count_mismatch(args.len()));
+    }
+    let sig_slots = entry.inputs.iter().filter(|input| input.type_name == "sig").count();
+    if sig_slots == 0 || mass::count_sig_ops(&compiled.script) == 0 {
+        return Err(format!("'{function_name}' has no signature checks to verify"));
+    }
+    if keypairs.len() < sig_slots {
+        return Err(format!("'{function_name}' needs {sig_slots} signing keys but {} were provided", keypairs.len()));
+    }
+
+    let reused_values = SigHashReusedValuesUnsync::new();
+    let spend = build_signed_spend(compiled, function_name, args, keypairs, SIGNED_SPEND_TX, &reused_values)?;
+    let sig_cache = Cache::new(0);
+    let verifiable = spend.tx.as_verifiable();
+    let mut engine: DebugEngine<'_> = TxScriptEngine::from_transaction_input(
+        &verifiable,
+        &verifiable.inputs()[0],
+        0,
+        &spend.utxo_entry,
+        EngineCtx::new(&sig_cache).with_reused(&reused_values),
+        EngineFlags { covenants_enabled: true },
+    );
+    Ok(match run_spend(&mut engine, &spend.sigscript, &compiled.script, None) {
+        Ok(()) => SignedSpendReport { signature_valid: true, error: None, likely_sighash_mismatch: false },
+        Err(failure) => SignedSpendReport {
+            signature_valid: false,
+            likely_sighash_mismatch: failed_at_signature_check(&failure),
+            error: Some(failure.reason),
+        },
+    })
+}
+
+/// A one-input transaction spending the contract back to itself, and the sigscript for it.
+struct SignedSpend {
+    tx: MutableTransaction<Transaction>,
+    utxo_entry: UtxoEntry,
+    sigscript: Vec<u8>,
+}
+
+/// Builds the spend of a UTXO holding `value`, signing `sig` arguments in param order with the next
+/// of `keypairs`; `sig` arguments beyond the last keypair keep the value passed in.
+fn build_signed_spend(
+    compiled: &CompiledContract,
+    function_name: &str,
+    mut args: Vec<Expr>,
+    keypairs: &[Keypair],
+    spend_tx: SpendTx<'_>,
+    reused_values: &SigHashReusedValuesUnsync,
+) -> Result<SignedSpend, String> {
+    let entry = compiled
+        .abi
+        .iter()
+        .find(|entry| entry.name == function_name)
+        .ok_or_else(|| format!("function '{function_name}' not found"))?;
+    let sig_slots: Vec<usize> =
+        entry.inputs.iter().enumerate().filter(|(_, input)| input.type_name == "sig").map(|(index, _)| index).collect();
+    let sig_ops = mass::count_sig_ops(&compiled.script);
+
+    let script_public_key = compiled.to_script_public_key(0);
+    let input = TransactionInput {
+        previous_outpoint: TransactionOutpoint { transaction_id: TransactionId::from_bytes([0u8; 32]), index: 0 },
+        signature_script: vec![],
+        sequence: 0,
+        sig_op_count: sig_ops.min(u8::MAX as u64) as u8,
+    };
+    let default_outputs = [spend_tx.utxo_value];
+    let output_values = if spend_tx.output_values.is_empty() { &default_outputs[..] } else { spend_tx.output_values };
+    let outputs = output_values
+        .iter()
+        .enumerate()
+        .map(|(index, &value)| {
+            let script_public_key = match spend_tx.output_scripts.get(index).filter(|script| !script.is_empty()) {
+                Some(script) => ScriptPublicKey::new(0, decode_output_script(index, script)?.into()),
+                None => script_public_key.clone(),
+            };
+            Ok(TransactionOutput { value, script_public_key, covenant: None })
+        })
+        .collect::<Result<_, String>>()?;
+    let tx = Transaction::new(0, vec![input], outputs, 0, Default::default(), 0, vec![]);
+    let utxo_entry = UtxoEntry::new(spend_tx.utxo_value, script_public_key, 0, false, None);
+    let mut tx = MutableTransaction::with_entries(tx, vec![utxo_entry.clone()]);
+
+    if !sig_slots.is_empty() && !keypairs.is_empty() {
+        let sig_hash = calc_schnorr_signature_hash(&tx.as_verifiable(), 0, SIG_HASH_ALL, reused_values);
+        let message = Message::from_digest_slice(sig_hash.as_bytes().as_slice()).map_err(|err| err.to_string())?;
+        for (slot, keypair) in sig_slots.iter().zip(keypairs) {
+            let mut signature = keypair.sign_schnorr(message).as_ref().to_vec();
+            signature.push(SIG_HASH_ALL.to_u8());
+            if let Some(arg) = args.get_mut(*slot) {
+                *arg = Expr::Bytes(signature);
+            }
+        }
+    }
+    let sigscript = compiled.build_sig_script(function_name, args).map_err(|err| err.to_string())?;
+    tx.tx.inputs[0].signature_script = sigscript.clone();
+    Ok(SignedSpend { tx, utxo_entry, sigscript })
+}
+
+/// Outcome of one spend run through a `DebugSession` and directly on the engine.
+///
+/// Errors are compared by message and stacks as hex, bottom first. Stacks are only meaningful
+/// when the spend passed, since the two sides stop at different points of a failing opcode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineComparison {
+    pub session_error: Option<String>,
+    pub engine_error: Option<String>,
+    pub session_stack: Vec<String>,
+    pub engine_stack: Vec<String>,
+}
+
+impl EngineComparison {
+    /// Both sides agree on the outcome and, for a passing spend, on the final stack.
+    pub fn matches(&self) -> bool {
+        self.session_error == self.engine_error && (self.engine_error.is_some() || self.session_stack == self.engine_stack)
+    }
+}
+
+/// Runs `function_name` twice over the same signed spend (see `verify_auto_signed`): once stepped
+/// opcode by opcode through a `DebugSession`, and once driving the engine directly as `simulate`
+/// does. A mismatch means the debugger wrapper perturbs execution.
+pub fn compare_session_with_engine(
+    compiled: &CompiledContract,
+    function_name: &str,
+    args: Vec<Expr>,
+    keypairs: &[Keypair],
+) -> Result<EngineComparison, String> {
+    let reused_values = SigHashReusedValuesUnsync::new();
+    let spend = build_signed_spend(compiled, function_name, args, keypairs, SIGNED_SPEND_TX, &reused_values)?;
+    let sig_cache = Cache::new(0);
+    let verifiable = spend.tx.as_verifiable();
+    let (verifiable, utxo_entry, sig_cache, reused_values) = (&verifiable, &spend.utxo_entry, &sig_cache, &reused_values);
+    let new_engine = move || {
+        TxScriptEngine::from_transaction_input(
+            verifiable,
+            &verifiable.inputs()[0],
+            0,
+            utxo_entry,
+            EngineCtx::new(sig_cache).with_reused(reused_values),
+            EngineFlags { covenants_enabled: true },
+        )
+    };
+
+    let mut engine: DebugEngine<'_> = new_engine();
+    let engine_error = run_spend(&mut engine, &spend.sigscript, &compiled.script, None).err().map(|failure| failure.reason);
+    let engine_stack = engine.stacks().dstack.iter().map(|bytes| faster_hex::hex_string(bytes)).collect();
+
+    let source = compiled.debug_info.as_ref().map(|debug_info| debug_info.source.clone()).unwrap_or_default();
+    let (session_error, session_stack) =
+        match DebugSession::full(&spend.sigscript, &compiled.script, &source, compiled.debug_info.clone(), new_engine()) {
+            Err(err) => (Some(err.to_string()), Vec::new()),
+            Ok(mut session) => {
+                let error = loop {
+                    match session.step_opcode() {
+                        Ok(Some(_)) => {}
+                        Ok(None) => break final_stack_error(&session.stack()),
+                        Err(err) => break Some(err.to_string()),
+                    }
+                };
+                (error, session.stack())
+            }
+        };
+    Ok(EngineComparison { session_error, engine_error, session_stack, engine_stack })
+}
+
+/// The end-of-script check of `run_spend`, applied to a hex-encoded stack.
+fn final_stack_error(stack: &[String]) -> Option<String> {
+    let mut items = Vec::with_capacity(stack.len());
+    for item in stack {
+        let mut bytes = vec![0u8; item.len() / 2];
+        faster_hex::hex_decode(item.as_bytes(), &mut bytes).ok()?;
+        items.push(bytes);
+    }
+    final_stack_status(&items).rejection()
+}
+
+/// A failing `OpCheckSigVerify`, or an `OpCheckSig` whose false result failed the next `OpVerify`
+/// or the final stack check.
+fn failed_at_signature_check(failure: &SpendFailure) -> bool {
+    match failure.opcode {
+        Some(OpCheckSigVerify) => true,
+        Some(OpVerify) | None => failure.previous == Some(OpCheckSig),
+        Some(_) => false,
+    }
+}
+
+/// Arguments filled in by `run_sample_spend` and the outcome of spending with them.
+#[derive(Debug, Clone)]
+pub struct SampleSpend {
+    pub ctor_args: Vec<Expr>,
+    pub args: Vec<Expr>,
+    /// Failure reason, or `None` when the spend passed.
+    pub error: Option<String>,
+}
+
+/// Deterministic keypair handed to the `index`-th `pubkey` param by `run_sample_spend`. The secret
+/// key is a hash of the full index, so no two indices share a key.
+pub fn sample_keypair(index: usize) -> Keypair {
+    let seed = blake2b_256(&[b"sample_keypair".as_slice(), &(index as u64).to_le_bytes()].concat());
+    let secret = SecretKey::from_slice(&seed).expect("a 32-byte hash is a valid secret key");
+    Keypair::from_secret_key(&Secp256k1::new(), &secret)
+}
+
+/// Compiles `source` and spends `function_name` without any user-provided arguments.
+///
+/// Every `pubkey` param, constructor params first, gets the key of the next `sample_keypair`, and
+/// the n-th `sig` param is signed with the n-th of those keys, so a contract checking a signature
+/// against a key it was given passes out of the box. Other params get zero or empty values.
+pub fn run_sample_spend(source: &str, function_name: &str) -> Result<SampleSpend, String> {
+    let contract = parse_contract_ast(source).map_err(|err| err.to_string())?;
+    let function = contract
+        .functions
+        .iter()
+        .find(|func| func.entrypoint && func.name == function_name)
+        .ok_or_else(|| format!("function '{function_name}' not found"))?;
+
+    let mut keypairs = Vec::new();
+    let mut sample_arg = |type_name: &str| match type_name {
+        "pubkey" => {
+            let keypair = sample_keypair(keypairs.len());
+            keypairs.push(keypair);
+            Expr::Bytes(keypair.x_only_public_key().0.serialize().to_vec())
+        }
+        _ => zero_value(type_name),
+    };
+    let ctor_args: Vec<Expr> = contract.params.iter().map(|param| sample_arg(&param.type_name)).collect();
+    let args: Vec<Expr> = function.params.iter().map(|param| sample_arg(&param.type_name)).collect();
+
+    let compiled = compile_contract(source, &ctor_args, CompileOptions::default()).map_err(|err| err.to_string())?;
+    let has_sig = function.params.iter().any(|param| param.type_name == "sig");
+    let error = if has_sig && mass::count_sig_ops(&compiled.script) > 0 {
+        let sig_count = function.params.iter().filter(|param| param.type_name == "sig").count();
+        while keypairs.len() < sig_count {
+            keypairs.push(sample_keypair(keypairs.len()));
+        }
+        verify_auto_signed(&compiled, function_name, args.clone(), &keypairs)?.error
+    } else {
+        simulate(&compiled, function_name, args.clone()).err()
+    };
+    Ok(SampleSpend { ctor_args, args, error })
+}
+
+/// Zero, false or empty value of `type_name`, sized to fit fixed-width types.
+fn zero_value(type_name: &str) -> Expr {
+    if type_name.ends_with("[]") {
+        return Expr::Array(Vec::new());
+    }
+    match type_name {
+        "int" => Expr::Int(0),
+        "bool" => Expr::Bool(false),
+        "string" => Expr::String(String::new()),
+        "byte" => Expr::Bytes(vec![0]),
+        "pubkey" => Expr::Bytes(vec![0; 32]),
+        "sig" => Expr::Bytes(vec![0; 65]),
+        "datasig" => Expr::Bytes(vec![0; 64]),
+        other => Expr::Bytes(vec![0; other.strip_prefix("bytes").and_then(|size| size.parse().ok()).unwrap_or(0)]),
+    }
+}
+
+/// Outcome of one `@test` vector run by `run_test_vectors`.
+#[derive(Debug, Clone)]
+pub struct TestVectorOutcome {
+    pub function: String,
+    /// Position among the function's `@test` annotations, from 1.
+    pub index: usize,
+    pub expect_pass: bool,
+    /// Failure reason, or `None` when the spend passed.
+    pub error: Option<String>,
+}
+
+impl TestVectorOutcome {
+    /// The spend passed or failed as the vector expects.
+    pub fn matches(&self) -> bool {
+        self.expect_pass == self.error.is_none()
+    }
+}
+
+/// Spends every entrypoint of `source` with the arguments of each of its `@test` vectors.
+///
+/// A vector whose contract does not compile or whose arguments do not fit the function is an
+/// error rather than a failed spend, so `expect="fail"` cannot hide a broken vector.
+pub fn run_test_vectors(source: &str) -> Result<Vec<TestVectorOutcome>, String> {
+    let contract = parse_contract_ast(source).map_err(|err| err.to_string())?;
+    let mut outcomes = Vec::new();
+    for function in contract.functions.iter().filter(|func| func.entrypoint) {
+        let vectors = test_vectors(function).map_err(|err| format!("{}: {err}", function.name))?;
+        for (index, vector) in vectors.into_iter().enumerate() {
+            let context = |err: String| format!("{} @test #{}: {err}", function.name, index + 1);
+            let compiled =
+                compile_contract(source, &vector.ctor_args, CompileOptions::default()).map_err(|err| context(err.to_string()))?;
+            compiled.build_sig_script(&function.name, vector.args.clone()).map_err(|err| context(err.to_string()))?;
+            let error = simulate(&compiled, &function.name, vector.args).err();
+            let expect_pass = vector.expect_pass;
+            outcomes.push(TestVectorOutcome { function: function.name.clone(), index: index + 1, expect_pass, error });
+        }
+    }
+    Ok(outcomes)
+}
+
+/// Shrinks `args` toward a minimal set that still fails with the same reason, delta-debugging style.
+///
+/// Each pass tries to zero, empty or halve one argument at a time and keeps any candidate that
+/// reproduces the original failure. Returns an error if the initial arguments do not fail.
+pub fn reduce_failing_args(compiled: &CompiledContract, function_name: &str, args: Vec<Expr>) -> Result<ReducedFailure, String> {
+    let failure = match simulate(compiled, function_name, args.clone()) {
+        Ok(()) => return Err("arguments do not fail; nothing to reduce".to_string()),
+        Err(failure) => failure,
+    };
+
+    let mut current = args;
+    for _ in 0..MAX_REDUCTION_PASSES {
+        let mut changed = false;
+        for index in 0..current.len() {
+            for candidate in simplified_candidates(&current[index]) {
+                let mut trial = current.clone();
+                trial[index] = candidate;
+                if simulate(compiled, function_name, trial.clone()).err().as_ref() == Some(&failure) {
+                    current = trial;
+                    changed = true;
+                    break;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    Ok(ReducedFailure { args: current, failure })
+}
+
+/// Simpler variants of a value, most aggressive first.
+fn simplified_candidates(value: &Expr) -> Vec<Expr> {
+    match value {
+        Expr::Int(0) | Expr::Bool(false) => vec![],
+        Expr::Int(v) => {
+            let half = v / 2;
+            if half == 0 { vec![Expr::Int(0)] } else { vec![Expr::Int(0), Expr::Int(half)] }
+        }
+        Expr::Bool(true) => vec![Expr::Bool(false)],
+        Expr::Bytes(bytes) if bytes.is_empty() => vec![],
+        Expr::Bytes(bytes) => {
+            let mut candidates = vec![Expr::Bytes(vec![]), Expr::Bytes(vec![0u8; bytes.len()])];
+            if bytes.len() > 1 {
+                candidates.push(Expr::Bytes(bytes[..bytes.len() / 2].to_vec()));
+            }
+            candidates.retain(|candidate| candidate != value);
+            candidates
+        }
+        Expr::String(text) if text.is_empty() => vec![],
+        Expr::String(_) => vec![Expr::String(String::new())],
+        Expr::Array(values) if values.is_empty() => vec![],
+        Expr::Array(values) => vec![Expr::Array(vec![]), Expr::Array(values[..values.len() / 2].to_vec())],
+        _ => vec![],
+    }
+}
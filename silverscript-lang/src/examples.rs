@@ -0,0 +1,51 @@
+//! Sample contracts bundled with the crate, for tools that offer a "load example" starting point.
+//!
+//! Sources are embedded at build time, so they are available without the repository on disk.
+
+use serde::Serialize;
+
+/// A bundled contract and the entrypoint to try first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Example {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub function: &'static str,
+    pub source: &'static str,
+}
+
+const EXAMPLES: &[Example] = &[
+    Example {
+        name: "p2pkh",
+        description: "Pay to public key hash: spend with the key matching the stored hash and its signature",
+        function: "spend",
+        source: include_str!("examples/p2pkh.sil"),
+    },
+    Example {
+        name: "multisig",
+        description: "2-of-3 multisig: any two of the three owners sign",
+        function: "spend",
+        source: include_str!("examples/multisig.sil"),
+    },
+    Example {
+        name: "timelock",
+        description: "Time lock: the owner can reclaim the funds once the unlock time has passed",
+        function: "reclaim",
+        source: include_str!("examples/timelock.sil"),
+    },
+    Example {
+        name: "covenant",
+        description: "Covenant: the owner may only forward the funds back to this contract, minus a capped fee",
+        function: "forward",
+        source: include_str!("examples/covenant.sil"),
+    },
+];
+
+/// All bundled examples, in display order.
+pub fn examples() -> &'static [Example] {
+    EXAMPLES
+}
+
+/// Looks up a bundled example by name.
+pub fn example(name: &str) -> Option<&'static Example> {
+    EXAMPLES.iter().find(|example| example.name == name)
+}
@@ -1,4 +1,8 @@
 pub mod ast;
+#[cfg(all(feature = "bench", not(target_arch = "wasm32")))]
+pub mod bench;
 pub mod compiler;
 pub mod debug;
+pub mod examples;
 pub mod parser;
+pub mod solidity;
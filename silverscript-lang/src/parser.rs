@@ -1,16 +1,145 @@
 use pest::Parser;
-use pest::error::Error;
+use pest::error::{Error, ErrorVariant, InputLocation};
 use pest::iterators::Pairs;
+use pest::{Position, Span};
 use pest_derive::Parser;
 
 #[derive(Parser)]
 #[grammar = "silverscript.pest"]
 pub struct SilverScriptParser;
 
+/// Mirrors the `keyword` rule in `silverscript.pest`; none of these can be used as identifiers.
+const RESERVED_KEYWORDS: &[&str] = &[
+    "pragma",
+    "silverscript",
+    "contract",
+    "entrypoint",
+    "function",
+    "let",
+    "if",
+    "else",
+    "require",
+    "for",
+    "yield",
+    "return",
+    "console.log",
+    "new",
+    "true",
+    "false",
+    "constant",
+    "date",
+    "int",
+    "bool",
+    "string",
+    "pubkey",
+    "sig",
+    "datasig",
+    "bytes",
+    "byte",
+    "this.age",
+    "tx.time",
+    "this.activeInputIndex",
+    "this.activeBytecode",
+    "this.scriptSizeDataPrefix",
+    "this.scriptSize",
+    "tx.inputs.length",
+    "tx.outputs.length",
+    "tx.version",
+    "tx.locktime",
+    "tx.fee",
+    "in",
+];
+
+/// Returns the grammar's reserved keywords, e.g. for editor autocomplete.
+pub fn reserved_keywords() -> &'static [&'static str] {
+    RESERVED_KEYWORDS
+}
+
 pub fn parse_source_file(input: &str) -> Result<Pairs<'_, Rule>, Error<Rule>> {
     SilverScriptParser::parse(Rule::source_file, input)
+        .map_err(|err| explain_reserved_keyword(input, err))
+        .map_err(|err| explain_missing_semicolon(input, err))
 }
 
 pub fn parse_expression(input: &str) -> Result<Pairs<'_, Rule>, Error<Rule>> {
     SilverScriptParser::parse(Rule::expression, input)
 }
+
+/// Parses `input` with the `comment_scan` rule, whose pairs are the file's comments and string literals.
+pub fn parse_comment_scan(input: &str) -> Result<Pairs<'_, Rule>, Error<Rule>> {
+    SilverScriptParser::parse(Rule::comment_scan, input)
+}
+
+/// Replaces the generic pest error with a targeted one when parsing stopped on a reserved
+/// keyword used where a name was expected (after a type, `function` or `contract`).
+fn explain_reserved_keyword(input: &str, err: Error<Rule>) -> Error<Rule> {
+    let pos = error_pos(&err);
+    let Some(rest) = input.get(pos..) else {
+        return err;
+    };
+    let word_len = rest.find(|c: char| !is_identifier_char(c)).unwrap_or(rest.len());
+    let word = &rest[..word_len];
+    if word.is_empty() || !RESERVED_KEYWORDS.contains(&word) {
+        return err;
+    }
+
+    let before = input[..pos].trim_end();
+    let before = before.strip_suffix("[]").unwrap_or(before);
+    let previous = before.rsplit(|c: char| !is_identifier_char(c)).next().unwrap_or("");
+    let role = match previous {
+        "function" => "function name",
+        "contract" => "contract name",
+        "int" | "bool" | "string" | "pubkey" | "sig" | "datasig" | "byte" | "bytes" => "variable name",
+        other if other.strip_prefix("bytes").is_some_and(|size| !size.is_empty() && size.chars().all(|c| c.is_ascii_digit())) => {
+            "variable name"
+        }
+        _ => return err,
+    };
+
+    let Some(span) = Span::new(input, pos, pos + word_len) else {
+        return err;
+    };
+    Error::new_from_span(
+        ErrorVariant::CustomError { message: format!("`{word}` is a reserved keyword and cannot be used as a {role}") },
+        span,
+    )
+}
+
+/// Replaces the generic pest error with "expected `;` after statement" when parsing stopped after
+/// a complete statement, i.e. inserting `;` at the end of the preceding text gets the parse further.
+/// The error points just past the statement missing its `;`.
+fn explain_missing_semicolon(input: &str, err: Error<Rule>) -> Error<Rule> {
+    if !matches!(err.variant, ErrorVariant::ParsingError { .. }) {
+        return err;
+    }
+    let pos = error_pos(&err);
+    let Some(before) = input.get(..pos) else {
+        return err;
+    };
+    let end = before.trim_end().len();
+    if end == 0 || before[..end].ends_with([';', '{', '}']) {
+        return err;
+    }
+
+    let patched = format!("{};{}", &input[..end], &input[end..]);
+    let progressed = match SilverScriptParser::parse(Rule::source_file, &patched) {
+        Ok(_) => true,
+        // The patched source is one byte longer, so failing at `pos + 1` is the same failure.
+        Err(patched_err) => error_pos(&patched_err) > pos + 1,
+    };
+    let Some(position) = Position::new(input, end).filter(|_| progressed) else {
+        return err;
+    };
+    Error::new_from_pos(ErrorVariant::CustomError { message: "expected `;` after statement".to_string() }, position)
+}
+
+fn error_pos(err: &Error<Rule>) -> usize {
+    match err.location {
+        InputLocation::Pos(pos) => pos,
+        InputLocation::Span((start, _)) => start,
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
@@ -0,0 +1,310 @@
+//! Best-effort import of Solidity declarations as silverscript stubs, for teams porting EVM contracts.
+//!
+//! Only signatures are read: contract and interface names, constructor params and function params.
+//! Bodies, modifiers, events, state variables and type definitions are skipped, so the reader does
+//! not need to understand the full Solidity grammar.
+
+use crate::parser::reserved_keywords;
+
+/// A parameter as declared in Solidity; `name` is `None` for unnamed params.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolidityParam {
+    pub type_name: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolidityFunction {
+    pub name: String,
+    pub params: Vec<SolidityParam>,
+    pub returns: Vec<SolidityParam>,
+    /// Declared `external` or `public`, or without visibility as interface functions are.
+    pub external: bool,
+}
+
+/// A Solidity `contract`, `abstract contract`, `interface` or `library` and its signatures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolidityContract {
+    pub name: String,
+    pub constructor: Vec<SolidityParam>,
+    pub functions: Vec<SolidityFunction>,
+}
+
+/// Reads every contract-like declaration in `source`, in source order.
+pub fn parse_solidity_signatures(source: &str) -> Result<Vec<SolidityContract>, String> {
+    let tokens = tokenize(source)?;
+    let mut contracts = Vec::new();
+    let mut pos = 0;
+    while pos < tokens.len() {
+        if matches!(tokens[pos].as_str(), "contract" | "interface" | "library") {
+            let name = tokens.get(pos + 1).filter(|token| is_word(token)).ok_or("expected a name after contract keyword")?;
+            let open = tokens[pos..].iter().position(|token| token == "{").map(|offset| pos + offset).ok_or("expected '{'")?;
+            let close = matching(&tokens, open)?;
+            contracts.push(parse_contract_body(name, &tokens[open + 1..close])?);
+            pos = close + 1;
+        } else if tokens[pos] == "{" {
+            pos = matching(&tokens, pos)? + 1;
+        } else {
+            pos += 1;
+        }
+    }
+    Ok(contracts)
+}
+
+/// Renders `contract` as a silverscript skeleton with one stub per function.
+///
+/// External functions become entrypoints and the rest plain functions; every body is a TODO that
+/// fails with `require(false)` until ported. Params whose Solidity type has no silverscript
+/// counterpart are declared `bytes` and flagged with a TODO comment, as are lossy mappings and
+/// dropped return values.
+pub fn silverscript_stub(contract: &SolidityContract) -> String {
+    let mut out = String::from("pragma silverscript ^0.1.0;\n\n");
+    out.push_str(&format!("// Signatures imported from Solidity `{}`; bodies are not translated.\n", contract.name));
+    out.push_str(&format!("contract {}({}) {{\n", identifier(&contract.name, "Imported"), stub_params(&contract.constructor)));
+    for (index, function) in contract.functions.iter().enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        let entrypoint = if function.external { "entrypoint " } else { "" };
+        let name = identifier(&function.name, &format!("function{index}"));
+        out.push_str(&format!("    {entrypoint}function {name}({}) {{\n", stub_params(&function.params)));
+        if !function.returns.is_empty() {
+            let returns: Vec<&str> = function.returns.iter().map(|param| param.type_name.as_str()).collect();
+            let note = if function.external { "entrypoints cannot return values" } else { "add return types when porting" };
+            out.push_str(&format!("        // TODO: Solidity returns ({}); {note}\n", returns.join(", ")));
+        }
+        out.push_str(&format!("        // TODO: port the body of `{}`\n", function.name));
+        out.push_str("        require(false);\n    }\n");
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn stub_params(params: &[SolidityParam]) -> String {
+    let rendered: Vec<String> = params
+        .iter()
+        .enumerate()
+        .map(|(index, param)| {
+            let name = identifier(param.name.as_deref().unwrap_or_default(), &format!("arg{index}"));
+            match solidity_type_to_silverscript(&param.type_name) {
+                Some(type_name) if is_lossy_solidity_type(&param.type_name) => {
+                    format!("{type_name} {name} /* TODO: Solidity `{}` does not fully fit in {type_name} */", param.type_name)
+                }
+                Some(type_name) => format!("{type_name} {name}"),
+                None => format!("bytes {name} /* TODO: unmapped Solidity type `{}` */", param.type_name),
+            }
+        })
+        .collect();
+    rendered.join(", ")
+}
+
+/// Silverscript type for a Solidity type, or `None` when silverscript has no counterpart.
+///
+/// | Solidity | silverscript | notes |
+/// |---|---|---|
+/// | `bool`, `string`, `bytes`, `bytes1`..`bytes32` | same | |
+/// | `int8`..`int64`, `uint8`..`uint56` | `int` | |
+/// | `int72`..`int256`, `uint64`..`uint256` | `int` | lossy: Kaspa ints are signed 64-bit script numbers |
+/// | `address`, `address payable` | `bytes20` | lossy: keeps the 20 bytes, but Kaspa keys are 32-byte `pubkey`s |
+/// | `T[]` | `T[]` | when `T` maps to a non-array type |
+///
+/// Fixed-size arrays, nested arrays, mappings, structs, tuples, function types and fixed-point
+/// types map to `None`.
+pub fn solidity_type_to_silverscript(type_name: &str) -> Option<String> {
+    let type_name = type_name.trim();
+    if let Some(element) = type_name.strip_suffix("[]") {
+        return solidity_type_to_silverscript(element)
+            .filter(|element| !element.ends_with("[]"))
+            .map(|element| format!("{element}[]"));
+    }
+    match type_name {
+        "bool" | "bytes" | "string" => Some(type_name.to_string()),
+        "address" | "address payable" => Some("bytes20".to_string()),
+        _ if int_width(type_name).is_some() => Some("int".to_string()),
+        _ => type_name
+            .strip_prefix("bytes")
+            .and_then(|size| size.parse::<u32>().ok())
+            .filter(|size| (1..=32).contains(size))
+            .map(|_| type_name.to_string()),
+    }
+}
+
+/// Whether `solidity_type_to_silverscript` maps `type_name` to a type that cannot hold all of its values.
+pub fn is_lossy_solidity_type(type_name: &str) -> bool {
+    let type_name = type_name.trim();
+    let type_name = type_name.strip_suffix("[]").unwrap_or(type_name);
+    match int_width(type_name) {
+        Some((signed, bits)) => bits > if signed { 64 } else { 56 },
+        None => matches!(type_name, "address" | "address payable"),
+    }
+}
+
+/// Signedness and bit width of `intN`/`uintN`; a bare `int` or `uint` is 256 bits.
+fn int_width(type_name: &str) -> Option<(bool, u32)> {
+    let (signed, bits) = match type_name.strip_prefix('u') {
+        Some(rest) => (false, rest.strip_prefix("int")?),
+        None => (true, type_name.strip_prefix("int")?),
+    };
+    let bits = if bits.is_empty() { 256 } else { bits.parse().ok()? };
+    (bits % 8 == 0 && (8..=256).contains(&bits)).then_some((signed, bits))
+}
+
+/// `name` made into a silverscript identifier: leading `_`/`$` dropped, `fallback` if nothing usable remains.
+fn identifier(name: &str, fallback: &str) -> String {
+    let trimmed = name.trim_start_matches(['_', '$']).replace('$', "_");
+    let usable = trimmed.starts_with(|c: char| c.is_ascii_alphabetic())
+        && trimmed.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !reserved_keywords().contains(&trimmed.as_str());
+    if usable { trimmed } else { fallback.to_string() }
+}
+
+fn parse_contract_body(name: &str, tokens: &[String]) -> Result<SolidityContract, String> {
+    let mut contract = SolidityContract { name: name.to_string(), constructor: Vec::new(), functions: Vec::new() };
+    let mut pos = 0;
+    while pos < tokens.len() {
+        match tokens[pos].as_str() {
+            "function" | "constructor" => {
+                let is_constructor = tokens[pos] == "constructor";
+                let name = if is_constructor { String::new() } else { tokens.get(pos + 1).cloned().unwrap_or_default() };
+                let open = pos + if is_constructor { 1 } else { 2 };
+                if tokens.get(open).map(String::as_str) != Some("(") {
+                    return Err(format!("expected '(' after {}", if is_constructor { "constructor" } else { "function name" }));
+                }
+                let close = matching(tokens, open)?;
+                let params = parse_params(&tokens[open + 1..close])?;
+
+                let mut returns = Vec::new();
+                let mut visibility = None;
+                pos = close + 1;
+                while pos < tokens.len() && tokens[pos] != "{" && tokens[pos] != ";" {
+                    match tokens[pos].as_str() {
+                        "returns" if tokens.get(pos + 1).map(String::as_str) == Some("(") => {
+                            let close = matching(tokens, pos + 1)?;
+                            returns = parse_params(&tokens[pos + 2..close])?;
+                            pos = close + 1;
+                        }
+                        "(" => pos = matching(tokens, pos)? + 1,
+                        word @ ("external" | "public" | "internal" | "private") => {
+                            visibility = Some(word.to_string());
+                            pos += 1;
+                        }
+                        _ => pos += 1,
+                    }
+                }
+                if tokens.get(pos).map(String::as_str) == Some("{") {
+                    pos = matching(tokens, pos)?;
+                }
+                pos += 1;
+
+                if is_constructor {
+                    contract.constructor = params;
+                } else {
+                    let external = matches!(visibility.as_deref(), None | Some("external" | "public"));
+                    contract.functions.push(SolidityFunction { name, params, returns, external });
+                }
+            }
+            "{" => pos = matching(tokens, pos)? + 1,
+            _ => pos += 1,
+        }
+    }
+    Ok(contract)
+}
+
+/// Splits a parameter list on top-level commas; data locations and `indexed`/`payable` are dropped.
+fn parse_params(tokens: &[String]) -> Result<Vec<SolidityParam>, String> {
+    let mut params = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut depth = 0usize;
+    for token in tokens.iter().map(String::as_str).chain([","]) {
+        match token {
+            "(" | "[" => depth += 1,
+            ")" | "]" => depth = depth.checked_sub(1).ok_or("unbalanced brackets in parameter list")?,
+            "," if depth == 0 => {
+                if !current.is_empty() {
+                    params.push(parse_param(&current));
+                }
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        if !matches!(token, "memory" | "calldata" | "storage" | "indexed" | "payable") {
+            current.push(token);
+        }
+    }
+    Ok(params)
+}
+
+fn parse_param(tokens: &[&str]) -> SolidityParam {
+    match tokens {
+        [type_tokens @ .., name] if !type_tokens.is_empty() && is_word(name) => {
+            SolidityParam { type_name: type_tokens.concat(), name: Some(name.to_string()) }
+        }
+        _ => SolidityParam { type_name: tokens.concat(), name: None },
+    }
+}
+
+fn is_word(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '$')
+}
+
+/// Index of the bracket closing the one at `open`.
+fn matching(tokens: &[String], open: usize) -> Result<usize, String> {
+    let (opening, closing) = match tokens[open].as_str() {
+        "(" => ("(", ")"),
+        "[" => ("[", "]"),
+        _ => ("{", "}"),
+    };
+    let mut depth = 0usize;
+    for (index, token) in tokens.iter().enumerate().skip(open) {
+        if token == opening {
+            depth += 1;
+        } else if token == closing {
+            depth -= 1;
+            if depth == 0 {
+                return Ok(index);
+            }
+        }
+    }
+    Err(format!("unclosed '{opening}'"))
+}
+
+/// Words (identifiers, keywords, numbers) and single punctuation characters; comments and string
+/// literals are dropped.
+fn tokenize(source: &str) -> Result<Vec<String>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let c = chars[pos];
+        if c.is_whitespace() {
+            pos += 1;
+        } else if c == '/' && chars.get(pos + 1) == Some(&'/') {
+            while pos < chars.len() && chars[pos] != '\n' {
+                pos += 1;
+            }
+        } else if c == '/' && chars.get(pos + 1) == Some(&'*') {
+            let end = (pos + 2..chars.len().saturating_sub(1)).find(|&i| chars[i] == '*' && chars[i + 1] == '/');
+            pos = end.ok_or("unterminated block comment")? + 2;
+        } else if c == '"' || c == '\'' {
+            pos += 1;
+            while pos < chars.len() && chars[pos] != c {
+                pos += if chars[pos] == '\\' { 2 } else { 1 };
+            }
+            if pos >= chars.len() {
+                return Err("unterminated string literal".to_string());
+            }
+            pos += 1;
+        } else if c.is_ascii_alphanumeric() || c == '_' || c == '$' {
+            let start = pos;
+            while pos < chars.len() && (chars[pos].is_ascii_alphanumeric() || chars[pos] == '_' || chars[pos] == '$') {
+                pos += 1;
+            }
+            tokens.push(chars[start..pos].iter().collect());
+        } else {
+            tokens.push(c.to_string());
+            pos += 1;
+        }
+    }
+    Ok(tokens)
+}
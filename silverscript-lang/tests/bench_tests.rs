@@ -0,0 +1,15 @@
+#![cfg(feature = "bench")]
+
+use silverscript_lang::bench::{bundled_samples, run_bench};
+
+#[test]
+fn bundled_samples_trace_cleanly_and_report_throughput() {
+    let samples = bundled_samples();
+    let results = run_bench(&samples, 2).expect("every sample compiles and its spend passes");
+    assert_eq!(results.len(), samples.len());
+    for result in results {
+        for (phase, rate) in [("parse", result.parse_per_sec), ("compile", result.compile_per_sec), ("trace", result.trace_per_sec)] {
+            assert!(rate > 0.0, "{} {phase} reported {rate} ops/s", result.sample);
+        }
+    }
+}
@@ -12,6 +12,7 @@ use kaspa_txscript::{EngineCtx, EngineFlags, TxScriptEngine, pay_to_script_hash_
 use rand::{RngCore, thread_rng};
 use secp256k1::{Keypair, Message, Secp256k1, SecretKey};
 use silverscript_lang::ast::Expr;
+use silverscript_lang::compiler::multisig::{multisig_locking_script, multisig_sigscript_layout};
 use silverscript_lang::compiler::{CompileOptions, CompiledContract, compile_contract, function_branch_index};
 use std::fs;
 
@@ -958,7 +959,7 @@ fn runs_cashc_valid_examples() {
                 assert!(result.is_err(), "{example} should fail");
             }
             "string_with_escaped_characters.sil" => {
-                // Unsatisfiable in this runtime: escaped string literals hash differently.
+                // Unsatisfiable: the two literals decode to different strings.
                 let constructor_args = vec![0i64.into()];
                 let compiled = compile_contract(&source, &constructor_args, CompileOptions::default()).expect("compile succeeds");
                 let selector = selector_for_compiled(&compiled, "hello");
@@ -1031,13 +1032,12 @@ fn runs_cashc_valid_examples() {
 #[test]
 fn compiles_cashc_valid_examples() {
     // Skipped examples (from cashc valid-contract-files) and reasons:
-    // - 2_of_3_multisig.sil: uses checkMultiSig.
-    // - multiline_array_multisig.cash: uses checkMultiSig.
-    // - simple_multisig.cash: uses checkMultiSig.
-    // - trailing_comma.cash: uses checkMultiSig.
+    // - multiline_array_multisig.cash: has no entrypoint function.
+    // - simple_multisig.cash: has no entrypoint function.
     // - covenant_all_fields.cash: cashtoken-related logic.
     // - token_category_comparison.cash: cashtoken-related logic.
     let examples = [
+        "2_of_3_multisig.sil",
         "bitwise.sil",
         "bytes1_equals_byte.sil",
         "cast_hash_checksig.sil",
@@ -1084,6 +1084,7 @@ fn compiles_cashc_valid_examples() {
         "split_typed.sil",
         "string_concatenation.sil",
         "string_with_escaped_characters.sil",
+        "trailing_comma.sil",
         "tuple_unpacking.sil",
         "tuple_unpacking_parameter.sil",
         "tuple_unpacking_single_side_type.sil",
@@ -1097,3 +1098,34 @@ fn compiles_cashc_valid_examples() {
         assert!(compiled.is_ok(), "{example} failed to compile: {}", compiled.unwrap_err());
     }
 }
+
+/// Spends a `required`-of-`keys` multisig signed by the keys at `signers`, built with the public layout helpers.
+fn spend_multisig(required: usize, keys: usize, signers: &[usize], dummy: bool) -> Result<(), kaspa_txscript_errors::TxScriptError> {
+    let keypairs: Vec<Keypair> = (0..keys).map(|_| random_keypair()).collect();
+    let pubkeys: Vec<Vec<u8>> = keypairs.iter().map(|keypair| keypair.x_only_public_key().0.serialize().to_vec()).collect();
+    let script = multisig_locking_script(required, &pubkeys).expect("locking script");
+    let (mut tx, utxo_entry, reused_values) = build_tx_context(script.clone(), vec![(1000, script)], 1000, 0, 0);
+    tx.tx.inputs[0].sig_op_count = keys as u8;
+    let signatures: Vec<Option<Vec<u8>>> =
+        (0..keys).map(|index| signers.contains(&index).then(|| sign_tx(&tx, &reused_values, &keypairs[index]))).collect();
+    let layout = multisig_sigscript_layout(required, &signatures).expect("sigscript layout");
+    // 0x00 is OP_0, the dummy element Bitcoin multisig sigscripts start with.
+    let mut sigscript = if dummy { vec![0x00] } else { vec![] };
+    sigscript.extend(layout);
+    tx.tx.inputs[0].signature_script = sigscript;
+    execute_tx(tx, utxo_entry, reused_values)
+}
+
+#[test]
+fn multisig_layout_has_no_dummy_element() {
+    assert!(spend_multisig(1, 2, &[1], false).is_ok(), "1-of-2");
+    assert!(spend_multisig(2, 3, &[0, 2], false).is_ok(), "2-of-3");
+    assert!(spend_multisig(3, 3, &[0, 1, 2], false).is_ok(), "3-of-3");
+
+    // A Bitcoin-style leading OP_0 is never popped and breaks the clean-stack rule.
+    assert!(spend_multisig(2, 3, &[0, 2], true).is_err());
+
+    let err = multisig_sigscript_layout(2, &[Some(vec![1]), None, None]).unwrap_err();
+    assert!(err.to_string().contains("multisig needs exactly 2 signatures, got 1"), "{err}");
+    assert!(multisig_locking_script(4, &[vec![2u8; 32]; 3]).is_err());
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use kaspa_addresses::{Address, Prefix, Version};
 use kaspa_consensus_core::Hash;
 use kaspa_consensus_core::hashing::sighash::SigHashReusedValuesUnsync;
@@ -12,9 +14,26 @@ use kaspa_txscript::opcodes::codes::*;
 use kaspa_txscript::script_builder::ScriptBuilder;
 use kaspa_txscript::{EngineCtx, EngineFlags, SeqCommitAccessor, TxScriptEngine, pay_to_address_script, pay_to_script_hash_script};
 use silverscript_lang::ast::{Expr, parse_contract_ast};
-use silverscript_lang::compiler::{CompileOptions, CompiledContract, compile_contract, compile_contract_ast, function_branch_index};
-
-const OPTIONS: CompileOptions = CompileOptions { allow_yield: false, allow_entrypoint_return: false, record_debug_infos: false };
+use silverscript_lang::compiler::artifact::Artifact;
+use silverscript_lang::compiler::callgraph::CallGraph;
+use silverscript_lang::compiler::selectors::{SelectorLock, check_selector_lock};
+use silverscript_lang::compiler::{
+    CompileOptions, CompiledContract, CompilerError, MAX_SCRIPT_SIZE, SOURCE_HASH_METADATA_SIZE, SelectorEncoding, SelectorPosition,
+    compile, compile_contract, compile_contract_ast, contract_abi, function_branch_index, mass, source_hash,
+};
+use silverscript_lang::debug::opcodes::disassemble;
+use silverscript_lang::debug::{DebugInfo, DebugLevel, MappingKind};
+
+const OPTIONS: CompileOptions = CompileOptions {
+    allow_yield: false,
+    allow_entrypoint_return: false,
+    record_debug_infos: false,
+    debug_level: DebugLevel::Full,
+    hardened: false,
+    covenants_disabled: false,
+    embed_source_hash: false,
+    optimize: false,
+};
 
 fn run_script_with_selector(script: Vec<u8>, selector: Option<i64>) -> Result<(), kaspa_txscript_errors::TxScriptError> {
     let sigscript = selector_sigscript(selector);
@@ -222,6 +241,239 @@ fn build_sig_script_rejects_wrong_argument_type() {
     assert!(result.is_err());
 }
 
+#[test]
+fn build_sig_script_named_matches_positional_args() {
+    let source = r#"
+        contract C() {
+            entrypoint function spend(bytes4 b, int i, bool flag) {
+                require(flag);
+                require(b == bytes4(i));
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], CompileOptions::default()).expect("compile succeeds");
+    let positional = vec![Expr::Bytes(vec![7u8, 0, 0, 0]), Expr::Int(7), Expr::Bool(true)];
+    let named: BTreeMap<String, Expr> = [("flag", Expr::Bool(true)), ("i", Expr::Int(7)), ("b", Expr::Bytes(vec![7u8, 0, 0, 0]))]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect();
+    assert_eq!(
+        compiled.build_sig_script_named("spend", &named).expect("named sigscript builds"),
+        compiled.build_sig_script("spend", positional).expect("positional sigscript builds")
+    );
+
+    let mut missing = named.clone();
+    missing.remove("i");
+    let err = compiled.build_sig_script_named("spend", &missing).unwrap_err().to_string();
+    assert!(err.contains("missing argument 'i'"), "{err}");
+
+    let mut unknown = named;
+    unknown.insert("amount".to_string(), Expr::Int(1));
+    let err = compiled.build_sig_script_named("spend", &unknown).unwrap_err().to_string();
+    assert!(err.contains("has no parameter 'amount'"), "{err}");
+}
+
+#[test]
+fn signature_builtins_check_their_argument_kinds() {
+    let compile = |body: &str| {
+        let params = "pubkey pk, sig s, datasig ds, bytes msg, int n";
+        let source = format!("contract C() {{ entrypoint function main({params}) {{ {body} }} }}");
+        compile_contract(&source, &[], CompileOptions::default()).map_err(|err| err.to_string())
+    };
+    assert!(compile("require(checkSig(s, pk));").is_ok());
+    assert!(compile("require(checkDataSig(ds, msg, pk));").is_ok());
+    assert!(compile("require(checkMultiSig([s], [pk, 0x0202020202020202020202020202020202020202020202020202020202020202]));").is_ok());
+
+    let err = compile("require(checkSig(pk, s));").unwrap_err();
+    assert!(err.contains("checkSig() expects a sig for its first argument, got pubkey"), "{err}");
+    let err = compile("require(checkDataSig(s, msg, pk));").unwrap_err();
+    assert!(err.contains("checkDataSig() expects a datasig for its first argument, got sig"), "{err}");
+    let err = compile("require(checkMultiSig([s, n], [pk, pk]));").unwrap_err();
+    assert!(err.contains("checkMultiSig() expects a sig for its signature list, got int"), "{err}");
+    let err = compile("require(checkMultiSig([s, s], [pk]));").unwrap_err();
+    assert!(err.contains("multisig threshold 2 must be between 1 and 1"), "{err}");
+
+    assert!(compile("require(pubkeyHash(pk) == blake2b(msg));").is_ok());
+    let err = compile("require(pubkeyHash(s) == blake2b(msg));").unwrap_err();
+    assert!(err.contains("pubkeyHash() expects a pubkey for its argument, got sig"), "{err}");
+    let err = compile("require(pubkeyHash(0x0102) == blake2b(msg));").unwrap_err();
+    assert!(err.contains("pubkeyHash() expects a 32-byte x-only public key"), "{err}");
+}
+
+#[test]
+fn arg_count_mismatch_reports_the_expected_signature() {
+    let source = r#"
+        contract Vault(pubkey owner, int timeout) {
+            entrypoint function spend(pubkey key, sig s, int amount) {
+                require(key == owner);
+                require(amount > timeout);
+                require(checkSig(s, key));
+            }
+        }
+    "#;
+    let err = compile_contract(source, &[Expr::Bytes(vec![2u8; 32])], CompileOptions::default()).unwrap_err().to_string();
+    assert!(err.contains("constructor expects 2 arguments, got 1: Vault(pubkey owner, int timeout)"), "{err}");
+
+    let compiled =
+        compile_contract(source, &[Expr::Bytes(vec![2u8; 32]), Expr::Int(10)], CompileOptions::default()).expect("compile succeeds");
+    let args = vec![Expr::Bytes(vec![2u8; 32]), Expr::Bytes(vec![0u8; 65])];
+    let err = compiled.build_sig_script("spend", args).unwrap_err().to_string();
+    assert!(err.contains("function 'spend' expects 3 arguments, got 2: spend(pubkey key, sig s, int amount)"), "{err}");
+}
+
+#[test]
+fn input_shorthand_compiles_like_active_input_introspection() {
+    let shorthand = r#"
+        contract C() {
+            entrypoint function spend(int input) {
+                require(input.value > input);
+                require(tx.outputs[0].lockingBytecode == input.lockingBytecode);
+            }
+        }
+    "#;
+    let explicit = r#"
+        contract C() {
+            entrypoint function spend(int input) {
+                require(tx.inputs[this.activeInputIndex].value > input);
+                require(tx.outputs[0].lockingBytecode == tx.inputs[this.activeInputIndex].lockingBytecode);
+            }
+        }
+    "#;
+    let shorthand = compile_contract(shorthand, &[], CompileOptions::default()).expect("compile succeeds");
+    let explicit = compile_contract(explicit, &[], CompileOptions::default()).expect("compile succeeds");
+    assert_eq!(shorthand.script, explicit.script);
+}
+
+#[test]
+fn introspection_indexes_accept_runtime_ints_and_reject_invalid_constants() {
+    let source = |index: &str, param: &str| {
+        format!(
+            r#"
+        contract C() {{
+            entrypoint function spend({param}) {{
+                require(tx.outputs[{index}].value >= 1000);
+            }}
+        }}
+    "#
+        )
+    };
+    compile_contract(&source("i", "int i"), &[], CompileOptions::default()).expect("runtime int index compiles");
+    compile_contract(&source("i + 1", "int i"), &[], CompileOptions::default()).expect("int expression index compiles");
+
+    let err = compile_contract(&source("-1", "int i"), &[], CompileOptions::default()).unwrap_err().to_string();
+    assert!(err.contains("tx.outputs index must not be negative, got -1"), "{err}");
+    let err = compile_contract(&source("b", "bytes b"), &[], CompileOptions::default()).unwrap_err().to_string();
+    assert!(err.contains("tx.outputs index must be an int, got b"), "{err}");
+    let err = compile_contract(&source("flag", "bool flag"), &[], CompileOptions::default()).unwrap_err().to_string();
+    assert!(err.contains("tx.outputs index must be an int, got flag"), "{err}");
+}
+
+#[test]
+fn artifact_json_round_trips_enough_to_build_sigscripts() {
+    let source = r#"
+        contract Vault(pubkey owner, int timeout) {
+            entrypoint function spend(pubkey key, sig s) {
+                require(key == owner);
+                require(checkSig(s, key));
+            }
+
+            @selector(5)
+            entrypoint function reclaim(@scale(2) int amount) {
+                require(tx.time >= timeout);
+                require(amount > 0);
+            }
+        }
+    "#;
+    let options = CompileOptions { record_debug_infos: true, ..Default::default() };
+    let compiled = compile_contract(source, &[Expr::Bytes(vec![2u8; 32]), Expr::Int(10)], options).expect("compile succeeds");
+    let json = compiled.to_artifact_json();
+    let value: serde_json::Value = serde_json::from_str(&json).expect("artifact is json");
+    assert_eq!(value["contractName"], "Vault");
+    assert_eq!(value["constructorInputs"][1], serde_json::json!({ "name": "timeout", "type": "int" }));
+    assert_eq!(value["abi"][0]["selector_index"], 0);
+    assert_eq!(value["abi"][1]["selector_index"], 5);
+    assert_eq!(value["abi"][1]["inputs"][0]["scale"], 2);
+    assert_eq!(value["bytecode"], faster_hex::hex_string(&compiled.script));
+    assert_eq!(value["source"], source);
+    assert_eq!(value["compilerVersion"], env!("CARGO_PKG_VERSION"));
+
+    let restored = CompiledContract::from_artifact_json(&json).expect("artifact reads back");
+    assert_eq!(restored.script, compiled.script);
+    assert_eq!(restored.artifact(), Artifact { source: String::new(), ..compiled.artifact() });
+    let args = vec![Expr::Bytes(vec![2u8; 32]), Expr::Bytes(vec![0u8; 65])];
+    assert_eq!(restored.build_sig_script("spend", args.clone()).unwrap(), compiled.build_sig_script("spend", args).unwrap());
+    let args = vec![Expr::Decimal { digits: 15, scale: 1 }];
+    assert_eq!(restored.build_sig_script("reclaim", args.clone()).unwrap(), compiled.build_sig_script("reclaim", args).unwrap());
+
+    let err = CompiledContract::from_artifact_json(&json.replace(&faster_hex::hex_string(&compiled.script), "0g")).unwrap_err();
+    assert!(err.to_string().contains("artifact bytecode is not valid hex"), "{err}");
+}
+
+#[test]
+fn constructor_block_checks_run_on_every_entrypoint() {
+    let source = r#"
+        contract Floor(int minValue) {
+            constructor {
+                require(tx.outputs[0].value >= minValue);
+            }
+
+            entrypoint function spend(int a) {
+                require(a > 0);
+            }
+
+            entrypoint function check(int b) {
+                require(b < 10);
+            }
+        }
+    "#;
+    for (min_value, passes) in [(500, true), (2000, false)] {
+        let compiled = compile_contract(source, &[Expr::Int(min_value)], OPTIONS).expect("compile succeeds");
+        for function in ["spend", "check"] {
+            let sigscript = compiled.build_sig_script(function, vec![Expr::Int(1)]).expect("sigscript builds");
+            let result = run_script_with_sigscript(compiled.script.clone(), sigscript);
+            assert_eq!(result.is_ok(), passes, "{function} with minValue {min_value}: {result:?}");
+        }
+    }
+
+    let twice = source.replace("entrypoint function check", "constructor { require(minValue > 0); } entrypoint function check");
+    let err = compile_contract(&twice, &[Expr::Int(1)], OPTIONS).unwrap_err().to_string();
+    assert!(err.contains("only one constructor block"), "{err}");
+    let not_require = source.replace("require(tx.outputs[0].value >= minValue);", "int x = minValue;");
+    let err = compile_contract(&not_require, &[Expr::Int(1)], OPTIONS).unwrap_err().to_string();
+    assert!(err.contains("a constructor block can only contain require statements"), "{err}");
+}
+
+#[test]
+fn sig_script_pushes_concatenate_to_the_sigscript() {
+    let source = r#"
+        contract Pushes() {
+            entrypoint function first(int a) {
+                require(a > 0);
+            }
+
+            entrypoint function second(int n, bytes data, bool flag, int[] values) {
+                require(values.length <= n);
+                require(flag);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], OPTIONS).expect("compile succeeds");
+    let args = vec![Expr::Int(300), Expr::Bytes(vec![0xab; 3]), Expr::Bool(true), Expr::Array(vec![Expr::Int(1), Expr::Int(2)])];
+    let pushes = compiled.sig_script_pushes("second", args.clone()).expect("pushes encode");
+
+    let names: Vec<(&str, &str)> = pushes.iter().map(|push| (push.name.as_str(), push.type_name.as_str())).collect();
+    assert_eq!(names, vec![("n", "int"), ("data", "bytes"), ("flag", "bool"), ("values", "int[]"), ("selector", "int")]);
+    assert_eq!(pushes[0].push_hex, "022c01");
+    assert_eq!(pushes[1].push_hex, "03ababab");
+    assert_eq!(pushes[2].push_hex, "51");
+    assert_eq!(pushes[4].push_hex, "51");
+    let concatenated: String = pushes.iter().map(|push| push.push_hex.as_str()).collect();
+    assert_eq!(concatenated, faster_hex::hex_string(&compiled.build_sig_script("second", args).unwrap()));
+
+    let err = compiled.sig_script_pushes("second", vec![Expr::Int(1)]).unwrap_err().to_string();
+    assert!(err.contains("function 'second' expects 4 arguments, got 1"), "{err}");
+}
+
 #[test]
 fn rejects_double_underscore_variable_names() {
     let source = r#"
@@ -459,7 +711,43 @@ fn recursive_fibonacci_inlining_behavior() {
 
     let err = compile_contract(source, &[], CompileOptions::default()).expect_err("recursive call should fail");
     let err_msg = err.to_string();
-    assert!(err_msg.contains("unknown function"), "expected 'unknown function' error, got: {err_msg}");
+    assert!(err_msg.contains("recursive call cycle: fib -> fib"), "expected recursion error, got: {err_msg}");
+}
+
+#[test]
+fn rejects_mutual_recursion_with_cycle() {
+    let source = r#"
+        contract PingPong() {
+            function ping(int n) {
+                require(n >= 0);
+                pong(n - 1);
+            }
+
+            function pong(int n) {
+                if (n > 0) {
+                    ping(n - 1);
+                }
+            }
+
+            entrypoint function main(int n) {
+                ping(n);
+            }
+        }
+    "#;
+
+    match compile_contract(source, &[], CompileOptions::default()) {
+        Err(CompilerError::RecursiveCall { cycle, spans }) => {
+            assert_eq!(cycle, vec!["ping", "pong", "ping"]);
+            assert_eq!(spans.iter().map(|span| span.line).collect::<Vec<_>>(), vec![5, 10], "spans point at each call");
+        }
+        other => panic!("expected RecursiveCall, got {:?}", other.map(|compiled| compiled.contract_name)),
+    }
+
+    let graph = CallGraph::build(&parse_contract_ast(source).unwrap());
+    let dot = graph.to_dot();
+    assert!(dot.contains("\"main\" [shape=box];"));
+    assert!(dot.contains("\"main\" -> \"ping\";"));
+    assert!(dot.contains("\"pong\" -> \"ping\";"));
 }
 
 #[test]
@@ -863,6 +1151,253 @@ fn allows_array_inequality_with_different_sizes() {
     assert!(result.is_ok(), "array inequality runtime failed: {}", result.unwrap_err());
 }
 
+#[test]
+fn hardened_mode_rejects_overflowing_arithmetic() {
+    let source = r#"
+        contract Sum() {
+            entrypoint function main(int a, int b) {
+                require(a + b > 0);
+            }
+        }
+    "#;
+    let plain = compile_contract(source, &[], OPTIONS).expect("compile succeeds");
+    let hardened = compile_contract(source, &[], CompileOptions { hardened: true, ..OPTIONS }).expect("compile succeeds");
+    assert!(hardened.script.len() > plain.script.len(), "hardened checks should add bytecode");
+
+    let ok = hardened.build_sig_script("main", vec![Expr::Int(2), Expr::Int(3)]).expect("sigscript builds");
+    let result = run_script_with_sigscript(hardened.script.clone(), ok);
+    assert!(result.is_ok(), "in-range arithmetic should pass: {}", result.unwrap_err());
+
+    // The plain script hands this straight to OpAdd; the hardened one refuses before the sum leaves the int range.
+    let overflow = hardened.build_sig_script("main", vec![Expr::Int(i64::MAX - 1), Expr::Int(5)]).expect("sigscript builds");
+    assert!(run_script_with_sigscript(hardened.script, overflow).is_err(), "overflowing sum should be rejected");
+}
+
+#[test]
+fn hardened_mode_checks_slice_bounds_and_clean_stack() {
+    let source = r#"
+        contract Slicer() {
+            entrypoint function main(bytes data, int end) {
+                require(data.slice(1, end) == 0x0203);
+            }
+        }
+    "#;
+    let hardened = compile_contract(source, &[], CompileOptions { hardened: true, ..OPTIONS }).expect("compile succeeds");
+
+    let ok = hardened.build_sig_script("main", vec![vec![1u8, 2, 3].into(), Expr::Int(3)]).expect("sigscript builds");
+    let result = run_script_with_sigscript(hardened.script.clone(), ok.clone());
+    assert!(result.is_ok(), "in-bounds slice should pass: {}", result.unwrap_err());
+
+    let past_end = hardened.build_sig_script("main", vec![vec![1u8, 2, 3].into(), Expr::Int(4)]).expect("sigscript builds");
+    assert!(run_script_with_sigscript(hardened.script.clone(), past_end).is_err(), "slice past the end should be rejected");
+
+    let mut extra_item = ScriptBuilder::new();
+    extra_item.add_i64(7).unwrap();
+    let mut padded = extra_item.drain();
+    padded.extend(ok);
+    assert!(run_script_with_sigscript(hardened.script, padded).is_err(), "leftover stack items should be rejected");
+}
+
+#[test]
+fn concatenating_fixed_width_bytes_keeps_the_summed_width() {
+    let source = r#"
+        contract Commitment() {
+            entrypoint function main(bytes32 left, bytes32 right) {
+                bytes64 joined = left + right;
+                require(joined.slice(32, 64) == right);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], OPTIONS).expect("bytes32 + bytes32 is a bytes64");
+    let sigscript = compiled.build_sig_script("main", vec![vec![1u8; 32].into(), vec![2u8; 32].into()]).expect("sigscript builds");
+    let result = run_script_with_sigscript(compiled.script, sigscript);
+    assert!(result.is_ok(), "second half should match: {}", result.unwrap_err());
+
+    let narrow = source.replace("bytes64 joined", "bytes32 joined");
+    let err = compile_contract(&narrow, &[], OPTIONS).expect_err("width mismatch");
+    assert!(err.to_string().contains("variable 'joined' is declared bytes32 but its value is 64 bytes"), "unexpected error: {err}");
+
+    let past_end = source.replace("slice(32, 64)", "slice(33, 65)");
+    let err = compile_contract(&past_end, &[], OPTIONS).expect_err("slice past the known width");
+    assert!(err.to_string().contains("slice end 65 is outside a 64-byte value"), "unexpected error: {err}");
+
+    // A dynamic operand leaves the width unknown, so only runtime checks apply.
+    let dynamic = source.replace("bytes32 right", "bytes right").replace("slice(32, 64)", "slice(32, 65)");
+    compile_contract(&dynamic, &[], OPTIONS).expect("dynamic concatenation has no static width");
+}
+
+#[test]
+fn embedded_source_hash_is_appended_without_changing_behavior() {
+    let source = r#"
+        contract Provenance(int threshold) {
+            entrypoint function spend(int value) {
+                require(value > threshold);
+            }
+
+            entrypoint function burn() {
+                require(this.scriptSize > 0);
+            }
+        }
+    "#;
+    let ctor_args = [Expr::Int(10)];
+    let plain = compile_contract(source, &ctor_args, OPTIONS).expect("compile succeeds");
+    let options = CompileOptions { embed_source_hash: true, record_debug_infos: true, ..OPTIONS };
+    let embedded = compile_contract(source, &ctor_args, options).expect("compile succeeds");
+
+    let hash = source_hash(source);
+    assert_eq!(hash.to_vec(), blake2b_simd::Params::new().hash_length(32).hash(source.as_bytes()).as_bytes().to_vec());
+    let mut metadata = vec![OpFalse, OpIf, OpReturn, OpData32];
+    metadata.extend_from_slice(&hash);
+    metadata.push(OpEndIf);
+    assert_eq!(metadata.len(), SOURCE_HASH_METADATA_SIZE);
+    assert!(embedded.script.ends_with(&metadata), "script should end with the source hash metadata");
+    // `this.scriptSize` counts the metadata, so only the size constant differs from the plain script.
+    assert_eq!(embedded.script.len(), plain.script.len() + SOURCE_HASH_METADATA_SIZE);
+
+    let passing = embedded.build_sig_script("spend", vec![Expr::Int(11)]).expect("sigscript builds");
+    let result = run_script_with_sigscript(embedded.script.clone(), passing);
+    assert!(result.is_ok(), "spend should still pass: {}", result.unwrap_err());
+    let failing = embedded.build_sig_script("spend", vec![Expr::Int(9)]).expect("sigscript builds");
+    assert!(run_script_with_sigscript(embedded.script.clone(), failing).is_err(), "spend should still enforce its require");
+    let burn = embedded.build_sig_script("burn", vec![]).expect("sigscript builds");
+    let result = run_script_with_sigscript(embedded.script.clone(), burn);
+    assert!(result.is_ok(), "burn should still pass: {}", result.unwrap_err());
+
+    let contract = parse_contract_ast(source).unwrap();
+    let err = compile_contract_ast(&contract, &ctor_args, options).expect_err("no source to hash");
+    assert!(err.to_string().contains("embed_source_hash requires the contract source"), "unexpected error: {err}");
+}
+
+#[test]
+fn runs_in_operator_against_constant_set() {
+    let source = r#"
+        contract Allowlist() {
+            bytes3[] constant ALLOWED = [0x010203, 0x0a0b0c, 0xffeedd];
+
+            entrypoint function main(bytes3 key) {
+                require(key in ALLOWED);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], OPTIONS).expect("compile succeeds");
+
+    let member = compiled.build_sig_script("main", vec![vec![0x0au8, 0x0b, 0x0c].into()]).expect("sigscript builds");
+    let result = run_script_with_sigscript(compiled.script.clone(), member);
+    assert!(result.is_ok(), "member key should be accepted: {}", result.unwrap_err());
+
+    let non_member = compiled.build_sig_script("main", vec![vec![0x0au8, 0x0b, 0x0d].into()]).expect("sigscript builds");
+    assert!(run_script_with_sigscript(compiled.script, non_member).is_err(), "non-member key should be rejected");
+}
+
+#[test]
+fn runs_in_operator_with_inline_int_set() {
+    let source = r#"
+        contract Allowlist() {
+            entrypoint function main(int value) {
+                require(value in [3, 5, 8]);
+                require(!(value in [1, 2]));
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], OPTIONS).expect("compile succeeds");
+
+    let member = compiled.build_sig_script("main", vec![Expr::Int(5)]).expect("sigscript builds");
+    let result = run_script_with_sigscript(compiled.script.clone(), member);
+    assert!(result.is_ok(), "member value should be accepted: {}", result.unwrap_err());
+
+    let non_member = compiled.build_sig_script("main", vec![Expr::Int(4)]).expect("sigscript builds");
+    assert!(run_script_with_sigscript(compiled.script, non_member).is_err(), "non-member value should be rejected");
+}
+
+#[test]
+fn rejects_in_operator_with_dynamic_or_mismatched_set() {
+    let dynamic = r#"
+        contract Allowlist() {
+            entrypoint function main(int value, int other) {
+                require(value in [1, other]);
+            }
+        }
+    "#;
+    assert!(compile_contract(dynamic, &[], OPTIONS).is_err(), "dynamic set elements must be rejected");
+
+    let mismatched = r#"
+        contract Allowlist() {
+            entrypoint function main(bytes3 key) {
+                require(key in [1, 2]);
+            }
+        }
+    "#;
+    assert!(compile_contract(mismatched, &[], OPTIONS).is_err(), "set element types must match the key");
+}
+
+#[test]
+fn runs_bytes_keyed_map_lookup_with_default() {
+    let source = r#"
+        contract Router() {
+            map bytes => int routes {
+                0x01: 10,
+                0x02: 20,
+                default: 0,
+            }
+
+            entrypoint function main(bytes key, int expected) {
+                require(routes[key] == expected);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], OPTIONS).expect("compile succeeds");
+
+    let hit = compiled.build_sig_script("main", vec![vec![0x02u8].into(), Expr::Int(20)]).expect("sigscript builds");
+    let result = run_script_with_sigscript(compiled.script.clone(), hit);
+    assert!(result.is_ok(), "existing key should select its value: {}", result.unwrap_err());
+
+    let miss = compiled.build_sig_script("main", vec![vec![0x09u8].into(), Expr::Int(0)]).expect("sigscript builds");
+    let result = run_script_with_sigscript(compiled.script.clone(), miss);
+    assert!(result.is_ok(), "missing key should select the default: {}", result.unwrap_err());
+
+    let wrong = compiled.build_sig_script("main", vec![vec![0x01u8].into(), Expr::Int(20)]).expect("sigscript builds");
+    assert!(run_script_with_sigscript(compiled.script, wrong).is_err(), "lookup must return the key's own value");
+}
+
+#[test]
+fn rejects_invalid_map_definitions() {
+    let duplicate = r#"
+        contract Router() {
+            map bytes => int routes { 0x01: 10, 0x01: 20 }
+
+            entrypoint function main(bytes key) {
+                require(routes[key] == 10);
+            }
+        }
+    "#;
+    let err = compile_contract(duplicate, &[], OPTIONS).expect_err("duplicate keys");
+    assert!(err.to_string().contains("duplicate key 0x01"), "unexpected error: {err}");
+
+    let mistyped = r#"
+        contract Router() {
+            map bytes => int routes { 0x01: 0x0a }
+
+            entrypoint function main(bytes key) {
+                require(routes[key] == 10);
+            }
+        }
+    "#;
+    let err = compile_contract(mistyped, &[], OPTIONS).expect_err("value type mismatch");
+    assert!(err.to_string().contains("values must be constant int literals"), "unexpected error: {err}");
+
+    let wrong_key = r#"
+        contract Router() {
+            map bytes => int routes { 0x01: 10 }
+
+            entrypoint function main(int key) {
+                require(routes[key] == 10);
+            }
+        }
+    "#;
+    let err = compile_contract(wrong_key, &[], OPTIONS).expect_err("lookup key type mismatch");
+    assert!(err.to_string().contains("map lookup key must be of type bytes"), "unexpected error: {err}");
+}
+
 #[test]
 fn runs_array_for_loop_example() {
     let source = r#"
@@ -908,6 +1443,70 @@ fn runs_array_for_loop_with_length_guard() {
     assert!(result.is_ok(), "array for-loop length-guard runtime failed: {}", result.unwrap_err());
 }
 
+#[test]
+fn c_style_for_loop_unrolls_like_the_range_form() {
+    let source = r#"
+        contract Loops() {
+            int constant LAST = 3;
+
+            entrypoint function main(int[] x) {
+                for (int i = 0; i < 3; i = i + 1) {
+                    require(x[i] == i + 1);
+                }
+                for (int i = 1; i <= LAST - 1; i = i + 1) {
+                    require(x[i] > x[i - 1]);
+                }
+            }
+        }
+    "#;
+    let range_form = source
+        .replace("for (int i = 0; i < 3; i = i + 1)", "for (i, 0, 3)")
+        .replace("for (int i = 1; i <= LAST - 1; i = i + 1)", "for (i, 1, LAST)");
+    let options = CompileOptions { record_debug_infos: true, ..OPTIONS };
+    let compiled = compile_contract(source, &[], options).expect("compile succeeds");
+    let ranged = compile_contract(&range_form, &[], OPTIONS).expect("compile succeeds");
+    assert_eq!(compiled.script, ranged.script);
+
+    let sigscript = compiled.build_sig_script("main", vec![vec![1i64, 2i64, 3i64].into()]).expect("sigscript builds");
+    let result = run_script_with_sigscript(compiled.script.clone(), sigscript);
+    assert!(result.is_ok(), "unrolled loop runtime failed: {}", result.unwrap_err());
+
+    // Each unrolled pass records the counter at the loop header, in order.
+    let debug_info = compiled.debug_info.expect("debug info recorded");
+    let passes: Vec<(u32, Expr, u32)> = debug_info
+        .variable_updates
+        .iter()
+        .filter(|update| update.name == "i")
+        .map(|update| (update.span.expect("span").line, update.expr.clone(), update.sequence))
+        .collect();
+    let lines_and_values: Vec<(u32, Expr)> = passes.iter().map(|(line, value, _)| (*line, value.clone())).collect();
+    assert_eq!(lines_and_values, [(6, Expr::Int(0)), (6, Expr::Int(1)), (6, Expr::Int(2)), (9, Expr::Int(1)), (9, Expr::Int(2))]);
+    assert!(passes.windows(2).all(|pair| pair[0].2 < pair[1].2), "iterations get increasing sequences");
+
+    let bounded_by_param = source.replace("i < 3;", "i < x.length;");
+    match compile_contract(&bounded_by_param, &[], OPTIONS) {
+        Err(CompilerError::InvalidLoopBound { span: Some(span), .. }) => assert_eq!((span.line, span.col), (6, 17)),
+        other => panic!("expected a loop bound error at the header, got {other:?}"),
+    }
+    let overflowing = source.replace("i < 3;", "i < 9223372036854775807 + 1;");
+    match compile_contract(&overflowing, &[], OPTIONS) {
+        Err(CompilerError::InvalidLoopBound { reason, .. }) => assert_eq!(reason, "integer overflow in constant expression"),
+        other => panic!("expected an overflowing loop bound to be rejected, got {other:?}"),
+    }
+
+    let cases = [
+        ("i = i + 1) {\n                    require(x[i] == i + 1);", "i = i + 2) {\n                    require(x[i] == i + 1);"),
+        ("for (int i = 0; i < 3;", "for (bool i = 0; i < 3;"),
+        ("for (int i = 0; i < 3;", "for (int i = 0; x < 3;"),
+    ];
+    let expected =
+        ["for loop must step with i = i + 1", "for loop counter 'i' must be an int", "for loop condition must test the counter 'i'"];
+    for ((from, to), expected) in cases.into_iter().zip(expected) {
+        let err = compile_contract(&source.replacen(from, to, 1), &[], OPTIONS).expect_err("malformed header rejected");
+        assert!(err.to_string().contains(expected), "expected '{expected}', got: {err}");
+    }
+}
+
 #[test]
 fn runs_array_loop_and_function_calls_example() {
     let source = r#"
@@ -1055,6 +1654,64 @@ fn locking_bytecode_p2sh_from_redeem_script_matches_pay_to_script_hash_script()
     assert!(result.is_ok(), "p2sh-from-redeem-script locking bytecode mismatch: {}", result.unwrap_err());
 }
 
+#[test]
+fn p2sh_script_public_key_wraps_the_script_hash_and_spends() {
+    let source = r#"
+        contract Gate() {
+            entrypoint function main(int a) {
+                require(a == 7);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], OPTIONS).expect("compile succeeds");
+    assert_eq!(compiled.to_script_public_key(0), ScriptPublicKey::new(0, compiled.script.clone().into()));
+    let p2sh = compiled.to_p2sh_script_public_key().expect("p2sh script builds");
+    assert_eq!(p2sh, pay_to_script_hash_script(&compiled.script));
+
+    let spend = |a: i64| {
+        let mut sigscript = compiled.build_sig_script("main", vec![a.into()]).expect("sigscript builds");
+        sigscript.extend(ScriptBuilder::new().add_data(&compiled.script).unwrap().drain());
+        let input = TransactionInput {
+            previous_outpoint: TransactionOutpoint { transaction_id: TransactionId::from_bytes([2u8; 32]), index: 0 },
+            signature_script: sigscript,
+            sequence: 0,
+            sig_op_count: 0,
+        };
+        let output = TransactionOutput { value: 1000, script_public_key: p2sh.clone(), covenant: None };
+        let tx = Transaction::new(1, vec![input.clone()], vec![output.clone()], 0, Default::default(), 0, vec![]);
+        let utxo_entry = UtxoEntry::new(output.value, p2sh.clone(), 0, tx.is_coinbase(), None);
+        let populated_tx = PopulatedTransaction::new(&tx, vec![utxo_entry.clone()]);
+        let reused_values = SigHashReusedValuesUnsync::new();
+        let sig_cache = Cache::new(10_000);
+        let ctx = EngineCtx::new(&sig_cache).with_reused(&reused_values);
+        TxScriptEngine::from_transaction_input(&populated_tx, &input, 0, &utxo_entry, ctx, EngineFlags { covenants_enabled: true })
+            .execute()
+    };
+    assert!(spend(7).is_ok(), "p2sh spend should pass");
+    assert!(spend(8).is_err(), "p2sh spend should run the contract");
+}
+
+#[test]
+fn p2sh_script_public_key_commits_to_the_script_hash_and_rejects_unspendable_scripts() {
+    let source = r#"
+        contract Gate() {
+            entrypoint function main(int a) {
+                require(a == 7);
+            }
+        }
+    "#;
+    let mut compiled = compile_contract(source, &[], OPTIONS).expect("compile succeeds");
+    let p2sh = compiled.to_p2sh_script_public_key().expect("p2sh script builds");
+    assert_eq!(p2sh.script()[2..34], compiled.script_hash());
+
+    compiled.script = vec![];
+    let err = compiled.to_p2sh_script_public_key().unwrap_err().to_string();
+    assert!(err.contains("cannot build a p2sh script for an empty script"), "{err}");
+    compiled.script = vec![OpTrue; MAX_SCRIPT_SIZE + 1];
+    let err = compiled.to_p2sh_script_public_key().unwrap_err().to_string();
+    assert!(err.contains("scripts are limited to 10000 bytes"), "{err}");
+}
+
 fn run_script_with_tx_and_covenants(
     script: Vec<u8>,
     tx: Transaction,
@@ -2461,3 +3118,796 @@ fn compiles_sigscript_reused_inputs_and_fails_on_wrong_value() {
     let result = run_script_with_sigscript(compiled.script, sigscript);
     assert!(result.is_err());
 }
+
+#[test]
+fn enforces_max_mass_annotation_budget() {
+    let within = r#"
+        contract Budget() {
+            @maxMass(1500)
+            entrypoint function spend(sig s, pubkey pk) {
+                require(checkSig(s, pk));
+            }
+        }
+    "#;
+    assert!(compile_contract(within, &[], OPTIONS).is_ok(), "function within its mass budget should compile");
+
+    let exceeding = r#"
+        contract Budget() {
+            @maxMass(500)
+            entrypoint function spend(sig s, pubkey pk) {
+                require(checkSig(s, pk));
+            }
+        }
+    "#;
+    match compile_contract(exceeding, &[], OPTIONS) {
+        Err(CompilerError::MassBudgetExceeded { function, computed, allowed }) => {
+            assert_eq!(function, "spend");
+            assert_eq!(allowed, 500);
+            assert!(computed > 1000, "the signature check alone costs 1000: {computed}");
+        }
+        other => panic!("expected MassBudgetExceeded, got {other:?}"),
+    }
+}
+
+#[test]
+fn max_mass_covers_the_selector_dispatch_of_the_entrypoint() {
+    let body_mass = |source: &str| match compile_contract(source, &[], OPTIONS) {
+        Err(CompilerError::MassBudgetExceeded { computed, .. }) => computed,
+        other => panic!("expected MassBudgetExceeded, got {other:?}"),
+    };
+    let alone = r#"
+        contract Budget() {
+            @maxMass(0)
+            entrypoint function spend(sig s, pubkey pk) {
+                require(checkSig(s, pk));
+            }
+        }
+    "#;
+    let mass = body_mass(alone);
+
+    // The same function behind a selector also pays for the dispatch guard around it.
+    let dispatched = format!(
+        r#"
+        contract Budget() {{
+            @maxMass({mass})
+            entrypoint function spend(sig s, pubkey pk) {{
+                require(checkSig(s, pk));
+            }}
+            entrypoint function other() {{
+                require(true);
+            }}
+        }}
+    "#
+    );
+    assert!(body_mass(&dispatched) > mass, "the dispatched path costs more than the function script");
+}
+
+#[test]
+fn rejects_unknown_function_annotation() {
+    let source = r#"
+        contract Budget() {
+            @fastPath
+            entrypoint function main() {
+                require(true);
+            }
+        }
+    "#;
+    let err = compile_contract(source, &[], OPTIONS).expect_err("unknown annotation");
+    assert!(err.to_string().contains("unknown annotation '@fastPath'"));
+}
+
+#[test]
+fn runs_xor_bytes_and_checks_lengths() {
+    let source = r#"
+        contract Pad() {
+            entrypoint function main(bytes a, bytes b) {
+                require(xorBytes(a, b) == 0xf00f);
+                require(xorBytes(0x0f0f, 0xff00) == 0xf00f);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], OPTIONS).expect("compile succeeds");
+
+    let sigscript =
+        compiled.build_sig_script("main", vec![vec![0x0fu8, 0x0f].into(), vec![0xffu8, 0x00].into()]).expect("sigscript builds");
+    let result = run_script_with_sigscript(compiled.script.clone(), sigscript);
+    assert!(result.is_ok(), "xorBytes runtime failed: {}", result.unwrap_err());
+
+    let mismatched =
+        compiled.build_sig_script("main", vec![vec![0x0fu8, 0x0f].into(), vec![0xffu8].into()]).expect("sigscript builds");
+    assert!(run_script_with_sigscript(compiled.script, mismatched).is_err(), "runtime length mismatch should fail");
+
+    let constant_mismatch = r#"
+        contract Pad() {
+            entrypoint function main() {
+                require(xorBytes(0x0f0f, 0xff) == 0xf0);
+            }
+        }
+    "#;
+    let err = compile_contract(constant_mismatch, &[], OPTIONS).expect_err("constant length mismatch");
+    assert!(err.to_string().contains("equal length"), "unexpected error: {err}");
+}
+
+#[test]
+fn head_and_tail_take_leading_and_trailing_bytes() {
+    let source = r#"
+        contract Parse() {
+            entrypoint function main(bytes data, int n) {
+                bytes4 prefix = head(data, 4);
+                require(prefix == 0x12345678);
+                require(tail(data, 2) == 0xcdef);
+                bytes first = head(data, n);
+                require(first.length == n);
+                require(tail(data, n) == data.split(data.length - n)[1]);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], OPTIONS).expect("compile succeeds");
+    let data = vec![0x12u8, 0x34, 0x56, 0x78, 0x90, 0xab, 0xcd, 0xef];
+
+    for n in [0, 3, 8] {
+        let sigscript = compiled.build_sig_script("main", vec![data.clone().into(), Expr::Int(n)]).expect("sigscript builds");
+        let result = run_script_with_sigscript(compiled.script.clone(), sigscript);
+        assert!(result.is_ok(), "head/tail with n = {n} failed: {}", result.unwrap_err());
+    }
+    for n in [9, -1] {
+        let sigscript = compiled.build_sig_script("main", vec![data.clone().into(), Expr::Int(n)]).expect("sigscript builds");
+        assert!(run_script_with_sigscript(compiled.script.clone(), sigscript).is_err(), "n = {n} is outside the data");
+    }
+
+    let too_long = r#"
+        contract Parse() {
+            entrypoint function main(bytes4 word) {
+                require(tail(word, 5) == 0x0102030405);
+            }
+        }
+    "#;
+    let err = compile_contract(too_long, &[], OPTIONS).expect_err("count exceeds the width");
+    assert!(err.to_string().contains("tail() takes 5 bytes of a 4-byte value"), "unexpected error: {err}");
+
+    let wrong_width = r#"
+        contract Parse() {
+            entrypoint function main(bytes data) {
+                bytes4 prefix = head(data, 3);
+                require(prefix.length == 4);
+            }
+        }
+    "#;
+    let err = compile_contract(wrong_width, &[], OPTIONS).expect_err("head(data, 3) is a bytes3");
+    assert!(err.to_string().contains("is declared bytes4 but its value is 3 bytes"), "unexpected error: {err}");
+}
+
+#[test]
+fn rejects_tweak_pubkey_on_kaspa() {
+    let source = r#"
+        contract Commit(pubkey internal) {
+            entrypoint function main(bytes32 commitment, sig s) {
+                pubkey output = tweakPubkey(internal, commitment);
+                require(checkSig(s, output));
+            }
+        }
+    "#;
+    let err = compile_contract(source, &[Expr::Bytes(vec![0x02; 32])], OPTIONS).expect_err("tweakPubkey is unavailable");
+    assert!(err.to_string().contains("tweakPubkey() is not supported"), "unexpected error: {err}");
+}
+
+#[test]
+fn reports_used_constructor_params() {
+    let source = r#"
+        contract Params(int used, int unused, int shadowed, int viaHelper) {
+            function helper(int x) : (int) {
+                return(x + viaHelper);
+            }
+
+            entrypoint function main(int shadowed) {
+                (int r) = helper(shadowed);
+                require(r == used);
+            }
+        }
+    "#;
+    let ctor_args = vec![Expr::Int(1), Expr::Int(2), Expr::Int(3), Expr::Int(4)];
+    let compiled = compile_contract(source, &ctor_args, OPTIONS).expect("compile succeeds");
+    assert_eq!(compiled.used_ctor_params, vec![true, false, false, true]);
+}
+
+#[test]
+fn compile_result_bundles_consistent_outputs() {
+    let source = r#"
+        contract Vault(pubkey owner, int unused) {
+            entrypoint function spend(sig s) {
+                require(checkSig(s, owner));
+            }
+
+            entrypoint function burn() {
+                require(true);
+            }
+        }
+    "#;
+    let ctor_args = vec![Expr::Bytes(vec![0x02; 32]), Expr::Int(7)];
+    let options = CompileOptions { record_debug_infos: true, ..OPTIONS };
+    let result = compile(source, &ctor_args, options).expect("compile succeeds");
+    let compiled = compile_contract(source, &ctor_args, options).expect("compile succeeds");
+
+    assert_eq!(result.contract_name, "Vault");
+    assert_eq!(result.script, compiled.script);
+    assert_eq!(result.abi, compiled.abi);
+    assert_eq!(result.metrics, compiled.metrics());
+    assert_eq!(result.metrics.script_size, result.script.len());
+    assert_eq!(result.metrics.sig_ops, 1);
+    assert_eq!(result.metrics.mass, mass::estimate_script_mass(&result.script));
+    assert_eq!(
+        result.warnings,
+        vec![
+            "constructor param 'unused' is never used".to_string(),
+            "require(true) on line 8 always passes and only adds bytes; remove it".to_string(),
+        ]
+    );
+
+    let debug_info = result.debug_info.expect("debug info recorded");
+    assert_eq!(debug_info.functions.len(), result.abi.len());
+    assert!(debug_info.functions.iter().all(|function| function.bytecode_end <= result.script.len()));
+}
+
+#[test]
+fn lines_debug_level_keeps_one_mapping_per_statement_line() {
+    let source = r#"
+        contract Ledger(int base) {
+            function scaled(int value) : (int) {
+                int doubled = value * 2;
+                return (doubled + base);
+            }
+
+            entrypoint function spend(int a, int b) {
+                int total = a + b; int half = total / 2;
+                log("half", half);
+                (int result) = scaled(half);
+                require(result > base);
+            }
+        }
+    "#;
+    let ctor_args = [Expr::Int(3)];
+    let full = compile_contract(source, &ctor_args, CompileOptions { record_debug_infos: true, ..OPTIONS }).expect("compile succeeds");
+    let lines_options = CompileOptions { record_debug_infos: true, debug_level: DebugLevel::Lines, ..OPTIONS };
+    let lines = compile_contract(source, &ctor_args, lines_options).expect("compile succeeds");
+    assert_eq!(full.script, lines.script, "the debug level never changes the script");
+
+    let full_info = full.debug_info.expect("debug info recorded");
+    let lines_info = lines.debug_info.expect("debug info recorded");
+    let size = |info: &DebugInfo| serde_json::to_string(info).expect("debug info serializes").len();
+    assert!(size(&lines_info) < size(&full_info));
+    assert!(lines_info.mappings.len() < full_info.mappings.len());
+    assert!(lines_info.variable_updates.is_empty() && lines_info.log_points.is_empty());
+    let is_statement = |kind: &MappingKind| matches!(kind, MappingKind::Statement {} | MappingKind::Virtual {});
+    assert!(lines_info.mappings.iter().all(|mapping| is_statement(&mapping.kind)));
+    assert_eq!(lines_info.params.len(), full_info.params.len());
+    assert_eq!(lines_info.checks.len(), full_info.checks.len());
+
+    let statement_lines = |info: &DebugInfo| {
+        let mut lines: Vec<u32> = info
+            .mappings
+            .iter()
+            .filter(|mapping| is_statement(&mapping.kind))
+            .filter_map(|mapping| mapping.span.map(|span| span.line))
+            .collect();
+        lines.dedup();
+        lines
+    };
+    assert_eq!(statement_lines(&lines_info), statement_lines(&full_info));
+    let line_9 = lines_info.mappings.iter().filter(|mapping| mapping.span.is_some_and(|span| span.line == 9)).count();
+    assert_eq!(line_9, 1, "both statements on line 9 share one mapping");
+}
+
+#[test]
+fn always_true_requires_warn_and_are_dropped_when_optimizing() {
+    let source = r#"
+        contract Scaffold(int threshold) {
+            entrypoint function spend(int a) {
+                require(true);
+                require(a > threshold);
+                if (a > 10) {
+                    require(1 == 1 && !false);
+                }
+                require(0x01 != 0x02);
+            }
+        }
+    "#;
+    let ctor_args = [Expr::Int(3)];
+    let compiled = compile_contract(source, &ctor_args, OPTIONS).expect("compile succeeds");
+    assert_eq!(
+        compiled.warnings(),
+        vec![
+            "require(true) on line 4 always passes and only adds bytes; remove it",
+            "require(1 == 1 && !false) on line 7 always passes and only adds bytes; remove it",
+            "require(0x01 != 0x02) on line 9 always passes and only adds bytes; remove it",
+        ]
+    );
+
+    let optimized = compile_contract(source, &ctor_args, CompileOptions { optimize: true, ..OPTIONS }).expect("compile succeeds");
+    let lean_source =
+        source.replace("require(true);", "").replace("require(1 == 1 && !false);", "").replace("require(0x01 != 0x02);", "");
+    let lean = compile_contract(&lean_source, &ctor_args, CompileOptions { optimize: true, ..OPTIONS }).expect("compile succeeds");
+    assert_eq!(optimized.script, lean.script, "no-op requires are left out of the optimized script");
+    assert!(compiled.script.len() > optimized.script.len());
+    assert!(lean.warnings().is_empty());
+
+    // Conditions that depend on a param are never folded away.
+    let sigscript = optimized.build_sig_script("spend", vec![Expr::Int(2)]).expect("sigscript builds");
+    assert!(run_script_with_sigscript(optimized.script, sigscript).is_err(), "the real check is kept");
+}
+
+#[test]
+fn optimize_folds_constant_arithmetic_and_notes_it_in_debug_info() {
+    let source = r#"
+        contract Fold(int left) {
+            entrypoint function spend(int a) {
+                int right = left * 2;
+                require(a + right == left * 2 + 6 - 1);
+            }
+        }
+    "#;
+    let ctor_args = [Expr::Int(5)];
+    let options = CompileOptions { optimize: true, record_debug_infos: true, ..OPTIONS };
+    let optimized = compile_contract(source, &ctor_args, options).expect("compile succeeds");
+    let literal_source = source.replace("left * 2;", "10;").replace("left * 2 + 6 - 1", "15");
+    let literal = compile_contract(&literal_source, &ctor_args, options).expect("compile succeeds");
+    assert_eq!(optimized.script, literal.script, "constant subexpressions are pushed as their value");
+    let unoptimized = compile_contract(source, &ctor_args, OPTIONS).expect("compile succeeds");
+    assert!(unoptimized.script.len() > optimized.script.len());
+
+    for (a, passes) in [(5, true), (4, false)] {
+        let sigscript = optimized.build_sig_script("spend", vec![Expr::Int(a)]).expect("sigscript builds");
+        assert_eq!(run_script_with_sigscript(optimized.script.clone(), sigscript).is_ok(), passes, "a = {a}");
+    }
+
+    let folds = optimized.debug_info.expect("debug info recorded").folds;
+    let notes: Vec<(u32, &str, &Expr)> =
+        folds.iter().map(|fold| (fold.span.map(|span| span.line).unwrap_or_default(), fold.expr.as_str(), &fold.value)).collect();
+    assert_eq!(notes, vec![(4, "left * 2", &Expr::Int(10)), (5, "left * 2 + 6 - 1", &Expr::Int(15))]);
+
+    // Overflow and division by zero keep their run-time behavior, so they are not folded.
+    for expr in ["left * 4611686018427387904", "left / 0"] {
+        let source = source.replace("left * 2;", &format!("{expr};"));
+        let optimized = compile_contract(&source, &ctor_args, CompileOptions { optimize: true, ..OPTIONS }).expect("compile succeeds");
+        let plain = compile_contract(&source, &ctor_args, OPTIONS).expect("compile succeeds");
+        assert!(optimized.script.len() < plain.script.len(), "{expr}: the require side still folds");
+        assert!(optimized.script.contains(&if expr.contains('*') { OpMul } else { OpDiv }), "{expr} is computed at run time");
+    }
+}
+
+#[test]
+fn contract_abi_lists_entrypoints_with_selectors() {
+    let source = r#"
+        contract Vault(pubkey owner) {
+            function helper(int x) : (int) {
+                return(x + 1);
+            }
+
+            entrypoint function spend(sig s) {
+                require(checkSig(s, owner));
+            }
+
+            entrypoint function burn(int amount, bytes memo) {
+                require(amount > 0);
+                require(memo.length < 80);
+            }
+        }
+    "#;
+    let abi = contract_abi(source, None).expect("abi builds");
+    assert_eq!(abi.contract_name, "Vault");
+    assert_eq!(abi.compile_error, None);
+    let names: Vec<_> = abi.entrypoints.iter().map(|entry| entry.name.as_str()).collect();
+    assert_eq!(names, vec!["spend", "burn"]);
+    let selectors: Vec<_> = abi.entrypoints.iter().map(|entry| entry.selector).collect();
+    assert_eq!(selectors, vec![Some(0), Some(1)]);
+    let contract = parse_contract_ast(source).unwrap();
+    assert_eq!(abi.entrypoints[1].selector, Some(function_branch_index(&contract, "burn").unwrap()));
+    assert_eq!(abi.entrypoints[1].inputs.len(), 2);
+
+    let broken = r#"
+        contract Broken() {
+            entrypoint function main(int a) {
+                require(frobnicate(a));
+            }
+        }
+    "#;
+    let abi = contract_abi(broken, None).expect("outline still builds");
+    assert_eq!(abi.entrypoints.len(), 1);
+    assert_eq!(abi.entrypoints[0].selector, None);
+    assert!(abi.compile_error.as_deref().is_some_and(|err| err.contains("frobnicate")), "compile error should be reported");
+}
+
+#[test]
+fn abi_dispatch_section_describes_the_selector_push() {
+    let source = r#"
+        contract Router() {
+            entrypoint function low(int a) {
+                require(a > 0);
+            }
+
+            @selector(20)
+            entrypoint function high(int a, bytes b) {
+                require(a > b.length);
+            }
+        }
+    "#;
+    let abi = contract_abi(source, None).expect("abi builds");
+    let dispatch = abi.dispatch.selector.as_ref().expect("two entrypoints push a selector");
+    assert_eq!(dispatch.position, SelectorPosition::AfterArgs);
+    assert_eq!(dispatch.encoding, SelectorEncoding::ScriptNum);
+    assert_eq!(dispatch.valid_selectors, vec![0, 20]);
+
+    // A wallet following the description alone produces the same sigscript as `build_sig_script`.
+    let compiled = compile_contract(source, &[], OPTIONS).expect("compile succeeds");
+    let args = vec![Expr::Int(300), Expr::Bytes(vec![1, 2])];
+    let entry = abi.entrypoints.iter().find(|entry| entry.name == "high").unwrap();
+    let mut external = ScriptBuilder::new();
+    external.add_i64(300).unwrap();
+    external.add_data(&[1, 2]).unwrap();
+    external.add_i64(entry.selector.expect("selector")).unwrap();
+    assert_eq!(compiled.build_sig_script("high", args).unwrap(), external.drain());
+
+    let json = serde_json::to_value(&abi).unwrap();
+    assert_eq!(json["dispatch"]["selector"]["position"], "after_args");
+    assert_eq!(json["dispatch"]["selector"]["encoding"], "script_num");
+
+    let single = r#"
+        contract Single() {
+            entrypoint function only(int a) {
+                require(a > 0);
+            }
+        }
+    "#;
+    let abi = contract_abi(single, None).expect("abi builds");
+    assert_eq!(abi.dispatch.selector, None);
+    let compiled = compile_contract(single, &[], OPTIONS).expect("compile succeeds");
+    let mut external = ScriptBuilder::new();
+    external.add_i64(7).unwrap();
+    assert_eq!(compiled.build_sig_script("only", vec![Expr::Int(7)]).unwrap(), external.drain());
+}
+
+#[test]
+fn abi_lists_unconditional_requires_as_preconditions() {
+    let source = r#"
+        contract Escrow(pubkey buyer, int deadline) {
+            entrypoint function release(sig s, int left, int right, bytes memo) {
+                require(checkSig(s, buyer));
+                require(right >= left && left >= 0, "bad range");
+                require((left + right) * 2 < 100);
+                require(memo.slice(0, 2) == 0x6869);
+                require(tx.time >= deadline);
+                if (left > 5) {
+                    require(right > 10);
+                }
+            }
+
+            entrypoint function refund(@scale(2) int fee) {
+                require(fee <= 1.50);
+                require(tx.outputs[0].value >= -fee);
+            }
+        }
+    "#;
+    let abi = contract_abi(source, None).expect("abi builds");
+    assert_eq!(
+        abi.entrypoints[0].preconditions,
+        vec![
+            "checkSig(s, buyer)",
+            "right >= left && left >= 0",
+            "(left + right) * 2 < 100",
+            "memo.slice(0, 2) == 0x6869",
+            "tx.time >= deadline",
+        ]
+    );
+    assert_eq!(abi.entrypoints[1].preconditions, vec!["fee <= 1.50", "tx.outputs[0].value >= -fee"]);
+
+    let ctor_args = vec![Expr::Bytes(vec![2; 32]), Expr::Int(500_000)];
+    let compiled = compile_contract(source, &ctor_args, CompileOptions::default()).expect("compile succeeds");
+    assert_eq!(compiled.abi[1].preconditions, abi.entrypoints[1].preconditions);
+    let json = serde_json::to_string(&compiled.abi[1]).unwrap();
+    assert!(json.contains(r#""preconditions":["fee <= 1.50","#), "preconditions should be serialized: {json}");
+}
+
+#[test]
+fn selector_lock_rejects_reordered_entrypoints_unless_pinned() {
+    let original = r#"
+        contract Upgradable() {
+            entrypoint function deposit(int a) {
+                require(a > 0);
+            }
+
+            entrypoint function withdraw(int a) {
+                require(a < 10);
+            }
+        }
+    "#;
+    let reordered = r#"
+        contract Upgradable() {
+            entrypoint function withdraw(int a) {
+                require(a < 10);
+            }
+
+            entrypoint function deposit(int a) {
+                require(a > 0);
+            }
+        }
+    "#;
+    let original_ast = parse_contract_ast(original).unwrap();
+    let reordered_ast = parse_contract_ast(reordered).unwrap();
+    assert_eq!(function_branch_index(&original_ast, "withdraw").unwrap(), 1);
+    assert_eq!(function_branch_index(&reordered_ast, "withdraw").unwrap(), 0, "without a lock, reordering shifts selectors");
+
+    let lock = check_selector_lock(&original_ast, &SelectorLock::new()).expect("empty lock accepts anything");
+    assert_eq!(lock, SelectorLock::from([("deposit".to_string(), 0), ("withdraw".to_string(), 1)]));
+    let err = check_selector_lock(&reordered_ast, &lock).expect_err("lock catches the shift");
+    assert!(err.to_string().contains("@selector(1)"), "error should suggest a pin: {err}");
+
+    let pinned = reordered
+        .replace("entrypoint function withdraw", "@selector(1) entrypoint function withdraw")
+        .replace("entrypoint function deposit", "@selector(0) entrypoint function deposit");
+    let pinned_ast = parse_contract_ast(&pinned).unwrap();
+    assert_eq!(check_selector_lock(&pinned_ast, &lock).unwrap(), lock);
+
+    let body = original.trim_end().strip_suffix('}').unwrap();
+    let extended = format!("{body}    entrypoint function audit(int a) {{ require(a == 7); }}\n}}");
+    let extended_ast = parse_contract_ast(&extended).unwrap();
+    let updated = check_selector_lock(&extended_ast, &lock).expect("new entrypoints are appended");
+    assert_eq!(updated.get("audit"), Some(&2));
+
+    let compiled = compile_contract(&pinned, &[], CompileOptions::default()).expect("pinned contract compiles");
+    let sigscript = compiled.build_sig_script("withdraw", vec![Expr::Int(3)]).unwrap();
+    assert_eq!(sigscript.last(), Some(&OpTrue), "withdraw keeps selector 1");
+}
+
+fn run_with_output_spk(compiled: &CompiledContract, output_spk: ScriptPublicKey) -> Result<(), kaspa_txscript_errors::TxScriptError> {
+    let sigscript = selector_sigscript(selector_for(compiled, "spend"));
+    let input = TransactionInput::new(TransactionOutpoint::new(Hash::from_u64_word(1), 0), sigscript, 0, 0);
+    let output = TransactionOutput { value: 900, script_public_key: output_spk, covenant: None };
+    let tx = Transaction::new(1, vec![input], vec![output], 0, SubnetworkId::from_bytes([0u8; 20]), 0, vec![]);
+    let entry = UtxoEntry::new(1_000, ScriptPublicKey::new(0, compiled.script.clone().into()), 0, false, None);
+    run_script_with_tx_and_covenants(compiled.script.clone(), tx, vec![entry], None)
+}
+
+#[test]
+fn require_covenant_accepts_only_outputs_carrying_the_same_script() {
+    let source = r#"
+        contract Perpetual(int fee) {
+            entrypoint function spend() {
+                requireCovenant(0);
+                require(tx.outputs[0].value >= tx.inputs[this.activeInputIndex].value - fee);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[Expr::Int(100)], OPTIONS).expect("compile succeeds");
+
+    let same = ScriptPublicKey::new(0, compiled.script.clone().into());
+    assert!(run_with_output_spk(&compiled, same).is_ok());
+
+    let other = ScriptPublicKey::new(0, b"elsewhere".to_vec().into());
+    assert!(run_with_output_spk(&compiled, other).is_err());
+
+    let err = compile_contract(&source.replace("requireCovenant(0)", "requireCovenant()"), &[Expr::Int(100)], OPTIONS)
+        .expect_err("missing index should fail");
+    assert!(err.to_string().contains("requireCovenant() expects a single output index"));
+}
+
+#[test]
+fn require_all_outputs_match_takes_an_optional_byte_string() {
+    let source = r#"
+        contract FanOut(bytes payee) {
+            entrypoint function split() {
+                requireAllOutputsMatch(payee);
+            }
+        }
+    "#;
+    let ctor_args = [Expr::Bytes(vec![0x00, 0x00, 0x51])];
+    assert!(compile_contract(source, &ctor_args, OPTIONS).is_ok());
+    assert!(compile_contract(&source.replace("(payee);", "();"), &ctor_args, OPTIONS).is_ok());
+    for call in ["(1);", "(payee, payee);"] {
+        let err = compile_contract(&source.replace("(payee);", call), &ctor_args, OPTIONS).expect_err("bad arguments should fail");
+        assert!(err.to_string().contains("requireAllOutputsMatch() expects no arguments or a single locking script"));
+    }
+}
+
+#[test]
+fn verify_arg_layout_matches_codegen_and_catches_swapped_args() {
+    let source = r#"
+        contract Layout(int threshold) {
+            entrypoint function spend(int a, int b, bytes32 tag, pubkey[] keys) {
+                require(a + b > threshold);
+                require(tag.length == 32);
+                require(keys.length > 0);
+            }
+
+            entrypoint function reclaim(sig s, pubkey pk) {
+                require(checkSig(s, pk));
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[Expr::Int(1)], OPTIONS).expect("compile succeeds");
+    compiled.verify_arg_layout().expect("sigscript layout matches the compiled script");
+
+    let tag = Expr::Bytes(vec![7u8; 32]);
+    let keys = Expr::Array(vec![Expr::Bytes(vec![1u8; 32])]);
+    let args = vec![Expr::Int(1), Expr::Int(2), tag.clone(), keys.clone()];
+    let sigscript = compiled.build_sig_script("spend", args.clone()).expect("sigscript builds");
+    compiled.verify_sig_script_layout("spend", &args, &sigscript).expect("matching sigscript passes");
+
+    let swapped = compiled.build_sig_script("spend", vec![Expr::Int(2), Expr::Int(1), tag, keys]).expect("sigscript builds");
+    let err = compiled.verify_sig_script_layout("spend", &args, &swapped).expect_err("swapped args must be caught");
+    assert!(err.to_string().contains("param 'a' of 'spend' is read from stack slot 3"), "unexpected error: {err}");
+}
+
+#[test]
+fn covenant_only_require_is_dropped_for_targets_without_covenants() {
+    let source = r#"
+        contract Guarded(int minValue) {
+            entrypoint function spend() {
+                @covenantOnly require(tx.outputs[0].lockingBytecode == this.activeBytecode);
+                require(tx.outputs[0].value >= minValue);
+            }
+        }
+    "#;
+    let ctor_args = [Expr::Int(500)];
+    let with_covenants = compile_contract(source, &ctor_args, OPTIONS).expect("covenant target compiles");
+    let without_covenants =
+        compile_contract(source, &ctor_args, CompileOptions { covenants_disabled: true, ..OPTIONS }).expect("plain target compiles");
+    assert!(with_covenants.script.len() > without_covenants.script.len(), "the covenant check adds to the script");
+
+    let plain_source = source.replace("@covenantOnly require(tx.outputs[0].lockingBytecode == this.activeBytecode);", "");
+    let plain = compile_contract(&plain_source, &ctor_args, OPTIONS).expect("compile succeeds");
+    assert_eq!(without_covenants.script, plain.script, "the annotated require is omitted entirely");
+
+    let err = compile_contract(&source.replace("@covenantOnly", "@mainnetOnly"), &ctor_args, OPTIONS).expect_err("unknown annotation");
+    assert!(err.to_string().contains("unknown require annotation '@mainnetOnly'"), "unexpected error: {err}");
+}
+
+#[test]
+fn decimal_literals_compile_to_ints_at_the_param_scale() {
+    let scaled = r#"
+        contract Price(@scale(2) int floor) {
+            entrypoint function spend(@scale(2) int price) {
+                require(price >= 1.5);
+                require(price - floor <= 0.25);
+            }
+        }
+    "#;
+    let plain = r#"
+        contract Price(int floor) {
+            entrypoint function spend(int price) {
+                require(price >= 150);
+                require(price - floor <= 25);
+            }
+        }
+    "#;
+    let decimal = compile_contract(scaled, &[Expr::Decimal { digits: 125, scale: 2 }], OPTIONS).expect("scaled source compiles");
+    let int = compile_contract(plain, &[Expr::Int(125)], OPTIONS).expect("plain source compiles");
+    assert_eq!(decimal.script, int.script, "1.50-style literals lower to the same ints");
+    assert_eq!(decimal.abi[0].inputs[0].scale, Some(2));
+
+    let sigscript = decimal.build_sig_script("spend", vec![Expr::Decimal { digits: 16, scale: 1 }]).expect("decimal arg scales");
+    assert_eq!(sigscript, int.build_sig_script("spend", vec![Expr::Int(160)]).expect("int arg"));
+    assert!(run_script_with_sigscript(decimal.script.clone(), sigscript).is_ok());
+}
+
+#[test]
+fn mixed_scale_arithmetic_is_rejected() {
+    let compile_body = |body: &str| {
+        let source = format!("contract Mixed() {{ entrypoint function spend(@scale(2) int a, @scale(4) int b) {{ {body} }} }}");
+        compile_contract(&source, &[], OPTIONS).map(|_| ()).map_err(|err| err.to_string())
+    };
+    assert!(compile_body("require(a > 1.05);").is_ok());
+    assert!(compile_body("require(a > 0);").is_ok(), "zero needs no scale");
+
+    let err = compile_body("require(a + b > 0);").expect_err("scales differ");
+    assert!(err.contains("mixed scales in '+': scale 2 and scale 4"), "unexpected error: {err}");
+    let err = compile_body("require(a > 1);").expect_err("unscaled int");
+    assert!(err.contains("unscaled int"), "unexpected error: {err}");
+    let err = compile_body("require(a > 1.005);").expect_err("too many places");
+    assert!(err.contains("3 fractional digits but the scale is 2"), "unexpected error: {err}");
+    let err = compile_body("require(a * a > 0);").expect_err("scaled product");
+    assert!(err.contains("multiplying two scaled values"), "unexpected error: {err}");
+}
+
+#[test]
+fn denied_opcodes_fail_with_the_span_that_emitted_them() {
+    let source = r#"
+        contract Joiner(bytes prefix) {
+            entrypoint function spend(bytes suffix, bytes expected) {
+                require(suffix.length < 10);
+                require(prefix + suffix == expected);
+            }
+        }
+    "#;
+    let ctor_args = [Expr::Bytes(vec![0xab])];
+    let options = CompileOptions { record_debug_infos: true, ..OPTIONS };
+    let compiled = compile_contract(source, &ctor_args, options).expect("compile succeeds");
+
+    assert_eq!(silverscript_lang::debug::opcodes::opcode_by_name("OP_CAT"), Some(OpCat));
+    match compiled.check_denied_opcodes(&[OpCheckMultiSig, OpCat]) {
+        Err(CompilerError::DeniedOpcode { opcode, span }) => {
+            assert_eq!(opcode, "OpCat");
+            assert_eq!(span.map(|span| span.line), Some(5), "the concatenating require emitted OpCat");
+        }
+        other => panic!("expected a denied opcode error, got {other:?}"),
+    }
+    assert!(compiled.check_denied_opcodes(&[OpCheckMultiSig]).is_ok());
+
+    // Without debug info the opcode is still caught, just without a location.
+    let bare = compile_contract(source, &ctor_args, OPTIONS).expect("compile succeeds");
+    let err = bare.check_denied_opcodes(&[OpCat]).expect_err("OpCat is denied");
+    assert_eq!(err.to_string(), "script uses denied opcode OpCat");
+}
+
+#[test]
+fn expect_script_locks_down_the_compiled_disassembly() {
+    let mut builder = ScriptBuilder::new();
+    builder.add_i64(0).unwrap().add_i64(-1).unwrap().add_i64(16).unwrap().add_data(&[0xab, 0xcd]).unwrap().add_op(OpAdd).unwrap();
+    assert_eq!(disassemble(builder.script()), "Op0 Op1Negate Op16 0xabcd OpAdd");
+
+    let golden = |expectation: Option<&str>| {
+        let line = expectation.map(|expected| format!("expectScript \"{expected}\";")).unwrap_or_default();
+        format!(
+            r#"
+            contract Golden() {{
+                {line}
+
+                entrypoint function check(int a, int b) {{
+                    require(a + b > 1);
+                }}
+            }}
+        "#
+        )
+    };
+    let listing = disassemble(&compile_contract(&golden(None), &[], OPTIONS).expect("compile succeeds").script);
+    assert!(listing.contains("OpAdd Op1 OpGreaterThan"), "unexpected codegen: {listing}");
+
+    // Names match as in --deny, so the listing can be written in the OP_ADD style too.
+    let shouted = listing
+        .split(' ')
+        .map(|token| token.strip_prefix("Op").map_or_else(|| token.to_string(), |name| format!("OP_{}", name.to_uppercase())))
+        .collect::<Vec<_>>()
+        .join(" ");
+    compile_contract(&golden(Some(&shouted)), &[], OPTIONS).expect("matching expectation compiles");
+
+    let changed = listing.replace("OpGreaterThan", "OpLessThan");
+    let err = compile_contract(&golden(Some(&changed)), &[], OPTIONS).expect_err("codegen differs").to_string();
+    assert!(err.contains("expected OpLessThan, got OpGreaterThan"), "unexpected error: {err}");
+    assert!(err.contains(&format!("  expected: {changed}\n  actual:   {listing}")), "unexpected error: {err}");
+
+    let longer = format!("{listing} OpVerify");
+    let err = compile_contract(&golden(Some(&longer)), &[], OPTIONS).expect_err("script is shorter").to_string();
+    assert!(err.contains("expected OpVerify, got end of script"), "unexpected error: {err}");
+
+    let err = compile_contract(&golden(Some("OpAdd OpFrobnicate")), &[], OPTIONS).expect_err("unknown opcode").to_string();
+    assert!(err.contains("unknown opcode 'OpFrobnicate' in expectScript"), "unexpected error: {err}");
+}
+
+#[test]
+fn ternary_picks_an_arm_at_runtime_and_requires_matching_arm_types() {
+    let source = r#"
+        contract Fees(int base) {
+            entrypoint function pay(bool high, int paid) {
+                int fee = high ? 1000 : 100;
+                bytes20 tag = high ? 0x11111111111111111111111111111111111111ff : 0x2222222222222222222222222222222222222222;
+                require(paid == base + fee);
+                require(tag.length == 20);
+                require(high ? paid > 1000 : paid < 1000 && paid > 100);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[Expr::Int(5)], OPTIONS).expect("compile succeeds");
+    for (high, paid, passes) in [(true, 1005, true), (false, 105, true), (true, 105, false), (false, 1005, false)] {
+        let sigscript = compiled.build_sig_script("pay", vec![Expr::Bool(high), Expr::Int(paid)]).expect("sigscript builds");
+        let result = run_script_with_sigscript(compiled.script.clone(), sigscript);
+        assert_eq!(result.is_ok(), passes, "high = {high}, paid = {paid}: {result:?}");
+    }
+
+    let mismatched = r#"
+        contract Fees() {
+            entrypoint function pay(bool high, int paid) {
+                require(paid == (high ? 1000 : 0x01));
+            }
+        }
+    "#;
+    let err = compile_contract(mismatched, &[], OPTIONS).expect_err("arm types differ").to_string();
+    assert!(err.contains("ternary arms must have the same type, got int and bytes"), "unexpected error: {err}");
+}
@@ -5,12 +5,14 @@ use std::path::PathBuf;
 
 use kaspa_consensus_core::hashing::sighash::SigHashReusedValuesUnsync;
 use kaspa_txscript::caches::Cache;
+use kaspa_txscript::opcodes::codes::{OpAdd, OpCheckSig, OpData1, OpDiv, OpTrue};
 use kaspa_txscript::{EngineCtx, EngineFlags};
 
-use silverscript_lang::ast::{Expr, parse_contract_ast};
+use silverscript_lang::ast::{Expr, format_decimal, parse_contract_ast};
 use silverscript_lang::compiler::{CompileOptions, compile_contract};
 use silverscript_lang::debug::MappingKind;
-use silverscript_lang::debug::session::DebugSession;
+use silverscript_lang::debug::opcodes::{OpcodeCategory, opcode_category};
+use silverscript_lang::debug::session::{DebugSession, DebugValue};
 
 fn example_contract_path() -> PathBuf {
     let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -35,6 +37,22 @@ fn with_session_for_source<F>(
     ctor_args: Vec<Expr>,
     function_name: &str,
     function_args: Vec<Expr>,
+    f: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&mut DebugSession<'_>) -> Result<(), Box<dyn Error>>,
+{
+    // Compile with debug metadata enabled so line mappings and variable updates are available.
+    let compile_opts = CompileOptions { record_debug_infos: true, ..Default::default() };
+    with_session_for_options(source, compile_opts, ctor_args, function_name, function_args, f)
+}
+
+fn with_session_for_options<F>(
+    source: &str,
+    compile_opts: CompileOptions,
+    ctor_args: Vec<Expr>,
+    function_name: &str,
+    function_args: Vec<Expr>,
     mut f: F,
 ) -> Result<(), Box<dyn Error>>
 where
@@ -43,8 +61,6 @@ where
     let parsed_contract = parse_contract_ast(source)?;
     assert_eq!(parsed_contract.params.len(), ctor_args.len());
 
-    // Compile with debug metadata enabled so line mappings and variable updates are available.
-    let compile_opts = CompileOptions { record_debug_infos: true, ..Default::default() };
     let compiled = compile_contract(source, &ctor_args, compile_opts)?;
     let debug_info = compiled.debug_info.clone();
 
@@ -252,6 +268,91 @@ contract VirtualBp() {
     })
 }
 
+#[test]
+fn debug_session_steps_the_taken_branch_and_scopes_branch_locals() -> Result<(), Box<dyn Error>> {
+    let source = r#"pragma silverscript ^0.1.0;
+
+contract Branches(int limit) {
+    entrypoint function route(int a) {
+        int total = a;
+        if (a > limit) {
+            int excess = a - limit;
+            total = total - excess;
+        } else if (a == 0) {
+            int bonus = 7;
+            total = total + bonus;
+        } else {
+            total = total + 1;
+        }
+        require(total >= 0);
+    }
+}
+"#;
+
+    for (a, expected_lines, expected_total) in [(0, vec![5, 6, 9, 10, 11, 15], "7"), (20, vec![5, 6, 7, 8, 15], "10")] {
+        with_session_for_source(source, vec![Expr::Int(10)], "route", vec![Expr::Int(a)], |session| {
+            session.run_to_first_executed_statement()?;
+            let mut lines = vec![session.current_span().ok_or("missing span")?.line];
+            let mut locals = vec![];
+            let mut total_after_if = None;
+            while session.step_over()?.is_some() {
+                let line = session.current_span().ok_or("missing span")?.line;
+                let vars = session.list_variables()?;
+                locals.push((line, vars.iter().map(|var| var.name.clone()).collect::<HashSet<_>>()));
+                lines.push(line);
+                if line == 15 {
+                    let total = session.variable_by_name("total")?;
+                    total_after_if = Some(session.format_value(&total.type_name, &total.value));
+                }
+            }
+            assert_eq!(lines, expected_lines, "a = {a}: conditions stop, then only the taken branch");
+
+            let (_, at_bonus_use) = locals.iter().find(|(line, _)| *line == 11).cloned().unwrap_or_default();
+            assert_eq!(at_bonus_use.contains("bonus"), a == 0, "a branch local is visible inside its branch");
+            assert!(!at_bonus_use.contains("excess"), "locals of the other branch never are");
+            let (_, after_if) = locals.last().cloned().ok_or("no steps recorded")?;
+            assert!(!after_if.contains("bonus") && !after_if.contains("excess"), "branch locals end with the if");
+            assert_eq!(total_after_if.as_deref(), Some(expected_total), "total holds the merged value after the if");
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+#[test]
+fn debug_session_steps_into_the_evaluated_ternary_arm() -> Result<(), Box<dyn Error>> {
+    let source = r#"pragma silverscript ^0.1.0;
+
+contract Fees(int base) {
+    entrypoint function pay(bool high, int paid) {
+        int fee = high ? 1000 : 100;
+        require(paid == base + fee);
+    }
+}
+"#;
+
+    // `fee` compiles where the require uses it; its ternary (col 19) and arms (cols 26 and 33) still map to line 5.
+    for (high, paid, taken, skipped) in [(true, 1005, 26, 33), (false, 105, 33, 26)] {
+        with_session_for_source(source, vec![Expr::Int(5)], "pay", vec![Expr::Bool(high), Expr::Int(paid)], |session| {
+            session.run_to_first_executed_statement()?;
+            let mut stops = vec![];
+            loop {
+                let span = session.current_span().ok_or("missing span")?;
+                stops.push((span.line, span.col));
+                if session.step_into()?.is_none() {
+                    break;
+                }
+            }
+            let require_at = stops.iter().position(|stop| *stop == (6, 9)).ok_or("require never stepped")?;
+            let after_require = &stops[require_at + 1..];
+            assert!(after_require.starts_with(&[(5, 19), (5, taken)]), "high = {high}: ternary, then its arm: {stops:?}");
+            assert!(!stops.contains(&(5, skipped)), "high = {high}: the other arm is never stopped at");
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
 #[test]
 fn debug_session_inline_stepping_supports_into_over_out() -> Result<(), Box<dyn Error>> {
     let source = r#"pragma silverscript ^0.1.0;
@@ -349,3 +450,470 @@ contract InlineAssign() {
         Ok(())
     })
 }
+
+#[test]
+fn debug_session_reports_violated_invariants() -> Result<(), Box<dyn Error>> {
+    let source = r#"pragma silverscript ^0.1.0;
+
+contract Invariants() {
+    entrypoint function main(int a, int b) {
+        /*@invariant: depth >= 1*/
+        require(a > 0);
+        /*@invariant: depth == 99*/
+        require(b > 0);
+    }
+}
+"#;
+
+    with_session_for_source(source, vec![], "main", vec![Expr::Int(3), Expr::Int(4)], |session| {
+        assert_eq!(session.debug_info().invariants.len(), 2);
+        let mut flagged = Vec::new();
+        while let Some(state) = session.step_opcode()? {
+            flagged.extend(state.invariant_violations);
+        }
+
+        assert_eq!(flagged.len(), 1, "only the wrong invariant should be flagged: {flagged:?}");
+        assert_eq!(flagged[0].line, 7);
+        assert_eq!(flagged[0].condition, "depth == 99");
+        assert_eq!(session.invariant_violations(), flagged.as_slice());
+        Ok(())
+    })
+}
+
+#[test]
+fn invariants_come_only_from_comments_inside_a_function() -> Result<(), Box<dyn Error>> {
+    let source = r#"pragma silverscript ^0.1.0;
+
+contract Invariants() {
+    entrypoint function main(int a) {
+        /*@invariant: depth >= 1*/
+        require(a > 0, "/*@invariant: depth == 98*/");
+        // /*@invariant: depth == 97*/
+        require(a < 10);
+        /*@invariant: depth == 96*/
+    }
+
+    entrypoint function other(int b) {
+        require(b > 0);
+    }
+}
+"#;
+
+    with_session_for_source(source, vec![], "main", vec![Expr::Int(3)], |session| {
+        let invariants = &session.debug_info().invariants;
+        assert_eq!(invariants.len(), 1, "{invariants:?}");
+        assert_eq!((invariants[0].line, invariants[0].condition.as_str()), (5, "depth >= 1"));
+        Ok(())
+    })
+}
+
+#[test]
+fn debug_session_opcode_metas_report_value_and_category() -> Result<(), Box<dyn Error>> {
+    let source = r#"
+        contract Sum() {
+            entrypoint function main(int a, int b) {
+                require(a + b == 7);
+            }
+        }
+    "#;
+    with_session_for_source(source, vec![], "main", vec![Expr::Int(3), Expr::Int(4)], |session| {
+        let metas = session.opcode_metas();
+        let add = metas.iter().find(|meta| meta.opcode == OpAdd).expect("script contains OP_ADD");
+        assert_eq!(add.opcode, 0x93);
+        assert_eq!(add.category, OpcodeCategory::Arithmetic);
+        assert!(metas.iter().any(|meta| meta.category == OpcodeCategory::Push));
+        assert_eq!(opcode_category(OpCheckSig), OpcodeCategory::Crypto);
+        Ok(())
+    })
+}
+
+#[test]
+fn logical_operators_skip_the_right_operand_once_the_left_settles_it() -> Result<(), Box<dyn Error>> {
+    // Dividing by a zero `a` fails the script, so the right operands must not run when `a == 0`.
+    let source = r#"
+        contract Guarded() {
+            entrypoint function main(int a) {
+                require(a == 0 || 10 / a > 1);
+                require(!(a != 0 && 10 / a < 2));
+            }
+        }
+    "#;
+    for (a, expected_divisions) in [(0, 0), (2, 2)] {
+        with_session_for_source(source, vec![], "main", vec![Expr::Int(a)], |session| {
+            let opcodes: Vec<u8> = session.opcode_metas().iter().map(|meta| meta.opcode).collect();
+            assert_eq!(opcodes.iter().filter(|opcode| **opcode == OpDiv).count(), 2, "both divisions are compiled");
+            let mut divisions = 0;
+            while let Some(state) = session.step_opcode()? {
+                if state.execution_index.is_some() && opcodes[state.pc - 1] == OpDiv {
+                    divisions += 1;
+                }
+            }
+            assert_eq!(divisions, expected_divisions, "a = {a}: divisions executed");
+            assert!(session.run_to_failure().is_none(), "a = {a}: the spend passes");
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+#[test]
+fn debug_session_renders_scaled_params_as_decimals() -> Result<(), Box<dyn Error>> {
+    let source = r#"
+        contract Price(@scale(2) int floor) {
+            entrypoint function spend(@scale(2) int price) {
+                require(price >= floor);
+            }
+        }
+    "#;
+    let ctor_args = vec![Expr::Decimal { digits: 5, scale: 2 }];
+    with_session_for_source(source, ctor_args, "spend", vec![Expr::Decimal { digits: 150, scale: 2 }], |session| {
+        session.run_to_first_executed_statement()?;
+        let price = session.variable_by_name("price")?;
+        assert!(matches!(price.value, DebugValue::Int(150)));
+        assert_eq!((price.type_name.as_str(), price.scale), ("int", Some(2)));
+        assert_eq!(session.format_variable(&price), "1.50");
+        assert_eq!(session.format_watch(&session.eval_expression("price")?), "1.50");
+        let floor = session.variable_by_name("floor")?;
+        assert_eq!(session.format_variable(&floor), "0.05");
+        assert_eq!(format_decimal(-150, 2), "-1.50");
+        assert_eq!(format_decimal(7, 0), "7");
+        Ok(())
+    })
+}
+
+#[test]
+fn debug_session_lists_let_bindings_with_inferred_types() -> Result<(), Box<dyn Error>> {
+    let source = r#"
+        contract Lets() {
+            entrypoint function main(int a, int b) {
+                let mid = a + b;
+                let big = mid > 3;
+                require(big);
+                require(mid == 7);
+            }
+        }
+    "#;
+    with_session_for_source(source, vec![], "main", vec![Expr::Int(3), Expr::Int(4)], |session| {
+        session.run_to_first_executed_statement()?;
+        session.step_over()?;
+        session.step_over()?;
+        let mid = session.variable_by_name("mid")?;
+        assert_eq!(mid.type_name, "int");
+        assert_eq!(session.format_value(&mid.type_name, &mid.value), "7");
+        let big = session.variable_by_name("big")?;
+        assert_eq!(big.type_name, "bool");
+        Ok(())
+    })
+}
+
+#[test]
+fn debug_session_runs_to_the_failing_require() -> Result<(), Box<dyn Error>> {
+    let source = r#"pragma silverscript ^0.1.0;
+
+contract Checks() {
+    entrypoint function main(int a, int b) {
+        require(a > 0);
+        require(b > a);
+        require(a + b < 100);
+    }
+}
+"#;
+
+    with_session_for_source(source, vec![], "main", vec![Expr::Int(5), Expr::Int(3)], |session| {
+        let failure = session.run_to_failure().expect("require(b > a) fails");
+        assert_eq!(failure.state.mapping.and_then(|mapping| mapping.span).map(|span| span.line), Some(6));
+        assert_eq!(failure.statement.as_deref(), Some("require(b > a);"));
+        assert!(!failure.message.is_empty());
+        Ok(())
+    })?;
+    with_session_for_source(source, vec![], "main", vec![Expr::Int(3), Expr::Int(5)], |session| {
+        assert!(session.run_to_failure().is_none(), "a passing spend has no failure");
+        Ok(())
+    })
+}
+
+#[test]
+fn peephole_optimized_script_leaves_the_same_stacks() -> Result<(), Box<dyn Error>> {
+    let source = r#"
+        contract Peephole(int expected) {
+            entrypoint function main(int a, int b) {
+                int sum = a + b;
+                require(sum == expected);
+                require(a != b);
+                require(a < b || a == 3);
+            }
+        }
+    "#;
+
+    let run = |optimize: bool, args: Vec<Expr>| -> Result<(usize, Option<String>, Vec<String>), Box<dyn Error>> {
+        let options = CompileOptions { optimize, record_debug_infos: true, ..Default::default() };
+        let mut outcome = None;
+        with_session_for_options(source, options, vec![Expr::Int(8)], "main", args.clone(), |session| {
+            let opcode_count = session.opcode_count();
+            let mut error = None;
+            loop {
+                match session.step_opcode() {
+                    Ok(Some(_)) => {}
+                    Ok(None) => break,
+                    Err(err) => {
+                        error = Some(err.to_string());
+                        break;
+                    }
+                }
+            }
+            outcome = Some((opcode_count, error, session.stack()));
+            Ok(())
+        })?;
+        Ok(outcome.expect("session ran"))
+    };
+
+    for args in [vec![Expr::Int(3), Expr::Int(5)], vec![Expr::Int(4), Expr::Int(4)], vec![Expr::Int(1), Expr::Int(2)]] {
+        let (plain_count, plain_error, plain_stack) = run(false, args.clone())?;
+        let (optimized_count, optimized_error, optimized_stack) = run(true, args.clone())?;
+        assert!(optimized_count < plain_count, "fused verifies shorten the script");
+        assert_eq!(optimized_error.is_some(), plain_error.is_some(), "args {args:?}");
+        if plain_error.is_none() {
+            assert_eq!(optimized_stack, plain_stack, "args {args:?}");
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn debug_session_step_out_unwinds_nested_frames_one_at_a_time() -> Result<(), Box<dyn Error>> {
+    let source = r#"pragma silverscript ^0.1.0;
+
+contract Nested() {
+    function inner(int x) : (int) {
+        int y = x * 2;
+        require(y > 0);
+        return(y);
+    }
+
+    function outer(int x) : (int) {
+        (int doubled) = inner(x);
+        int z = doubled + 1;
+        return(z);
+    }
+
+    entrypoint function main(int a) {
+        (int r) = outer(a);
+        require(r > 0);
+    }
+}
+"#;
+
+    with_session_for_source(source, vec![], "main", vec![Expr::Int(4)], |session| {
+        session.run_to_first_executed_statement()?;
+        while session.current_location().ok_or("missing mapping")?.call_depth < 2 {
+            session.step_into()?.ok_or("script ended before reaching inner")?;
+        }
+        assert_eq!(session.call_stack(), vec!["outer".to_string(), "inner".to_string()]);
+
+        let back_in_outer = session.step_out()?.ok_or("step out of inner failed")?.mapping.ok_or("missing mapping")?;
+        assert_eq!(back_in_outer.call_depth, 1);
+        assert_eq!(session.call_stack(), vec!["outer".to_string()]);
+
+        let back_in_main = session.step_out()?.ok_or("step out of outer failed")?.mapping.ok_or("missing mapping")?;
+        assert_eq!(back_in_main.call_depth, 0);
+        assert!(session.call_stack().is_empty());
+
+        // The entrypoint has no caller, so stepping out of it runs the script to completion.
+        assert!(session.step_out()?.is_none());
+        assert!(session.step_opcode()?.is_none());
+        Ok(())
+    })
+}
+
+#[test]
+fn debug_session_evaluates_watch_expressions_in_the_current_frame() -> Result<(), Box<dyn Error>> {
+    let source = r#"
+        contract Watch(int fee) {
+            entrypoint function main(int seed, int y) {
+                int total = seed + y;
+                require(total > fee);
+                require(y > 0);
+            }
+
+            entrypoint function other(int z) {
+                require(z == 1);
+            }
+        }
+    "#;
+    with_session_for_source(source, vec![Expr::Int(2)], "main", vec![Expr::Int(10), Expr::Int(5)], |session| {
+        session.run_to_first_executed_statement()?;
+        session.step_over()?;
+        let stack = session.stack();
+        let watch = session.eval_expression("y > 0")?;
+        assert_eq!(watch.type_name, "bool");
+        assert_eq!(session.format_value(&watch.type_name, &watch.value), "true");
+        let watch = session.eval_expression("seed - fee")?;
+        assert_eq!(watch.type_name, "int");
+        assert_eq!(session.format_value(&watch.type_name, &watch.value), "8");
+        let watch = session.eval_expression("total * 2 + fee")?;
+        assert_eq!(session.format_value(&watch.type_name, &watch.value), "32");
+        assert_eq!(session.stack(), stack, "evaluating watches leaves the engine untouched");
+
+        let err = session.eval_expression("other(1)").expect_err("contract calls are rejected");
+        assert!(err.contains("cannot call 'other'"), "unexpected error: {err}");
+        assert!(session.eval_expression("y >").is_err());
+        assert!(session.eval_expression("y > 0 y").is_err(), "trailing input is rejected");
+        Ok(())
+    })
+}
+
+#[test]
+fn debug_session_conditional_breakpoints_stop_only_when_the_condition_holds() -> Result<(), Box<dyn Error>> {
+    let source = r#"pragma silverscript ^0.1.0;
+
+contract Cond() {
+    entrypoint function main(int a) {
+        int x = a + 1;
+        x = x + 1;
+        x = x + 1;
+        require(x > 0);
+    }
+}
+"#;
+    with_session_for_source(source, vec![], "main", vec![Expr::Int(0)], |session| {
+        session.run_to_first_executed_statement()?;
+        for line in 6..=8 {
+            session.set_conditional_breakpoint(line, "x == 3".to_string())?;
+        }
+        assert_eq!(session.breakpoint_condition(7), Some("x == 3"));
+
+        let hit = session.continue_to_breakpoint()?;
+        assert!(hit.is_some(), "expected to stop once x reaches 3");
+        assert_eq!(session.current_span().map(|span| span.line), Some(8));
+        let x = session.variable_by_name("x")?;
+        assert_eq!(session.format_value(&x.type_name, &x.value), "3");
+        Ok(())
+    })?;
+
+    with_session_for_source(source, vec![], "main", vec![Expr::Int(0)], |session| {
+        session.run_to_first_executed_statement()?;
+        for line in 6..=8 {
+            session.set_conditional_breakpoint(line, "x == 100".to_string())?;
+        }
+        assert!(session.continue_to_breakpoint()?.is_none(), "a condition that never holds runs to the end");
+
+        assert!(session.set_conditional_breakpoint(6, "x ==".to_string()).is_err(), "unparsable condition");
+        let err = session.set_conditional_breakpoint(6, "y > 1".to_string()).expect_err("unknown variable");
+        assert!(err.contains("unknown variable 'y'"), "unexpected error: {err}");
+        assert!(session.set_conditional_breakpoint(1, "x == 1".to_string()).is_err(), "line without a statement");
+        Ok(())
+    })
+}
+
+#[test]
+fn debug_session_step_back_restores_journaled_states() -> Result<(), Box<dyn Error>> {
+    let source = r#"
+        contract Back() {
+            entrypoint function main(int a, int b) {
+                int sum = a + b;
+                require(sum == 7);
+                require(a < b);
+            }
+        }
+    "#;
+    with_session_for_source(source, vec![], "main", vec![Expr::Int(3), Expr::Int(4)], |session| {
+        assert!(session.step_back().is_none(), "journaling is off by default");
+        session.enable_journal(64);
+        session.run_to_first_executed_statement()?;
+
+        let mut states = vec![(session.state().pc, session.stack())];
+        for _ in 0..6 {
+            session.step_opcode()?.ok_or("script ended early")?;
+            states.push((session.state().pc, session.stack()));
+        }
+
+        for expected in states.iter().rev().skip(1) {
+            let state = session.step_back().ok_or("journal should reach back")?;
+            assert_eq!((state.pc, state.stack.clone()), *expected);
+            assert_eq!(session.stack(), expected.1);
+        }
+        for expected in states.iter().skip(1) {
+            let state = session.step_opcode()?.ok_or("replay should not end")?;
+            assert_eq!((state.pc, state.stack), *expected, "stepping forward replays the journal");
+        }
+
+        session.step_back().ok_or("journal should reach back")?;
+        assert!(session.continue_to_breakpoint()?.is_none(), "execution resumes live after the replay");
+        assert_eq!(session.stack(), vec!["01".to_string()]);
+        Ok(())
+    })?;
+
+    with_session_for_source(source, vec![], "main", vec![Expr::Int(3), Expr::Int(4)], |session| {
+        session.enable_journal(2);
+        for _ in 0..5 {
+            session.step_opcode()?;
+        }
+        assert!(session.step_back().is_some());
+        assert!(session.step_back().is_some());
+        assert!(session.step_back().is_none(), "only the last two states are kept");
+        Ok(())
+    })
+}
+
+#[test]
+fn debug_session_stops_at_a_failing_opcode_and_reports_it_again() -> Result<(), Box<dyn Error>> {
+    let source = r#"
+        contract Divide() {
+            entrypoint function main(int a) {
+                int q = 10 / a;
+                require(q > 0);
+            }
+        }
+    "#;
+    with_session_for_source(source, vec![], "main", vec![Expr::Int(0)], |session| {
+        let opcodes: Vec<u8> = session.opcode_metas().iter().map(|meta| meta.opcode).collect();
+        let err = loop {
+            match session.step_opcode() {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("dividing by zero must fail the spend"),
+                Err(err) => break err.to_string(),
+            }
+        };
+        assert!(!err.contains("engine panicked"), "an engine error is not a panic: {err}");
+        assert_eq!(opcodes[session.state().pc], OpDiv, "the session stops at the division");
+
+        let again = session.step_opcode().expect_err("a failed opcode is not stepped again").to_string();
+        assert!(again.contains(&err), "unexpected error: {again}");
+        let failure = session.run_to_failure().expect("the spend fails");
+        assert!(failure.message.contains(&err), "unexpected failure: {}", failure.message);
+        Ok(())
+    })
+}
+
+#[test]
+fn logical_operators_treat_any_nonzero_operand_as_true_and_yield_zero_or_one() -> Result<(), Box<dyn Error>> {
+    let source = r#"
+        contract Flags() {
+            entrypoint function main(bool flag, int n) {
+                require((flag && n) == true);
+                require((n || false) == true);
+            }
+        }
+    "#;
+    for (n, passes) in [(2, true), (-3, true), (0, false)] {
+        with_session_for_source(source, vec![], "main", vec![Expr::Bool(true), Expr::Int(n)], |session| {
+            assert_eq!(session.run_to_failure().is_none(), passes, "n = {n}");
+            Ok(())
+        })?;
+    }
+
+    // A bool argument pushed as 0x02 rather than OpTrue is still true.
+    let compiled = compile_contract(source, &[], CompileOptions::default())?;
+    let canonical = compiled.build_sig_script("main", vec![Expr::Bool(true), Expr::Int(2)])?;
+    assert_eq!(canonical[0], OpTrue, "true is pushed as OpTrue");
+    let sigscript = [&[OpData1, 0x02][..], &canonical[1..]].concat();
+
+    let sig_cache = Cache::new(10_000);
+    let reused_values = SigHashReusedValuesUnsync::new();
+    let ctx = EngineCtx::new(&sig_cache).with_reused(&reused_values);
+    let engine = silverscript_lang::debug::session::DebugEngine::new(ctx, EngineFlags { covenants_enabled: true });
+    let mut session = DebugSession::full(&sigscript, &compiled.script, source, compiled.debug_info.clone(), engine)?;
+    let failure = session.run_to_failure();
+    assert!(failure.is_none(), "a non-canonical true passes: {:?}", failure.map(|failure| failure.message));
+    Ok(())
+}
@@ -48,3 +48,320 @@ fn sil_debug_repl_all_commands_smoke() {
     assert!(stdout.contains("Breakpoint set at line 7"), "missing breakpoint confirmation");
     assert!(stdout.contains("Breakpoints: 7"), "missing breakpoint listing");
 }
+
+#[test]
+fn sil_debug_saves_and_replays_trace() {
+    let contract_path = example_contract_path();
+    let trace_path = std::env::temp_dir().join(format!("sil_debug_trace_{}.json", std::process::id()));
+
+    let save = Command::new(env!("CARGO_BIN_EXE_sil-debug"))
+        .arg(&contract_path)
+        .args(["--function", "hello", "--ctor-arg", "3", "--ctor-arg", "10", "--arg", "5", "--arg", "5"])
+        .arg("--save-trace")
+        .arg(&trace_path)
+        .output()
+        .expect("failed to run sil-debug --save-trace");
+    assert!(save.status.success(), "save-trace failed: {}", String::from_utf8_lossy(&save.stderr));
+    assert!(String::from_utf8_lossy(&save.stdout).contains("Saved trace"));
+
+    let replay = Command::new(env!("CARGO_BIN_EXE_sil-debug"))
+        .arg("--replay")
+        .arg(&trace_path)
+        .output()
+        .expect("failed to run sil-debug --replay");
+    let _ = std::fs::remove_file(&trace_path);
+    assert!(replay.status.success(), "replay failed: {}", String::from_utf8_lossy(&replay.stdout));
+    assert!(String::from_utf8_lossy(&replay.stdout).contains("Replay matches saved trace"));
+}
+
+#[test]
+fn sil_debug_strict_hex_requires_prefixed_bytes() {
+    let dir = std::env::temp_dir().join(format!("sil_debug_strict_hex_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create temp dir");
+    let contract_path = dir.join("bytes_arg.sil");
+    let source = r#"
+        contract BytesArg() {
+            entrypoint function check(bytes data) {
+                require(data.length == 2);
+            }
+        }
+    "#;
+    std::fs::write(&contract_path, source).expect("write contract");
+    let trace_path = dir.join("trace.json");
+    let run = |value: &str, strict: bool| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_sil-debug"));
+        command.arg(&contract_path).args(["--function", "check", "--arg", value, "--save-trace"]).arg(&trace_path);
+        if strict {
+            command.arg("--strict-hex");
+        }
+        command.output().expect("failed to run sil-debug")
+    };
+
+    assert!(run("1234", false).status.success(), "bare hex stays accepted by default");
+
+    let rejected = run("1234", true);
+    assert!(!rejected.status.success(), "strict mode should reject bare hex");
+    assert!(String::from_utf8_lossy(&rejected.stderr).contains("must start with 0x"));
+
+    let accepted = run("0x1234", true);
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(accepted.status.success(), "strict mode accepts prefixed hex: {}", String::from_utf8_lossy(&accepted.stderr));
+}
+
+#[test]
+fn sil_debug_num_and_hex_prefixes_override_byte_args() {
+    let dir = std::env::temp_dir().join(format!("sil_debug_arg_prefixes_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create temp dir");
+    let contract_path = dir.join("prefixes.sil");
+    let source = r#"
+        contract Prefixes() {
+            entrypoint function check(bytes4 word, bytes tail, bool flag) {
+                require(word == 0x01000000);
+                require(tail == 0x8000);
+                require(flag);
+            }
+        }
+    "#;
+    std::fs::write(&contract_path, source).expect("write contract");
+    let trace_path = dir.join("trace.json");
+    let run = |word: &str, tail: &str, flag: &str| {
+        Command::new(env!("CARGO_BIN_EXE_sil-debug"))
+            .arg(&contract_path)
+            .args(["--strict-hex", "--function", "check", "--arg", word, "--arg", tail, "--arg", flag, "--save-trace"])
+            .arg(&trace_path)
+            .output()
+            .expect("failed to run sil-debug")
+    };
+
+    let numbers = run("num:1", "num:128", "true");
+    assert!(numbers.status.success(), "num: args: {}", String::from_utf8_lossy(&numbers.stderr));
+
+    let hex = run("hex:01000000", "hex:8000", "true");
+    assert!(hex.status.success(), "hex: args pass --strict-hex: {}", String::from_utf8_lossy(&hex.stderr));
+
+    let overflow = run("num:4294967295", "num:128", "true");
+    assert!(String::from_utf8_lossy(&overflow.stderr).contains("4294967295 does not fit in bytes4"));
+
+    let wrong_type = run("num:1", "num:128", "num:1");
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(!wrong_type.status.success());
+    let stderr = String::from_utf8_lossy(&wrong_type.stderr);
+    assert!(stderr.contains("num: prefix is only valid for int, bytes and bytesN params, not bool"), "{stderr}");
+}
+
+#[test]
+fn sil_debug_parses_pubkey_array_elements() {
+    let dir = std::env::temp_dir().join(format!("sil_debug_pubkey_array_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create temp dir");
+    let contract_path = dir.join("keys.sil");
+    let source = r#"
+        contract Keys() {
+            entrypoint function check(pubkey[] keys) {
+                require(keys.length == 3);
+            }
+        }
+    "#;
+    std::fs::write(&contract_path, source).expect("write contract");
+    let trace_path = dir.join("trace.json");
+    let run = |value: &str| {
+        Command::new(env!("CARGO_BIN_EXE_sil-debug"))
+            .arg(&contract_path)
+            .args(["--function", "check", "--arg", value, "--save-trace"])
+            .arg(&trace_path)
+            .output()
+            .expect("failed to run sil-debug")
+    };
+    let key = |byte: u8| format!("0x{}", format!("{byte:02x}").repeat(32));
+
+    let json = format!("[\"{}\",\"{}\",\"{}\"]", key(1), key(2), key(3));
+    let accepted = run(&json);
+    assert!(accepted.status.success(), "json pubkey array: {}", String::from_utf8_lossy(&accepted.stderr));
+
+    let listed = run(&format!("{},{},{}", key(1), key(2), key(3)));
+    assert!(listed.status.success(), "comma pubkey array: {}", String::from_utf8_lossy(&listed.stderr));
+
+    let rejected = run(&format!("{},0x1234,{}", key(1), key(3)));
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(!rejected.status.success(), "short pubkey element should be rejected");
+    let stderr = String::from_utf8_lossy(&rejected.stderr);
+    assert!(stderr.contains("pubkey[] element 1"), "error names the element: {stderr}");
+    assert!(stderr.contains("pubkey expects 32 bytes, got 2"), "error names the length: {stderr}");
+}
+
+#[test]
+fn sil_debug_quiet_suppresses_info_but_keeps_errors() {
+    let contract_path = example_contract_path();
+    let trace_path = std::env::temp_dir().join(format!("sil_debug_quiet_{}.json", std::process::id()));
+    let run = |extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_sil-debug"))
+            .arg(&contract_path)
+            .args(["--quiet", "--function", "hello", "--ctor-arg", "3", "--ctor-arg", "10"])
+            .args(extra)
+            .arg("--save-trace")
+            .arg(&trace_path)
+            .output()
+            .expect("failed to run sil-debug --quiet")
+    };
+
+    let saved = run(&["--arg", "5", "--arg", "5"]);
+    assert!(saved.status.success(), "quiet save-trace failed: {}", String::from_utf8_lossy(&saved.stderr));
+    assert!(saved.stdout.is_empty(), "quiet run printed: {}", String::from_utf8_lossy(&saved.stdout));
+    assert!(trace_path.exists(), "trace is still written");
+
+    let replay = Command::new(env!("CARGO_BIN_EXE_sil-debug")).args(["-q", "--replay"]).arg(&trace_path).output().expect("replay");
+    let _ = std::fs::remove_file(&trace_path);
+    assert!(replay.status.success());
+    assert!(replay.stdout.is_empty(), "quiet replay printed: {}", String::from_utf8_lossy(&replay.stdout));
+
+    let failed = run(&["--arg", "not-a-number", "--arg", "5"]);
+    assert!(!failed.status.success(), "bad argument should fail");
+    assert!(failed.stdout.is_empty());
+    assert!(!failed.stderr.is_empty(), "errors are still reported on stderr");
+}
+
+fn dap_frame(message: serde_json::Value) -> Vec<u8> {
+    let body = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{body}", body.len()).into_bytes()
+}
+
+fn dap_messages(mut stdout: &str) -> Vec<serde_json::Value> {
+    let mut messages = Vec::new();
+    while let Some((header, rest)) = stdout.split_once("\r\n\r\n") {
+        let length: usize = header.trim_start_matches("Content-Length: ").parse().expect("content length");
+        messages.push(serde_json::from_str(&rest[..length]).expect("message json"));
+        stdout = &rest[length..];
+    }
+    messages
+}
+
+#[test]
+fn sil_dap_stops_at_breakpoints_and_reports_frames_and_variables() {
+    let contract_path = example_contract_path();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sil-dap"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn sil-dap");
+
+    let requests = [
+        serde_json::json!({ "seq": 1, "type": "request", "command": "initialize", "arguments": { "adapterID": "silverscript" } }),
+        serde_json::json!({ "seq": 2, "type": "request", "command": "launch", "arguments": {
+            "program": contract_path, "function": "hello", "ctorArgs": ["3", "10"], "args": ["5", "5"], "stopOnEntry": false,
+        } }),
+        serde_json::json!({ "seq": 3, "type": "request", "command": "setBreakpoints", "arguments": {
+            "source": { "path": contract_path }, "breakpoints": [{ "line": 12 }, { "line": 1 }],
+        } }),
+        serde_json::json!({ "seq": 4, "type": "request", "command": "configurationDone" }),
+        serde_json::json!({ "seq": 5, "type": "request", "command": "stackTrace", "arguments": { "threadId": 1 } }),
+        serde_json::json!({ "seq": 6, "type": "request", "command": "variables", "arguments": { "variablesReference": 1 } }),
+        serde_json::json!({ "seq": 7, "type": "request", "command": "continue", "arguments": { "threadId": 1 } }),
+        serde_json::json!({ "seq": 8, "type": "request", "command": "disconnect" }),
+    ];
+    let stdin = child.stdin.as_mut().expect("stdin available");
+    for request in requests {
+        stdin.write_all(&dap_frame(request)).expect("write stdin");
+    }
+
+    let output = child.wait_with_output().expect("wait for sil-dap");
+    assert!(output.status.success(), "sil-dap exited with status {:?}", output.status.code());
+    let messages = dap_messages(std::str::from_utf8(&output.stdout).expect("utf8 stdout"));
+    let response = |command: &str| {
+        messages.iter().find(|message| message["type"] == "response" && message["command"] == command).expect("response")
+    };
+
+    let failed = messages.iter().filter(|message| message["type"] == "response" && message["success"] != true).count();
+    assert_eq!(failed, 0, "failed requests: {messages:?}");
+    let breakpoints = &response("setBreakpoints")["body"]["breakpoints"];
+    assert_eq!(breakpoints[0]["verified"], true);
+    assert_eq!(breakpoints[1]["verified"], false);
+
+    let events: Vec<&str> =
+        messages.iter().filter(|message| message["type"] == "event").filter_map(|message| message["event"].as_str()).collect();
+    assert_eq!(events, ["initialized", "stopped", "terminated", "exited"]);
+
+    let frames = &response("stackTrace")["body"]["stackFrames"];
+    assert_eq!(frames[0]["name"], "hello");
+    assert_eq!(frames[0]["line"], 12);
+
+    let variables = response("variables")["body"]["variables"].as_array().expect("variables");
+    let d = variables.iter().find(|var| var["name"] == "d").expect("local d");
+    assert_eq!(d["value"], "5");
+}
+
+#[test]
+fn sil_dap_steps_by_opcode_and_restarts_with_its_breakpoints() {
+    let contract_path = example_contract_path();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sil-dap"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn sil-dap");
+
+    let launch = serde_json::json!({ "program": contract_path, "function": "hello", "ctorArgs": ["3", "10"], "args": ["5", "5"] });
+    let breakpoints = serde_json::json!({ "breakpoints": [{ "line": 12 }] });
+    let by_opcode = serde_json::json!({ "threadId": 1, "granularity": "instruction" });
+    let missing_program = serde_json::json!({ "arguments": { "program": "missing.sil" } });
+    let requests = [
+        serde_json::json!({ "seq": 1, "type": "request", "command": "initialize" }),
+        serde_json::json!({ "seq": 2, "type": "request", "command": "launch", "arguments": launch }),
+        serde_json::json!({ "seq": 3, "type": "request", "command": "setBreakpoints", "arguments": breakpoints }),
+        serde_json::json!({ "seq": 4, "type": "request", "command": "configurationDone" }),
+        serde_json::json!({ "seq": 5, "type": "request", "command": "next", "arguments": by_opcode }),
+        serde_json::json!({ "seq": 6, "type": "request", "command": "restart", "arguments": missing_program }),
+        serde_json::json!({ "seq": 7, "type": "request", "command": "restart" }),
+        serde_json::json!({ "seq": 8, "type": "request", "command": "continue", "arguments": { "threadId": 1 } }),
+        serde_json::json!({ "seq": 9, "type": "request", "command": "stackTrace", "arguments": { "threadId": 1 } }),
+        serde_json::json!({ "seq": 10, "type": "request", "command": "disconnect" }),
+    ];
+    let stdin = child.stdin.as_mut().expect("stdin available");
+    for request in requests {
+        stdin.write_all(&dap_frame(request)).expect("write stdin");
+    }
+
+    let output = child.wait_with_output().expect("wait for sil-dap");
+    assert!(output.status.success(), "sil-dap exited with status {:?}", output.status.code());
+    let messages = dap_messages(std::str::from_utf8(&output.stdout).expect("utf8 stdout"));
+
+    let restarts: Vec<&serde_json::Value> = messages.iter().filter(|message| message["command"] == "restart").collect();
+    assert_eq!(restarts.len(), 2);
+    assert_eq!(restarts[0]["success"], false, "a program that fails to load keeps the current session");
+    assert_eq!(restarts[1]["success"], true);
+
+    let stops: Vec<&str> = messages
+        .iter()
+        .filter(|message| message["event"] == "stopped")
+        .filter_map(|message| message["body"]["reason"].as_str())
+        .collect();
+    assert_eq!(stops, ["entry", "step", "entry", "breakpoint"]);
+    let frames = messages.iter().find(|message| message["command"] == "stackTrace").expect("stackTrace response");
+    assert_eq!(frames["body"]["stackFrames"][0]["line"], 12, "the breakpoint survives the restart");
+}
+
+#[test]
+fn sil_dap_answers_malformed_and_oversized_messages_and_keeps_reading() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sil-dap"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn sil-dap");
+
+    let oversized = (4 << 20) + 1;
+    let stdin = child.stdin.as_mut().expect("stdin available");
+    stdin.write_all(b"Content-Length: 9\r\n\r\n{not json").expect("write stdin");
+    stdin.write_all(format!("Content-Length: {oversized}\r\n\r\n").as_bytes()).expect("write stdin");
+    stdin.write_all(&vec![b' '; oversized]).expect("write stdin");
+    stdin.write_all(&dap_frame(serde_json::json!({ "seq": 1, "type": "request", "command": "initialize" }))).expect("write stdin");
+    stdin.write_all(&dap_frame(serde_json::json!({ "seq": 2, "type": "request", "command": "disconnect" }))).expect("write stdin");
+
+    let output = child.wait_with_output().expect("wait for sil-dap");
+    assert!(output.status.success(), "sil-dap exited with status {:?}", output.status.code());
+    let messages = dap_messages(std::str::from_utf8(&output.stdout).expect("utf8 stdout"));
+    let outcomes: Vec<(bool, &str)> =
+        messages.iter().map(|message| (message["success"] == true, message["command"].as_str().unwrap_or_default())).collect();
+    assert_eq!(outcomes, [(false, ""), (false, ""), (true, "initialize"), (true, "disconnect")]);
+    assert!(messages[0]["message"].as_str().expect("message").starts_with("malformed message"));
+    assert!(messages[1]["message"].as_str().expect("message").contains("byte limit"));
+}
@@ -1,4 +1,10 @@
-use silverscript_lang::parser::parse_source_file;
+use pest::Parser;
+use silverscript_lang::ast::visit::{Visitor, walk_contract};
+use silverscript_lang::ast::{
+    CommentKind, Expr, FunctionAst, SourceSpan, Statement, StatementKind, parse_contract_ast, parse_contract_ast_with_comments,
+};
+use silverscript_lang::compiler::CompilerError;
+use silverscript_lang::parser::{Rule, SilverScriptParser, parse_source_file, reserved_keywords};
 
 #[test]
 fn parses_minimal_contract() {
@@ -50,3 +56,276 @@ fn parses_arrays_and_introspection() {
         panic!("{}", err);
     }
 }
+
+#[test]
+fn reports_reserved_keyword_used_as_variable_name() {
+    let input = r#"
+        contract Foo() {
+            entrypoint function bar() {
+                int require = 5;
+            }
+        }
+    "#;
+
+    let err = parse_contract_ast(input).expect_err("reserved keyword should be rejected").to_string();
+    assert!(err.contains("`require` is a reserved keyword and cannot be used as a variable name"), "unexpected error: {err}");
+    assert!(err.contains("4:21"), "error should point at the keyword: {err}");
+}
+
+#[test]
+fn rejects_a_function_named_like_the_log_statement() {
+    let input = r#"
+        contract Foo() {
+            function log(int x) {
+                require(x > 0);
+            }
+            entrypoint function bar(int a) {
+                log(a);
+            }
+        }
+    "#;
+
+    let err = parse_contract_ast(input).expect_err("a function named log is never called").to_string();
+    assert!(err.contains("function name 'log' is reserved"), "unexpected error: {err}");
+}
+
+#[test]
+fn rejects_a_function_named_like_the_warn_if_statement() {
+    let input = r#"
+        contract Foo() {
+            function warnIf(bool c, string m) {
+                require(!c);
+            }
+            entrypoint function bar(int a) {
+                warnIf(a > 10, "too big");
+            }
+        }
+    "#;
+
+    let err = parse_contract_ast(input).expect_err("a function named warnIf is never called").to_string();
+    assert!(err.contains("function name 'warnIf' is reserved"), "unexpected error: {err}");
+}
+
+#[test]
+fn reports_missing_semicolon_at_end_of_statement() {
+    let input = r#"
+        contract Foo() {
+            entrypoint function bar(int a) {
+                int x = a + 1
+                require(x > 0);
+            }
+        }
+    "#;
+
+    let err = parse_contract_ast(input).expect_err("missing semicolon should be rejected").to_string();
+    assert!(err.contains("expected `;` after statement"), "unexpected error: {err}");
+    assert!(err.contains("4:30"), "error should point just past the statement: {err}");
+}
+
+#[test]
+fn exposes_reserved_keywords() {
+    let keywords = reserved_keywords();
+    for keyword in ["require", "function", "contract", "int", "bytes", "in"] {
+        assert!(keywords.contains(&keyword), "missing keyword {keyword}");
+    }
+}
+
+#[test]
+fn reserved_keywords_match_the_grammar_keyword_rule() {
+    let grammar = include_str!("../src/silverscript.pest");
+    let rule = grammar.split_once("\nkeyword = {").and_then(|(_, rest)| rest.split_once('}')).expect("grammar has a keyword rule").0;
+    let mut grammar_keywords: Vec<&str> = rule.split('"').skip(1).step_by(2).collect();
+    for keyword in &grammar_keywords {
+        let parsed = SilverScriptParser::parse(Rule::keyword_boundary, keyword).map(|pairs| pairs.as_str().len()).ok();
+        assert_eq!(parsed, Some(keyword.len()), "{keyword} parses as a keyword");
+        assert!(SilverScriptParser::parse(Rule::Identifier, keyword).is_err(), "{keyword} is not an identifier");
+    }
+
+    let mut listed = reserved_keywords().to_vec();
+    grammar_keywords.sort_unstable();
+    listed.sort_unstable();
+    assert_eq!(listed, grammar_keywords, "reserved_keywords() is out of sync with the grammar");
+}
+
+fn literal_contract(literal: &str) -> String {
+    format!(
+        r#"
+        contract Bounds() {{
+            entrypoint function main(int a) {{
+                int limit = {literal};
+                require(a < limit);
+            }}
+        }}
+    "#
+    )
+}
+
+fn parse_literal(literal: &str) -> Result<Expr, CompilerError> {
+    let contract = parse_contract_ast(&literal_contract(literal))?;
+    match &contract.functions[0].body[0].kind {
+        StatementKind::VariableDefinition { expr: Some(expr), .. } => Ok(expr.clone()),
+        other => panic!("unexpected statement {other:?}"),
+    }
+}
+
+#[test]
+fn string_escapes_decode_to_expected_bytes() {
+    assert_eq!(parse_literal(r#""a\n\tb\\\"c""#).unwrap(), Expr::String("a\n\tb\\\"c".to_string()));
+    assert_eq!(parse_literal(r#"'\x41\u{e9}\0\''"#).unwrap(), Expr::String("A\u{e9}\0'".to_string()));
+    assert_eq!(parse_literal(r#""\xff\x00""#).unwrap(), Expr::Bytes(vec![0xff, 0x00]), "non-UTF-8 escapes give bytes");
+    assert_eq!(
+        parse_literal("\"\"\"first line\nsecond \\n line\"\"\"").unwrap(),
+        Expr::String("first line\nsecond \\n line".to_string()),
+        "triple-quoted strings are raw and may span lines"
+    );
+}
+
+#[test]
+fn rejects_invalid_string_escapes_with_a_span() {
+    for (literal, escape, col) in [(r#""bad \q escape""#, r"\q", 34), (r#""\x4""#, r"\x4", 30), (r#""\u{110000}""#, r"\u", 30)] {
+        match parse_literal(literal) {
+            Err(CompilerError::InvalidEscape { escape: reported, span }) => {
+                assert_eq!(reported, escape);
+                assert_eq!((span.line, span.col), (4, col), "span points at the escape in {literal}");
+            }
+            other => panic!("{literal} should have an invalid escape, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn accepts_literals_at_script_number_bounds() {
+    let contract = parse_contract_ast(&literal_contract("9223372036854775807")).expect("i64::MAX fits");
+    match &contract.functions[0].body[0].kind {
+        StatementKind::VariableDefinition { expr: Some(Expr::Int(value)), .. } => assert_eq!(*value, i64::MAX),
+        other => panic!("unexpected statement {other:?}"),
+    }
+    assert!(parse_contract_ast(&literal_contract("92233720368 kas")).is_ok());
+    assert!(parse_contract_ast(&literal_contract("int(0xffffffffffffff7f)")).is_ok(), "8-byte hex fits a script number");
+}
+
+#[test]
+fn rejects_literals_outside_script_number_range() {
+    for literal in ["9223372036854775808", "99999999999999999999999999", "10e19", "200000000000 kas"] {
+        match parse_contract_ast(&literal_contract(literal)) {
+            Err(CompilerError::LiteralOutOfRange { literal: reported, span }) => {
+                assert_eq!(reported, literal);
+                assert_eq!((span.line, span.col), (4, 29), "span points at the literal");
+            }
+            other => panic!("{literal} should be out of range, got {other:?}"),
+        }
+    }
+
+    let err = parse_contract_ast(&literal_contract("int(0xffffffffffffffff01)")).expect_err("9-byte hex cast to int");
+    assert!(matches!(err, CompilerError::LiteralOutOfRange { ref literal, .. } if literal == "0xffffffffffffffff01"), "{err:?}");
+}
+
+#[test]
+fn rejects_duplicate_and_misplaced_pragmas_with_their_spans() {
+    let duplicate = "pragma silverscript ^0.1.0;\npragma silverscript ^0.2.0;\ncontract C() {}\n";
+    match parse_contract_ast(duplicate) {
+        Err(CompilerError::InvalidPragma { reason, span }) => {
+            assert_eq!(reason, "duplicate pragma; the first one is at line 1");
+            assert_eq!((span.line, span.col), (2, 1));
+        }
+        other => panic!("duplicate pragma should be rejected, got {other:?}"),
+    }
+
+    let after_contract = "contract C() {}\npragma silverscript ^0.1.0;\n";
+    let inside_contract = "contract C() {\n    pragma silverscript ^0.1.0;\n}\n";
+    for (source, col) in [(after_contract, 1), (inside_contract, 5)] {
+        match parse_contract_ast(source) {
+            Err(CompilerError::InvalidPragma { reason, span }) => {
+                assert_eq!(reason, "pragma must come before the contract definition");
+                assert_eq!((span.line, span.col), (2, col));
+            }
+            other => panic!("misplaced pragma should be rejected, got {other:?}"),
+        }
+    }
+
+    assert!(parse_contract_ast("pragma silverscript ^0.1.0;\ncontract C() {}\n").is_ok());
+}
+
+#[derive(Default)]
+struct RequireCounter {
+    requires: usize,
+    functions: Vec<String>,
+    identifiers: Vec<String>,
+}
+
+impl Visitor for RequireCounter {
+    fn visit_function(&mut self, function: &FunctionAst) {
+        self.functions.push(function.name.clone());
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        if matches!(statement.kind, StatementKind::Require { .. }) {
+            self.requires += 1;
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Identifier(name) = expr {
+            self.identifiers.push(name.clone());
+        }
+    }
+}
+
+#[test]
+fn visitor_reaches_nested_statements_and_expressions() {
+    let source = r#"
+        contract Walk(int limit) {
+            int constant FLOOR = 2;
+
+            function check(int x) {
+                require(x > FLOOR);
+            }
+
+            entrypoint function main(int a, int b) {
+                require(a < limit);
+                if (a > b) {
+                    require(b >= 0);
+                } else {
+                    for (i, 0, 2) {
+                        require(a + i != b);
+                    }
+                }
+                check(a);
+            }
+        }
+    "#;
+    let contract = parse_contract_ast(source).expect("contract parses");
+    let mut counter = RequireCounter::default();
+    walk_contract(&contract, &mut counter);
+    assert_eq!(counter.requires, 4);
+    assert_eq!(counter.functions, vec!["check", "main"]);
+    assert_eq!(counter.identifiers, vec!["x", "FLOOR", "a", "limit", "a", "b", "b", "a", "i", "b", "a"]);
+}
+
+#[test]
+fn keeps_comments_with_their_spans() {
+    let input = r#"/// Holds funds until `owner` signs.
+contract Vault(pubkey owner) {
+    /* spend path */
+    entrypoint function spend(sig s) { // owner only
+        string note = "// not a comment";
+        require(checkSig(s, owner)); /** checked
+        above */
+    }
+}
+"#;
+    let (contract, comments) = parse_contract_ast_with_comments(input).expect("contract parses");
+    assert_eq!(contract.name, "Vault");
+
+    let found: Vec<(CommentKind, &str, SourceSpan)> =
+        comments.iter().map(|comment| (comment.kind, comment.text.as_str(), comment.span)).collect();
+    assert_eq!(
+        found,
+        [
+            (CommentKind::DocLine, "/// Holds funds until `owner` signs.", SourceSpan { line: 1, col: 1, end_line: 1, end_col: 37 }),
+            (CommentKind::Block, "/* spend path */", SourceSpan { line: 3, col: 5, end_line: 3, end_col: 21 }),
+            (CommentKind::Line, "// owner only", SourceSpan { line: 4, col: 40, end_line: 4, end_col: 53 }),
+            (CommentKind::DocBlock, "/** checked\n        above */", SourceSpan { line: 6, col: 38, end_line: 7, end_col: 17 }),
+        ]
+    );
+}
@@ -13,6 +13,7 @@ use kaspa_txscript::{EngineCtx, EngineFlags, TxScriptEngine};
 use rand::RngCore;
 use silverscript_lang::ast::Expr;
 use silverscript_lang::compiler::{CompiledContract, function_branch_index};
+use silverscript_lang::debug::simulate::{TraceMeta, record_trace};
 
 fn temp_dir(name: &str) -> PathBuf {
     let mut rng = rand::thread_rng();
@@ -108,3 +109,102 @@ fn silverc_accepts_constructor_args_and_output_flag() {
         if compiled.without_selector { None } else { Some(function_branch_index(&compiled.ast, "main").expect("selector resolved")) };
     assert!(run_script_with_selector(compiled.script, selector).is_ok());
 }
+
+#[test]
+fn silverc_abi_writes_selectors_only() {
+    let dir = temp_dir("abi");
+    let src_path = dir.join("pair.sil");
+    let source = r#"
+        contract Pair() {
+            entrypoint function left(int a) {
+                require(a == 1);
+            }
+
+            entrypoint function right(bytes b) {
+                require(b.length == 2);
+            }
+        }
+    "#;
+    fs::write(&src_path, source).expect("write source");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_silverc")).arg(&src_path).arg("--abi").status().expect("run silverc");
+    assert!(status.success());
+
+    let json = fs::read_to_string(dir.join("pair.abi.json")).expect("read abi output");
+    let abi: serde_json::Value = serde_json::from_str(&json).expect("parse abi output");
+    assert_eq!(abi["contract_name"], "Pair");
+    assert_eq!(abi["entrypoints"][1]["name"], "right");
+    assert_eq!(abi["entrypoints"][1]["selector"], 1);
+    assert!(abi["compile_error"].is_null());
+    assert!(abi.get("script").is_none());
+}
+
+#[test]
+fn silverc_init_scaffolds_a_project_that_compiles_and_traces() {
+    let dir = temp_dir("init");
+    for kind in ["basic", "p2pkh"] {
+        let name = format!("my-{kind}");
+        let status = Command::new(env!("CARGO_BIN_EXE_silverc"))
+            .current_dir(&dir)
+            .args(["init", &name, "--kind", kind])
+            .status()
+            .expect("run silverc init");
+        assert!(status.success(), "silverc init --kind {kind} failed");
+
+        let project = dir.join(&name);
+        assert!(project.join("README.md").exists(), "README stub is written");
+        let status = Command::new(env!("CARGO_BIN_EXE_silverc"))
+            .current_dir(&project)
+            .args([format!("{name}.sil").as_str(), "--constructor-args", "args.json"])
+            .status()
+            .expect("run silverc");
+        assert!(status.success(), "scaffolded {kind} contract compiles");
+        assert!(project.join(format!("{name}.json")).exists());
+    }
+
+    let project = dir.join("my-basic");
+    let source = fs::read_to_string(project.join("my-basic.sil")).expect("read scaffolded contract");
+    assert!(source.contains("contract MyBasic("), "contract is named after the project: {source}");
+    let ctor_args: Vec<Expr> =
+        serde_json::from_str(&fs::read_to_string(project.join("args.json")).expect("read args")).expect("parse args");
+    let meta = TraceMeta { source, function: "spend".to_string(), ctor_args, args: vec![Expr::Int(1)], ..Default::default() };
+    let trace = record_trace(meta).expect("trace records");
+    assert!(trace.error.is_none(), "scaffolded contract traces successfully: {:?}", trace.error);
+
+    let rerun = Command::new(env!("CARGO_BIN_EXE_silverc")).current_dir(&dir).args(["init", "my-basic"]).output().expect("run");
+    assert!(!rerun.status.success(), "init refuses to overwrite an existing directory");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn silverc_deny_rejects_contracts_using_a_denied_opcode() {
+    let dir = temp_dir("deny");
+    let src_path = dir.join("joiner.sil");
+    let source = r#"
+        contract Joiner() {
+            entrypoint function spend(bytes suffix, bytes expected) {
+                require(0xab + suffix == expected);
+            }
+        }
+    "#;
+    fs::write(&src_path, source).expect("write source");
+
+    let run = |deny: &str| {
+        Command::new(env!("CARGO_BIN_EXE_silverc"))
+            .arg(src_path.to_str().unwrap())
+            .arg("--deny")
+            .arg(deny)
+            .arg("-o")
+            .arg(dir.join("out.json").to_str().unwrap())
+            .output()
+            .expect("run silverc")
+    };
+    let denied = run("OP_CHECKMULTISIG,OP_CAT");
+    assert!(!denied.status.success());
+    let stderr = String::from_utf8_lossy(&denied.stderr);
+    assert!(stderr.contains("denied opcode OpCat at line 4"), "stderr: {stderr}");
+
+    assert!(run("OP_CHECKMULTISIG").status.success());
+    let unknown = run("OP_NOPE");
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("unknown opcode in --deny: OP_NOPE"));
+}
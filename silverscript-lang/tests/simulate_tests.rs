@@ -0,0 +1,1106 @@
+use std::collections::BTreeMap;
+
+use secp256k1::{Keypair, Secp256k1, SecretKey};
+use silverscript_lang::ast::Expr;
+use silverscript_lang::compiler::{CompileOptions, CompiledContract, compile_contract, compile_entrypoint, pubkey_hash};
+use silverscript_lang::debug::DebugLevel;
+use silverscript_lang::debug::html::trace_to_html;
+use silverscript_lang::debug::simulate::{
+    ExecutionTrace, LineStatus, SpendStatus, TraceMeta, compare_session_with_engine, first_divergence, merge_require_coverage,
+    record_trace, record_trace_at_level, reduce_failing_args, replay_trace, run_sample_spend, run_test_vectors, sample_keypair,
+    simulate, simulate_status, trace_compiled, verify_auto_signed,
+};
+use silverscript_lang::examples::{example, examples};
+
+#[test]
+fn simulate_reports_success_and_failure() {
+    let source = r#"
+        contract Limit() {
+            entrypoint function main(int a) {
+                require(a < 100);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], CompileOptions::default()).expect("compile succeeds");
+
+    assert!(simulate(&compiled, "main", vec![Expr::Int(5)]).is_ok());
+    assert!(simulate(&compiled, "main", vec![Expr::Int(500)]).is_err());
+}
+
+#[test]
+fn simulate_status_distinguishes_a_clean_stack_from_leftovers() {
+    let source = r#"
+        contract Yields() {
+            entrypoint function two(int a) {
+                yield(a, a);
+            }
+
+            entrypoint function one(int a) {
+                yield(a);
+            }
+        }
+    "#;
+    let options = CompileOptions { allow_yield: true, ..Default::default() };
+    let compiled = compile_contract(source, &[], options).expect("compile succeeds");
+
+    assert_eq!(simulate_status(&compiled, "two", vec![Expr::Int(1)]), Ok(SpendStatus::InvalidDirtyStack { extra: 1 }));
+    assert_eq!(simulate_status(&compiled, "one", vec![Expr::Int(0)]), Ok(SpendStatus::InvalidFalse));
+    assert_eq!(simulate_status(&compiled, "one", vec![Expr::Int(1)]), Ok(SpendStatus::Valid));
+
+    let err = simulate(&compiled, "two", vec![Expr::Int(1)]).expect_err("a dirty stack is not a valid spend");
+    assert!(err.contains("1 extra stack entries"), "unexpected error: {err}");
+    assert!(simulate(&compiled, "one", vec![Expr::Int(1)]).is_ok());
+}
+
+#[test]
+fn entrypoint_only_compile_drops_other_functions_and_the_selector() {
+    let source = r#"
+        contract Vault(int floor) {
+            function atLeast(int value) {
+                require(value >= floor);
+            }
+
+            function unused(int value) {
+                require(value * 3 != 7);
+            }
+
+            entrypoint function spend(int value) {
+                atLeast(value);
+            }
+
+            entrypoint function sweep(int value, int other) {
+                unused(value);
+                require(value + other == 42);
+            }
+        }
+    "#;
+    let ctor_args = [Expr::Int(10)];
+    let full = compile_contract(source, &ctor_args, CompileOptions::default()).expect("compile succeeds");
+    let stripped = compile_entrypoint(source, &ctor_args, CompileOptions::default(), "spend").expect("compile succeeds");
+
+    assert!(!full.without_selector);
+    assert!(stripped.without_selector);
+    assert!(stripped.script.len() < full.script.len());
+    let names: Vec<&str> = stripped.ast.functions.iter().map(|func| func.name.as_str()).collect();
+    assert_eq!(names, ["atLeast", "spend"]);
+    let abi_names: Vec<&str> = stripped.abi.iter().map(|entry| entry.name.as_str()).collect();
+    assert_eq!(abi_names, ["spend"]);
+
+    assert!(simulate(&stripped, "spend", vec![Expr::Int(12)]).is_ok());
+    assert!(simulate(&stripped, "spend", vec![Expr::Int(3)]).is_err());
+    let err = compile_entrypoint(source, &ctor_args, CompileOptions::default(), "atLeast").unwrap_err();
+    assert!(err.to_string().contains("function 'atLeast' is not an entrypoint"), "{err}");
+}
+
+#[test]
+fn reducer_shrinks_failure_to_responsible_argument() {
+    let source = r#"
+        contract Limit() {
+            entrypoint function main(int a, int b, bytes c) {
+                require(b >= 0);
+                require(c.length <= 8);
+                require(a < 100);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], CompileOptions::default()).expect("compile succeeds");
+
+    let args = vec![Expr::Int(500), Expr::Int(77), Expr::Bytes(vec![0x12, 0x34])];
+    let reduced = reduce_failing_args(&compiled, "main", args).expect("reduction succeeds");
+
+    // Only `a` is responsible; it is halved down to the smallest value that still fails.
+    assert_eq!(reduced.args, vec![Expr::Int(125), Expr::Int(0), Expr::Bytes(vec![])]);
+    assert_eq!(simulate(&compiled, "main", reduced.args.clone()).unwrap_err(), reduced.failure);
+}
+
+#[test]
+fn reducer_rejects_passing_arguments() {
+    let source = r#"
+        contract Limit() {
+            entrypoint function main(int a) {
+                require(a < 100);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], CompileOptions::default()).expect("compile succeeds");
+
+    assert!(reduce_failing_args(&compiled, "main", vec![Expr::Int(1)]).is_err());
+}
+
+#[test]
+fn trace_reports_phase_timings() {
+    let source = r#"
+        contract Timed() {
+            entrypoint function main(int a) {
+                log("a", a);
+                require(a > 0);
+            }
+        }
+    "#;
+    let meta = TraceMeta { source: source.to_string(), function: "main".to_string(), args: vec![Expr::Int(5)], ..Default::default() };
+    let trace = record_trace(meta).expect("trace records");
+    assert!(trace.error.is_none(), "trace should pass: {:?}", trace.error);
+    let timings = trace.timings;
+    let phases =
+        [("parse", timings.parse_ms), ("compile", timings.compile_ms), ("execute", timings.execute_ms), ("logs", timings.logs_ms)];
+    for (phase, ms) in phases {
+        assert!(ms.is_finite() && ms >= 0.0, "{phase} took {ms}ms");
+    }
+
+    let json = serde_json::to_value(&trace).unwrap();
+    for field in ["parse_ms", "compile_ms", "execute_ms", "logs_ms"] {
+        assert!(json["timings"][field].is_number(), "timings.{field} is serialized");
+    }
+}
+
+#[test]
+fn trace_records_an_engine_error_where_the_spend_stopped() {
+    let source = r#"
+        contract Divide() {
+            entrypoint function main(int a) {
+                int q = 10 / a;
+                require(q > 0);
+            }
+        }
+    "#;
+    let meta = TraceMeta { source: source.to_string(), function: "main".to_string(), args: vec![Expr::Int(0)], ..Default::default() };
+    let trace = record_trace(meta).expect("a failing spend still records a trace");
+    let err = trace.error.as_deref().expect("dividing by zero fails the spend");
+    assert!(!err.contains("engine panicked"), "an engine error is not a panic: {err}");
+    let last = trace.source_steps.last().expect("the division's statement was reached");
+    assert_eq!(last.span.line, 4, "the trace ends in the statement that divides");
+}
+
+#[test]
+fn trace_accepts_named_args() {
+    let source = r#"
+        contract Named() {
+            entrypoint function main(int a, int b) {
+                require(a - b == 3);
+            }
+        }
+    "#;
+    let meta = TraceMeta {
+        source: source.to_string(),
+        function: "main".to_string(),
+        named_args: [("b".to_string(), Expr::Int(2)), ("a".to_string(), Expr::Int(5))].into_iter().collect(),
+        ..Default::default()
+    };
+    let named = record_trace(meta.clone()).expect("trace records");
+    assert!(named.error.is_none(), "trace should pass: {:?}", named.error);
+    let positional = record_trace(TraceMeta { args: vec![Expr::Int(5), Expr::Int(2)], named_args: BTreeMap::new(), ..meta.clone() })
+        .expect("trace records");
+    assert_eq!(named.steps, positional.steps);
+
+    let both = TraceMeta { args: vec![Expr::Int(5), Expr::Int(2)], ..meta };
+    assert!(record_trace(both).is_err());
+}
+
+#[test]
+fn input_value_reflects_the_configured_utxo() {
+    let source = r#"
+        contract Keep(int expected) {
+            entrypoint function spend() {
+                require(input.value == expected);
+                require(input.lockingBytecode == tx.inputs[this.activeInputIndex].lockingBytecode);
+                require(tx.outputs[0].lockingBytecode == input.lockingBytecode);
+            }
+        }
+    "#;
+    let meta = |utxo_value: u64| TraceMeta {
+        source: source.to_string(),
+        function: "spend".to_string(),
+        ctor_args: vec![Expr::Int(250_000)],
+        utxo_value: Some(utxo_value),
+        ..Default::default()
+    };
+    let trace = record_trace(meta(250_000)).expect("trace records");
+    assert!(trace.error.is_none(), "input.value should match the UTXO: {:?}", trace.error);
+    let trace = record_trace(meta(250_001)).expect("trace records");
+    assert!(trace.error.is_some(), "a different UTXO value should fail");
+}
+
+#[test]
+fn tx_fee_is_what_the_outputs_leave_of_the_inputs() {
+    let source = r#"
+        contract FeeCap(int maxFee) {
+            entrypoint function spend() {
+                require(tx.fee <= maxFee);
+            }
+        }
+    "#;
+    let meta = |output_values: Vec<u64>| TraceMeta {
+        source: source.to_string(),
+        function: "spend".to_string(),
+        ctor_args: vec![Expr::Int(1_000)],
+        utxo_value: Some(100_000),
+        output_values,
+        ..Default::default()
+    };
+    let trace = record_trace(meta(vec![60_000, 39_500])).expect("trace records");
+    assert!(trace.error.is_none(), "a fee of 500 is under the cap: {:?}", trace.error);
+    let trace = record_trace(meta(vec![60_000, 38_000])).expect("trace records");
+    assert!(trace.error.is_some(), "a fee of 2000 is over the cap");
+    assert!(record_trace(meta(vec![100_001])).is_err(), "outputs cannot pay more than the input holds");
+}
+
+#[test]
+fn require_all_outputs_match_checks_every_output_script() {
+    let source = r#"
+        contract FanOut() {
+            entrypoint function split() {
+                requireAllOutputsMatch();
+            }
+        }
+    "#;
+    let meta = |output_values: Vec<u64>, output_scripts: Vec<&str>| TraceMeta {
+        source: source.to_string(),
+        function: "split".to_string(),
+        utxo_value: Some(90_000),
+        output_values,
+        output_scripts: output_scripts.into_iter().map(str::to_string).collect(),
+        ..Default::default()
+    };
+    let trace = record_trace(meta(vec![30_000, 30_000, 30_000], vec![])).expect("trace records");
+    assert!(trace.error.is_none(), "every output pays back to the contract: {:?}", trace.error);
+    let trace = record_trace(meta(vec![30_000, 30_000, 30_000], vec!["", "", "51"])).expect("trace records");
+    assert!(trace.error.is_some(), "the last output is locked by another script");
+    let trace = record_trace(meta(vec![10_000; 9], vec![])).expect("trace records");
+    assert!(trace.error.is_some(), "more outputs than the check covers fail the spend");
+    assert!(record_trace(meta(vec![30_000], vec!["", "51"])).is_err(), "more scripts than outputs");
+
+    let payout = r#"
+        contract Payout(bytes payee) {
+            entrypoint function split() {
+                requireAllOutputsMatch(payee);
+            }
+        }
+    "#;
+    let meta = |output_scripts: Vec<&str>| TraceMeta {
+        source: payout.to_string(),
+        // Locking bytecode carries the script version ahead of the script.
+        ctor_args: vec![Expr::Bytes(vec![0x00, 0x00, 0x51])],
+        ..meta(vec![45_000, 45_000], output_scripts)
+    };
+    let trace = record_trace(meta(vec!["51", "0x51"])).expect("trace records");
+    assert!(trace.error.is_none(), "both outputs pay the given script: {:?}", trace.error);
+    let trace = record_trace(meta(vec!["51"])).expect("trace records");
+    assert!(trace.error.is_some(), "the second output pays back to the contract");
+}
+
+#[test]
+fn compiled_contract_json_round_trip_builds_and_traces_identically() {
+    let source = r#"
+        contract Split(int limit) {
+            entrypoint function low(int a) {
+                log("low", a);
+                require(a < limit);
+            }
+
+            entrypoint function high(int a, bytes2 tag) {
+                if (a > limit) {
+                    require(tag == 0x0102);
+                }
+            }
+        }
+    "#;
+    let options = CompileOptions { record_debug_infos: true, ..Default::default() };
+    let compiled = compile_contract(source, &[Expr::Int(10)], options).expect("compile succeeds");
+    let restored = CompiledContract::from_json(&compiled.to_json().expect("serializes")).expect("deserializes");
+
+    assert_eq!(restored.script, compiled.script);
+    assert_eq!(restored.without_selector, compiled.without_selector);
+    assert_eq!(restored.used_ctor_params, compiled.used_ctor_params);
+    assert!(restored.ast.functions[0].body.iter().all(|stmt| stmt.span.is_some()), "statement spans survive");
+    let args = vec![Expr::Int(12), Expr::Bytes(vec![1, 2])];
+    assert!(restored.debug_info.as_ref().is_some_and(|info| !info.log_points.is_empty()), "debug info survives");
+    assert_eq!(restored.build_sig_script("high", args.clone()).unwrap(), compiled.build_sig_script("high", args.clone()).unwrap());
+
+    for (function, args) in [("low", vec![Expr::Int(3)]), ("high", args)] {
+        let meta = TraceMeta { source: source.to_string(), function: function.to_string(), args, ..Default::default() };
+        let original = trace_compiled(&compiled, meta.clone()).expect("trace records");
+        let replayed = trace_compiled(&restored, meta).expect("trace records");
+        assert!(original.error.is_none(), "{function}: {:?}", original.error);
+        assert_eq!(original.steps, replayed.steps);
+        assert_eq!(original.line_coverage, replayed.line_coverage);
+        assert_eq!(original.logs, replayed.logs);
+    }
+}
+
+/// The contract `silverc init` writes by default.
+const BASIC_TEMPLATE: &str = r#"pragma silverscript ^0.1.0;
+
+contract Basic(int threshold) {
+    entrypoint function spend(int value) {
+        require(value >= threshold);
+    }
+}
+"#;
+
+#[test]
+fn debug_session_matches_direct_engine_execution() {
+    let options = CompileOptions { record_debug_infos: true, ..Default::default() };
+    let basic = compile_contract(BASIC_TEMPLATE, &[Expr::Int(10)], options).expect("compile succeeds");
+    for value in [10, 42, 9] {
+        let comparison = compare_session_with_engine(&basic, "spend", vec![Expr::Int(value)], &[]).expect("spend runs");
+        assert!(comparison.matches(), "value {value}: {comparison:?}");
+        assert_eq!(comparison.engine_error.is_none(), value >= 10);
+    }
+
+    let p2pkh = r#"
+        contract P2PKH(pubkey owner) {
+            entrypoint function spend(sig ownerSig) {
+                require(checkSig(ownerSig, owner));
+            }
+        }
+    "#;
+    let owner = sample_keypair(0);
+    let owner_key = Expr::Bytes(owner.x_only_public_key().0.serialize().to_vec());
+    let signed = compile_contract(p2pkh, &[owner_key], options).expect("compile succeeds");
+    let placeholder = vec![Expr::Bytes(vec![0u8; 65])];
+
+    let valid = compare_session_with_engine(&signed, "spend", placeholder.clone(), &[owner]).expect("spend runs");
+    assert!(valid.matches(), "{valid:?}");
+    assert_eq!(valid.engine_error, None);
+
+    let wrong_key = compare_session_with_engine(&signed, "spend", placeholder, &[sample_keypair(1)]).expect("spend runs");
+    assert!(wrong_key.matches(), "{wrong_key:?}");
+    assert!(wrong_key.engine_error.is_some());
+}
+
+#[test]
+fn sample_keypairs_differ_for_every_index() {
+    let keys: Vec<_> = [0, 1, 255, 256, 511].into_iter().map(|index| sample_keypair(index).x_only_public_key().0).collect();
+    for (i, key) in keys.iter().enumerate() {
+        assert!(!keys[i + 1..].contains(key), "key {i} repeats");
+    }
+    assert_eq!(sample_keypair(256).secret_bytes(), sample_keypair(256).secret_bytes(), "keys are deterministic");
+}
+
+#[test]
+fn replay_matches_saved_trace_and_detects_divergence() {
+    let source = r#"
+        contract Replay() {
+            entrypoint function main(int a) {
+                int b = a * 2;
+                require(b == 10);
+            }
+        }
+    "#;
+    let meta = TraceMeta { source: source.to_string(), function: "main".to_string(), args: vec![Expr::Int(5)], ..Default::default() };
+    let trace = record_trace(meta).expect("trace records");
+    assert!(trace.error.is_none(), "trace should pass: {:?}", trace.error);
+    assert!(!trace.steps.is_empty());
+
+    // Round-trip through JSON like a trace saved to disk.
+    let saved: ExecutionTrace = serde_json::from_str(&serde_json::to_string(&trace).unwrap()).unwrap();
+    let report = replay_trace(&saved).expect("replay runs");
+    assert!(report.matches, "replay of unchanged source should match");
+    assert_eq!(report.divergence, None);
+
+    // Force a divergence by changing the compiled behavior for the same inputs.
+    let mut diverged = saved.clone();
+    diverged.meta.source = source.replace("a * 2", "a * 3");
+    let report = replay_trace(&diverged).expect("replay runs");
+    assert!(!report.matches);
+    assert!(report.divergence.is_some(), "step sequences should diverge");
+    assert!(report.expected_error.is_none());
+    assert!(report.actual_error.is_some(), "modified contract should fail for the saved inputs");
+}
+
+#[test]
+fn replay_records_at_the_saved_debug_level() {
+    let source = r#"
+        contract Replay() {
+            entrypoint function main(int a) {
+                int b = a * 2; int c = b + 1;
+                require(c == 11);
+            }
+        }
+    "#;
+    let meta = TraceMeta { source: source.to_string(), function: "main".to_string(), args: vec![Expr::Int(5)], ..Default::default() };
+    let full = record_trace(meta.clone()).expect("trace records");
+    let lines = record_trace_at_level(meta, DebugLevel::Lines).expect("trace records");
+    assert_eq!(lines.meta.debug_level, DebugLevel::Lines);
+    assert!(lines.source_steps.len() < full.source_steps.len(), "statements sharing a line share a source step");
+
+    let saved: ExecutionTrace = serde_json::from_str(&serde_json::to_string(&lines).unwrap()).unwrap();
+    let report = replay_trace(&saved).expect("replay runs");
+    assert!(report.matches, "replay should record at the saved level: {:?}", report.divergence);
+}
+
+#[test]
+fn diff_reports_first_divergent_step_between_contracts() {
+    let left_source = r#"
+        contract Diff() {
+            entrypoint function main(int a) {
+                require(a > 0);
+                require(a + 2 == 7);
+            }
+        }
+    "#;
+    let right_source = left_source.replace("a + 2", "a * 2");
+    let meta = |source: &str| TraceMeta {
+        source: source.to_string(),
+        function: "main".to_string(),
+        args: vec![Expr::Int(5)],
+        ..Default::default()
+    };
+
+    let left = record_trace(meta(left_source)).expect("left trace records");
+    let right = record_trace(meta(&right_source)).expect("right trace records");
+    assert!(first_divergence(&left, &left).is_none(), "a trace never diverges from itself");
+
+    // Source step 0 is the shared `require(a > 0)`; the arithmetic opcode sits in step 1.
+    let divergence = first_divergence(&left, &right).expect("traces diverge");
+    assert_eq!(divergence.step_index, 1);
+    assert_eq!(left.source_steps[1].span.line, 5);
+    let (left_step, right_step) = (divergence.left.expect("left step"), divergence.right.expect("right step"));
+    assert_ne!(left_step.opcode, right_step.opcode);
+    assert_eq!(left_step.byte_offset, right_step.byte_offset);
+    let shared = left.source_steps[0].opcode_steps.clone();
+    assert_eq!(left.steps[shared.clone()], right.steps[shared]);
+}
+
+#[test]
+fn line_coverage_marks_untaken_branch_as_skipped() {
+    let source = r#"pragma silverscript ^0.1.0;
+
+contract Branches() {
+    entrypoint function main(int a) {
+        if (a > 10) {
+            require(a < 100);
+        } else {
+            require(a == 3);
+        }
+        require(a != 0);
+    }
+}
+"#;
+    let meta = TraceMeta { source: source.to_string(), function: "main".to_string(), args: vec![Expr::Int(50)], ..Default::default() };
+    let trace = record_trace(meta).expect("trace records");
+    assert!(trace.error.is_none(), "trace should pass: {:?}", trace.error);
+    assert_eq!(trace.line_coverage.len(), source.lines().count());
+
+    let status = |line: u32| trace.line_coverage.iter().find(|entry| entry.line == line).map(|entry| entry.status);
+    assert_eq!(status(1), Some(LineStatus::Unmapped));
+    assert_eq!(status(6), Some(LineStatus::Executed), "taken branch runs");
+    assert_eq!(status(8), Some(LineStatus::Skipped), "else branch is not executed");
+    assert_eq!(status(10), Some(LineStatus::Executed));
+}
+
+#[test]
+fn html_export_bundles_viewer_assets_with_the_trace() {
+    let source = r#"pragma silverscript ^0.1.0;
+
+// A stray </script> in a comment must not end the embedded trace early.
+contract Html() {
+    entrypoint function main(int a) {
+        require(a == 7);
+    }
+}
+"#;
+    let meta = TraceMeta { source: source.to_string(), function: "main".to_string(), args: vec![Expr::Int(7)], ..Default::default() };
+    let trace = record_trace(meta).expect("trace records");
+    let html = trace_to_html(&trace).expect("html renders");
+
+    assert!(html.contains(include_str!("../src/debug/html/viewer.js")), "viewer script is inlined");
+    assert!(html.contains(include_str!("../src/debug/html/viewer.css")), "viewer styles are inlined");
+    assert!(!html.contains("{{"), "every placeholder is filled");
+    assert_eq!(html.matches("</script>").count(), 2, "only the data and viewer elements close a script");
+
+    let start = html.find(r#"<script type="application/json" id="trace-data">"#).expect("trace data element");
+    let body = &html[start..];
+    let json = &body[body.find('>').unwrap() + 1..body.find("</script>").unwrap()];
+    let embedded: ExecutionTrace = serde_json::from_str(json).expect("embedded trace parses");
+    assert_eq!(embedded.meta.source, source);
+    assert_eq!(embedded.steps, trace.steps);
+    assert_eq!(embedded.source_steps, trace.source_steps);
+}
+
+#[test]
+fn log_statements_surface_evaluated_values_in_trace() {
+    let source = r#"
+        contract Logged() {
+            entrypoint function spend(int a, bytes memo) {
+                int fee = a * 2;
+                log("entered spend", fee, memo);
+                if (a > 100) {
+                    log("large spend");
+                }
+                require(fee < 100);
+            }
+        }
+    "#;
+    let meta = TraceMeta {
+        source: source.to_string(),
+        function: "spend".to_string(),
+        args: vec![Expr::Int(21), Expr::Bytes(vec![0xab, 0xcd])],
+        ..Default::default()
+    };
+    let trace = record_trace(meta).expect("trace records");
+    assert!(trace.error.is_none(), "trace should pass: {:?}", trace.error);
+
+    // The untaken branch's log never fires.
+    assert_eq!(trace.logs.len(), 1);
+    assert_eq!(trace.logs[0].line, 5);
+    assert_eq!(trace.logs[0].values, vec!["entered spend", "42", "0xabcd"]);
+
+    let stripped: Vec<_> = source.lines().filter(|line| !line.trim_start().starts_with("log(")).collect();
+    let compiled = compile_contract(source, &[], CompileOptions::default()).expect("compile succeeds");
+    let plain = compile_contract(&stripped.join("\n"), &[], CompileOptions::default()).expect("compile succeeds");
+    assert_eq!(compiled.script, plain.script, "log statements emit no opcodes");
+}
+
+#[test]
+fn triggered_warn_if_is_reported_without_failing_the_run() {
+    let source = r#"
+        contract Soft() {
+            entrypoint function spend(int fee) {
+                warnIf(fee > 50, "unusually high fee");
+                warnIf(fee < 0, "negative fee");
+                require(fee < 100);
+            }
+        }
+    "#;
+    let meta =
+        TraceMeta { source: source.to_string(), function: "spend".to_string(), args: vec![Expr::Int(80)], ..Default::default() };
+    let trace = record_trace(meta.clone()).expect("trace records");
+    assert!(trace.error.is_none(), "warnings never fail the run: {:?}", trace.error);
+    assert_eq!(trace.warnings.len(), 1, "{:?}", trace.warnings);
+    assert_eq!(trace.warnings[0].line, 4);
+    assert_eq!(trace.warnings[0].message, "unusually high fee");
+    assert!(trace.logs.is_empty());
+
+    let quiet = record_trace(TraceMeta { args: vec![Expr::Int(10)], ..meta }).expect("trace records");
+    assert!(quiet.warnings.is_empty());
+
+    let stripped: Vec<_> = source.lines().filter(|line| !line.trim_start().starts_with("warnIf(")).collect();
+    let compiled = compile_contract(source, &[], CompileOptions::default()).expect("compile succeeds");
+    let plain = compile_contract(&stripped.join("\n"), &[], CompileOptions::default()).expect("compile succeeds");
+    assert_eq!(compiled.script, plain.script, "warnIf emits no opcodes");
+}
+
+#[test]
+fn length_and_require_length_check_byte_lengths() {
+    let source = r#"
+        contract Sized() {
+            entrypoint function main(bytes data) {
+                log("size", length(data));
+                requireLength(data, 3);
+            }
+        }
+    "#;
+    let meta = |data: Vec<u8>| TraceMeta {
+        source: source.to_string(),
+        function: "main".to_string(),
+        args: vec![Expr::Bytes(data)],
+        ..Default::default()
+    };
+    let trace = record_trace(meta(vec![0xaa, 0xbb, 0xcc])).expect("trace records");
+    assert!(trace.error.is_none(), "requireLength should pass: {:?}", trace.error);
+    assert_eq!(trace.logs[0].values, vec!["size", "3"]);
+    let size_step = trace.steps.iter().find(|step| step.opcode.contains("OpSize")).expect("OpSize runs");
+    assert_eq!(size_step.stack.last().map(String::as_str), Some("03"), "the computed length is on top of the stack");
+
+    let coverage = |trace: &ExecutionTrace| -> Vec<(String, bool, bool)> {
+        trace.require_coverage.iter().map(|entry| (entry.condition.clone(), entry.seen_true, entry.seen_false)).collect()
+    };
+    assert_eq!(coverage(&trace), vec![("data.length == 3".to_string(), true, false)]);
+
+    let trace = record_trace(meta(vec![0xaa, 0xbb])).expect("trace records");
+    assert!(trace.error.is_some(), "requireLength should fail for a 2-byte value");
+    assert_eq!(coverage(&trace), vec![("data.length == 3".to_string(), false, true)]);
+
+    for (body, expected) in [
+        ("int n = 5; require(length(n) == 1);", "length() expects a byte string or an array"),
+        ("int n = 5; requireLength(n, 1);", "requireLength() expects a byte string as its first argument"),
+        ("bytes2 b = 0x0102; requireLength(b, 3);", "requireLength() always fails: the value is 2 bytes, not 3"),
+    ] {
+        let source = format!("contract Bad() {{ entrypoint function main() {{ {body} }} }}");
+        let err = compile_contract(&source, &[], CompileOptions::default()).unwrap_err().to_string();
+        assert!(err.contains(expected), "{body}: {err}");
+    }
+}
+
+#[test]
+fn script_public_key_matches_the_debugger_spend() {
+    let source = r#"
+        contract SelfAware() {
+            entrypoint function main(bytes expected) {
+                require(input.lockingBytecode == expected);
+            }
+        }
+    "#;
+    let compiled = compile_contract(source, &[], CompileOptions::default()).expect("compile succeeds");
+    let spk = compiled.to_script_public_key(0);
+    assert_eq!(spk.script(), compiled.script.as_slice());
+    let mut expected = spk.version().to_be_bytes().to_vec();
+    expected.extend_from_slice(spk.script());
+
+    let meta = TraceMeta {
+        source: source.to_string(),
+        function: "main".to_string(),
+        args: vec![Expr::Bytes(expected)],
+        utxo_value: Some(1_000),
+        ..Default::default()
+    };
+    let trace = record_trace(meta).expect("trace records");
+    assert!(trace.error.is_none(), "the debugger spends the same locking script: {:?}", trace.error);
+}
+
+#[test]
+fn execution_index_follows_taken_else_branch() {
+    let source = r#"
+        contract Branch() {
+            entrypoint function main(int a) {
+                if (a > 10) {
+                    require(a < 100);
+                    require(a != 50);
+                } else {
+                    require(a == 3);
+                }
+            }
+        }
+    "#;
+    let meta = TraceMeta { source: source.to_string(), function: "main".to_string(), args: vec![Expr::Int(3)], ..Default::default() };
+    let trace = record_trace(meta).expect("trace records");
+    assert!(trace.error.is_none(), "trace should pass: {:?}", trace.error);
+
+    // The then-branch opcodes are stepped over without executing, so `pc` and execution order diverge.
+    let skipped = trace.steps.iter().filter(|step| step.execution_index.is_none()).count();
+    assert!(skipped > 0, "then-branch opcodes should be skipped");
+    let executed: Vec<_> = trace.steps.iter().filter_map(|step| step.execution_index.map(|index| (index, step.byte_offset))).collect();
+    assert_eq!(executed.iter().map(|(index, _)| *index).collect::<Vec<_>>(), (0..executed.len()).collect::<Vec<_>>());
+
+    // Executed byte offsets jump over the untaken branch instead of advancing one opcode at a time.
+    let first_gap = trace.steps.iter().position(|step| step.execution_index.is_none()).unwrap();
+    let resumed = trace.steps[first_gap..].iter().find(|step| step.execution_index.is_some()).unwrap();
+    assert_eq!(resumed.execution_index, Some(first_gap));
+    assert!(resumed.byte_offset > trace.steps[first_gap].byte_offset);
+}
+
+#[test]
+fn source_steps_and_opcode_steps_point_at_each_other() {
+    let source = r#"
+        contract Linked() {
+            function double(int x) : (int) {
+                return(x * 2);
+            }
+
+            entrypoint function main(int a) {
+                (int twice) = double(a);
+                if (a > 10) {
+                    require(twice < 200);
+                } else {
+                    require(twice == 6);
+                }
+            }
+        }
+    "#;
+    let meta = TraceMeta { source: source.to_string(), function: "main".to_string(), args: vec![Expr::Int(3)], ..Default::default() };
+    let trace = record_trace(meta).expect("trace records");
+    assert!(trace.error.is_none(), "trace should pass: {:?}", trace.error);
+    assert!(!trace.source_steps.is_empty());
+
+    for (index, source_step) in trace.source_steps.iter().enumerate() {
+        assert!(!source_step.opcode_steps.is_empty());
+        for step in &trace.steps[source_step.opcode_steps.clone()] {
+            assert_eq!(step.source_step, Some(index), "every opcode in a source step's range points back at it");
+        }
+    }
+    for (index, step) in trace.steps.iter().enumerate() {
+        if let Some(source_step) = step.source_step {
+            assert!(trace.source_steps[source_step].opcode_steps.contains(&index));
+        } else {
+            assert!(trace.source_steps.iter().all(|source_step| !source_step.opcode_steps.contains(&index)));
+        }
+    }
+    assert!(trace.source_steps.windows(2).all(|pair| pair[0].opcode_steps.end <= pair[1].opcode_steps.start));
+
+    // The untaken branch has no source step; the taken one does.
+    let lines: Vec<u32> = trace.source_steps.iter().map(|source_step| source_step.span.line).collect();
+    assert!(!lines.contains(&10), "lines: {lines:?}");
+    assert!(lines.contains(&12), "lines: {lines:?}");
+}
+
+#[test]
+fn source_step_over_targets_skip_inlined_calls() {
+    let source = r#"
+        contract Over() {
+            function double(int x) : (int) {
+                require(x >= 0);
+                return(x * 2);
+            }
+
+            entrypoint function main(int a) {
+                (int twice) = double(a);
+                require(twice == 6);
+            }
+        }
+    "#;
+    let meta = TraceMeta { source: source.to_string(), function: "main".to_string(), args: vec![Expr::Int(3)], ..Default::default() };
+    let trace = record_trace(meta).expect("trace records");
+    assert!(trace.error.is_none(), "trace should pass: {:?}", trace.error);
+    let steps = &trace.source_steps;
+    assert!(steps.iter().any(|step| step.call_depth > 0 && step.span.line == 4), "the callee body runs inlined");
+
+    for (index, step) in steps.iter().enumerate() {
+        let skipped = match step.step_over {
+            Some(target) => {
+                assert!(target > index && steps[target].call_depth <= step.call_depth);
+                &steps[index + 1..target]
+            }
+            None => &steps[index + 1..],
+        };
+        assert!(skipped.iter().all(|skipped| skipped.call_depth > step.call_depth), "step {index} skips only deeper steps");
+    }
+
+    // Stepping over from the entrypoint body never stops inside `double`.
+    let mut index = steps.iter().position(|step| step.call_depth == 0).expect("an entrypoint statement runs");
+    let mut visited = vec![steps[index].span.line];
+    while let Some(next) = steps[index].step_over {
+        visited.push(steps[next].span.line);
+        index = next;
+    }
+    assert!(visited.iter().all(|line| *line >= 9), "visited lines: {visited:?}");
+    assert_eq!(visited.last(), Some(&10));
+}
+
+#[test]
+fn auto_signed_spend_separates_signing_failures_from_logic_failures() {
+    let secp = Secp256k1::new();
+    let keypair = |byte: u8| Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[byte; 32]).expect("valid secret key"));
+    let owner = keypair(1);
+    let source = r#"
+        contract P2PK(pubkey owner) {
+            entrypoint function spend(sig s, int nonce) {
+                require(nonce == 1);
+                require(checkSig(s, owner));
+            }
+        }
+    "#;
+    let owner_key = Expr::Bytes(owner.x_only_public_key().0.serialize().to_vec());
+    let compiled = compile_contract(source, &[owner_key], CompileOptions::default()).expect("compile succeeds");
+    let placeholder = || Expr::Bytes(vec![0u8; 65]);
+
+    let signed = verify_auto_signed(&compiled, "spend", vec![placeholder(), Expr::Int(1)], &[owner]).expect("spend runs");
+    assert!(signed.signature_valid, "owner signature should pass: {:?}", signed.error);
+
+    let wrong_key = verify_auto_signed(&compiled, "spend", vec![placeholder(), Expr::Int(1)], &[keypair(2)]).expect("spend runs");
+    assert!(!wrong_key.signature_valid);
+    assert!(wrong_key.likely_sighash_mismatch, "failure happens at the signature check");
+
+    let bad_logic = verify_auto_signed(&compiled, "spend", vec![placeholder(), Expr::Int(2)], &[owner]).expect("spend runs");
+    assert!(!bad_logic.signature_valid);
+    assert!(!bad_logic.likely_sighash_mismatch, "failure happens before any signature check");
+
+    let err = verify_auto_signed(&compiled, "spend", vec![placeholder(), Expr::Int(1)], &[]).expect_err("no keys");
+    assert!(err.contains("needs 1 signing keys but 0 were provided"), "unexpected error: {err}");
+}
+
+#[test]
+fn length_prefixed_payload_round_trips_through_encode_and_decode() {
+    let source = r#"
+        contract Framing() {
+            entrypoint function main(bytes payload) {
+                bytes encoded = encodeLP(payload, 2);
+                require(encoded.length == payload.length + 2);
+                require(encoded.slice(0, 2) == 0x0300);
+                require(decodeLP(encoded, 2) == payload);
+            }
+        }
+    "#;
+    let meta = |payload: Vec<u8>| TraceMeta {
+        source: source.to_string(),
+        function: "main".to_string(),
+        args: vec![Expr::Bytes(payload)],
+        ..Default::default()
+    };
+    let trace = record_trace(meta(vec![0xaa, 0xbb, 0xcc])).expect("trace records");
+    assert!(trace.error.is_none(), "round trip should pass: {:?}", trace.error);
+    let trace = record_trace(meta(vec![0xaa, 0xbb])).expect("trace records");
+    assert!(trace.error.is_some(), "a two-byte payload has a different prefix");
+
+    let err = compile_contract(&source.replace("encodeLP(payload, 2)", "encodeLP(payload, 9)"), &[], CompileOptions::default())
+        .expect_err("width out of range");
+    assert!(err.to_string().contains("encodeLP() expects a constant prefix width between 1 and 8"), "unexpected error: {err}");
+}
+
+#[test]
+fn seeded_alt_stack_is_visible_in_trace_snapshots() {
+    let source = r#"
+        contract Seeded() {
+            entrypoint function main(int a) {
+                require(a == 4);
+            }
+        }
+    "#;
+    let meta = |alt_stack: Vec<&str>| TraceMeta {
+        source: source.to_string(),
+        function: "main".to_string(),
+        args: vec![Expr::Int(4)],
+        alt_stack: alt_stack.into_iter().map(str::to_string).collect(),
+        ..Default::default()
+    };
+    let trace = record_trace(meta(vec!["0xaabb", "01"])).expect("trace records");
+    assert!(trace.error.is_none(), "seeded trace should pass: {:?}", trace.error);
+    assert_eq!(trace.steps[0].alt_stack, vec!["aabb", "01"]);
+    assert_eq!(trace.steps.last().unwrap().alt_stack, vec!["aabb", "01"], "the lockscript leaves the alt stack alone");
+    assert!(!trace.steps[0].stack.contains(&"aabb".to_string()), "seeded items do not leak onto the data stack");
+
+    let unseeded = record_trace(meta(vec![])).expect("trace records");
+    assert!(unseeded.steps.iter().all(|step| step.alt_stack.is_empty()));
+
+    let err = record_trace(meta(vec!["abc"])).expect_err("odd-length hex");
+    assert!(err.contains("alt stack item 0 is not valid hex"), "unexpected error: {err}");
+    let oversized = "00".repeat(521);
+    let err = record_trace(meta(vec!["01", &oversized])).expect_err("oversized item");
+    assert!(err.contains("alt stack item 1 is 521 bytes"), "unexpected error: {err}");
+}
+
+#[test]
+fn sample_spend_of_a_p2pkh_contract_passes_without_arguments() {
+    let source = r#"
+        contract Wallet(pubkey owner) {
+            entrypoint function spend(sig ownerSig) {
+                require(checkSig(ownerSig, owner));
+            }
+        }
+    "#;
+    let sample = run_sample_spend(source, "spend").expect("sample spend runs");
+    assert!(sample.error.is_none(), "sample spend should pass: {:?}", sample.error);
+    let owner = sample_keypair(0).x_only_public_key().0.serialize().to_vec();
+    assert_eq!(sample.ctor_args, vec![Expr::Bytes(owner)]);
+
+    // The key and signature can also both come from the function's own params.
+    let inline = r#"
+        contract Inline() {
+            entrypoint function spend(pubkey key, sig keySig, int amount) {
+                require(amount == 0);
+                require(checkSig(keySig, key));
+            }
+        }
+    "#;
+    let sample = run_sample_spend(inline, "spend").expect("sample spend runs");
+    assert!(sample.error.is_none(), "sample spend should pass: {:?}", sample.error);
+    assert_eq!(sample.args[2], Expr::Int(0));
+}
+
+#[test]
+fn pubkey_hash_matches_the_host_side_derivation() {
+    let source = r#"
+        contract Hashed(bytes32 storedHash) {
+            entrypoint function spend(pubkey pk) {
+                require(pubkeyHash(pk) == storedHash);
+            }
+        }
+    "#;
+    let owner = sample_keypair(0).x_only_public_key().0.serialize();
+    let meta = |pk: [u8; 32]| TraceMeta {
+        source: source.to_string(),
+        function: "spend".to_string(),
+        ctor_args: vec![Expr::Bytes(pubkey_hash(&owner).to_vec())],
+        args: vec![Expr::Bytes(pk.to_vec())],
+        ..Default::default()
+    };
+    let trace = record_trace(meta(owner)).expect("trace records");
+    assert!(trace.error.is_none(), "owner key should match its hash: {:?}", trace.error);
+
+    let other = sample_keypair(1).x_only_public_key().0.serialize();
+    let trace = record_trace(meta(other)).expect("trace records");
+    assert!(trace.error.is_some(), "another key must not match");
+}
+
+#[test]
+fn as_pubkey_feeds_a_sliced_key_into_check_sig() {
+    let source = r#"
+        contract Registry(bytes record) {
+            entrypoint function spend(sig s) {
+                pubkey owner = asPubkey(record.slice(1, 33));
+                require(checkSig(s, owner));
+            }
+        }
+    "#;
+    let owner = sample_keypair(0);
+    let record = [vec![0x01], owner.x_only_public_key().0.serialize().to_vec(), vec![0xff]].concat();
+    let compiled = compile_contract(source, &[Expr::Bytes(record)], CompileOptions::default()).expect("compile succeeds");
+    let signed = verify_auto_signed(&compiled, "spend", vec![Expr::Bytes(vec![0u8; 65])], &[owner]).expect("spend runs");
+    assert!(signed.signature_valid, "the sliced key should verify: {:?}", signed.error);
+
+    let runtime = r#"
+        contract Checked() {
+            entrypoint function main(bytes raw) {
+                require(asPubkey(raw) == raw);
+            }
+        }
+    "#;
+    let meta = |source: &str, raw: Vec<u8>| TraceMeta {
+        source: source.to_string(),
+        function: "main".to_string(),
+        args: vec![Expr::Bytes(raw)],
+        ..Default::default()
+    };
+    let as_sig = runtime.replace("asPubkey", "asSig");
+    let as_sig = as_sig.as_str();
+    let cases = [(runtime, 32, true), (runtime, 31, false), (as_sig, 64, true), (as_sig, 65, true), (as_sig, 66, false)];
+    for (source, len, converts) in cases {
+        let trace = record_trace(meta(source, vec![7; len])).expect("trace records");
+        assert_eq!(trace.error.is_none(), converts, "{len} bytes: {:?}", trace.error);
+    }
+
+    let compile_err = |source: &str, ctor_args: &[Expr]| {
+        compile_contract(source, ctor_args, CompileOptions::default()).expect_err("conversion rejected").to_string()
+    };
+    let err = compile_err(&source.replace("slice(1, 33)", "slice(0, 33)"), &[Expr::Bytes(vec![0; 34])]);
+    assert!(err.contains("asPubkey() cannot convert a 33-byte value"), "unexpected error: {err}");
+    let err = compile_err(&runtime.replace("bytes raw", "int raw"), &[]);
+    assert!(err.contains("asPubkey() expects a bytes or bytesN value, got int"), "unexpected error: {err}");
+    let err = compile_err(&source.replace("checkSig(s, owner)", "checkSig(asPubkey(s), owner)"), &[Expr::Bytes(vec![0; 34])]);
+    assert!(err.contains("asPubkey() expects a bytes or bytesN value, got sig"), "unexpected error: {err}");
+    let err = compile_err(&source.replace("checkSig(s, owner)", "checkSig(owner, owner)"), &[Expr::Bytes(vec![0; 34])]);
+    assert!(err.contains("checkSig() expects a sig for its first argument, got pubkey"), "unexpected error: {err}");
+}
+
+#[test]
+fn check_multi_sig_passes_with_signatures_in_key_order() {
+    let source = r#"
+        contract MultiSig(pubkey pk1, pubkey pk2, pubkey pk3) {
+            entrypoint function spend(sig s1, sig s2) {
+                require(checkMultiSig([s1, s2], [pk1, pk2, pk3]));
+            }
+        }
+    "#;
+    let sample = run_sample_spend(source, "spend").expect("sample spend runs");
+    assert!(sample.error.is_none(), "2-of-3 should pass: {:?}", sample.error);
+
+    // Signatures are matched against the keys in order, so swapping them fails.
+    let swapped = source.replace("[s1, s2]", "[s2, s1]");
+    let sample = run_sample_spend(&swapped, "spend").expect("sample spend runs");
+    assert!(sample.error.is_some(), "out-of-order signatures should fail");
+}
+
+#[test]
+fn test_vectors_report_whether_each_spend_behaves_as_expected() {
+    let source = r#"
+        contract Limit(int max) {
+            @test(ctor=[10], args=[3], expect="pass")
+            @test(ctor=[10], args=[11], expect="fail")
+            @test(ctor=[10], args=[12], expect="pass")
+            entrypoint function spend(int value) {
+                require(value <= max);
+            }
+        }
+    "#;
+    let outcomes = run_test_vectors(source).expect("vectors run");
+    let summary: Vec<(usize, bool, bool)> =
+        outcomes.iter().map(|outcome| (outcome.index, outcome.error.is_none(), outcome.matches())).collect();
+    assert_eq!(summary, [(1, true, true), (2, false, true), (3, false, false)]);
+
+    let err = run_test_vectors(&source.replace("args=[3]", "args=[3, 4]")).unwrap_err();
+    assert!(err.contains("spend @test #1: function 'spend' expects 1 arguments, got 2"), "{err}");
+}
+
+#[test]
+fn tracing_with_covenants_disabled_exercises_the_fallback_path() {
+    let source = r#"
+        contract Fallback() {
+            entrypoint function main(int a) {
+                @covenantOnly require(OpAuthOutputCount(0) >= 0);
+                require(a == 1);
+            }
+        }
+    "#;
+    let meta = |source: &str, covenants_disabled: bool| TraceMeta {
+        source: source.to_string(),
+        function: "main".to_string(),
+        args: vec![Expr::Int(1)],
+        covenants_disabled,
+        ..Default::default()
+    };
+
+    // This trace has no transaction, so the covenant check itself cannot pass here.
+    let with_covenants = record_trace(meta(source, false)).expect("trace records");
+    let err = with_covenants.error.expect("the covenant opcode fails without a transaction");
+    assert!(!err.contains("covenants disabled"), "unexpected error: {err}");
+
+    let without_covenants = record_trace(meta(source, true)).expect("trace records");
+    assert!(without_covenants.error.is_none(), "fallback path should pass: {:?}", without_covenants.error);
+    assert!(without_covenants.steps.iter().all(|step| !step.opcode.contains("AuthOutputCount")), "the covenant check is compiled out");
+
+    let unguarded = source.replace("@covenantOnly ", "");
+    let trace = record_trace(meta(&unguarded, true)).expect("trace records");
+    let err = trace.error.expect("covenant opcode is unavailable");
+    assert!(err.contains("covenant opcodes are unavailable with covenants disabled"), "unexpected error: {err}");
+}
+
+#[test]
+fn require_coverage_flags_checks_never_seen_false() {
+    let source = r#"
+        contract Guard(int limit) {
+            entrypoint function spend(int a) {
+                require(a >= 0);
+                require(a < limit);
+            }
+
+            entrypoint function sweep() {
+                require(tx.inputs.length == 1);
+            }
+        }
+    "#;
+    let meta = |a: i64| TraceMeta {
+        source: source.to_string(),
+        function: "spend".to_string(),
+        ctor_args: vec![Expr::Int(100)],
+        args: vec![Expr::Int(a)],
+        ..Default::default()
+    };
+    let passing = record_trace(meta(5)).expect("trace records");
+    let failing = record_trace(meta(500)).expect("trace records");
+    assert!(failing.error.is_some());
+
+    let outcomes = |trace: &ExecutionTrace| -> Vec<(String, bool, bool)> {
+        trace.require_coverage.iter().map(|entry| (entry.condition.clone(), entry.seen_true, entry.seen_false)).collect()
+    };
+    assert_eq!(
+        outcomes(&failing),
+        vec![
+            ("a >= 0".to_string(), true, false),
+            ("a < limit".to_string(), false, true),
+            ("tx.inputs.length == 1".to_string(), false, false),
+        ]
+    );
+
+    let merged = merge_require_coverage([&passing, &failing]);
+    let flagged: Vec<(&str, &str)> =
+        merged.iter().filter(|entry| entry.never_false()).map(|entry| (entry.function.as_str(), entry.condition.as_str())).collect();
+    assert_eq!(flagged, vec![("spend", "a >= 0")], "only the guard no run made fail is flagged");
+    let sweep = merged.iter().find(|entry| entry.function == "sweep").expect("every entrypoint's checks are listed");
+    assert!(!sweep.seen_true && !sweep.seen_false, "sweep never ran");
+}
+
+#[test]
+fn bundled_examples_compile_and_trace_with_sample_args() {
+    let names = examples().iter().map(|example| example.name).collect::<Vec<_>>();
+    assert_eq!(names, vec!["p2pkh", "multisig", "timelock", "covenant"]);
+    assert_eq!(example("timelock").map(|example| example.function), Some("reclaim"));
+    assert!(example("missing").is_none());
+
+    let keypairs = (0..3).map(sample_keypair).collect::<Vec<_>>();
+    let options = CompileOptions { record_debug_infos: true, ..Default::default() };
+    for example in examples() {
+        let sample = run_sample_spend(example.source, example.function).expect("sample spend runs");
+        // The only non-key constructor value the samples cannot guess is the p2pkh owner hash.
+        let ctor_args = if example.name == "p2pkh" {
+            let owner = keypairs[0].x_only_public_key().0.serialize();
+            vec![Expr::Bytes(pubkey_hash(&owner).to_vec())]
+        } else {
+            assert!(sample.error.is_none(), "{}: sample spend should pass: {:?}", example.name, sample.error);
+            sample.ctor_args
+        };
+        let compiled = compile_contract(example.source, &ctor_args, options).expect("example compiles");
+        let comparison = compare_session_with_engine(&compiled, example.function, sample.args, &keypairs).expect("spend runs");
+        assert!(comparison.matches(), "{}: {comparison:?}", example.name);
+        assert_eq!(comparison.engine_error, None, "{} should pass", example.name);
+    }
+}
@@ -0,0 +1,123 @@
+use silverscript_lang::ast::{ContractAst, parse_contract_ast};
+use silverscript_lang::solidity::{
+    is_lossy_solidity_type, parse_solidity_signatures, silverscript_stub, solidity_type_to_silverscript,
+};
+
+const VAULT_INTERFACE: &str = r#"
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+/* Deposits and withdrawals. */
+interface IVault {
+    event Deposited(address indexed from, uint256 amount);
+
+    struct Receipt { uint256 amount; }
+
+    function deposit(uint256 amount, bytes32 memo) external payable;
+    function withdraw(uint64 _amount, bool all) external returns (uint256);
+    function owners() external view returns (address[] memory);
+    function setOwner(address owner) external;
+    function configure(uint256[3] limits) external;
+}
+"#;
+
+fn signature(contract: &ContractAst, name: &str) -> Vec<String> {
+    let function = contract.functions.iter().find(|function| function.name == name).expect("function exists");
+    function.params.iter().map(|param| format!("{} {}", param.type_name, param.name)).collect()
+}
+
+#[test]
+fn solidity_interface_becomes_entrypoint_stubs() {
+    let contracts = parse_solidity_signatures(VAULT_INTERFACE).expect("signatures parse");
+    assert_eq!(contracts.len(), 1);
+    let names: Vec<&str> = contracts[0].functions.iter().map(|function| function.name.as_str()).collect();
+    assert_eq!(names, ["deposit", "withdraw", "owners", "setOwner", "configure"]);
+
+    let stub = silverscript_stub(&contracts[0]);
+    let contract = parse_contract_ast(&stub).unwrap_or_else(|err| panic!("stub should parse: {err}\n{stub}"));
+    assert_eq!(contract.name, "IVault");
+    assert!(contract.functions.iter().all(|function| function.entrypoint));
+    assert_eq!(signature(&contract, "deposit"), ["int amount", "bytes32 memo"]);
+    assert_eq!(signature(&contract, "withdraw"), ["int amount", "bool all"]);
+    assert!(signature(&contract, "owners").is_empty());
+
+    // Unmapped types fall back to `bytes` with a TODO, lossy ones are flagged, and return values are noted and dropped.
+    assert_eq!(signature(&contract, "setOwner"), ["bytes20 owner"]);
+    assert_eq!(signature(&contract, "configure"), ["bytes limits"]);
+    assert!(stub.contains("TODO: unmapped Solidity type `uint256[3]`"), "{stub}");
+    assert!(stub.contains("TODO: Solidity `uint256` does not fully fit in int"), "{stub}");
+    assert!(stub.contains("TODO: Solidity returns (uint256); entrypoints cannot return values"), "{stub}");
+}
+
+#[test]
+fn solidity_contract_keeps_constructor_and_internal_functions() {
+    let source = r#"
+        contract Escrow is Ownable {
+            uint256 public deadline;
+
+            constructor(uint256 _deadline, bytes memory arbiterKey) {
+                deadline = _deadline;
+            }
+
+            function release(bytes32 secret) public {
+                if (check(secret)) { deadline = 0; }
+            }
+
+            function check(bytes32 secret) internal view returns (bool) {
+                return secret != bytes32(0);
+            }
+        }
+    "#;
+    let contracts = parse_solidity_signatures(source).expect("signatures parse");
+    let stub = silverscript_stub(&contracts[0]);
+    let contract = parse_contract_ast(&stub).unwrap_or_else(|err| panic!("stub should parse: {err}\n{stub}"));
+
+    let params: Vec<String> = contract.params.iter().map(|param| format!("{} {}", param.type_name, param.name)).collect();
+    assert_eq!(params, ["int deadline", "bytes arbiterKey"]);
+    let kinds: Vec<(&str, bool)> = contract.functions.iter().map(|function| (function.name.as_str(), function.entrypoint)).collect();
+    assert_eq!(kinds, [("release", true), ("check", false)]);
+}
+
+#[test]
+fn solidity_names_that_silverscript_reserves_fall_back() {
+    let source = "interface ILet { function let(uint256 let, bool in) external; }";
+    let contracts = parse_solidity_signatures(source).expect("signatures parse");
+    let stub = silverscript_stub(&contracts[0]);
+    let contract = parse_contract_ast(&stub).unwrap_or_else(|err| panic!("stub should parse: {err}\n{stub}"));
+    assert_eq!(signature(&contract, "function0"), ["int arg0", "bool arg1"]);
+}
+
+#[test]
+fn solidity_types_map_to_silverscript_types() {
+    let cases = [
+        ("bool", "bool"),
+        ("string", "string"),
+        ("bytes", "bytes"),
+        ("bytes1", "bytes1"),
+        ("bytes32", "bytes32"),
+        ("int8", "int"),
+        ("int64", "int"),
+        ("uint32", "int"),
+        ("uint256", "int"),
+        ("uint", "int"),
+        ("address", "bytes20"),
+        ("address payable", "bytes20"),
+        ("uint64[]", "int[]"),
+        ("bytes32[]", "bytes32[]"),
+    ];
+    for (solidity, expected) in cases {
+        assert_eq!(solidity_type_to_silverscript(solidity).as_deref(), Some(expected), "{solidity}");
+    }
+
+    let lossy = ["uint64", "uint256", "uint", "int128", "address", "uint256[]"];
+    let lossless = ["bool", "bytes32", "int64", "uint56", "uint8[]"];
+    assert!(lossy.iter().all(|ty| is_lossy_solidity_type(ty)));
+    assert!(lossless.iter().all(|ty| !is_lossy_solidity_type(ty)));
+}
+
+#[test]
+fn unmappable_solidity_types_return_none() {
+    for ty in ["mapping(address => uint256)", "uint256[3]", "uint8[][]", "fixed128x18", "bytes33", "uint7", "IERC20", "function"] {
+        assert_eq!(solidity_type_to_silverscript(ty), None, "{ty}");
+    }
+}